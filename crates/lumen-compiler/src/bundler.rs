@@ -0,0 +1,601 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use log::{debug, warn};
+use anyhow::{Result, anyhow};
+
+use lumen_parser::{SourceEncoding, read_source};
+
+/// 模块标识：磁盘上为规范化的绝对路径，内存中为虚拟路径
+pub type ModuleId = PathBuf;
+
+/// 模块解析接口 - 把导入说明符解析为模块并读取源码
+///
+/// 打包、模块内联和依赖分析都通过它访问模块；换成`MemoryResolver`即可完全脱离磁盘打包。
+pub trait Resolver: Send + Sync {
+    /// 解析`importer`中的导入说明符，外部模块和无法解析的说明符返回None
+    fn resolve(&self, specifier: &str, importer: &Path) -> Option<ModuleId>;
+
+    /// 读取模块源码
+    fn load(&self, id: &Path) -> Result<String>;
+
+    /// 入口文件对应的模块，入口不存在时报错
+    fn resolve_entry(&self, entry: &Path) -> Result<ModuleId>;
+
+    /// 说明符是否为外部模块，外部模块不解析、不打包
+    fn is_external(&self, _specifier: &str) -> bool {
+        false
+    }
+
+    /// 计算入口的依赖闭包，返回的模块列表中依赖位于依赖者之前，入口位于最后
+    fn dependency_closure(&self, entry: &Path) -> Result<DependencyClosure> {
+        let entry = self.resolve_entry(entry)?;
+
+        let mut closure = DependencyClosure::default();
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        visit_module(self, &entry, &mut visited, &mut stack, &mut closure)?;
+
+        Ok(closure)
+    }
+}
+
+/// 默认的解析顺序：原路径、依次补全扩展名、目录下的`index.*`
+fn default_extensions() -> Vec<String> {
+    ["js", "ts", "jsx", "tsx"].iter().map(|ext| ext.to_string()).collect()
+}
+
+/// 说明符是否为`externals`中的外部模块或其子路径（如`react/jsx-runtime`）
+fn matches_external(externals: &[String], specifier: &str) -> bool {
+    externals.iter().any(|external| {
+        specifier.strip_prefix(external.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier.starts_with("./") || specifier.starts_with("../")
+}
+
+/// 依次尝试原路径、补全扩展名后的路径和目录下的index文件，返回第一个存在的文件
+fn resolve_candidate(extensions: &[String], base: &Path, is_file: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+    if is_file(base) {
+        return Some(base.to_path_buf());
+    }
+    let with_extension = extensions.iter().map(|ext| {
+        let mut name = base.as_os_str().to_os_string();
+        name.push(".");
+        name.push(ext);
+        PathBuf::from(name)
+    });
+    let index = extensions.iter().map(|ext| base.join(format!("index.{}", ext)));
+    with_extension.chain(index).find(|p| is_file(p))
+}
+
+/// 磁盘上的模块解析器
+#[derive(Debug, Clone)]
+pub struct FsResolver {
+    /// 按顺序尝试的扩展名
+    pub extensions: Vec<String>,
+    /// 外部模块，匹配的说明符（包括`react/jsx-runtime`这样的子路径）不解析
+    pub externals: Vec<String>,
+    /// 裸模块名（不以`./`、`../`开头）依次在这些目录下查找，为空时不解析裸模块名
+    pub search_dirs: Vec<PathBuf>,
+}
+
+impl Default for FsResolver {
+    fn default() -> Self {
+        Self {
+            extensions: default_extensions(),
+            externals: Vec::new(),
+            search_dirs: Vec::new(),
+        }
+    }
+}
+
+impl FsResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_externals(mut self, externals: Vec<String>) -> Self {
+        self.externals = externals;
+        self
+    }
+
+    pub fn with_search_dirs(mut self, search_dirs: Vec<PathBuf>) -> Self {
+        self.search_dirs = search_dirs;
+        self
+    }
+}
+
+impl Resolver for FsResolver {
+    /// 相对路径相对于导入者所在目录解析，结果为规范化的绝对路径
+    fn resolve(&self, specifier: &str, importer: &Path) -> Option<ModuleId> {
+        if self.is_external(specifier) {
+            return None;
+        }
+        let resolved = if is_relative(specifier) {
+            let base_dir = importer.parent().unwrap_or_else(|| Path::new("."));
+            resolve_candidate(&self.extensions, &base_dir.join(specifier), Path::is_file)
+        } else {
+            self.search_dirs.iter()
+                .find_map(|dir| resolve_candidate(&self.extensions, &dir.join(specifier), Path::is_file))
+        };
+        resolved.map(|p| std::fs::canonicalize(&p).unwrap_or(p))
+    }
+
+    fn load(&self, id: &Path) -> Result<String> {
+        read_source(id, SourceEncoding::Auto)
+            .map_err(|e| anyhow!("读取模块失败 {}: {}", id.display(), e))
+    }
+
+    fn resolve_entry(&self, entry: &Path) -> Result<ModuleId> {
+        std::fs::canonicalize(entry)
+            .map_err(|e| anyhow!("无法解析入口文件 {}: {}", entry.display(), e))
+    }
+
+    fn is_external(&self, specifier: &str) -> bool {
+        matches_external(&self.externals, specifier)
+    }
+}
+
+/// 内存中的模块解析器 - 模块保存在以虚拟路径为键的表中，不访问磁盘
+///
+/// 解析规则与`FsResolver`相同；虚拟路径中的`.`和`..`按字面消去。
+#[derive(Debug, Clone)]
+pub struct MemoryResolver {
+    modules: HashMap<PathBuf, String>,
+    /// 按顺序尝试的扩展名
+    pub extensions: Vec<String>,
+    /// 外部模块，匹配的说明符不解析
+    pub externals: Vec<String>,
+}
+
+impl Default for MemoryResolver {
+    fn default() -> Self {
+        Self {
+            modules: HashMap::new(),
+            extensions: default_extensions(),
+            externals: Vec::new(),
+        }
+    }
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个模块，同一路径再次添加时覆盖原来的源码
+    pub fn with_module<P: AsRef<Path>>(mut self, path: P, source: &str) -> Self {
+        self.modules.insert(lexical_path(path.as_ref()), source.to_string());
+        self
+    }
+
+    pub fn with_externals(mut self, externals: Vec<String>) -> Self {
+        self.externals = externals;
+        self
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn resolve(&self, specifier: &str, importer: &Path) -> Option<ModuleId> {
+        if self.is_external(specifier) || !is_relative(specifier) {
+            return None;
+        }
+        let base = lexical_path(&importer.parent().unwrap_or_else(|| Path::new("")).join(specifier));
+        resolve_candidate(&self.extensions, &base, |p| self.modules.contains_key(p))
+    }
+
+    fn load(&self, id: &Path) -> Result<String> {
+        self.modules.get(id).cloned()
+            .ok_or_else(|| anyhow!("找不到模块 {}", id.display()))
+    }
+
+    fn resolve_entry(&self, entry: &Path) -> Result<ModuleId> {
+        let id = lexical_path(entry);
+        if self.modules.contains_key(&id) {
+            Ok(id)
+        } else {
+            Err(anyhow!("无法解析入口文件 {}: 模块不存在", entry.display()))
+        }
+    }
+
+    fn is_external(&self, specifier: &str) -> bool {
+        matches_external(&self.externals, specifier)
+    }
+}
+
+/// 去掉路径中的`.`并消去能够抵消的`..`，不访问文件系统
+fn lexical_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn visit_module<R: Resolver + ?Sized>(
+    resolver: &R,
+    path: &Path,
+    visited: &mut HashSet<PathBuf>,
+    stack: &mut Vec<PathBuf>,
+    closure: &mut DependencyClosure,
+) -> Result<()> {
+    // 仍在访问栈上说明遇到了循环导入
+    if let Some(pos) = stack.iter().position(|p| p == path) {
+        let cycle = stack[pos..].to_vec();
+        warn!("检测到循环导入: {}", format_cycle(&cycle));
+        closure.cycles.push(cycle);
+        return Ok(());
+    }
+    if !visited.insert(path.to_path_buf()) {
+        return Ok(());
+    }
+
+    let source = resolver.load(path)?;
+
+    // 动态导入是异步依赖，单独记录，不合并到当前闭包中
+    for specifier in extract_dynamic_imports(&source) {
+        match resolver.resolve(&specifier, path) {
+            Some(dep) if !closure.dynamic_imports.contains(&dep) => closure.dynamic_imports.push(dep),
+            Some(_) => {},
+            None => debug!("跳过无法解析的动态导入: '{}' (来自 {})", specifier, path.display()),
+        }
+    }
+
+    stack.push(path.to_path_buf());
+    for specifier in extract_imports(&source) {
+        if resolver.is_external(&specifier) {
+            if !closure.externals.contains(&specifier) {
+                closure.externals.push(specifier);
+            }
+            continue;
+        }
+        match resolver.resolve(&specifier, path) {
+            Some(dep) => visit_module(resolver, &dep, visited, stack, closure)?,
+            None => debug!("跳过无法解析的导入: '{}' (来自 {})", specifier, path.display()),
+        }
+    }
+    stack.pop();
+
+    closure.modules.push(path.to_path_buf());
+    Ok(())
+}
+
+/// 入口的依赖闭包
+#[derive(Debug, Clone, Default)]
+pub struct DependencyClosure {
+    /// 闭包中的模块（依赖在前，入口在最后）
+    pub modules: Vec<PathBuf>,
+    /// 检测到的循环导入，每个循环按导入顺序列出成员
+    pub cycles: Vec<Vec<PathBuf>>,
+    /// 闭包中的模块通过`import()`异步加载的模块，按首次出现的顺序排列
+    pub dynamic_imports: Vec<PathBuf>,
+    /// 闭包中的模块导入的外部模块说明符，按首次出现的顺序排列
+    pub externals: Vec<String>,
+}
+
+/// 打包过程中产生的警告
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleWarning {
+    /// 模块之间存在循环导入
+    CircularImport {
+        /// 循环中的模块，按导入顺序排列
+        members: Vec<PathBuf>,
+    },
+}
+
+impl fmt::Display for BundleWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleWarning::CircularImport { members } => {
+                write!(f, "循环导入: {}", format_cycle(members))
+            }
+        }
+    }
+}
+
+/// 将循环格式化为 `a -> b -> a`
+fn format_cycle(members: &[PathBuf]) -> String {
+    let mut parts: Vec<String> = members.iter().map(|p| p.display().to_string()).collect();
+    if let Some(first) = parts.first().cloned() {
+        parts.push(first);
+    }
+    parts.join(" -> ")
+}
+
+/// 共享chunk的输出文件名
+pub const SHARED_CHUNK_NAME: &str = "chunk-shared.js";
+
+/// 打包选项
+#[derive(Debug, Clone)]
+pub struct BundleOptions {
+    /// 是否将多个入口共同依赖的模块提取到共享chunk
+    pub extract_shared: bool,
+    /// 模块被提取前至少需要被多少个入口引用
+    pub min_importers: usize,
+    /// 是否将打包警告（如循环导入）视为错误
+    pub fail_on_warning: bool,
+    /// 外部模块，不解析、不打包，导入语句原样保留
+    pub externals: Vec<String>,
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        Self {
+            extract_shared: false,
+            min_importers: 2,
+            fail_on_warning: false,
+            externals: Vec::new(),
+        }
+    }
+}
+
+/// 打包产物类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+    /// 入口产物
+    Entry,
+    /// 多个入口共享的chunk
+    Shared,
+}
+
+/// 单个chunk的打包产物
+#[derive(Debug, Clone)]
+pub struct BundleOutput {
+    /// 产物类型
+    pub kind: ChunkKind,
+    /// 入口文件（共享chunk没有入口）
+    pub entry: Option<PathBuf>,
+    /// 输出文件路径
+    pub output_path: PathBuf,
+    /// 打包进来的模块（依赖在前）
+    pub modules: Vec<PathBuf>,
+    /// 该产物引用的共享chunk
+    pub shared_chunks: Vec<PathBuf>,
+    /// 打包警告
+    pub warnings: Vec<BundleWarning>,
+    /// 打包后的代码
+    pub code: String,
+}
+
+/// 找出被至少`min_importers`个入口闭包包含的模块
+///
+/// 入口本身不会被提取。返回顺序沿用各闭包中的首次出现顺序，因此依赖仍然位于依赖者之前。
+pub fn find_shared_modules(closures: &[Vec<PathBuf>], min_importers: usize) -> Vec<PathBuf> {
+    let entries: HashSet<&PathBuf> = closures.iter().filter_map(|c| c.last()).collect();
+    let mut shared = Vec::new();
+
+    for closure in closures {
+        for module in closure {
+            if entries.contains(module) || shared.contains(module) {
+                continue;
+            }
+            let importers = closures.iter().filter(|c| c.contains(module)).count();
+            if importers >= min_importers.max(1) {
+                shared.push(module.clone());
+            }
+        }
+    }
+
+    shared
+}
+
+/// 从源码中提取静态导入说明符（`import ... from 'x'`、`import 'x'`、`export ... from 'x'`）
+pub fn extract_imports(source: &str) -> Vec<String> {
+    scan_imports(source).0
+}
+
+/// 从源码中提取动态导入`import('x')`的说明符，参数不是字符串字面量的动态导入无法静态确定，会被忽略
+pub fn extract_dynamic_imports(source: &str) -> Vec<String> {
+    scan_imports(source).1
+}
+
+/// 扫描源码，返回(静态导入, 动态导入)的说明符
+fn scan_imports(source: &str) -> (Vec<String>, Vec<String>) {
+    let chars: Vec<char> = source.chars().collect();
+    let mut specifiers = Vec::new();
+    let mut dynamic = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // 跳过注释和字符串，避免误匹配其中的关键字
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        }
+        if c == '"' || c == '\'' || c == '`' {
+            i = skip_string(&chars, i).1;
+            continue;
+        }
+
+        if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_part(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let after_dot = start > 0 && chars[start - 1] == '.';
+
+            if !after_dot && word == "import" {
+                if let Some((specifier, end)) = scan_dynamic_import(&chars, i) {
+                    dynamic.push(specifier);
+                    i = end;
+                    continue;
+                }
+            }
+            if !after_dot && (word == "import" || word == "export") {
+                if let Some((specifier, end)) = scan_module_specifier(&chars, i, word == "export") {
+                    specifiers.push(specifier);
+                    i = end;
+                }
+            }
+            continue;
+        }
+
+        i += 1;
+    }
+
+    (specifiers, dynamic)
+}
+
+/// 从`import`关键字之后扫描动态导入`(<字符串>)`
+fn scan_dynamic_import(chars: &[char], i: usize) -> Option<(String, usize)> {
+    let i = skip_whitespace(chars, i);
+    if chars.get(i) != Some(&'(') {
+        return None;
+    }
+    let i = skip_whitespace(chars, i + 1);
+    if !matches!(chars.get(i), Some('"') | Some('\'') | Some('`')) {
+        return None;
+    }
+    let (specifier, end) = skip_string(chars, i);
+    let end = skip_whitespace(chars, end);
+    (chars.get(end) == Some(&')')).then_some((specifier, end + 1))
+}
+
+/// 从`import`/`export`关键字之后扫描模块说明符
+fn scan_module_specifier(chars: &[char], mut i: usize, is_export: bool) -> Option<(String, usize)> {
+    i = skip_whitespace(chars, i);
+    let first = *chars.get(i)?;
+
+    // 副作用导入: import 'x'
+    if !is_export && (first == '"' || first == '\'') {
+        let (value, end) = skip_string(chars, i);
+        return Some((value, end));
+    }
+
+    // export只有 `export * from` 和 `export { ... } from` 形式才引用其他模块
+    if is_export && first != '*' && first != '{' {
+        return None;
+    }
+
+    // 动态导入 import(...) 和 import.meta 不属于静态导入
+    if first == '(' || first == '.' {
+        return None;
+    }
+
+    // 向后查找 from 关键字，遇到语句结束则放弃
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ';' {
+            return None;
+        }
+        if c == '"' || c == '\'' {
+            i = skip_string(chars, i).1;
+            continue;
+        }
+        if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_part(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if word == "from" {
+                let j = skip_whitespace(chars, i);
+                return match chars.get(j) {
+                    Some('"') | Some('\'') => Some(skip_string(chars, j)),
+                    _ => None,
+                };
+            }
+            continue;
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// 跳过字符串字面量，返回其内容和结束位置
+fn skip_string(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() && chars[i] != quote {
+        if chars[i] == '\\' && i + 1 < chars.len() {
+            i += 1;
+        }
+        value.push(chars[i]);
+        i += 1;
+    }
+
+    (value, i + 1)
+}
+
+fn skip_whitespace(chars: &[char], mut i: usize) -> usize {
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_ident_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_imports() {
+        let source = r#"
+            import a from './a';
+            import { b, c } from "./b.js";
+            import './side-effect';
+            export * from './reexport';
+            export const x = 1;
+            // import ignored from './comment';
+            const s = "import fake from './string'";
+        "#;
+
+        assert_eq!(
+            extract_imports(source),
+            vec!["./a", "./b.js", "./side-effect", "./reexport"]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_import_recorded_as_async_dependency() {
+        let source = "import a from './a';\nconst page = import('./page');\nimport(name);\n";
+        assert_eq!(extract_imports(source), vec!["./a"]);
+        assert_eq!(extract_dynamic_imports(source), vec!["./page"], "参数不是字面量的动态导入应被忽略");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.js"), source).unwrap();
+        std::fs::write(dir.path().join("a.js"), "export default 1;\n").unwrap();
+        std::fs::write(dir.path().join("page.js"), "export const title = 'page';\n").unwrap();
+
+        let closure = FsResolver::new().dependency_closure(&dir.path().join("main.js")).unwrap();
+        let names = |paths: &[PathBuf]| -> Vec<String> {
+            paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect()
+        };
+        assert_eq!(names(&closure.modules), vec!["a.js", "main.js"], "动态导入的模块不应合并到闭包中");
+        assert_eq!(names(&closure.dynamic_imports), vec!["page.js"]);
+    }
+}
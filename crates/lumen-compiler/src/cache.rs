@@ -0,0 +1,194 @@
+//! 编译结果缓存 - 在同一个`Compiler`的多次编译之间复用输出，并统计命中情况
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// 缓存归档格式版本，格式不兼容时递增
+const ARCHIVE_VERSION: u32 = 2;
+
+/// 缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+    /// 因超出容量而淘汰的条目数
+    pub evictions: u64,
+    /// 当前缓存的输出总字节数
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    /// 命中率，没有查询过时为0
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups > 0 {
+            self.hits as f64 / lookups as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 一次编译中需要缓存的输出
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CachedOutput {
+    pub(crate) code: String,
+    pub(crate) source_map: Option<String>,
+    pub(crate) chunks: Vec<String>,
+    pub(crate) legal_comments: Vec<String>,
+}
+
+impl CachedOutput {
+    /// 占用的字节数，计入缓存容量
+    fn len(&self) -> usize {
+        self.code.len()
+            + self.source_map.as_ref().map_or(0, |map| map.len())
+            + self.chunks.iter().map(|chunk| chunk.len()).sum::<usize>()
+            + self.legal_comments.iter().map(|comment| comment.len()).sum::<usize>()
+    }
+}
+
+/// 缓存归档文件的内容，条目按从旧到新的使用顺序排列
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheArchive {
+    version: u32,
+    entries: Vec<(String, CachedOutput)>,
+}
+
+/// 按字节数限制容量的LRU缓存
+#[derive(Debug)]
+pub(crate) struct CompileCache {
+    entries: HashMap<String, CachedOutput>,
+    /// 最近使用的键排在末尾
+    order: VecDeque<String>,
+    max_bytes: usize,
+    stats: CacheStats,
+}
+
+impl CompileCache {
+    pub(crate) fn new(max_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            max_bytes,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &str) -> Option<CachedOutput> {
+        match self.entries.get(key) {
+            Some(value) => {
+                self.stats.hits += 1;
+                let value = value.clone();
+                self.touch(key);
+                Some(value)
+            },
+            None => {
+                self.stats.misses += 1;
+                None
+            },
+        }
+    }
+
+    /// 插入结果，超出容量时从最久未使用的条目开始淘汰；单条超过容量的结果不缓存
+    pub(crate) fn insert(&mut self, key: &str, value: &CachedOutput) {
+        if value.len() > self.max_bytes {
+            return;
+        }
+        if let Some(old) = self.entries.insert(key.to_string(), value.clone()) {
+            self.stats.bytes -= old.len();
+            self.touch(key);
+        } else {
+            self.order.push_back(key.to_string());
+        }
+        self.stats.bytes += value.len();
+
+        while self.stats.bytes > self.max_bytes {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.stats.bytes -= evicted.len();
+                self.stats.evictions += 1;
+            }
+        }
+    }
+
+    /// 把所有条目写入单个归档文件
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        let archive = CacheArchive {
+            version: ARCHIVE_VERSION,
+            entries: self.order.iter()
+                .filter_map(|key| self.entries.get(key).map(|value| (key.clone(), value.clone())))
+                .collect(),
+        };
+        let json = serde_json::to_string(&archive)?;
+        std::fs::write(path, json)
+            .map_err(|e| anyhow!("写入缓存文件失败 {}: {}", path.display(), e))
+    }
+
+    /// 从归档文件加载条目，与现有条目合并；返回加载的条目数
+    pub(crate) fn load(&mut self, path: &Path) -> Result<usize> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("读取缓存文件失败 {}: {}", path.display(), e))?;
+        let archive: CacheArchive = serde_json::from_str(&json)
+            .map_err(|e| anyhow!("缓存文件格式无效 {}: {}", path.display(), e))?;
+        if archive.version != ARCHIVE_VERSION {
+            return Err(anyhow!("不支持的缓存文件版本: {}", archive.version));
+        }
+
+        let count = archive.entries.len();
+        for (key, value) in archive.entries {
+            self.insert(&key, &value);
+        }
+        Ok(count)
+    }
+
+    /// 删除所有条目，命中、未命中和淘汰次数不变
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.stats.bytes = 0;
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(pos) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(code: &str) -> CachedOutput {
+        CachedOutput { code: code.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = CompileCache::new(6);
+        cache.insert("a", &output("aaa"));
+        cache.insert("b", &output("bbb"));
+        assert!(cache.get("a").is_some());
+        cache.insert("c", &output("ccc"));
+
+        assert!(cache.get("b").is_none(), "最久未使用的条目应被淘汰");
+        assert!(cache.get("a").is_some());
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.bytes, 6);
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+    }
+}
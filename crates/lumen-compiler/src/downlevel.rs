@@ -0,0 +1,398 @@
+//! 语法降级 - 为不支持新语法的目标环境改写函数体
+
+use anyhow::{Result, anyhow};
+use log::warn;
+use lumen_core::{CodegenOptions, IR, Node, NodeType, TargetFeatures};
+
+use crate::CodeGenerator;
+
+/// 降级后回调参数的名字，用于接收上一步await的结果
+const AWAITED_VALUE: &str = "__lumen_result";
+
+/// async函数体按await切分出的一段
+struct AsyncStep<'a> {
+    /// 上一步await的结果要赋给的变量
+    binding: Option<&'a str>,
+    /// 本段内的普通语句
+    statements: Vec<&'a Node>,
+    /// 本段结束时等待（或返回）的表达式
+    awaited: Option<&'a Node>,
+}
+
+impl CodeGenerator {
+    /// 把async函数降级为Promise链
+    ///
+    /// 目前只支持函数体顶层的线性await序列：`var x = await e;`、`await e;`、`return await e;`。
+    /// 函数体中的声明统一提升到函数顶部，使各个回调共享同一组变量；无法处理的结构保留async原样输出。
+    pub(crate) fn emit_async_function_downlevel(&self, node: &Node, output: &mut String) {
+        let Some((body, params)) = node.children.split_last() else {
+            return;
+        };
+        let Some(steps) = split_async_steps(&body.0) else {
+            warn!(
+                "async函数 '{}' 包含非线性的await，暂不支持降级",
+                node.get_string_value("name").unwrap_or("<匿名>")
+            );
+            self.emit_function(node, output);
+            return;
+        };
+
+        output.push_str(&format!(
+            "function {}({}) {{\n",
+            node.get_string_value("name").unwrap_or(""),
+            Self::param_names(params).join(", ")
+        ));
+
+        let mut hoisted: Vec<&str> = Vec::new();
+        for step in &steps {
+            let declared = step.statements.iter()
+                .filter(|s| s.node_type == NodeType::VariableDeclaration)
+                .filter_map(|s| Self::declared_name(s));
+            for name in step.binding.into_iter().chain(declared) {
+                if !hoisted.contains(&name) {
+                    hoisted.push(name);
+                }
+            }
+        }
+
+        self.nested(|| {
+            if !hoisted.is_empty() {
+                self.indent(output);
+                output.push_str(&format!("var {};\n", hoisted.join(", ")));
+            }
+
+            self.indent(output);
+            output.push_str("return Promise.resolve()");
+            for step in &steps {
+                match step.binding {
+                    Some(_) => output.push_str(&format!(".then(function ({}) {{\n", AWAITED_VALUE)),
+                    None => output.push_str(".then(function () {\n"),
+                }
+                self.nested(|| {
+                    if let Some(name) = step.binding {
+                        self.indent(output);
+                        output.push_str(&format!("{} = {};\n", name, AWAITED_VALUE));
+                    }
+                    for stmt in &step.statements {
+                        self.emit_hoisted_statement(stmt, output);
+                    }
+                    if let Some(expr) = step.awaited {
+                        self.indent(output);
+                        output.push_str("return ");
+                        self.emit_expression(expr, output);
+                        output.push_str(";\n");
+                    }
+                });
+                self.indent(output);
+                output.push('}');
+                output.push(')');
+            }
+            output.push_str(";\n");
+        });
+        self.indent(output);
+        output.push_str("}\n");
+    }
+
+    /// 生成已提升声明的语句：变量声明改写为赋值
+    fn emit_hoisted_statement(&self, node: &Node, output: &mut String) {
+        if node.node_type != NodeType::VariableDeclaration {
+            self.emit_statement(node, output);
+            return;
+        }
+        if let (Some(name), Some(init)) = (Self::declared_name(node), node.children.get(1)) {
+            self.indent(output);
+            output.push_str(&format!("{} = ", name));
+            self.emit_expression(&init.0, output);
+            output.push_str(";\n");
+        }
+    }
+}
+
+/// 按顶层await把函数体切分为若干步，遇到非线性await时返回None
+fn split_async_steps(body: &Node) -> Option<Vec<AsyncStep<'_>>> {
+    let mut steps = Vec::new();
+    let mut current = AsyncStep { binding: None, statements: Vec::new(), awaited: None };
+
+    for stmt in &body.children {
+        let stmt = &stmt.0;
+        let (binding, awaited, is_return) = match stmt.node_type {
+            NodeType::AwaitExpression => (None, stmt.children.first(), false),
+            NodeType::VariableDeclaration => match stmt.children.get(1) {
+                Some(init) if init.0.node_type == NodeType::AwaitExpression => {
+                    (CodeGenerator::declared_name(stmt), init.0.children.first(), false)
+                },
+                _ => (None, None, false),
+            },
+            NodeType::ReturnStatement => match stmt.children.first() {
+                // return await e 与 return e 在Promise链中等价
+                Some(arg) if arg.0.node_type == NodeType::AwaitExpression => {
+                    (None, arg.0.children.first(), true)
+                },
+                _ => (None, None, true),
+            },
+            _ => (None, None, false),
+        };
+
+        if let Some(expr) = awaited {
+            current.awaited = Some(&expr.0);
+            let next = AsyncStep { binding, statements: Vec::new(), awaited: None };
+            steps.push(std::mem::replace(&mut current, next));
+        } else if contains_await(stmt) {
+            return None;
+        } else if is_return {
+            // return之后的语句不可达，同时避免后续回调继续执行
+            current.awaited = stmt.children.first().map(|c| c.0.as_ref());
+            steps.push(current);
+            return Some(steps);
+        } else {
+            current.statements.push(stmt);
+        }
+
+        // return await e 之后同样不可达
+        if is_return {
+            return Some(steps);
+        }
+    }
+
+    steps.push(current);
+    Some(steps)
+}
+
+/// 子树中是否包含await（不进入嵌套函数）
+fn contains_await(node: &Node) -> bool {
+    node.children.iter().any(|child| match child.0.node_type {
+        NodeType::AwaitExpression => true,
+        NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression => false,
+        _ => contains_await(&child.0),
+    })
+}
+
+/// 检查IR中是否有目标环境不支持的语法，供不允许降级、代码必须原样运行的场景（如嵌入式JS引擎）使用
+///
+/// 发现不支持的语法时返回错误，指出第一处的特性名称和位置。
+pub fn check_target_syntax(ir: &IR, options: &CodegenOptions) -> Result<()> {
+    let features = options.features();
+    let mut found = None;
+    ir.visit(|node| {
+        if found.is_none() {
+            found = unsupported_feature(node, &features).map(|feature| (feature, node.location));
+        }
+    });
+
+    match found {
+        None => Ok(()),
+        Some((feature, Some(loc))) => Err(anyhow!(
+            "目标 {} 不支持{}（{}:{}）", options.target, feature, loc.start_line, loc.start_column + 1
+        )),
+        Some((feature, None)) => Err(anyhow!("目标 {} 不支持{}", options.target, feature)),
+    }
+}
+
+/// 节点使用的、目标环境不支持的语法特性
+fn unsupported_feature(node: &Node, features: &TargetFeatures) -> Option<&'static str> {
+    let is_async = node.get_boolean_value("async") == Some(true);
+    match node.node_type {
+        NodeType::ArrowFunctionExpression if !features.arrow_functions => Some("箭头函数（ES2015）"),
+        NodeType::VariableDeclaration if !features.block_scoping
+            && matches!(node.get_string_value("kind"), Some("let" | "const")) => Some("let/const声明（ES2015）"),
+        NodeType::ClassDeclaration if !features.classes => Some("class语法（ES2015）"),
+        NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression if is_async && !features.async_await => {
+            Some("async函数（ES2017）")
+        },
+        NodeType::AwaitExpression if !features.async_await => Some("await表达式（ES2017）"),
+        NodeType::TemplateLiteral if !features.template_literals => Some("模板字符串（ES2015）"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::{NodeValue, SourceLocation, test_utils::ident};
+
+    fn await_expr(ir: &mut IR, arg: usize) -> usize {
+        let id = ir.create_node(NodeType::AwaitExpression);
+        ir.add_child(id, arg);
+        id
+    }
+
+    fn var_with_init(ir: &mut IR, name: &str, init: usize) -> usize {
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String("var".to_string()));
+        let name = ident(ir, name);
+        ir.add_child(decl, name);
+        ir.add_child(decl, init);
+        decl
+    }
+
+    /// async function add(a) { var x = await a; var y = await 2; return x + y; }
+    fn async_add_module() -> IR {
+        let mut ir = IR::new();
+
+        let a = ident(&mut ir, "a");
+        let await_a = await_expr(&mut ir, a);
+        let decl_x = var_with_init(&mut ir, "x", await_a);
+
+        let two = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(two).unwrap().set_value("value", NodeValue::Number(2.0));
+        let await_two = await_expr(&mut ir, two);
+        let decl_y = var_with_init(&mut ir, "y", await_two);
+
+        let sum = ir.create_node(NodeType::BinaryExpression);
+        ir.get_node_mut(sum).unwrap().set_value("operator", NodeValue::String("+".to_string()));
+        let (x, y) = (ident(&mut ir, "x"), ident(&mut ir, "y"));
+        ir.add_child(sum, x);
+        ir.add_child(sum, y);
+        let ret = ir.create_node(NodeType::ReturnStatement);
+        ir.add_child(ret, sum);
+
+        let body = ir.create_node(NodeType::BlockStatement);
+        for stmt in [decl_x, decl_y, ret] {
+            ir.add_child(body, stmt);
+        }
+
+        let func = ir.create_node(NodeType::FunctionDeclaration);
+        {
+            let node = ir.get_node_mut(func).unwrap();
+            node.set_value("name", NodeValue::String("add".to_string()));
+            node.set_value("async", NodeValue::Boolean(true));
+        }
+        let param = ident(&mut ir, "a");
+        ir.add_child(func, param);
+        ir.add_child(func, body);
+        ir.add_child(ir.root_id, func);
+        ir
+    }
+
+    /// 用本机的node执行代码并返回标准输出，环境中没有node时返回None
+    fn run_js(code: &str) -> Option<String> {
+        let output = std::process::Command::new("node").arg("-e").arg(code).output().ok()?;
+        assert!(output.status.success(), "执行失败: {}", String::from_utf8_lossy(&output.stderr));
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// `async_add_module`降级到es2015的结果
+    const LOWERED_ADD: &str = concat!(
+        "function add(a) {\n",
+        "var x, y;\n",
+        "return Promise.resolve().then(function () {\n",
+        "return a;\n",
+        "}).then(function (__lumen_result) {\n",
+        "x = __lumen_result;\n",
+        "return 2;\n",
+        "}).then(function (__lumen_result) {\n",
+        "y = __lumen_result;\n",
+        "return x + y;\n",
+        "});\n",
+        "}\n",
+    );
+
+    #[test]
+    fn test_async_function_downlevels_to_promise_chain() {
+        let ir = async_add_module();
+
+        let native = CodeGenerator::new(CodegenOptions { target: "es2020".to_string(), ..Default::default() })
+            .generate(&ir);
+        assert!(native.starts_with("async function add(a) {"), "es2020应保留async: {}", native);
+
+        // 声明提升到函数顶部，各步骤通过回调参数传递await的结果
+        let lowered = CodeGenerator::new(CodegenOptions { target: "es2015".to_string(), ..Default::default() })
+            .generate(&ir);
+        assert_eq!(lowered, LOWERED_ADD);
+
+        // 有node时再确认两种输出在引擎中得到相同的结果
+        let driver = "\nadd(40).then(function (v) { console.log(v); });";
+        match (run_js(&format!("{}{}", native, driver)), run_js(&format!("{}{}", lowered, driver))) {
+            (Some(expected), Some(actual)) => {
+                assert_eq!(expected, "42");
+                assert_eq!(actual, expected, "降级后的结果应与原生async一致");
+            },
+            _ => eprintln!("未找到node，跳过执行结果校验"),
+        }
+    }
+
+    #[test]
+    fn test_async_function_from_source_downlevels() {
+        let source = "async function add(a) {\n  var x = await a;\n  var y = await 2;\n  return x + y;\n}\n";
+        let native = crate::Compiler::new().with_cache(false).with_target("es2020").compile_string(source).unwrap();
+        assert_eq!(native.code, "async function add(a) {\nvar x = await a;\nvar y = await 2;\nreturn x + y;\n}\n");
+        let lowered = crate::Compiler::new().with_cache(false).with_target("es2015").compile_string(source).unwrap();
+        assert_eq!(lowered.code, LOWERED_ADD);
+
+        let lowered = crate::Compiler::new().with_cache(false).with_target("es2015")
+            .compile_string("async function f() { await g(); }").unwrap();
+        assert_eq!(lowered.code, "function f() {\nreturn Promise.resolve().then(function () {\nreturn g();\n}).then(function () {\n});\n}\n");
+    }
+
+    /// function* g() { yield 1; yield* other; }
+    fn generator_module(is_async: bool) -> IR {
+        let mut ir = IR::new();
+        let one = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(one).unwrap().set_value("value", NodeValue::Number(1.0));
+        let yield_one = ir.create_node(NodeType::YieldExpression);
+        ir.add_child(yield_one, one);
+        let other = ident(&mut ir, "other");
+        let yield_other = ir.create_node(NodeType::YieldExpression);
+        ir.get_node_mut(yield_other).unwrap().set_value("delegate", NodeValue::Boolean(true));
+        ir.add_child(yield_other, other);
+
+        let body = ir.create_node(NodeType::BlockStatement);
+        ir.add_child(body, yield_one);
+        ir.add_child(body, yield_other);
+        let func = ir.create_node(NodeType::FunctionDeclaration);
+        {
+            let node = ir.get_node_mut(func).unwrap();
+            node.set_value("name", NodeValue::String("g".to_string()));
+            node.set_value("generator", NodeValue::Boolean(true));
+            node.set_value("async", NodeValue::Boolean(is_async));
+        }
+        ir.add_child(func, body);
+        ir.add_child(ir.root_id, func);
+        ir
+    }
+
+    #[test]
+    fn test_generator_round_trip() {
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&generator_module(false));
+        assert_eq!(code, "function* g() {\nyield 1;\nyield* other;\n}\n");
+
+        // 从源码解析得到同样的输出；没有参数的yield在换行或右括号处结束
+        let source = "function* g() {\nyield 1;\nyield* other;\nyield\nf(yield);\n}\n";
+        let parsed = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&parsed);
+        assert_eq!(code, "function* g() {\nyield 1;\nyield* other;\nyield;\nf(yield);\n}\n");
+
+        // 异步生成器不会被async降级改写
+        let options = CodegenOptions { target: "es2015".to_string(), ..Default::default() };
+        let code = CodeGenerator::new(options).generate(&generator_module(true));
+        assert!(code.contains("async function* g() {"), "异步生成器应原样保留: {}", code);
+    }
+
+    #[test]
+    fn test_restrict_to_target_rejects_arrow_function() {
+        // var f = (x) => x;
+        let mut ir = IR::new();
+        let param = ident(&mut ir, "x");
+        let body = ident(&mut ir, "x");
+        let arrow = ir.create_node(NodeType::ArrowFunctionExpression);
+        ir.get_node_mut(arrow).unwrap().location = Some(SourceLocation::new(1, 8, 1, 16));
+        ir.add_child(arrow, param);
+        ir.add_child(arrow, body);
+        let decl = var_with_init(&mut ir, "f", arrow);
+        ir.add_child(ir.root_id, decl);
+
+        let es5 = CodegenOptions { target: "es5".to_string(), ..Default::default() };
+        let err = check_target_syntax(&ir, &es5).unwrap_err();
+        assert_eq!(err.to_string(), "目标 es5 不支持箭头函数（ES2015）（1:9）");
+        let es2015 = CodegenOptions { target: "es2015".to_string(), ..Default::default() };
+        assert!(check_target_syntax(&ir, &es2015).is_ok());
+
+        // 不限制时照常降级
+        let compiler = crate::Compiler::new();
+        let results = compiler.generate_variants("", ir.clone(), vec![es5.clone()], std::time::Instant::now()).unwrap();
+        assert_eq!(results[0].code, "var f = function (x) {\nreturn x;\n};\n");
+        let restricted = crate::Compiler::new().with_restrict_to_target(true);
+        let err = restricted.generate_variants("", ir, vec![es5], std::time::Instant::now()).unwrap_err();
+        assert!(err.to_string().contains("箭头函数"), "{}", err);
+    }
+}
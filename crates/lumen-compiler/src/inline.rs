@@ -0,0 +1,338 @@
+//! 小模块内联 - 把体积低于阈值的被导入模块直接并入导入方，删除对应的导入语句
+
+use std::collections::{HashMap, HashSet};
+
+use lumen_core::{IR, Node, NodeRef, NodeType, NodeValue};
+
+/// 把顶层导入的模块内联到`ir`中，返回被内联的模块说明符
+///
+/// `load`按说明符返回可以内联的模块IR（已解析且体积低于阈值），返回None的导入保持原样。
+/// 导入声明的`source`值是模块说明符，每个标识符子节点是一个导入的名字（`imported`值为导出名，缺省时与本地名相同）。
+/// 被内联的模块不能再有导入，导入的名字都必须是该模块的导出，否则保持原样；模块的顶层名字与导入方的名字冲突时
+/// 加上`$<n>`后缀重命名。本地名与内联后的名字不同时（如`import { a as b }`）生成`const b = a;`。
+pub fn inline_imports<F>(ir: &mut IR, mut load: F) -> Vec<String>
+where
+    F: FnMut(&str) -> Option<IR>,
+{
+    let root = ir.get_root();
+    let mut taken = names_in(&root);
+    // 说明符 -> (导出名 -> 内联后的名字)
+    let mut inlined: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut order = Vec::new();
+    let mut statements = Vec::new();
+    let mut dropped = Vec::new();
+
+    for stmt in &root.children {
+        let node = &stmt.0;
+        let specifier = match node.get_string_value("source") {
+            Some(specifier) if node.node_type == NodeType::ImportDeclaration => specifier,
+            _ => {
+                statements.push(stmt.clone());
+                continue;
+            },
+        };
+        let bindings = import_bindings(node);
+
+        let exports = match inlined.get(specifier) {
+            Some(exports) => exports.clone(),
+            None => {
+                let Some(module) = load(specifier).and_then(|m| PreparedModule::new(&m, &bindings, &taken)) else {
+                    statements.push(stmt.clone());
+                    continue;
+                };
+                if !bindings.iter().all(|(_, imported)| module.exports.contains_key(imported)) {
+                    statements.push(stmt.clone());
+                    continue;
+                }
+                for module_stmt in &module.statements {
+                    let id = graft(ir, &module_stmt.0, &module.renames, false);
+                    statements.push(ir.nodes[&id].clone());
+                }
+                taken.extend(module.top_level.iter().map(|name| module.renames.get(name).unwrap_or(name).clone()));
+                inlined.insert(specifier.to_string(), module.exports.clone());
+                order.push(specifier.to_string());
+                module.exports
+            },
+        };
+
+        let Some(finals) = bindings.iter().map(|(_, imported)| exports.get(imported)).collect::<Option<Vec<_>>>() else {
+            statements.push(stmt.clone());
+            continue;
+        };
+        for ((local, _), target) in bindings.iter().zip(finals) {
+            if local != target {
+                let alias = alias_declaration(ir, local, target);
+                statements.push(ir.nodes[&alias].clone());
+            }
+        }
+        dropped.push(stmt.clone());
+    }
+
+    for stmt in &dropped {
+        remove_subtree(ir, &stmt.0);
+    }
+    let root_id = ir.root_id;
+    if let Some(root) = ir.get_node_mut(root_id) {
+        root.children = statements;
+    }
+    order
+}
+
+/// 准备内联的模块：去掉导出包装后的顶层语句，以及避免名字冲突的重命名
+struct PreparedModule {
+    statements: Vec<NodeRef>,
+    top_level: Vec<String>,
+    renames: HashMap<String, String>,
+    /// 导出名 -> 重命名后的本地名
+    exports: HashMap<String, String>,
+}
+
+impl PreparedModule {
+    /// 模块中有导入或重命名无法保证正确时返回None
+    fn new(module: &IR, bindings: &[(String, String)], taken: &HashSet<String>) -> Option<Self> {
+        let mut statements = Vec::new();
+        let mut exports = HashMap::new();
+        for stmt in &module.get_root().children {
+            let node = &stmt.0;
+            match node.node_type {
+                NodeType::ImportDeclaration => return None,
+                NodeType::ExportDeclaration => {
+                    for child in &node.children {
+                        if child.0.node_type == NodeType::Identifier {
+                            let local = child.0.get_string_value("name")?;
+                            let exported = child.0.get_string_value("exported").unwrap_or(local);
+                            exports.insert(exported.to_string(), local.to_string());
+                        } else {
+                            if let Some(name) = declared_name(&child.0) {
+                                exports.insert(name.clone(), name);
+                            }
+                            statements.push(child.clone());
+                        }
+                    }
+                },
+                _ => statements.push(stmt.clone()),
+            }
+        }
+        let top_level: Vec<String> = statements.iter().flat_map(|s| declared_name(&s.0)).collect();
+
+        // 本地名与导出的本地名相同的导入会直接被内联内容替代，不算冲突
+        let reused: HashSet<&str> = bindings.iter()
+            .filter(|(local, imported)| exports.get(imported) == Some(local))
+            .map(|(local, _)| local.as_str())
+            .collect();
+        let mut declarations = HashMap::new();
+        for stmt in &statements {
+            count_declarations(&stmt.0, &mut declarations);
+        }
+        let module_names = names_in(&module.get_root());
+
+        let mut renames = HashMap::new();
+        for name in &top_level {
+            if !taken.contains(name) || reused.contains(name.as_str()) {
+                continue;
+            }
+            // 同名的内层声明会遮蔽顶层名字，按名字重命名会改变引用关系
+            if declarations.get(name).copied().unwrap_or(0) > 1 {
+                return None;
+            }
+            let renamed = (1..)
+                .map(|n| format!("{}${}", name, n))
+                .find(|candidate| !taken.contains(candidate) && !module_names.contains(candidate))?;
+            renames.insert(name.clone(), renamed);
+        }
+        for local in exports.values_mut() {
+            if let Some(renamed) = renames.get(local) {
+                *local = renamed.clone();
+            }
+        }
+
+        Some(Self { statements, top_level, renames, exports })
+    }
+}
+
+/// 导入声明中的（本地名, 导出名）
+fn import_bindings(node: &Node) -> Vec<(String, String)> {
+    node.children.iter()
+        .filter_map(|ident| {
+            let local = ident.0.get_string_value("name")?;
+            let imported = ident.0.get_string_value("imported").unwrap_or(local);
+            Some((local.to_string(), imported.to_string()))
+        })
+        .collect()
+}
+
+/// 顶层声明引入的名字
+fn declared_name(node: &Node) -> Option<String> {
+    match node.node_type {
+        NodeType::VariableDeclaration => node.children.first()?.0.get_string_value("name").map(String::from),
+        NodeType::FunctionDeclaration | NodeType::ClassDeclaration => node.get_string_value("name").map(String::from),
+        _ => None,
+    }
+}
+
+/// 统计子树中每个名字被声明的次数（变量、函数、类和参数）
+fn count_declarations(node: &Node, counts: &mut HashMap<String, usize>) {
+    if let Some(name) = declared_name(node) {
+        *counts.entry(name).or_default() += 1;
+    }
+    if let NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression = node.node_type {
+        if let Some((_, params)) = node.children.split_last() {
+            for param in params.iter().filter_map(|p| p.0.get_string_value("name")) {
+                *counts.entry(param.to_string()).or_default() += 1;
+            }
+        }
+    }
+    for child in &node.children {
+        count_declarations(&child.0, counts);
+    }
+}
+
+/// 子树中出现的所有名字
+fn names_in(node: &Node) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if let NodeType::Identifier | NodeType::FunctionDeclaration | NodeType::ClassDeclaration = node.node_type {
+            if let Some(name) = node.get_string_value("name") {
+                names.insert(name.to_string());
+            }
+        }
+        stack.extend(node.children.iter().map(|c| c.0.as_ref()));
+    }
+    names
+}
+
+/// 把其他IR中的子树复制到`ir`中（分配新的节点ID），按`renames`改写名字，返回新子树根节点的ID
+fn graft(ir: &mut IR, node: &Node, renames: &HashMap<String, String>, is_property: bool) -> usize {
+    let id = ir.create_node(node.node_type.clone());
+    let computed = node.get_boolean_value("computed") == Some(true);
+    let property_index = match node.node_type {
+        NodeType::MemberExpression if !computed => Some(1),
+        NodeType::Property if !computed => Some(0),
+        _ => None,
+    };
+    let children: Vec<usize> = node.children.iter()
+        .enumerate()
+        .map(|(i, child)| graft(ir, &child.0, renames, property_index == Some(i)))
+        .collect();
+
+    let renamable = matches!(node.node_type, NodeType::Identifier | NodeType::FunctionDeclaration | NodeType::ClassDeclaration);
+    if let Some(copy) = ir.get_node_mut(id) {
+        copy.values = node.values.clone();
+        copy.location = node.location;
+        if renamable && !is_property {
+            if let Some(renamed) = node.get_string_value("name").and_then(|name| renames.get(name)) {
+                copy.set_value("name", NodeValue::String(renamed.clone()));
+            }
+        }
+    }
+    for child in children {
+        ir.add_child(id, child);
+    }
+    id
+}
+
+/// `const <local> = <target>;`
+fn alias_declaration(ir: &mut IR, local: &str, target: &str) -> usize {
+    let decl = ir.create_node(NodeType::VariableDeclaration);
+    let name = ir.create_node(NodeType::Identifier);
+    let init = ir.create_node(NodeType::Identifier);
+    if let Some(node) = ir.get_node_mut(decl) {
+        node.set_value("kind", NodeValue::String("const".to_string()));
+    }
+    if let Some(node) = ir.get_node_mut(name) {
+        node.set_value("name", NodeValue::String(local.to_string()));
+    }
+    if let Some(node) = ir.get_node_mut(init) {
+        node.set_value("name", NodeValue::String(target.to_string()));
+    }
+    ir.add_child(decl, name);
+    ir.add_child(decl, init);
+    decl
+}
+
+fn remove_subtree(ir: &mut IR, node: &Node) {
+    ir.nodes.remove(&node.id);
+    for child in &node.children {
+        remove_subtree(ir, &child.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::{CodegenOptions, test_utils::ident};
+    use crate::CodeGenerator;
+
+    /// helper.js: `export function double(n) { return n * 2; }`
+    fn helper_module() -> IR {
+        let mut ir = IR::new();
+        let param = ident(&mut ir, "n");
+        let n = ident(&mut ir, "n");
+        let two = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(two).unwrap().set_value("value", NodeValue::Number(2.0));
+        let product = ir.create_node(NodeType::BinaryExpression);
+        ir.get_node_mut(product).unwrap().set_value("operator", NodeValue::String("*".to_string()));
+        ir.add_child(product, n);
+        ir.add_child(product, two);
+        let ret = ir.create_node(NodeType::ReturnStatement);
+        ir.add_child(ret, product);
+        let body = ir.create_node(NodeType::BlockStatement);
+        ir.add_child(body, ret);
+        let function = ir.create_node(NodeType::FunctionDeclaration);
+        ir.get_node_mut(function).unwrap().set_value("name", NodeValue::String("double".to_string()));
+        ir.add_child(function, param);
+        ir.add_child(function, body);
+        let export = ir.create_node(NodeType::ExportDeclaration);
+        ir.add_child(export, function);
+        ir.add_child(ir.root_id, export);
+        ir
+    }
+
+    /// main.js: `import { <imported> as <local> } from "./helper"; [var <own>;] <local>(21);`
+    fn importer(local: &str, imported: &str, own: Option<&str>) -> IR {
+        let mut ir = IR::new();
+        let import = ir.create_node(NodeType::ImportDeclaration);
+        ir.get_node_mut(import).unwrap().set_value("source", NodeValue::String("./helper".to_string()));
+        let binding = ident(&mut ir, local);
+        ir.get_node_mut(binding).unwrap().set_value("imported", NodeValue::String(imported.to_string()));
+        ir.add_child(import, binding);
+        ir.add_child(ir.root_id, import);
+        if let Some(own) = own {
+            let decl = ir.create_node(NodeType::VariableDeclaration);
+            let name = ident(&mut ir, own);
+            ir.add_child(decl, name);
+            ir.add_child(ir.root_id, decl);
+        }
+        let callee = ident(&mut ir, local);
+        let arg = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(arg).unwrap().set_value("value", NodeValue::Number(21.0));
+        let call = ir.create_node(NodeType::CallExpression);
+        ir.add_child(call, callee);
+        ir.add_child(call, arg);
+        ir.add_child(ir.root_id, call);
+        ir
+    }
+
+    #[test]
+    fn test_tiny_helper_inlined_into_importer() {
+        let mut ir = importer("double", "double", None);
+        let inlined = inline_imports(&mut ir, |specifier| (specifier == "./helper").then(helper_module));
+
+        assert_eq!(inlined, vec!["./helper".to_string()]);
+        assert!(!ir.nodes.values().any(|n| n.0.node_type == NodeType::ImportDeclaration), "导入语句应被删除");
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "function double(n) {\nreturn n * 2;\n}\ndouble(21);\n");
+
+        // 模块中的double与导入方的顶层名字冲突，重命名后通过别名引用
+        let mut ir = importer("twice", "double", Some("double"));
+        inline_imports(&mut ir, |_| Some(helper_module()));
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "function double$1(n) {\nreturn n * 2;\n}\nconst twice = double$1;\nvar double;\ntwice(21);\n");
+
+        // 不能内联的模块保留导入
+        let mut ir = importer("double", "double", None);
+        assert!(inline_imports(&mut ir, |_| None).is_empty());
+        assert_eq!(ir.get_root().children[0].0.node_type, NodeType::ImportDeclaration);
+    }
+}
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::Instant;
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use log::{debug, info, warn};
 use anyhow::{Result, anyhow};
 
@@ -9,8 +11,57 @@ use anyhow::{Result, anyhow};
 extern crate lumen_core;
 extern crate lumen_parser;
 
-use lumen_core::{IR, NodeType, NodeValue, CodegenOptions};
-use lumen_parser::{ParseOptions, JsParser, parse_string};
+use lumen_core::{IR, Node, NodeRef, NodeType, NodeValue, SourceLocation, CodegenOptions, LegalComments, OutputFormat, StrictMode, comment_source, is_legal_comment, is_shorthand_property, is_strict_prologue, legal_comments_in, numeric_literal_text, quote_string, separates_tokens, space_after_unary};
+use lumen_parser::{ParseOptions, JsParser, decode_source, parse_string, read_source};
+use lumen_optimizers::{OptimizationPipeline, OptimizerOptions};
+
+mod bundler;
+mod cache;
+mod downlevel;
+mod inline;
+mod modules;
+mod progress;
+mod sourcemap;
+mod transform;
+
+pub use cache::CacheStats;
+use cache::{CachedOutput, CompileCache};
+pub use bundler::{FsResolver, MemoryResolver, ModuleId, Resolver, BundleOptions, BundleOutput, BundleWarning, ChunkKind, DependencyClosure, extract_dynamic_imports, extract_imports};
+pub use downlevel::check_target_syntax;
+pub use inline::inline_imports;
+pub use modules::lower_module_syntax;
+pub use progress::{BatchProgress, ProgressTracker};
+pub use lumen_core::paths::{common_base_dir, display_path, normalize_path, output_path_in_dir, relative_output_path};
+pub use sourcemap::{SourceMap, SourceMapBuilder, MappingEntry, decode_source_map};
+pub use transform::{StripConsole, Transform, TransformRegistry, UnknownTransform};
+
+/// 文件忽略标注：文件开头的注释中带有`/* @lumen-ignore-file */`时跳过编译，原样输出
+pub const IGNORE_FILE_ANNOTATION: &str = "@lumen-ignore-file";
+
+/// 源码开头（第一条语句之前）的注释中是否带有`@lumen-ignore-file`
+pub fn has_ignore_file_annotation(source: &str) -> bool {
+    let mut rest = source.trim_start_matches('\u{feff}');
+    if rest.starts_with("#!") {
+        rest = rest.find('\n').map_or("", |i| &rest[i..]);
+    }
+    loop {
+        rest = rest.trim_start();
+        let (comment, after) = if let Some(body) = rest.strip_prefix("//") {
+            body.split_at(body.find('\n').unwrap_or(body.len()))
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(end) => (&body[..end], &body[end + 2..]),
+                None => (body, ""),
+            }
+        } else {
+            return false;
+        };
+        if comment.contains(IGNORE_FILE_ANNOTATION) {
+            return true;
+        }
+        rest = after;
+    }
+}
 
 /// 编译结果
 #[derive(Debug, Clone)]
@@ -27,6 +78,33 @@ pub struct CompileResult {
     pub output_size: usize,
     /// 压缩率
     pub compression_ratio: f64,
+    /// 按`max_chunk_bytes`拆分后的代码块，未拆分时为空
+    pub chunks: Vec<String>,
+    /// 编译过程的峰值内存估算（源码、IR和输出同时存在时），结果来自缓存或跳过编译时为None
+    pub peak_memory_bytes: Option<usize>,
+    /// 源码中的法律注释（去重，带注释符号），`LegalComments::Linked`时由`compile_file`写入单独的文件
+    pub legal_comments: Vec<String>,
+    /// 结果是否来自编译缓存
+    pub from_cache: bool,
+    /// 实际修改了语法树的转换和优化器，按执行顺序排列；结果来自缓存时为空
+    pub applied_transforms: Vec<String>,
+    /// 解析（`parse`）、优化（`optimize`）和代码生成（`codegen`）各阶段的耗时（毫秒）；
+    /// 结果来自缓存或跳过编译时为空
+    pub phase_times: HashMap<String, u64>,
+    /// 上述各阶段各占这几个阶段总耗时的比例，
+    /// 之和为1；结果来自缓存或跳过编译时为空
+    pub phase_fractions: HashMap<String, f64>,
+}
+
+/// 限时批量编译中单个文件的结果
+#[derive(Debug)]
+pub enum FileOutcome {
+    /// 在截止时间之前开始并完成编译
+    Compiled(Box<CompileResult>),
+    /// 编译失败，附带错误信息
+    Failed(String),
+    /// 到截止时间时还没有开始编译，也没有写入输出
+    NotStarted,
 }
 
 /// 编译选项
@@ -42,8 +120,28 @@ pub struct CompileOptions {
     pub distributed: bool,
     /// 是否启用缓存
     pub cache_enabled: bool,
+    /// 缓存输出的总字节数上限，超出后淘汰最久未使用的结果
+    pub cache_max_bytes: usize,
+    /// 批量编译时按输入文件相对于`base_dir`的路径在输出目录中重建目录结构，否则所有输出放在同一目录
+    pub preserve_structure: bool,
+    /// 保留目录结构时的基准目录，未指定时使用所有输入文件所在目录的公共前缀
+    pub base_dir: Option<PathBuf>,
+    /// 打包选项
+    pub bundle_options: BundleOptions,
+    /// 把源码不超过该字节数的相对路径导入模块内联到导入方，None表示不内联；需要`parse_options.filename`来解析导入路径
+    pub inline_max_bytes: Option<usize>,
     /// 额外选项
     pub extra_options: HashMap<String, String>,
+    /// 不压缩时在输出末尾追加`//# lumen <版本> <配置哈希>`注释，便于追查线上代码的构建来源
+    pub fingerprint: bool,
+    /// 遇到目标环境不支持的语法时报错而不是降级
+    pub restrict_to_target: bool,
+    /// 重新解析生成的代码，无法解析时报错，用于发现代码生成和转换的缺陷
+    pub verify_output: bool,
+    /// 代码优化选项，None表示不优化
+    pub optimizer_options: Option<OptimizerOptions>,
+    /// 优化之后、代码生成之前依次运行的转换名，如`strip-console`
+    pub transformers: Vec<String>,
     pub minify: bool,
     pub sourcemap: bool,
     pub target: String,
@@ -57,7 +155,17 @@ impl Default for CompileOptions {
             use_gpu: false,
             distributed: false,
             cache_enabled: true,
+            cache_max_bytes: 64 * 1024 * 1024,
+            preserve_structure: false,
+            base_dir: None,
+            bundle_options: BundleOptions::default(),
+            inline_max_bytes: None,
             extra_options: HashMap::new(),
+            fingerprint: false,
+            restrict_to_target: false,
+            verify_output: false,
+            optimizer_options: None,
+            transformers: Vec::new(),
             minify: false,
             sourcemap: false,
             target: "es2020".to_string(),
@@ -65,406 +173,3234 @@ impl Default for CompileOptions {
     }
 }
 
+impl CompileOptions {
+    /// 编译配置的哈希（FNV-1a），与输入文件名和哈希表的遍历顺序无关，相同配置在不同运行之间结果相同
+    pub fn config_hash(&self) -> String {
+        let mut options = self.clone();
+        options.parse_options.filename = None;
+        let mut extra: Vec<(String, String)> = options.extra_options.drain().collect();
+        extra.sort();
+        let canonical = format!("{:?}{:?}", options, extra);
+
+        let hash = canonical.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// 构建指纹注释，压缩输出或未启用时为None
+    fn fingerprint_comment(&self) -> Option<String> {
+        if !self.fingerprint || self.minify || self.codegen_options.minify {
+            return None;
+        }
+        Some(format!("//# lumen {} {}", env!("CARGO_PKG_VERSION"), self.config_hash()))
+    }
+}
+
 /// 编译器上下文
 #[derive(Debug)]
 struct CompilerContext {
     /// 编译选项
     options: CompileOptions,
-    /// 缓存
-    cache: Option<HashMap<String, String>>,
-    /// 性能统计
-    perf_stats: HashMap<String, u64>,
+    /// 各编译阶段的耗时
+    perf_stats: HashMap<String, Duration>,
 }
 
 impl CompilerContext {
     fn new(options: CompileOptions) -> Self {
-        let cache = if options.cache_enabled {
-            Some(HashMap::new())
-        } else {
-            None
-        };
-        
         Self {
             options,
-            cache,
             perf_stats: HashMap::new(),
         }
     }
     
-    fn record_perf(&mut self, key: &str, time_ms: u64) {
-        self.perf_stats.insert(key.to_string(), time_ms);
+    fn record_perf(&mut self, key: &str, time: Duration) {
+        self.perf_stats.insert(key.to_string(), time);
     }
     
-    fn get_cache(&self, key: &str) -> Option<String> {
-        self.cache.as_ref().and_then(|c| c.get(key).cloned())
+    /// 各阶段的耗时（毫秒）
+    fn phase_times(&self) -> HashMap<String, u64> {
+        self.perf_stats.iter()
+            .map(|(phase, time)| (phase.clone(), time.as_millis() as u64))
+            .collect()
     }
     
-    fn set_cache(&mut self, key: &str, value: &str) {
-        if let Some(cache) = &mut self.cache {
-            cache.insert(key.to_string(), value.to_string());
+    /// 各阶段耗时占已记录阶段总耗时的比例
+    fn phase_fractions(&self) -> HashMap<String, f64> {
+        let total: f64 = self.perf_stats.values().map(Duration::as_secs_f64).sum();
+        if total <= 0.0 {
+            return HashMap::new();
         }
+        self.perf_stats.iter()
+            .map(|(phase, time)| (phase.clone(), time.as_secs_f64() / total))
+            .collect()
     }
 }
 
 /// 代码生成器 - 将IR转换为目标代码
 pub struct CodeGenerator {
     options: CodegenOptions,
+    /// 启用Source Map时登记的映射：节点输出在未经后处理的代码中的字节偏移和节点的源码位置
+    mappings: RefCell<Vec<(usize, SourceLocation)>>,
+    /// 当前代码块的嵌套层数
+    depth: Cell<usize>,
 }
 
 impl CodeGenerator {
     pub fn new(options: CodegenOptions) -> Self {
-        Self { options }
+        Self { options, mappings: RefCell::new(Vec::new()), depth: Cell::new(0) }
     }
     
     /// 生成代码
     pub fn generate(&self, ir: &IR) -> String {
-        // TODO: 实现代码生成
-        // 这里只是一个极简的示例
+        self.generate_mapped(ir).0
+    }
+    
+    /// 生成代码，启用`sourcemap`时同时生成Source Map v3的JSON字符串
+    ///
+    /// 每个带有位置信息的语句和表达式在输出中的起始位置映射回源码中的起始位置；
+    /// `source`在启用`inline_sources`时写入`sourcesContent`。
+    pub fn generate_with_source_map(&self, ir: &IR, source_name: &str, source: &str) -> (String, Option<String>) {
+        let (output, mappings) = self.generate_mapped(ir);
+        if !self.options.sourcemap {
+            return (output, None);
+        }
         
+        let mut builder = SourceMapBuilder::new();
+        let source_index = builder.add_source(source_name, Some(source.to_string()));
+        // 按偏移顺序扫描一遍输出，把字节偏移换算为行号和列号
+        let (mut line, mut line_start, mut scanned) = (0, 0, 0);
+        for (offset, location) in mappings {
+            for (i, byte) in output.as_bytes()[scanned..offset].iter().enumerate() {
+                if *byte == b'\n' {
+                    line += 1;
+                    line_start = scanned + i + 1;
+                }
+            }
+            scanned = offset;
+            builder.add_mapping(
+                line,
+                offset - line_start,
+                source_index,
+                location.start_line.saturating_sub(1),
+                location.start_column,
+            );
+        }
+        (output, Some(builder.to_json(self.options.inline_sources)))
+    }
+    
+    /// 生成代码和按偏移排序的映射，映射的偏移已换算到最终输出中
+    fn generate_mapped(&self, ir: &IR) -> (String, Vec<(usize, SourceLocation)>) {
+        self.mappings.borrow_mut().clear();
         let mut output = String::new();
         
-        // 遍历IR节点生成代码
-        ir.visit(|node| {
-            match node.node_type {
-                NodeType::VariableDeclaration => {
-                    let kind = node.get_string_value("kind").unwrap_or("var");
-                    output.push_str(kind);
-                    output.push_str(" ");
-                    
-                    // 这里简化处理，假设第一个子节点是标识符，第二个是值
-                    if node.children.len() >= 2 {
-                        let ident = &node.children[0];
-                        let value = &node.children[1];
-                        
-                        if let Some(name) = ident.0.get_string_value("name") {
-                            output.push_str(name);
-                            output.push_str(" = ");
-                        }
-                        
-                        match value.0.node_type {
-                            NodeType::NumericLiteral => {
-                                if let Some(val) = value.0.get_number_value("value") {
-                                    output.push_str(&val.to_string());
-                                }
-                            },
-                            NodeType::StringLiteral => {
-                                if let Some(val) = value.0.get_string_value("value") {
-                                    output.push_str("\"");
-                                    output.push_str(val);
-                                    output.push_str("\"");
-                                }
-                            },
-                            _ => {}
-                        }
-                        
-                        output.push_str(";");
-                    }
-                },
-                _ => {}
+        // 按顺序生成顶层语句
+        let statements = &ir.get_root().children;
+        for (i, stmt) in statements.iter().enumerate() {
+            if self.options.strict == StrictMode::Never && is_strict_prologue(statements, i) {
+                continue;
             }
-        });
+            // 保留注释时法律注释随其他注释在原处输出
+            if self.options.legal_comments == LegalComments::Inline && !self.preserves_comments() {
+                for comment in legal_comments_in(&stmt.0) {
+                    output.push_str(&comment);
+                    output.push('\n');
+                }
+            }
+            self.emit_statement(&stmt.0, &mut output);
+        }
+        let mut mappings = self.mappings.take();
+        
+        // 按需添加严格模式指令，源码中已有时不重复添加
+        let before = output.len();
+        let output = self.apply_strict_mode(output, ir.has_strict_directive());
+        let prefix = output.len() - before;
         
-        // 应用目标环境转换
-        let output = self.apply_target_transform(&output);
+        // 应用输出模块格式
+        let (wrapper_prefix, wrapper_suffix) = self.output_format_wrapper();
+        let output = format!("{}{}{}", wrapper_prefix, output, wrapper_suffix);
+        let prefix = prefix + wrapper_prefix.len();
+        for (offset, _) in &mut mappings {
+            *offset += prefix;
+        }
         
-        // 应用代码压缩（如果启用）
-        if self.options.minify {
+        // 应用代码压缩（如果启用），删除的空白之后的位置相应前移
+        let mut output = if self.options.minify {
+            let removed = removable_whitespace(&output);
+            for (offset, _) in &mut mappings {
+                *offset -= removed.partition_point(|&i| i < *offset);
+            }
             self.minify(&output)
         } else {
             output
+        };
+        
+        if self.options.legal_comments == LegalComments::Eof {
+            for comment in unique_legal_comments(ir) {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str(&comment);
+                output.push('\n');
+            }
         }
-    }
-    
-    /// 应用目标环境转换
-    fn apply_target_transform(&self, code: &str) -> String {
-        // TODO: 根据目标环境转换代码
-        // 这里只是一个简单示例
-        match self.options.target.as_str() {
-            "es5" => {
-                // 将const/let转为var
-                code.replace("const ", "var ")
-                    .replace("let ", "var ")
-            },
-            "es2015" => {
-                // var保持不变，添加严格模式
-                format!("\"use strict\";\n{}", code)
-            },
-            _ => code.to_string(),
+        
+        // shebang必须位于文件第一行，在包装和压缩之后加上
+        let before = output.len();
+        let output = with_shebang(ir, output);
+        let prefix = output.len() - before;
+        for (offset, _) in &mut mappings {
+            *offset += prefix;
         }
+        mappings.sort_by_key(|(offset, _)| *offset);
+        (output, mappings)
     }
     
-    /// 压缩代码
-    fn minify(&self, code: &str) -> String {
-        // TODO: 实现代码压缩
-        // 这里只是一个简单示例
-        code.replace(" ", "")
-            .replace("\n", "")
-            .replace("\t", "")
-    }
-}
-
-/// 编译器主类
-pub struct Compiler {
-    options: CompileOptions,
-}
-
-impl Compiler {
-    pub fn new() -> Self {
-        Self {
-            options: CompileOptions::default(),
+    /// 位于行首时按当前嵌套层数输出缩进，在登记映射之前调用，使映射指向缩进之后的代码
+    pub(crate) fn indent(&self, output: &mut String) {
+        if self.options.indent == 0 || self.options.minify {
+            return;
+        }
+        if output.is_empty() || output.ends_with('\n') {
+            output.push_str(&" ".repeat(self.options.indent * self.depth.get()));
         }
     }
     
-    pub fn with_options(options: CompileOptions) -> Self {
-        Self { options }
+    /// 在嵌套一层的代码块中生成
+    pub(crate) fn nested(&self, emit: impl FnOnce()) {
+        self.depth.set(self.depth.get() + 1);
+        emit();
+        self.depth.set(self.depth.get() - 1);
     }
     
-    /// 编译JavaScript/TypeScript字符串
-    pub fn compile_string(&self, source: &str) -> Result<CompileResult> {
-        let start = Instant::now();
-        info!("开始编译字符串, 长度: {} 字节", source.len());
-        
-        // 创建编译上下文
-        let mut ctx = CompilerContext::new(self.options.clone());
-        
-        // 尝试从缓存获取
-        let cache_key = format!("{:x}", {
-            use std::collections::hash_map::DefaultHasher;
-            use std::hash::{Hash, Hasher};
-            let mut hasher = DefaultHasher::new();
-            source.hash(&mut hasher);
-            format!("{:?}", self.options).hash(&mut hasher);
-            hasher.finish()
-        });
-        
-        if let Some(cached) = ctx.get_cache(&cache_key) {
-            info!("从缓存中获取编译结果");
-            let elapsed = start.elapsed();
-            
-            // 计算输出大小
-            let output_size = cached.len();
-            
-            return Ok(CompileResult {
-                code: cached,
-                source_map: None, // TODO: 缓存source map
-                time_ms: elapsed.as_millis() as u64,
-                input_size: source.len(),
-                output_size,
-                compression_ratio: if source.len() > 0 {
-                    1.0 - (output_size as f64 / source.len() as f64)
-                } else {
-                    0.0
-                },
-            });
+    /// 启用Source Map时登记节点在`output`当前末尾处开始输出
+    fn mark(&self, node: &Node, output: &str) {
+        if !self.options.sourcemap {
+            return;
         }
-        
-        // 1. 解析源码
-        let parse_start = Instant::now();
-        let parser = JsParser::new(self.options.parse_options.clone());
-        let ir = parser.parse_string(source)?;
-        let parse_time = parse_start.elapsed();
-        ctx.record_perf("parse", parse_time.as_millis() as u64);
-        debug!("解析完成，耗时: {:?}", parse_time);
-        
-        // 2. 代码优化
-        // TODO: 实现代码优化
-        
-        // 3. 代码生成
-        let codegen_start = Instant::now();
-        let generator = CodeGenerator::new(self.options.codegen_options.clone());
-        let output = generator.generate(&ir);
-        let codegen_time = codegen_start.elapsed();
-        ctx.record_perf("codegen", codegen_time.as_millis() as u64);
-        debug!("代码生成完成，耗时: {:?}", codegen_time);
-        
-        // 计算压缩率和输出大小
-        let output_size = output.len();
-        let compression_ratio = if source.len() > 0 {
-            1.0 - (output_size as f64 / source.len() as f64)
-        } else {
-            0.0
+        let Some(location) = node.location else {
+            return;
         };
-        
-        // 保存到缓存
-        if self.options.cache_enabled {
-            ctx.set_cache(&cache_key, &output);
+        let mut mappings = self.mappings.borrow_mut();
+        // 表达式语句和其中的表达式从同一位置开始，只保留一条
+        if mappings.last().is_none_or(|(offset, _)| *offset != output.len()) {
+            mappings.push((output.len(), location));
         }
-        
-        let elapsed = start.elapsed();
-        info!("编译完成，耗时: {:?}, 压缩率: {:.2}%", 
-            elapsed, compression_ratio * 100.0);
-        
-        Ok(CompileResult {
-            code: output,
-            source_map: None, // TODO: 实现sourcemap生成
-            time_ms: elapsed.as_millis() as u64,
-            input_size: source.len(),
-            output_size,
-            compression_ratio,
-        })
     }
     
-    /// 编译文件
-    pub fn compile_file<P: AsRef<Path>>(&self, input: P, output: Option<P>) -> Result<CompileResult> {
-        let input_path = input.as_ref();
-        let output_path = output.map(|p| p.as_ref().to_path_buf());
-        
-        info!("编译文件: {}", input_path.display());
+    /// 按`max_chunk_bytes`在语句边界拆分生成代码
+    ///
+    /// 拆分后的chunk按顺序作为经典脚本加载，共享全局作用域，而经典脚本中不能使用`import`和`export`，
+    /// 因此只对ESM格式且不含导入、导出声明的程序拆分；其余情况返回单个chunk。单条语句超过上限时独占一个chunk，不会被截断。
+    pub fn generate_chunks(&self, ir: &IR) -> Vec<String> {
+        let statements = &ir.get_root().children;
+        let splittable = self.options.output_format == OutputFormat::Esm
+            && !statements.iter().any(|s| matches!(s.0.node_type, NodeType::ImportDeclaration | NodeType::ExportDeclaration));
         
-        // 读取输入文件
-        let source = std::fs::read_to_string(input_path)
-            .map_err(|e| anyhow!("读取文件失败: {}", e))?;
+        let limit = match self.options.max_chunk_bytes {
+            Some(limit) if splittable => limit,
+            Some(_) => {
+                warn!("包含导入、导出或使用包装格式的模块无法拆分，忽略max_chunk_bytes");
+                return vec![self.generate(ir)];
+            },
+            None => return vec![self.generate(ir)],
+        };
         
-        // 根据文件扩展名自动配置解析选项
-        let mut options = self.options.clone();
-        if let Some(ext) = input_path.extension().and_then(|e| e.to_str()) {
-            match ext.to_lowercase().as_str() {
-                "jsx" => options.parse_options.jsx = true,
-                "tsx" => {
-                    options.parse_options.jsx = true;
-                    options.parse_options.typescript = true;
-                },
-                "ts" => options.parse_options.typescript = true,
-                _ => {},
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        for (i, stmt) in statements.iter().enumerate() {
+            if self.options.strict == StrictMode::Never && is_strict_prologue(statements, i) {
+                continue;
+            }
+            let mut code = String::new();
+            self.emit_statement(&stmt.0, &mut code);
+            if !current.is_empty() && current.len() + code.len() > limit {
+                chunks.push(std::mem::take(&mut current));
             }
+            current.push_str(&code);
         }
-        
-        options.parse_options.filename = Some(input_path.to_string_lossy().to_string());
-        
-        // 编译源码
-        let compiler = Compiler::with_options(options);
-        let result = compiler.compile_string(&source)?;
-        
-        // 如果指定了输出路径，写入文件
-        if let Some(path) = output_path {
-            std::fs::write(&path, &result.code)
-                .map_err(|e| anyhow!("写入输出文件失败: {}", e))?;
-            info!("输出文件已写入: {}", path.display());
+        if !current.is_empty() || chunks.is_empty() {
+            chunks.push(current);
         }
         
-        Ok(result)
+        chunks.iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                // 每个chunk是独立的脚本，源码中的指令只在第一个chunk中
+                let chunk = self.apply_strict_mode(chunk.clone(), i == 0 && ir.has_strict_directive());
+                let chunk = if self.options.minify { self.minify(&chunk) } else { chunk };
+                // 只有第一个chunk作为入口执行
+                if i == 0 { with_shebang(ir, chunk) } else { chunk }
+            })
+            .collect()
     }
     
-    /// 批量编译文件
-    pub fn compile_files<P: AsRef<Path>>(&self, inputs: &[P], output_dir: Option<P>) -> Result<Vec<CompileResult>> {
-        let output_dir = output_dir.map(|p| p.as_ref().to_path_buf());
-        
-        // 如果指定了输出目录，确保它存在
-        if let Some(dir) = &output_dir {
-            if !dir.exists() {
-                std::fs::create_dir_all(dir)
-                    .map_err(|e| anyhow!("创建输出目录失败: {}", e))?;
-            }
-        }
-        
-        // 如果启用了分布式编译
-        if self.options.distributed {
-            // TODO: 实现分布式编译逻辑
-            warn!("分布式编译尚未实现，回退到本地编译");
+    /// 生成单条语句
+    fn emit_statement(&self, node: &Node, output: &mut String) {
+        if self.preserves_comments() {
+            self.emit_leading_comments(node, output);
         }
-        
-        // 使用迭代器处理
-        let results: Vec<Result<CompileResult>> = inputs.iter().map(|input| {
-            let input_path = input.as_ref();
-            let output_path = match &output_dir {
-                Some(dir) => {
-                    let file_name = input_path.file_name().unwrap_or_default();
-                    let mut path = dir.join(file_name);
-                    
-                    // 修改扩展名为.js
-                    path.set_extension("js");
-                    Some(path)
-                },
-                None => None,
-            };
-            
-            // 创建引用转换，解决类型不匹配问题
-            let output_ref = output_path.as_ref().map(|p| p.as_path());
-            self.compile_file(input_path, output_ref)
-        }).collect();
-        
-        // 处理结果
-        let mut successful_results = Vec::new();
-        let mut error_count = 0;
-        
-        for result in results {
-            match result {
-                Ok(res) => successful_results.push(res),
-                Err(e) => {
-                    error_count += 1;
-                    warn!("编译文件失败: {}", e);
+        self.indent(output);
+        self.mark(node, output);
+        match node.node_type {
+            NodeType::VariableDeclaration => {
+                self.emit_declarator(node, output);
+                output.push_str(";\n");
+            },
+            NodeType::ImportDeclaration => self.emit_import(node, output),
+            NodeType::ExportDeclaration => self.emit_export(node, output),
+            NodeType::BlockStatement => {
+                output.push_str("{\n");
+                self.nested(|| {
+                    for stmt in &node.children {
+                        self.emit_statement(&stmt.0, output);
+                    }
+                });
+                self.indent(output);
+                output.push_str("}\n");
+            },
+            NodeType::FunctionDeclaration => {
+                let is_async = node.get_boolean_value("async") == Some(true);
+                let is_generator = node.get_boolean_value("generator") == Some(true);
+                if is_async && is_generator {
+                    // 异步生成器无法用Promise链表达，按原样输出
+                    if !self.options.features().async_await {
+                        warn!("异步生成器 '{}' 暂不支持降级", node.get_string_value("name").unwrap_or("<匿名>"));
+                    }
+                    self.emit_function(node, output);
+                } else if is_async && !self.options.features().async_await {
+                    self.emit_async_function_downlevel(node, output);
+                } else {
+                    self.emit_function(node, output);
+                }
+            },
+            NodeType::ReturnStatement => {
+                output.push_str("return");
+                if let Some(arg) = node.children.first() {
+                    output.push(' ');
+                    self.emit_expression(&arg.0, output);
+                }
+                output.push_str(";\n");
+            },
+            NodeType::IfStatement => {
+                let [test, consequent, rest @ ..] = node.children.as_slice() else {
+                    return;
+                };
+                output.push_str("if (");
+                self.emit_expression(&test.0, output);
+                output.push_str(") ");
+                self.emit_body(&consequent.0, output);
+                if let Some(alternate) = rest.first() {
+                    while output.ends_with('\n') {
+                        output.pop();
+                    }
+                    output.push_str(" else ");
+                    self.emit_body(&alternate.0, output);
+                }
+            },
+            NodeType::WhileStatement => {
+                let [test, body] = node.children.as_slice() else {
+                    return;
+                };
+                output.push_str("while (");
+                self.emit_loop_clause(&test.0, output);
+                output.push_str(") ");
+                self.emit_body(&body.0, output);
+            },
+            NodeType::ForStatement => {
+                let [init, test, update, body] = node.children.as_slice() else {
+                    return;
+                };
+                output.push_str("for (");
+                self.emit_loop_clause(&init.0, output);
+                for clause in [test, update] {
+                    output.push(';');
+                    if clause.0.node_type != NodeType::Unknown {
+                        output.push(' ');
+                        self.emit_loop_clause(&clause.0, output);
+                    }
+                }
+                output.push_str(") ");
+                self.emit_body(&body.0, output);
+            },
+            NodeType::LabeledStatement => {
+                let Some(body) = node.children.first() else {
+                    return;
+                };
+                output.push_str(node.get_string_value("label").unwrap_or(""));
+                output.push_str(": ");
+                self.emit_statement(&body.0, output);
+            },
+            NodeType::BreakStatement | NodeType::ContinueStatement => {
+                output.push_str(if node.node_type == NodeType::BreakStatement { "break" } else { "continue" });
+                if let Some(label) = node.get_string_value("label") {
+                    output.push(' ');
+                    output.push_str(label);
+                }
+                output.push_str(";\n");
+            },
+            // 逗号表达式作为语句时不需要括号
+            NodeType::SequenceExpression => {
+                self.emit_sequence(node, output);
+                output.push_str(";\n");
+            },
+            // 其余节点作为表达式语句处理
+            _ => {
+                let before = output.len();
+                self.emit_expression(node, output);
+                if output.len() > before {
+                    output.push_str(";\n");
                 }
             }
         }
-        
-        if error_count > 0 {
-            warn!("{} 个文件编译失败", error_count);
-        }
-        
-        info!("批量编译完成: {} 成功, {} 失败", 
-            successful_results.len(), error_count);
-        
-        Ok(successful_results)
     }
     
-    // 设置选项方法
-    
-    pub fn with_sourcemap(mut self, enable: bool) -> Self {
-        self.options.codegen_options.sourcemap = enable;
-        self
+    /// 生成变量声明（不含结尾的分号），第一个子节点是标识符，第二个（可选）是初始值
+    fn emit_declarator(&self, node: &Node, output: &mut String) {
+        output.push_str(self.declaration_kind(node));
+        output.push(' ');
+        if let Some(name) = node.children.first().and_then(|c| c.0.get_string_value("name")) {
+            output.push_str(name);
+        }
+        if let Some(value) = node.children.get(1) {
+            output.push_str(" = ");
+            self.emit_expression(&value.0, output);
+        }
     }
     
-    pub fn with_minify(mut self, enable: bool) -> Self {
-        self.options.codegen_options.minify = enable;
-        self
+    /// 生成循环语句头部括号中的一部分，逗号表达式不加括号，省略的部分是`Unknown`节点
+    fn emit_loop_clause(&self, node: &Node, output: &mut String) {
+        match node.node_type {
+            NodeType::Unknown => {},
+            NodeType::VariableDeclaration => self.emit_declarator(node, output),
+            NodeType::SequenceExpression => self.emit_sequence(node, output),
+            _ => self.emit_expression(node, output),
+        }
     }
     
-    pub fn with_target(mut self, target: &str) -> Self {
-        self.options.codegen_options.target = target.to_string();
-        self
+    /// 变量声明的关键字，目标不支持块级作用域时const/let降级为var
+    fn declaration_kind<'a>(&self, node: &'a Node) -> &'a str {
+        match node.get_string_value("kind").unwrap_or("var") {
+            "let" | "const" if !self.options.features().block_scoping => "var",
+            kind => kind,
+        }
     }
     
-    pub fn with_jsx(mut self, enable: bool) -> Self {
-        self.options.parse_options.jsx = enable;
-        self
+    /// 生成控制语句的语句体，只包含一条非声明语句的代码块省略花括号
+    fn emit_body(&self, node: &Node, output: &mut String) {
+        if node.node_type == NodeType::BlockStatement {
+            if let [only] = node.children.as_slice() {
+                let is_declaration = matches!(
+                    only.0.node_type,
+                    NodeType::VariableDeclaration | NodeType::FunctionDeclaration | NodeType::ClassDeclaration
+                );
+                if !is_declaration {
+                    self.emit_statement(&only.0, output);
+                    return;
+                }
+            }
+        }
+        self.emit_statement(node, output);
     }
     
-    pub fn with_typescript(mut self, enable: bool) -> Self {
-        self.options.parse_options.typescript = enable;
-        self
+    /// 生成逗号分隔的表达式序列（不含括号）
+    fn emit_sequence(&self, node: &Node, output: &mut String) {
+        for (i, expr) in node.children.iter().enumerate() {
+            if i > 0 {
+                output.push_str(", ");
+            }
+            self.emit_expression(&expr.0, output);
+        }
     }
     
-    pub fn with_distributed(mut self, enable: bool) -> Self {
-        self.options.distributed = enable;
-        self
+    /// 生成函数声明，子节点为参数列表，最后一个子节点是函数体
+    fn emit_function(&self, node: &Node, output: &mut String) {
+        let Some((body, params)) = node.children.split_last() else {
+            return;
+        };
+        if node.get_boolean_value("async") == Some(true) {
+            output.push_str("async ");
+        }
+        let star = if node.get_boolean_value("generator") == Some(true) { "*" } else { "" };
+        output.push_str(&format!(
+            "function{} {}({}) ",
+            star,
+            node.get_string_value("name").unwrap_or(""),
+            Self::param_names(params).join(", ")
+        ));
+        self.emit_statement(&body.0, output);
     }
     
-    pub fn with_gpu(mut self, enable: bool) -> Self {
-        self.options.use_gpu = enable;
-        self
+    /// 参数标识符的名字
+    fn param_names(params: &[NodeRef]) -> Vec<&str> {
+        params.iter()
+            .filter_map(|p| p.0.get_string_value("name"))
+            .collect()
     }
     
-    pub fn with_cache(mut self, enable: bool) -> Self {
-        self.options.cache_enabled = enable;
-        self
+    /// 生成导入声明，`source`为模块说明符；标识符子节点的`imported`为`default`时是默认导入，为`*`时是命名空间导入
+    fn emit_import(&self, node: &Node, output: &mut String) {
+        let source = node.get_string_value("source").unwrap_or("");
+        let mut default = None;
+        let mut namespace = None;
+        let mut named = Vec::new();
+        for ident in &node.children {
+            let Some(local) = ident.0.get_string_value("name") else {
+                continue;
+            };
+            match ident.0.get_string_value("imported").unwrap_or(local) {
+                "default" => default = Some(local),
+                "*" => namespace = Some(local),
+                imported if imported == local => named.push(local.to_string()),
+                imported => named.push(format!("{} as {}", imported, local)),
+            }
+        }
+        
+        let mut clauses = Vec::new();
+        clauses.extend(default.map(String::from));
+        clauses.extend(namespace.map(|ns| format!("* as {}", ns)));
+        if !named.is_empty() {
+            clauses.push(format!("{{ {} }}", named.join(", ")));
+        }
+        if clauses.is_empty() {
+            output.push_str(&format!("import \"{}\";\n", source));
+        } else {
+            output.push_str(&format!("import {} from \"{}\";\n", clauses.join(", "), source));
+        }
+    }
+    
+    /// 生成导出语句
+    ///
+    /// ESM格式保留`export`关键字；包装格式（IIFE等）把导出改写为对`exports`对象的赋值。
+    fn emit_export(&self, node: &Node, output: &mut String) {
+        let wrapped = self.options.output_format.wraps_exports();
+        
+        if node.get_boolean_value("default") == Some(true) {
+            if let Some(expr) = node.children.first() {
+                output.push_str(if wrapped { "exports.default = " } else { "export default " });
+                self.emit_expression(&expr.0, output);
+                output.push_str(";\n");
+            }
+            return;
+        }
+        
+        let mut names = Vec::new();
+        for child in &node.children {
+            let child = &child.0;
+            match child.node_type {
+                // export { local as exported }
+                NodeType::Identifier => {
+                    if let Some(local) = child.get_string_value("name") {
+                        let exported = child.get_string_value("exported").unwrap_or(local);
+                        names.push((local.to_string(), exported.to_string()));
+                    }
+                },
+                // export const x = ...; / export function f() {}
+                _ => {
+                    if !wrapped {
+                        output.push_str("export ");
+                    }
+                    self.emit_statement(child, output);
+                    if let Some(name) = Self::declared_name(child) {
+                        names.push((name.to_string(), name.to_string()));
+                    }
+                },
+            }
+        }
+        
+        let has_specifiers = node.children.iter().any(|c| c.0.node_type == NodeType::Identifier);
+        if wrapped {
+            for (local, exported) in &names {
+                output.push_str(&format!("exports.{} = {};\n", exported, local));
+            }
+        } else if has_specifiers {
+            let specifiers: Vec<String> = names.iter()
+                .map(|(local, exported)| if local == exported {
+                    local.clone()
+                } else {
+                    format!("{} as {}", local, exported)
+                })
+                .collect();
+            output.push_str(&format!("export {{ {} }};\n", specifiers.join(", ")));
+        }
+    }
+    
+    /// 声明语句引入的名字
+    fn declared_name(node: &Node) -> Option<&str> {
+        match node.node_type {
+            NodeType::VariableDeclaration => node.children.first().and_then(|c| c.0.get_string_value("name")),
+            NodeType::FunctionDeclaration | NodeType::ClassDeclaration => node.get_string_value("name"),
+            _ => None,
+        }
+    }
+    
+    /// 是否在原处输出源码中的注释：启用`preserve_comments`且不压缩
+    fn preserves_comments(&self) -> bool {
+        self.options.preserve_comments && !self.options.minify
+    }
+    
+    /// 在语句之前逐行输出它的前导注释
+    ///
+    /// 法律注释只在`LegalComments::Inline`时输出，其他方式由`legal_comments`统一处理。
+    fn emit_leading_comments(&self, node: &Node, output: &mut String) {
+        let Some(NodeValue::Array(comments)) = node.get_value("leading_comments") else {
+            return;
+        };
+        for comment in comments {
+            let is_legal = comment.0.get_string_value("value").is_some_and(is_legal_comment);
+            if is_legal && self.options.legal_comments != LegalComments::Inline {
+                continue;
+            }
+            if let Some(text) = comment_source(&comment.0) {
+                self.indent(output);
+                output.push_str(&text);
+                output.push('\n');
+            }
+        }
+    }
+    
+    /// 生成表达式
+    fn emit_expression(&self, node: &Node, output: &mut String) {
+        self.mark(node, output);
+        match node.node_type {
+            NodeType::NumericLiteral => {
+                if let Some(text) = numeric_literal_text(node, self.options.minify) {
+                    output.push_str(&text);
+                }
+            },
+            NodeType::StringLiteral => {
+                if let Some(val) = node.get_string_value("value") {
+                    output.push_str(&quote_string(val));
+                }
+            },
+            NodeType::BooleanLiteral => {
+                if let Some(val) = node.get_boolean_value("value") {
+                    output.push_str(if val { "true" } else { "false" });
+                }
+            },
+            NodeType::NullLiteral => output.push_str("null"),
+            NodeType::Identifier => {
+                if let Some(name) = node.get_string_value("name") {
+                    output.push_str(name);
+                }
+            },
+            NodeType::ArrowFunctionExpression => self.emit_arrow_function(node, output),
+            NodeType::BinaryExpression => {
+                let operator = node.get_string_value("operator").unwrap_or("+");
+                if let [left, right] = node.children.as_slice() {
+                    self.emit_operand(&left.0, output);
+                    output.push_str(&format!(" {} ", operator));
+                    self.emit_operand(&right.0, output);
+                }
+            },
+            NodeType::MemberExpression => {
+                if let [object, property] = node.children.as_slice() {
+                    self.emit_callee(&object.0, output);
+                    if node.get_boolean_value("computed") == Some(true) {
+                        output.push('[');
+                        self.emit_expression(&property.0, output);
+                        output.push(']');
+                    } else {
+                        output.push('.');
+                        self.emit_expression(&property.0, output);
+                    }
+                }
+            },
+            NodeType::CallExpression => {
+                if let Some((callee, args)) = node.children.split_first() {
+                    self.emit_callee(&callee.0, output);
+                    self.emit_arguments(args, output);
+                }
+            },
+            NodeType::NewExpression => {
+                if let Some((callee, args)) = node.children.split_first() {
+                    output.push_str("new ");
+                    // 构造函数中的调用加括号，否则它的参数列表会被当作`new`的参数
+                    if callee.0.node_type == NodeType::CallExpression {
+                        output.push('(');
+                        self.emit_expression(&callee.0, output);
+                        output.push(')');
+                    } else {
+                        self.emit_callee(&callee.0, output);
+                    }
+                    self.emit_arguments(args, output);
+                }
+            },
+            NodeType::UnaryExpression => {
+                let operator = node.get_string_value("operator").unwrap_or("");
+                output.push_str(operator);
+                if let Some(arg) = node.children.first() {
+                    if space_after_unary(operator, &arg.0) {
+                        output.push(' ');
+                    }
+                    self.emit_operand(&arg.0, output);
+                }
+            },
+            NodeType::ObjectExpression => {
+                if node.children.is_empty() {
+                    output.push_str("{}");
+                    return;
+                }
+                output.push_str("{ ");
+                for (i, property) in node.children.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+                    self.emit_expression(&property.0, output);
+                }
+                output.push_str(" }");
+            },
+            NodeType::Property => {
+                if let [key, value] = node.children.as_slice() {
+                    let kind = node.get_string_value("kind").unwrap_or("init");
+                    let is_method = kind != "init" || node.get_boolean_value("method") == Some(true);
+                    if is_method {
+                        if value.0.get_boolean_value("async") == Some(true) {
+                            output.push_str("async ");
+                        }
+                        if value.0.get_boolean_value("generator") == Some(true) {
+                            output.push('*');
+                        }
+                        if kind != "init" {
+                            output.push_str(kind);
+                            output.push(' ');
+                        }
+                    }
+                    if node.get_boolean_value("computed") == Some(true) {
+                        output.push('[');
+                        self.emit_expression(&key.0, output);
+                        output.push(']');
+                    } else {
+                        self.emit_expression(&key.0, output);
+                    }
+                    if is_method {
+                        self.emit_method(&value.0, output);
+                    } else if !is_shorthand_property(node) {
+                        output.push_str(": ");
+                        self.emit_expression(&value.0, output);
+                    }
+                }
+            },
+            NodeType::ConditionalExpression => {
+                if let [test, consequent, alternate] = node.children.as_slice() {
+                    self.emit_operand(&test.0, output);
+                    output.push_str(" ? ");
+                    self.emit_expression(&consequent.0, output);
+                    output.push_str(" : ");
+                    self.emit_expression(&alternate.0, output);
+                }
+            },
+            NodeType::SequenceExpression => {
+                output.push('(');
+                self.emit_sequence(node, output);
+                output.push(')');
+            },
+            NodeType::YieldExpression => {
+                // yield* 委托给另一个可迭代对象
+                output.push_str(if node.get_boolean_value("delegate") == Some(true) { "yield*" } else { "yield" });
+                if let Some(arg) = node.children.first() {
+                    output.push(' ');
+                    self.emit_operand(&arg.0, output);
+                }
+            },
+            NodeType::AwaitExpression => {
+                output.push_str("await ");
+                if let Some(arg) = node.children.first() {
+                    self.emit_operand(&arg.0, output);
+                }
+            },
+            NodeType::ImportExpression => {
+                output.push_str("import(");
+                if let Some(specifier) = node.children.first() {
+                    self.emit_expression(&specifier.0, output);
+                }
+                output.push(')');
+            },
+            NodeType::TemplateLiteral => self.emit_template(node, output),
+            NodeType::MetaProperty => {
+                output.push_str(node.get_string_value("meta").unwrap_or("import"));
+                output.push('.');
+                output.push_str(node.get_string_value("property").unwrap_or("meta"));
+            },
+            _ => {}
+        }
+    }
+    
+    /// 生成运算数，复合表达式加括号以保持原有结合顺序
+    fn emit_operand(&self, node: &Node, output: &mut String) {
+        let needs_parens = matches!(
+            node.node_type,
+            NodeType::BinaryExpression
+                | NodeType::ConditionalExpression
+                | NodeType::ArrowFunctionExpression
+                | NodeType::AwaitExpression
+                | NodeType::YieldExpression
+        );
+        if needs_parens {
+            output.push('(');
+        }
+        self.emit_expression(node, output);
+        if needs_parens {
+            output.push(')');
+        }
+    }
+    
+    /// 生成成员访问的对象或被调用者，一元表达式也需要加括号，如`(typeof x).length`
+    fn emit_callee(&self, node: &Node, output: &mut String) {
+        if node.node_type == NodeType::UnaryExpression {
+            output.push('(');
+            self.emit_expression(node, output);
+            output.push(')');
+        } else {
+            self.emit_operand(node, output);
+        }
+    }
+    
+    /// 生成括号中的参数列表
+    fn emit_arguments(&self, args: &[NodeRef], output: &mut String) {
+        output.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                output.push_str(", ");
+            }
+            self.emit_expression(&arg.0, output);
+        }
+        output.push(')');
+    }
+    
+    /// 生成模板字符串，目标不支持时降级为字符串拼接
+    fn emit_template(&self, node: &Node, output: &mut String) {
+        if self.options.features().template_literals {
+            output.push('`');
+            for (i, part) in node.children.iter().enumerate() {
+                if i % 2 == 0 {
+                    output.push_str(part.0.get_string_value("raw").unwrap_or(""));
+                } else {
+                    output.push_str("${");
+                    self.emit_expression(&part.0, output);
+                    output.push('}');
+                }
+            }
+            output.push('`');
+            return;
+        }
+        
+        // 省略空的字符串片段；以字符串开头，保证之后的+都是字符串拼接
+        let parts: Vec<(bool, &Node)> = node.children.iter()
+            .enumerate()
+            .map(|(i, part)| (i % 2 == 0, &*part.0))
+            .filter(|(is_quasi, part)| !is_quasi || part.get_string_value("value").is_some_and(|v| !v.is_empty()))
+            .collect();
+        if parts.first().is_none_or(|(is_quasi, _)| !is_quasi) {
+            output.push_str("\"\"");
+            if !parts.is_empty() {
+                output.push_str(" + ");
+            }
+        }
+        for (i, (is_quasi, part)) in parts.iter().enumerate() {
+            if i > 0 {
+                output.push_str(" + ");
+            }
+            if *is_quasi {
+                output.push_str(&quote_string(part.get_string_value("value").unwrap_or("")));
+                continue;
+            }
+            let is_primary = matches!(
+                part.node_type,
+                NodeType::Identifier
+                    | NodeType::StringLiteral
+                    | NodeType::NumericLiteral
+                    | NodeType::BooleanLiteral
+                    | NodeType::NullLiteral
+                    | NodeType::MemberExpression
+                    | NodeType::CallExpression
+            );
+            if is_primary {
+                self.emit_expression(part, output);
+            } else {
+                output.push('(');
+                self.emit_expression(part, output);
+                output.push(')');
+            }
+        }
+    }
+    
+    /// 生成箭头函数，子节点为参数列表，最后一个子节点是函数体
+    ///
+    /// 目标不支持箭头函数时降级为`function`表达式（不处理`this`/`arguments`的绑定差异）。
+    fn emit_arrow_function(&self, node: &Node, output: &mut String) {
+        let Some((body, params)) = node.children.split_last() else {
+            return;
+        };
+        let params = Self::param_names(params);
+        let is_block = body.0.node_type == NodeType::BlockStatement;
+        
+        if self.options.features().arrow_functions {
+            output.push_str(&format!("({}) => ", params.join(", ")));
+            if is_block {
+                self.emit_block_inline(&body.0, output);
+            } else {
+                self.emit_expression(&body.0, output);
+            }
+        } else {
+            output.push_str(&format!("function ({}) ", params.join(", ")));
+            if is_block {
+                self.emit_block_inline(&body.0, output);
+            } else {
+                output.push_str("{\n");
+                self.nested(|| {
+                    self.indent(output);
+                    output.push_str("return ");
+                    self.emit_expression(&body.0, output);
+                    output.push_str(";\n");
+                });
+                self.indent(output);
+                output.push('}');
+            }
+        }
+    }
+    
+    /// 生成对象方法和访问器键之后的部分：`(<参数>) { ... }`
+    fn emit_method(&self, function: &Node, output: &mut String) {
+        let Some((body, params)) = function.children.split_last() else {
+            return;
+        };
+        output.push_str(&format!("({}) ", Self::param_names(params).join(", ")));
+        self.emit_block_inline(&body.0, output);
+    }
+    
+    /// 以表达式形式生成代码块（结尾不换行）
+    fn emit_block_inline(&self, node: &Node, output: &mut String) {
+        self.emit_statement(node, output);
+        while output.ends_with('\n') {
+            output.pop();
+        }
+    }
+    
+    /// 输出模块格式在代码前后添加的包装，ESM和CommonJS不需要包装
+    fn output_format_wrapper(&self) -> (String, &'static str) {
+        match &self.options.output_format {
+            OutputFormat::Esm | OutputFormat::CommonJs => (String::new(), ""),
+            OutputFormat::Iife { global_name } => (
+                format!("window.{} = (function () {{\nvar exports = {{}};\n", global_name),
+                "return exports;\n})();\n",
+            ),
+            OutputFormat::Umd { global_name } => {
+                let mut prefix = String::new();
+                prefix.push_str("(function (root, factory) {\n");
+                prefix.push_str("if (typeof module === \"object\" && module.exports) {\n");
+                prefix.push_str("module.exports = factory();\n");
+                prefix.push_str("} else if (typeof define === \"function\" && define.amd) {\n");
+                prefix.push_str("define([], factory);\n");
+                prefix.push_str("} else {\n");
+                prefix.push_str(&format!("root.{} = factory();\n", global_name));
+                prefix.push_str("}\n");
+                prefix.push_str("})(typeof self !== \"undefined\" ? self : this, function () {\n");
+                prefix.push_str("var exports = {};\n");
+                (prefix, "return exports;\n});\n")
+            },
+        }
+    }
+    
+    /// `StrictMode::Always`时在代码开头添加`"use strict";`，`has_directive`表示代码中已经有该指令
+    fn apply_strict_mode(&self, code: String, has_directive: bool) -> String {
+        if self.options.strict == StrictMode::Always && !has_directive {
+            format!("\"use strict\";\n{}", code)
+        } else {
+            code
+        }
+    }
+    
+    /// 压缩代码
+    fn minify(&self, code: &str) -> String {
+        let removed = removable_whitespace(code);
+        code.char_indices()
+            .filter(|(i, _)| removed.binary_search(i).is_err())
+            .map(|(_, c)| c)
+            .collect()
+    }
+}
+
+/// 编译器主类
+pub struct Compiler {
+    options: CompileOptions,
+    /// 编译结果缓存，在多次编译之间共享，批量编译中每个文件的编译也使用这个缓存
+    cache: Arc<Mutex<CompileCache>>,
+    /// 打包和模块内联使用的解析器，None时使用磁盘上的`FsResolver`
+    resolver: Option<Arc<dyn Resolver>>,
+    /// `transformers`中的名字在此查找对应的转换
+    transforms: TransformRegistry,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::with_options(CompileOptions::default())
+    }
+    
+    pub fn with_options(options: CompileOptions) -> Self {
+        let cache = Arc::new(Mutex::new(CompileCache::new(options.cache_max_bytes)));
+        Self { options, cache, resolver: None, transforms: TransformRegistry::new() }
+    }
+    
+    /// 通过`resolver`解析和读取打包、内联的模块，例如用`MemoryResolver`打包内存中的源码
+    pub fn with_resolver<R: Resolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+    
+    /// 注册自定义转换，在`transformers`中按`name()`引用
+    pub fn with_transform<T: Transform + 'static>(mut self, transform: T) -> Self {
+        self.transforms.register(transform);
+        self
+    }
+    
+    /// 按`options`编译的编译器，沿用当前编译器的缓存、解析器和已注册的转换
+    fn with_derived_options(&self, options: CompileOptions) -> Compiler {
+        Compiler {
+            options,
+            cache: Arc::clone(&self.cache),
+            resolver: self.resolver.clone(),
+            transforms: self.transforms.clone(),
+        }
+    }
+    
+    /// 当前使用的解析器，未指定时为按`bundle_options.externals`配置的`FsResolver`
+    fn resolver(&self) -> Arc<dyn Resolver> {
+        self.resolver.clone().unwrap_or_else(|| {
+            Arc::new(FsResolver::new().with_externals(self.options.bundle_options.externals.clone()))
+        })
+    }
+    
+    /// 缓存的命中、未命中、淘汰次数和当前占用的字节数
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().map(|c| c.stats()).unwrap_or_default()
+    }
+    
+    /// 清空缓存的所有条目，命中和未命中次数保留
+    pub fn clear_cache(&self) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.clear();
+        }
+        debug!("编译缓存已清空");
+    }
+    
+    /// 把缓存内容保存到单个归档文件，供之后的构建通过`load_cache`复用
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let cache = self.cache.lock().map_err(|_| anyhow!("缓存锁已损坏"))?;
+        cache.save(path.as_ref())?;
+        info!("缓存已保存到: {}", path.as_ref().display());
+        Ok(())
+    }
+    
+    /// 从`save_cache`写入的归档文件预热缓存，返回加载的条目数
+    pub fn load_cache<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
+        let mut cache = self.cache.lock().map_err(|_| anyhow!("缓存锁已损坏"))?;
+        let count = cache.load(path.as_ref())?;
+        info!("从 {} 加载了 {} 条缓存", path.as_ref().display(), count);
+        Ok(count)
+    }
+    
+    /// 缓存键不包含被内联模块的内容，内联小模块时不使用缓存
+    fn cache_usable(&self) -> bool {
+        self.options.cache_enabled && self.options.inline_max_bytes.is_none()
+    }
+    
+    fn get_cache(&self, key: &str) -> Option<CachedOutput> {
+        if !self.cache_usable() {
+            return None;
+        }
+        self.cache.lock().ok().and_then(|mut c| c.get(key))
+    }
+    
+    /// 缓存键：源码和编译选项的128位FNV-1a哈希，不同运行之间结果相同
+    fn cache_key(&self, source: &str) -> String {
+        let mut extra: Vec<_> = self.options.extra_options.iter().collect();
+        extra.sort();
+        let options = CompileOptions { extra_options: HashMap::new(), ..self.options.clone() };
+        let canonical_options = format!("{:?}{:?}", options, extra);
+        // 先写入源码长度，源码和选项的边界不会有歧义
+        format!("{:032x}", fnv1a_128(&[&(source.len() as u64).to_le_bytes(), source.as_bytes(), canonical_options.as_bytes()]))
+    }
+    
+    fn set_cache(&self, key: &str, value: &CachedOutput) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, value);
+        }
+    }
+    
+    /// 编译JavaScript/TypeScript字符串
+    pub fn compile_string(&self, source: &str) -> Result<CompileResult> {
+        let start = Instant::now();
+        info!("开始编译字符串, 长度: {} 字节", source.len());
+        
+        if has_ignore_file_annotation(source) {
+            info!("源码带有 {} 标注，跳过编译", IGNORE_FILE_ANNOTATION);
+            return Ok(passthrough_result(source, start));
+        }
+        
+        // 创建编译上下文
+        let mut ctx = CompilerContext::new(self.options.clone());
+        
+        // 尝试从缓存获取
+        let cache_key = self.cache_key(source);
+        
+        if let Some(cached) = self.get_cache(&cache_key) {
+            info!("从缓存中获取编译结果");
+            let elapsed = start.elapsed();
+            
+            // 计算输出大小
+            let output_size = cached.code.len();
+            
+            return Ok(CompileResult {
+                code: cached.code,
+                source_map: cached.source_map,
+                time_ms: elapsed.as_millis() as u64,
+                input_size: source.len(),
+                output_size,
+                compression_ratio: if source.len() > 0 {
+                    1.0 - (output_size as f64 / source.len() as f64)
+                } else {
+                    0.0
+                },
+                chunks: cached.chunks,
+                peak_memory_bytes: None,
+                legal_comments: cached.legal_comments,
+                from_cache: true,
+                applied_transforms: Vec::new(),
+                phase_times: HashMap::new(),
+                phase_fractions: HashMap::new(),
+            });
+        }
+        
+        // 1. 解析源码
+        let parse_start = Instant::now();
+        let parser = JsParser::new(self.options.parse_options.clone());
+        let mut ir = parser.parse_string(source)?;
+        let parse_time = parse_start.elapsed();
+        ctx.record_perf("parse", parse_time);
+        debug!("解析完成，耗时: {:?}", parse_time);
+        
+        // 内联体积较小的导入模块
+        let mut applied = Vec::new();
+        if let Some(max_bytes) = self.options.inline_max_bytes {
+            track_transform(&mut ir, "InlineSmallModules", &mut applied, |ir| self.inline_small_modules(ir, max_bytes));
+        }
+        
+        // 2. 按输出格式改写模块语法（如import.meta）
+        track_transform(&mut ir, "LowerModuleSyntax", &mut applied, |ir| {
+            lower_module_syntax(ir, &self.options.codegen_options.output_format)
+        })?;
+        if self.options.restrict_to_target {
+            check_target_syntax(&ir, &self.options.codegen_options)?;
+        }
+        
+        // 3. 代码优化
+        let optimize_start = Instant::now();
+        applied.extend(self.optimize(&mut ir));
+        ctx.record_perf("optimize", optimize_start.elapsed());
+        self.run_transforms(&mut ir, &mut applied)?;
+        
+        // 4. 代码生成
+        let mut result = Self::generate_result(&self.options, source, &ir, &mut ctx, start);
+        result.applied_transforms = applied;
+        if self.options.verify_output {
+            verify_reparses(&result.code)?;
+        }
+        
+        // 保存到缓存
+        if self.cache_usable() {
+            self.set_cache(&cache_key, &CachedOutput {
+                code: result.code.clone(),
+                source_map: result.source_map.clone(),
+                chunks: result.chunks.clone(),
+                legal_comments: result.legal_comments.clone(),
+            });
+        }
+        
+        info!("编译完成，耗时: {} 毫秒, 压缩率: {:.2}%", 
+            result.time_ms, result.compression_ratio * 100.0);
+        
+        Ok(result)
+    }
+    
+    /// 单独编译一个函数（函数声明或箭头函数），便于在最小的输入上检验各个编译阶段
+    ///
+    /// 源码中只能有这一个函数，否则报错；结果不写入缓存。
+    pub fn compile_function(&self, source: &str) -> Result<CompileResult> {
+        let start = Instant::now();
+        let parser = JsParser::new(self.options.parse_options.clone());
+        let ir = parser.parse_string(source)?;
+        let root = ir.get_root();
+        match root.children.as_slice() {
+            [function] if matches!(
+                function.0.node_type,
+                NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression
+            ) => {},
+            statements => {
+                return Err(anyhow!("compile_function只接受单个函数，而源码中有 {} 条语句: {:?}",
+                    statements.len(), statements.iter().map(|s| &s.0.node_type).collect::<Vec<_>>()));
+            },
+        }
+        
+        let mut results = self.generate_variants(source, ir, vec![self.options.codegen_options.clone()], start)?;
+        Ok(results.remove(0))
+    }
+    
+    /// 相对于当前文件解析导入，把源码不超过`max_bytes`的模块内联进来
+    fn inline_small_modules(&self, ir: &mut IR, max_bytes: usize) {
+        let Some(importer) = self.options.parse_options.filename.as_deref().map(Path::new) else {
+            warn!("未指定文件名，无法解析导入路径，跳过模块内联");
+            return;
+        };
+        let resolver = self.resolver();
+        let inlined = inline_imports(ir, |specifier| {
+            let path = resolver.resolve(specifier, importer)?;
+            let source = resolver.load(&path).ok()?;
+            if source.len() > max_bytes {
+                return None;
+            }
+            let options = ParseOptions {
+                filename: Some(path.to_string_lossy().to_string()),
+                ..self.options.parse_options.clone()
+            };
+            JsParser::new(options).parse_string(&source).ok()
+        });
+        if !inlined.is_empty() {
+            debug!("{} 内联了 {} 个模块: {:?}", importer.display(), inlined.len(), inlined);
+        }
+    }
+    
+    /// 解析和优化一次，按每组代码生成选项分别生成代码，如同时输出`foo.js`和`foo.min.js`
+    ///
+    /// 结果与`variants`一一对应，`time_ms`包含共享的解析时间；变体的结果不写入缓存。
+    pub fn compile_variants(&self, source: &str, variants: Vec<CodegenOptions>) -> Result<Vec<CompileResult>> {
+        let start = Instant::now();
+        if has_ignore_file_annotation(source) {
+            info!("源码带有 {} 标注，跳过编译", IGNORE_FILE_ANNOTATION);
+            return Ok(variants.iter().map(|_| passthrough_result(source, start)).collect());
+        }
+        
+        let parser = JsParser::new(self.options.parse_options.clone());
+        let ir = parser.parse_string(source)?;
+        self.generate_variants(source, ir, variants, start)
+    }
+    
+    /// 对已经解析的IR按每组选项生成代码
+    fn generate_variants(&self, source: &str, mut ir: IR, variants: Vec<CodegenOptions>, start: Instant) -> Result<Vec<CompileResult>> {
+        // 3. 代码优化，所有变体共用优化后的IR
+        let optimize_start = Instant::now();
+        let mut optimized = self.optimize(&mut ir);
+        let optimize_time = optimize_start.elapsed();
+        self.run_transforms(&mut ir, &mut optimized)?;
+        
+        variants.into_iter()
+            .map(|codegen_options| {
+                let options = CompileOptions { codegen_options, ..self.options.clone() };
+                let mut ctx = CompilerContext::new(options.clone());
+                ctx.record_perf("optimize", optimize_time);
+                // 模块语法的改写取决于输出格式，每个变体在自己的副本上进行
+                let mut ir = ir.clone();
+                let mut applied = optimized.clone();
+                track_transform(&mut ir, "LowerModuleSyntax", &mut applied, |ir| {
+                    lower_module_syntax(ir, &options.codegen_options.output_format)
+                })?;
+                if options.restrict_to_target {
+                    check_target_syntax(&ir, &options.codegen_options)?;
+                }
+                let mut result = Self::generate_result(&options, source, &ir, &mut ctx, start);
+                result.applied_transforms = applied;
+                if options.verify_output {
+                    verify_reparses(&result.code)?;
+                }
+                Ok(result)
+            })
+            .collect()
+    }
+    
+    /// 按`optimizer_options`运行默认的优化管道，返回修改了语法树的优化器
+    fn optimize(&self, ir: &mut IR) -> Vec<String> {
+        let Some(options) = &self.options.optimizer_options else {
+            return Vec::new();
+        };
+        let mut pipeline = OptimizationPipeline::new(options.clone());
+        pipeline.setup_default_pipeline();
+        pipeline.run_tracked(ir).1
+    }
+    
+    /// 依次运行`transformers`中的转换，修改了语法树的转换记入`applied`
+    fn run_transforms(&self, ir: &mut IR, applied: &mut Vec<String>) -> Result<()> {
+        for transform in self.transforms.resolve(&self.options.transformers)? {
+            track_transform(ir, transform.name(), applied, |ir| transform.apply(ir))?;
+        }
+        Ok(())
+    }
+    
+    /// 由IR生成代码、Source Map和统计信息
+    fn generate_result(options: &CompileOptions, source: &str, ir: &IR, ctx: &mut CompilerContext, start: Instant) -> CompileResult {
+        let codegen_start = Instant::now();
+        let generator = CodeGenerator::new(options.codegen_options.clone());
+        let source_name = options.parse_options.filename.as_deref().unwrap_or("<input>");
+        let (mut output, source_map) = generator.generate_with_source_map(ir, source_name, source);
+        if let Some(comment) = options.fingerprint_comment() {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str(&comment);
+            output.push('\n');
+        }
+        let codegen_time = codegen_start.elapsed();
+        ctx.record_perf("codegen", codegen_time);
+        debug!("代码生成完成，耗时: {:?}", codegen_time);
+        
+        let chunks = if options.codegen_options.max_chunk_bytes.is_some() {
+            let chunks = generator.generate_chunks(ir);
+            if chunks.len() > 1 { chunks } else { Vec::new() }
+        } else {
+            Vec::new()
+        };
+        
+        
+        // 计算压缩率和输出大小
+        let output_size = output.len();
+        let chunk_bytes: usize = chunks.iter().map(|c| c.len()).sum();
+        let peak_memory_bytes = source.len() + ir.estimated_memory_bytes() + output_size + chunk_bytes;
+        let compression_ratio = if source.len() > 0 {
+            1.0 - (output_size as f64 / source.len() as f64)
+        } else {
+            0.0
+        };
+        
+        CompileResult {
+            code: output,
+            source_map,
+            time_ms: start.elapsed().as_millis() as u64,
+            input_size: source.len(),
+            output_size,
+            compression_ratio,
+            chunks,
+            peak_memory_bytes: Some(peak_memory_bytes),
+            legal_comments: unique_legal_comments(ir),
+            from_cache: false,
+            applied_transforms: Vec::new(),
+            phase_times: ctx.phase_times(),
+            phase_fractions: ctx.phase_fractions(),
+        }
+    }
+    
+    /// 编译文件
+    pub fn compile_file<P: AsRef<Path>>(&self, input: P, output: Option<P>) -> Result<CompileResult> {
+        let input_path = input.as_ref();
+        let output_path = output.map(|p| p.as_ref().to_path_buf());
+        
+        info!("编译文件: {}", input_path.display());
+        
+        // 读取输入文件
+        let source = read_source(input_path, self.options.parse_options.encoding)
+            .map_err(|e| anyhow!("读取文件失败: {}", e))?;
+        
+        // 编译源码
+        let compiler = self.with_derived_options(self.options_for_file(input_path));
+        let mut result = compiler.compile_string(&source)?;
+        
+        // 如果指定了输出路径，写入文件
+        if let Some(path) = output_path {
+            if self.options.codegen_options.legal_comments == LegalComments::Linked {
+                link_legal_comments(&path, &mut result)?;
+            }
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty() && !p.exists()) {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| anyhow!("创建输出目录失败: {}", e))?;
+            }
+            if result.chunks.is_empty() {
+                std::fs::write(&path, &result.code)
+                    .map_err(|e| anyhow!("写入输出文件失败: {}", e))?;
+            } else {
+                write_chunked_output(&path, &result.chunks)?;
+            }
+            info!("输出文件已写入: {}", path.display());
+        }
+        
+        Ok(result)
+    }
+    
+    /// 从读取器读入源码，把编译后的代码写入写入器
+    ///
+    /// `filename`用于按扩展名配置解析选项以及Source Map中的源文件名。
+    pub fn compile_reader<R: Read, W: Write>(&self, mut reader: R, mut writer: W, filename: Option<&str>) -> Result<CompileResult> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)
+            .map_err(|e| anyhow!("读取输入失败: {}", e))?;
+        let source = decode_source(&bytes, self.options.parse_options.encoding)
+            .map_err(|e| anyhow!("读取输入失败: {}", e))?;
+        
+        let result = match filename {
+            Some(name) => self.with_derived_options(self.options_for_file(Path::new(name))).compile_string(&source)?,
+            None => self.compile_string(&source)?,
+        };
+        
+        writer.write_all(result.code.as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| anyhow!("写入输出失败: {}", e))?;
+        
+        Ok(result)
+    }
+    
+    /// 根据文件扩展名自动配置解析选项
+    fn options_for_file(&self, path: &Path) -> CompileOptions {
+        let mut options = self.options.clone();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            match ext.to_lowercase().as_str() {
+                "jsx" => options.parse_options.jsx = true,
+                "tsx" => {
+                    options.parse_options.jsx = true;
+                    options.parse_options.typescript = true;
+                },
+                "ts" => options.parse_options.typescript = true,
+                _ => {},
+            }
+        }
+        
+        options.parse_options.filename = Some(path.to_string_lossy().to_string());
+        options
+    }
+    
+    /// 批量编译文件
+    pub fn compile_files<P: AsRef<Path>>(&self, inputs: &[P], output_dir: Option<P>) -> Result<Vec<CompileResult>> {
+        self.compile_files_with_progress(inputs, output_dir, |_| {})
+    }
+    
+    /// 批量编译文件，每完成一个文件调用一次`on_progress`报告进度和预计剩余时间
+    pub fn compile_files_with_progress<P, F>(&self, inputs: &[P], output_dir: Option<P>, on_progress: F) -> Result<Vec<CompileResult>>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&BatchProgress),
+    {
+        let outcomes = self.compile_batch(inputs, output_dir, None, on_progress)?;
+        
+        // 处理结果
+        let mut successful_results = Vec::new();
+        let mut error_count = 0;
+        
+        for outcome in outcomes {
+            match outcome {
+                FileOutcome::Compiled(res) => successful_results.push(*res),
+                FileOutcome::Failed(e) => {
+                    error_count += 1;
+                    warn!("编译文件失败: {}", e);
+                },
+                FileOutcome::NotStarted => {},
+            }
+        }
+        
+        if error_count > 0 {
+            warn!("{} 个文件编译失败", error_count);
+        }
+        
+        info!("批量编译完成: {} 成功, {} 失败", 
+            successful_results.len(), error_count);
+        
+        Ok(successful_results)
+    }
+    
+    /// 在`deadline`内批量编译文件，返回与`inputs`一一对应的结果
+    ///
+    /// 到截止时间后不再开始新的文件，其余文件标记为`NotStarted`；已经开始的文件会完整编译并写出，
+    /// 不会留下写了一半的输出。
+    pub fn compile_files_within<P: AsRef<Path>>(&self, inputs: &[P], output_dir: Option<P>, deadline: Duration) -> Result<Vec<FileOutcome>> {
+        let outcomes = self.compile_batch(inputs, output_dir, Some(deadline), |_| {})?;
+        let not_started = outcomes.iter().filter(|outcome| matches!(outcome, FileOutcome::NotStarted)).count();
+        if not_started > 0 {
+            info!("已到截止时间 {:?}，{} 个文件未开始编译", deadline, not_started);
+        }
+        Ok(outcomes)
+    }
+    
+    fn compile_batch<P, F>(&self, inputs: &[P], output_dir: Option<P>, deadline: Option<Duration>, mut on_progress: F) -> Result<Vec<FileOutcome>>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&BatchProgress),
+    {
+        let batch_start = Instant::now();
+        let mut tracker = ProgressTracker::new(inputs.len());
+        let output_dir = output_dir.map(|p| p.as_ref().to_path_buf());
+        
+        // 如果指定了输出目录，确保它存在
+        if let Some(dir) = &output_dir {
+            if !dir.exists() {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| anyhow!("创建输出目录失败: {}", e))?;
+            }
+        }
+        
+        // 如果启用了分布式编译
+        if self.options.distributed {
+            // TODO: 实现分布式编译逻辑
+            warn!("分布式编译尚未实现，回退到本地编译");
+        }
+        
+        let base_dir = match &self.options.base_dir {
+            Some(dir) => dir.clone(),
+            None => common_base_dir(inputs),
+        };
+        
+        // 使用迭代器处理
+        let outcomes = inputs.iter().map(|input| {
+            if deadline.is_some_and(|deadline| batch_start.elapsed() >= deadline) {
+                return FileOutcome::NotStarted;
+            }
+            let input_path = input.as_ref();
+            let result = match &output_dir {
+                Some(dir) if self.options.preserve_structure => relative_output_path(input_path, &base_dir, dir).map(Some),
+                Some(dir) => Ok(Some(output_path_in_dir(input_path, dir))),
+                None => Ok(None),
+            }.and_then(|output_path| self.compile_file(input_path, output_path.as_deref()));
+            on_progress(&tracker.record(batch_start.elapsed()));
+            match result {
+                Ok(result) => FileOutcome::Compiled(Box::new(result)),
+                Err(e) => FileOutcome::Failed(e.to_string()),
+            }
+        }).collect();
+        
+        Ok(outcomes)
+    }
+    
+    /// 编译目录中所有JavaScript/TypeScript文件，输出按输入目录的结构放到`output_dir`下
+    pub fn compile_dir<P: AsRef<Path>>(&self, input_dir: P, output_dir: P) -> Result<Vec<CompileResult>> {
+        let input_dir = input_dir.as_ref();
+        let mut inputs = Vec::new();
+        collect_source_files(input_dir, &mut inputs)?;
+        inputs.sort();
+        info!("编译目录 {}: {} 个文件", input_dir.display(), inputs.len());
+        
+        let mut options = self.options.clone();
+        options.preserve_structure = true;
+        options.base_dir = Some(input_dir.to_path_buf());
+        self.with_derived_options(options).compile_files(&inputs, Some(output_dir.as_ref().to_path_buf()))
+    }
+    
+    /// 多入口编译 - 每个入口作为独立的打包根，输出各自的依赖闭包
+    ///
+    /// 启用`bundle_options.extract_shared`后，被至少`min_importers`个入口引用的
+    /// 模块会被提取到共享chunk中，而不是在每个入口的产物里重复一份。
+    pub fn compile_entries<P: AsRef<Path>>(&self, entries: Vec<PathBuf>, output_dir: P) -> Result<Vec<BundleOutput>> {
+        let output_dir = output_dir.as_ref();
+        if !output_dir.exists() {
+            std::fs::create_dir_all(output_dir)
+                .map_err(|e| anyhow!("创建输出目录失败: {}", e))?;
+        }
+        
+        let outputs = self.bundle_entries(entries, output_dir)?;
+        for output in &outputs {
+            std::fs::write(&output.output_path, &output.code)
+                .map_err(|e| anyhow!("写入输出文件失败: {}", e))?;
+            info!("已写入: {}", output.output_path.display());
+        }
+        Ok(outputs)
+    }
+    
+    /// 打包各入口但不写入文件，产物的`output_path`位于`output_dir`下
+    ///
+    /// 模块通过解析器读取，配合`with_resolver`使用`MemoryResolver`时完全不访问磁盘。
+    pub fn bundle_entries(&self, entries: Vec<PathBuf>, output_dir: &Path) -> Result<Vec<BundleOutput>> {
+        info!("多入口编译: {} 个入口", entries.len());
+        
+        // 产物按入口的文件名命名，不同目录下的同名入口（如a/index.js和b/index.js）会互相覆盖
+        let mut output_paths: HashMap<PathBuf, &PathBuf> = HashMap::new();
+        for entry in &entries {
+            let output_path = entry_output_path(entry, output_dir);
+            if let Some(previous) = output_paths.insert(output_path.clone(), entry) {
+                return Err(anyhow!("入口 {} 和 {} 的输出文件相同: {}", previous.display(), entry.display(), output_path.display()));
+            }
+        }
+        
+        let resolver = self.resolver();
+        let mut closures = Vec::new();
+        let mut entry_warnings = Vec::new();
+        for entry in &entries {
+            let closure = resolver.dependency_closure(entry)?;
+            debug!("入口 {} 包含 {} 个模块", entry.display(), closure.modules.len());
+            
+            let warnings: Vec<BundleWarning> = closure.cycles.into_iter()
+                .map(|members| BundleWarning::CircularImport { members })
+                .collect();
+            if self.options.bundle_options.fail_on_warning && !warnings.is_empty() {
+                let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+                return Err(anyhow!("打包入口 {} 时出现警告: {}", entry.display(), messages.join("; ")));
+            }
+            
+            closures.push(closure.modules);
+            entry_warnings.push(warnings);
+        }
+        
+        let shared_modules = if self.options.bundle_options.extract_shared {
+            bundler::find_shared_modules(&closures, self.options.bundle_options.min_importers)
+        } else {
+            Vec::new()
+        };
+        
+        let mut outputs = Vec::new();
+        let mut chunk_refs = Vec::new();
+        
+        if !shared_modules.is_empty() {
+            let output_path = output_dir.join(bundler::SHARED_CHUNK_NAME);
+            let code = self.emit_chunk(resolver.as_ref(), &shared_modules, &[])?;
+            info!("{} 个共享模块已提取到: {}", shared_modules.len(), output_path.display());
+            
+            chunk_refs.push(output_path.clone());
+            outputs.push(BundleOutput {
+                kind: ChunkKind::Shared,
+                entry: None,
+                output_path,
+                modules: shared_modules.clone(),
+                shared_chunks: Vec::new(),
+                warnings: Vec::new(),
+                code,
+            });
+        }
+        
+        for ((entry, closure), warnings) in entries.iter().zip(closures).zip(entry_warnings) {
+            let modules: Vec<PathBuf> = closure.into_iter()
+                .filter(|m| !shared_modules.contains(m))
+                .collect();
+            
+            let code = self.emit_chunk(resolver.as_ref(), &modules, &chunk_refs)?;
+            
+            let output_path = entry_output_path(entry, output_dir);
+            info!("入口 {} 已打包到: {}", entry.display(), output_path.display());
+            
+            outputs.push(BundleOutput {
+                kind: ChunkKind::Entry,
+                entry: Some(entry.clone()),
+                output_path,
+                modules,
+                shared_chunks: chunk_refs.clone(),
+                warnings,
+                code,
+            });
+        }
+        
+        Ok(outputs)
+    }
+    
+    /// 编译并拼接一组模块，`chunk_refs`中的共享chunk以副作用导入的形式放在最前面
+    fn emit_chunk(&self, resolver: &dyn Resolver, modules: &[PathBuf], chunk_refs: &[PathBuf]) -> Result<String> {
+        let mut code = String::new();
+        
+        for chunk in chunk_refs {
+            let file_name = chunk.file_name().unwrap_or_default().to_string_lossy();
+            code.push_str(&format!("import \"./{}\";\n", file_name));
+        }
+        
+        for module in modules {
+            let source = resolver.load(module)?;
+            let result = self.with_derived_options(self.options_for_file(module)).compile_string(&source)?;
+            code.push_str(&format!("// 模块: {}\n", module.display()));
+            code.push_str(&result.code);
+            if !result.code.ends_with('\n') {
+                code.push('\n');
+            }
+        }
+        
+        Ok(code)
+    }
+    
+    // 设置选项方法
+    
+    pub fn with_sourcemap(mut self, enable: bool) -> Self {
+        self.options.codegen_options.sourcemap = enable;
+        self
+    }
+    
+    /// 编译时按`options`优化代码
+    pub fn with_optimizer_options(mut self, options: OptimizerOptions) -> Self {
+        self.options.optimizer_options = Some(options);
+        self
+    }
+    
+    /// 设置优化之后运行的转换
+    pub fn with_transformers(mut self, names: Vec<String>) -> Self {
+        self.options.transformers = names;
+        self
+    }
+    
+    /// 未压缩的输出中代码块每层缩进`spaces`个空格
+    pub fn with_indent(mut self, spaces: usize) -> Self {
+        self.options.codegen_options.indent = spaces;
+        self
+    }
+    
+    pub fn with_minify(mut self, enable: bool) -> Self {
+        self.options.codegen_options.minify = enable;
+        self
+    }
+    
+    pub fn with_target(mut self, target: &str) -> Self {
+        self.options.codegen_options.target = target.to_string();
+        self
+    }
+    
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.options.codegen_options.output_format = format;
+        self
+    }
+    
+    /// 限制单个输出文件的大小，超出时拆分为多个编号的chunk
+    pub fn with_max_chunk_bytes(mut self, max_bytes: usize) -> Self {
+        self.options.codegen_options.max_chunk_bytes = Some(max_bytes);
+        self
+    }
+    
+    pub fn with_jsx(mut self, enable: bool) -> Self {
+        self.options.parse_options.jsx = enable;
+        self
+    }
+    
+    pub fn with_typescript(mut self, enable: bool) -> Self {
+        self.options.parse_options.typescript = enable;
+        self
+    }
+    
+    pub fn with_distributed(mut self, enable: bool) -> Self {
+        self.options.distributed = enable;
+        self
+    }
+    
+    pub fn with_gpu(mut self, enable: bool) -> Self {
+        self.options.use_gpu = enable;
+        self
+    }
+    
+    pub fn with_cache(mut self, enable: bool) -> Self {
+        self.options.cache_enabled = enable;
+        self
+    }
+    
+    /// 批量编译时在输出目录中保留输入文件的目录结构
+    pub fn with_preserve_structure(mut self, enable: bool) -> Self {
+        self.options.preserve_structure = enable;
+        self
+    }
+    
+    /// 重新解析生成的代码，无法解析时返回错误而不是输出无效的代码
+    pub fn with_verify_output(mut self, enable: bool) -> Self {
+        self.options.verify_output = enable;
+        self
+    }
+    
+    /// 遇到目标环境不支持的语法时报错，而不是降级为目标支持的写法
+    pub fn with_restrict_to_target(mut self, enable: bool) -> Self {
+        self.options.restrict_to_target = enable;
+        self
+    }
+    
+    /// 不压缩时在输出末尾追加构建指纹注释（lumen版本和配置哈希）
+    pub fn with_fingerprint(mut self, enable: bool) -> Self {
+        self.options.fingerprint = enable;
+        self
+    }
+    
+    /// 把源码不超过`max_bytes`字节的导入模块内联到导入方
+    pub fn with_inline_small_modules(mut self, max_bytes: usize) -> Self {
+        self.options.inline_max_bytes = Some(max_bytes);
+        self
+    }
+    
+    /// 设置缓存容量（字节）
+    pub fn with_cache_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.options.cache_max_bytes = max_bytes;
+        self.cache = Arc::new(Mutex::new(CompileCache::new(max_bytes)));
+        self
+    }
+    
+    /// 将打包警告（如循环导入）视为错误
+    pub fn with_fail_on_warning(mut self, enable: bool) -> Self {
+        self.options.bundle_options.fail_on_warning = enable;
+        self
+    }
+    
+    /// 把这些模块视为外部依赖：不解析、不打包也不内联，导入语句原样保留
+    pub fn with_externals(mut self, externals: Vec<String>) -> Self {
+        self.options.bundle_options.externals = externals;
+        self
+    }
+    
+    /// 将被至少`min_importers`个入口引用的模块提取到共享chunk
+    pub fn with_shared_chunks(mut self, min_importers: usize) -> Self {
+        self.options.bundle_options.extract_shared = true;
+        self.options.bundle_options.min_importers = min_importers;
+        self
+    }
+}
+
+/// IR中去重后的法律注释
+fn unique_legal_comments(ir: &IR) -> Vec<String> {
+    let mut comments = legal_comments_in(&ir.get_root());
+    let mut seen = std::collections::HashSet::new();
+    comments.retain(|c| seen.insert(c.clone()));
+    comments
+}
+
+/// 把法律注释写入`<输出文件>.LEGAL.txt`，并在输出末尾加上指向该文件的注释；没有法律注释时不做任何事
+fn link_legal_comments(output_path: &Path, result: &mut CompileResult) -> Result<()> {
+    if result.legal_comments.is_empty() {
+        return Ok(());
+    }
+    let file_name = output_path.file_name().unwrap_or_default().to_string_lossy();
+    let legal_name = format!("{}.LEGAL.txt", file_name);
+    let legal_path = output_path.with_file_name(&legal_name);
+    let mut content = result.legal_comments.join("\n");
+    content.push('\n');
+    std::fs::write(&legal_path, content)
+        .map_err(|e| anyhow!("写入法律注释文件失败: {}", e))?;
+
+    if !result.code.is_empty() && !result.code.ends_with('\n') {
+        result.code.push('\n');
+    }
+    result.code.push_str(&format!("/*! For license information please see {} */\n", legal_name));
+    result.output_size = result.code.len();
+    Ok(())
+}
+
+/// 运行`transform`，语法树发生变化时把`name`记入`applied`
+fn track_transform<T>(ir: &mut IR, name: &str, applied: &mut Vec<String>, transform: impl FnOnce(&mut IR) -> T) -> T {
+    let before = ir.get_root();
+    let result = transform(ir);
+    if ir.get_root() != before {
+        applied.push(name.to_string());
+    }
+    result
+}
+
+/// 压缩时删除的空白字符的字节偏移，按升序排列
+///
+/// 注释和字符串中的空白保留，如`/*! 版权声明 */`；行注释之后的换行也保留，否则之后的代码会变成注释。
+/// 两侧都是标识符字符（如`var x`）或都是`+`、`-`（如`a - -b`）时保留一个空白，否则会粘成另一个记号。
+fn removable_whitespace(code: &str) -> Vec<usize> {
+    let mut removed = Vec::new();
+    let mut chars = code.char_indices().peekable();
+    // 上一个保留下来的字符
+    let mut previous = None;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ' ' | '\n' | '\t' => {
+                let mut run = vec![i];
+                while let Some((j, _)) = chars.next_if(|&(_, next)| matches!(next, ' ' | '\n' | '\t')) {
+                    run.push(j);
+                }
+                let next = chars.peek().map(|&(_, next)| next);
+                let keep_one = match (previous, next) {
+                    (Some(before), Some(after)) => separates_tokens(before, after),
+                    _ => false,
+                };
+                removed.extend_from_slice(&run[keep_one as usize..]);
+                continue;
+            },
+            '"' | '\'' | '`' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        break;
+                    }
+                }
+            },
+            '/' if chars.next_if(|&(_, next)| next == '*').is_some() => {
+                let mut previous = ' ';
+                for (_, next) in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            },
+            '/' if chars.next_if(|&(_, next)| next == '/').is_some() => {
+                while chars.next_if(|&(_, next)| next != '\n').is_some() {}
+                chars.next();
+            },
+            _ => {},
+        }
+        // 字符串和注释的结尾字符也是非标识符字符，这里只需要区分标识符字符和运算符
+        previous = Some(c);
+    }
+    removed
+}
+
+/// 依次对各段字节计算128位FNV-1a哈希
+fn fnv1a_128(parts: &[&[u8]]) -> u128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    parts.iter()
+        .flat_map(|part| part.iter())
+        .fold(OFFSET_BASIS, |hash, byte| (hash ^ *byte as u128).wrapping_mul(PRIME))
+}
+
+/// 入口打包产物的路径：`output_dir`下与入口同名的`.js`文件
+fn entry_output_path(entry: &Path, output_dir: &Path) -> PathBuf {
+    let mut output_path = output_dir.join(entry.file_stem().unwrap_or_default());
+    output_path.set_extension("js");
+    output_path
+}
+
+/// 带`@lumen-ignore-file`标注的文件原样输出
+fn passthrough_result(source: &str, start: Instant) -> CompileResult {
+    CompileResult {
+        code: source.to_string(),
+        source_map: None,
+        time_ms: start.elapsed().as_millis() as u64,
+        input_size: source.len(),
+        output_size: source.len(),
+        compression_ratio: 0.0,
+        chunks: Vec::new(),
+        peak_memory_bytes: None,
+        legal_comments: Vec::new(),
+        from_cache: false,
+        applied_transforms: Vec::new(),
+        phase_times: HashMap::new(),
+        phase_fractions: HashMap::new(),
+    }
+}
+
+/// 把解析时保留的shebang加到输出的第一行
+fn with_shebang(ir: &IR, output: String) -> String {
+    match ir.get_root().get_string_value("shebang") {
+        Some(line) => format!("{}\n{}", line, output),
+        None => output,
+    }
+}
+
+// 便捷函数
+
+/// 快速编译JavaScript字符串
+pub fn compile_js(source: &str) -> Result<String> {
+    let compiler = Compiler::new();
+    compiler.compile_string(source).map(|r| r.code)
+}
+
+/// 快速编译JavaScript文件
+pub fn compile_file<P: AsRef<Path>>(input: P, output: Option<P>) -> Result<()> {
+    let compiler = Compiler::new();
+    compiler.compile_file(input, output).map(|_| ())
+}
+
+pub fn compile_string(source: &str) -> Result<String> {
+    let compiler = Compiler::new();
+    compiler.compile_string(source).map(|r| r.code)
+} 
+/// 检查生成的代码能否重新解析
+///
+/// 只能验证解析器支持的语法，输出中包含解析器尚不支持的语法时也会报错。
+fn verify_reparses(code: &str) -> Result<()> {
+    parse_string(code)
+        .map(|_| ())
+        .map_err(|e| anyhow!("生成的代码无法重新解析，可能是代码生成或转换的缺陷: {}", e))
+}
+
+/// 递归收集目录中扩展名为js/jsx/ts/tsx的文件
+fn collect_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("读取目录失败 {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| anyhow!("读取目录失败 {}: {}", dir.display(), e))?.path();
+        if path.is_dir() {
+            collect_source_files(&path, files)?;
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| matches!(e, "js" | "jsx" | "ts" | "tsx")) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 拆分输出的chunk文件路径：`<stem>.<n>.js`（n从1开始）
+fn chunk_path(output: &Path, index: usize) -> PathBuf {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    output.with_file_name(format!("{}.{}.js", stem, index + 1))
+}
+
+/// 写入拆分后的chunk，并在原输出路径写入按顺序加载它们的加载器
+fn write_chunked_output(output: &Path, chunks: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let path = chunk_path(output, i);
+        std::fs::write(&path, chunk)
+            .map_err(|e| anyhow!("写入chunk失败 {}: {}", path.display(), e))?;
+        paths.push(path);
+    }
+    
+    let names: Vec<String> = paths.iter()
+        .filter_map(|p| p.file_name().and_then(|n| n.to_str()))
+        .map(|n| format!("\"./{}\"", n))
+        .collect();
+    
+    // async=false保证脚本按插入顺序执行
+    let loader = format!(
+        "[{}].forEach(function (src) {{\nvar script = document.createElement(\"script\");\nscript.src = src;\nscript.async = false;\ndocument.head.appendChild(script);\n}});\n",
+        names.join(", ")
+    );
+    std::fs::write(output, loader)
+        .map_err(|e| anyhow!("写入chunk加载器失败 {}: {}", output.display(), e))?;
+    debug!("输出已拆分为 {} 个chunk: {}", chunks.len(), output.display());
+    
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::{TargetFeatures, shortest_number, test_utils::ident};
+
+    /// 构造 `<kind> <name> = <value>;`（不挂到父节点）
+    fn var_decl(ir: &mut IR, kind: &str, name: &str, value: f64) -> usize {
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        let ident = ir.create_node(NodeType::Identifier);
+        let lit = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String(kind.to_string()));
+        ir.get_node_mut(ident).unwrap().set_value("name", NodeValue::String(name.to_string()));
+        ir.get_node_mut(lit).unwrap().set_value("value", NodeValue::Number(value));
+        ir.add_child(decl, ident);
+        ir.add_child(decl, lit);
+        decl
+    }
+
+    /// 构造只包含 `export const answer = 42;` 的模块
+    fn single_export_module() -> IR {
+        let mut ir = IR::new();
+        let decl = var_decl(&mut ir, "const", "answer", 42.0);
+        let export = ir.create_node(NodeType::ExportDeclaration);
+        ir.add_child(export, decl);
+        ir.add_child(ir.root_id, export);
+        ir
+    }
+
+    #[test]
+    fn test_esm_output_keeps_export() {
+        let ir = single_export_module();
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "export const answer = 42;\n");
+    }
+
+    #[test]
+    fn test_iife_output_assigns_global() {
+        let ir = single_export_module();
+        let options = CodegenOptions {
+            output_format: OutputFormat::Iife { global_name: "MyLib".to_string() },
+            ..Default::default()
+        };
+        let code = CodeGenerator::new(options).generate(&ir);
+
+        assert!(code.starts_with("window.MyLib = (function () {"), "应赋值给全局变量: {}", code);
+        assert!(code.contains("var exports = {};"));
+        assert!(code.contains("exports.answer = answer;"));
+        assert!(code.contains("return exports;"));
+        assert!(code.trim_end().ends_with("})();"));
+        assert!(!code.contains("export const"), "IIFE内部不应保留export关键字");
+    }
+
+    #[test]
+    fn test_max_chunk_bytes_splits_at_statements() {
+        let mut ir = IR::new();
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            let decl = var_decl(&mut ir, "let", name, i as f64);
+            ir.add_child(ir.root_id, decl);
+        }
+        let whole = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+
+        let options = CodegenOptions { max_chunk_bytes: Some(12), ..Default::default() };
+        let chunks = CodeGenerator::new(options).generate_chunks(&ir);
+        assert!(chunks.len() > 1, "应拆分为多个chunk: {:?}", chunks);
+        assert!(chunks.iter().all(|c| c.ends_with(";\n")), "不应在语句中间拆分: {:?}", chunks);
+        assert_eq!(chunks.concat(), whole, "所有chunk拼接后应还原完整程序");
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("main.js");
+        let paths = write_chunked_output(&output, &chunks).unwrap();
+        assert_eq!(paths.len(), chunks.len());
+        let written: String = paths.iter().map(|p| std::fs::read_to_string(p).unwrap()).collect();
+        assert_eq!(written, whole);
+
+        let loader = std::fs::read_to_string(&output).unwrap();
+        assert!(loader.contains("\"./main.1.js\", \"./main.2.js\""), "加载器应按顺序引用chunk: {}", loader);
+
+        // 含有导入声明的模块不能作为经典脚本加载，保持单个chunk
+        let source = "import { x } from \"./x.js\";\nlet a = x;\nlet b = a;\nlet c = b;\n";
+        let result = Compiler::new().with_cache(false).with_max_chunk_bytes(12).compile_string(source).unwrap();
+        assert!(result.chunks.is_empty(), "{:?}", result.chunks);
+        assert_eq!(result.code, Compiler::new().with_cache(false).compile_string(source).unwrap().code);
     }
-}
 
-// 便捷函数
+    #[test]
+    fn test_target_feature_override_only_downlevels_arrows() {
+        // const f = (x) => x; let y = f;
+        let mut ir = IR::new();
+        let param = ir.create_node(NodeType::Identifier);
+        let body = ir.create_node(NodeType::Identifier);
+        ir.get_node_mut(param).unwrap().set_value("name", NodeValue::String("x".to_string()));
+        ir.get_node_mut(body).unwrap().set_value("name", NodeValue::String("x".to_string()));
+        let arrow = ir.create_node(NodeType::ArrowFunctionExpression);
+        ir.add_child(arrow, param);
+        ir.add_child(arrow, body);
+        
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        let name = ir.create_node(NodeType::Identifier);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String("const".to_string()));
+        ir.get_node_mut(name).unwrap().set_value("name", NodeValue::String("f".to_string()));
+        ir.add_child(decl, name);
+        ir.add_child(decl, arrow);
+        ir.add_child(ir.root_id, decl);
+        let decl = var_decl(&mut ir, "let", "y", 1.0);
+        ir.add_child(ir.root_id, decl);
+        
+        let base = CodegenOptions { target: "esnext".to_string(), ..Default::default() };
+        let code = CodeGenerator::new(base.clone()).generate(&ir);
+        assert_eq!(code, "const f = (x) => x;\nlet y = 1;\n");
+        
+        let mut features = TargetFeatures::for_target("esnext");
+        features.arrow_functions = false;
+        let options = CodegenOptions { target_features: Some(features), ..base };
+        let code = CodeGenerator::new(options).generate(&ir);
+        assert_eq!(code, "const f = function (x) {\nreturn x;\n};\nlet y = 1;\n", "只应降级箭头函数");
+    }
 
-/// 快速编译JavaScript字符串
-pub fn compile_js(source: &str) -> Result<String> {
-    let compiler = Compiler::new();
-    compiler.compile_string(source).map(|r| r.code)
-}
+    /// 构造 `object.property` 或 `object[property]`
+    fn member(ir: &mut IR, object: usize, property: usize, computed: bool) -> usize {
+        let id = ir.create_node(NodeType::MemberExpression);
+        ir.get_node_mut(id).unwrap().set_value("computed", NodeValue::Boolean(computed));
+        ir.add_child(id, object);
+        ir.add_child(id, property);
+        id
+    }
 
-/// 快速编译JavaScript文件
-pub fn compile_file<P: AsRef<Path>>(input: P, output: Option<P>) -> Result<()> {
-    let compiler = Compiler::new();
-    compiler.compile_file(input, output).map(|_| ())
-}
+    #[test]
+    fn test_member_expression_round_trip() {
+        let mut ir = IR::new();
 
-pub fn compile_string(source: &str) -> Result<String> {
-    let compiler = Compiler::new();
-    compiler.compile_string(source).map(|r| r.code)
-} 
\ No newline at end of file
+        // a.b.c()
+        let (a, b, c) = (ident(&mut ir, "a"), ident(&mut ir, "b"), ident(&mut ir, "c"));
+        let a_b = member(&mut ir, a, b, false);
+        let a_b_c = member(&mut ir, a_b, c, false);
+        let call = ir.create_node(NodeType::CallExpression);
+        ir.add_child(call, a_b_c);
+        ir.add_child(ir.root_id, call);
+
+        // a["x"]
+        let a = ident(&mut ir, "a");
+        let x = ir.create_node(NodeType::StringLiteral);
+        ir.get_node_mut(x).unwrap().set_value("value", NodeValue::String("x".to_string()));
+        let a_x = member(&mut ir, a, x, true);
+        ir.add_child(ir.root_id, a_x);
+
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "a.b.c();\na[\"x\"];\n", "应保留点号访问与计算属性访问的区别");
+    }
+
+    #[test]
+    fn test_conditional_expression_round_trip() {
+        // const r = ok ? 1 : (a ? b : c);
+        let mut ir = IR::new();
+        let (a, b, c) = (ident(&mut ir, "a"), ident(&mut ir, "b"), ident(&mut ir, "c"));
+        let inner = ir.create_node(NodeType::ConditionalExpression);
+        for child in [a, b, c] {
+            ir.add_child(inner, child);
+        }
+        let ok = ident(&mut ir, "ok");
+        let one = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(one).unwrap().set_value("value", NodeValue::Number(1.0));
+        let outer = ir.create_node(NodeType::ConditionalExpression);
+        for child in [ok, one, inner] {
+            ir.add_child(outer, child);
+        }
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String("const".to_string()));
+        let r = ident(&mut ir, "r");
+        ir.add_child(decl, r);
+        ir.add_child(decl, outer);
+        ir.add_child(ir.root_id, decl);
+
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "const r = ok ? 1 : a ? b : c;\n");
+    }
+
+    #[test]
+    fn test_sequence_expression_round_trip() {
+        let mut ir = IR::new();
+
+        // a, b;
+        let (a, b) = (ident(&mut ir, "a"), ident(&mut ir, "b"));
+        let seq = ir.create_node(NodeType::SequenceExpression);
+        ir.add_child(seq, a);
+        ir.add_child(seq, b);
+        ir.add_child(ir.root_id, seq);
+
+        // f((a, b));
+        let (a, b) = (ident(&mut ir, "a"), ident(&mut ir, "b"));
+        let seq = ir.create_node(NodeType::SequenceExpression);
+        ir.add_child(seq, a);
+        ir.add_child(seq, b);
+        let call = ir.create_node(NodeType::CallExpression);
+        let f = ident(&mut ir, "f");
+        ir.add_child(call, f);
+        ir.add_child(call, seq);
+        ir.add_child(ir.root_id, call);
+
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "a, b;\nf((a, b));\n", "作为参数的逗号表达式必须保留括号");
+    }
+
+    #[test]
+    fn test_estimated_output_size_close_to_codegen() {
+        let mut statements = IR::new();
+        for (i, name) in ["a", "b", "c"].iter().enumerate() {
+            let decl = var_decl(&mut statements, "let", name, i as f64);
+            statements.add_child(statements.root_id, decl);
+        }
+        let (a, b, c) = (ident(&mut statements, "console"), ident(&mut statements, "log"), ident(&mut statements, "a"));
+        let callee = member(&mut statements, a, b, false);
+        let call = statements.create_node(NodeType::CallExpression);
+        statements.add_child(call, callee);
+        statements.add_child(call, c);
+        statements.add_child(statements.root_id, call);
+
+        let formats = [
+            CodegenOptions::default(),
+            CodegenOptions { minify: true, ..Default::default() },
+            CodegenOptions { target: "es5".to_string(), ..Default::default() },
+            CodegenOptions { output_format: OutputFormat::Iife { global_name: "Lib".to_string() }, ..Default::default() },
+            CodegenOptions { output_format: OutputFormat::Umd { global_name: "Lib".to_string() }, minify: true, ..Default::default() },
+        ];
+        for ir in [single_export_module(), statements] {
+            for options in &formats {
+                let actual = CodeGenerator::new(options.clone()).generate(&ir).len();
+                let estimate = ir.estimated_output_size(options);
+                let tolerance = (actual / 10).max(2);
+                assert!(
+                    estimate.abs_diff(actual) <= tolerance,
+                    "估算值 {} 与实际大小 {} 相差过大（{:?}）", estimate, actual, options
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_minified_numbers_use_shortest_form() {
+        let mut ir = IR::new();
+        for (name, value) in [("a", 1000000.0), ("b", 0.5), ("c", 255.0), ("d", 4096.0), ("e", -0.25)] {
+            let decl = var_decl(&mut ir, "var", name, value);
+            ir.add_child(ir.root_id, decl);
+        }
+
+        let minified = CodeGenerator::new(CodegenOptions { minify: true, ..Default::default() }).generate(&ir);
+        assert_eq!(minified, "var a=1e6;var b=.5;var c=255;var d=4096;var e=-.25;", "0x1000不比4096短，应保留十进制");
+        let plain = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert!(plain.contains("var a = 1000000;") && plain.contains("var b = 0.5;"), "未压缩时按原样输出");
+
+        assert_eq!(shortest_number(1000.0), "1e3");
+        assert_eq!(shortest_number(100.0), "100", "长度相同时保留十进制");
+        assert_eq!(shortest_number(0.00001), "1e-5");
+        assert_eq!(shortest_number(1.5e14), "1.5e14");
+        assert_eq!(shortest_number(4503599627370495.0), "0xfffffffffffff", "十六进制更短时使用十六进制");
+    }
+
+    #[test]
+    fn test_compile_reader_writes_output() {
+        let source = "var x = 1;";
+        let mut output = Vec::new();
+        let compiler = Compiler::new().with_sourcemap(true);
+        let result = compiler.compile_reader(std::io::Cursor::new(source), &mut output, Some("input.js")).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), result.code, "写入的内容应与编译结果一致");
+        assert_eq!(result.input_size, source.len());
+        let map = result.source_map.expect("应生成Source Map");
+        assert!(map.contains("\"input.js\""), "Source Map应使用传入的文件名: {}", map);
+    }
+
+    #[test]
+    fn test_ignore_file_annotation_passes_source_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let vendored_source = "// 由工具生成\n/* @lumen-ignore-file */\nvar  vendored = 1 ;\n";
+        let vendored = dir.path().join("vendor.js");
+        let sibling = dir.path().join("app.js");
+        std::fs::write(&vendored, vendored_source).unwrap();
+        std::fs::write(&sibling, "var  app = 1 ;\n").unwrap();
+
+        let out_dir = dir.path().join("dist");
+        let compiler = Compiler::new().with_minify(true);
+        compiler.compile_files(&[vendored.clone(), sibling.clone()], Some(out_dir.clone())).unwrap();
+
+        assert_eq!(std::fs::read_to_string(out_dir.join("vendor.js")).unwrap(), vendored_source, "带标注的文件应原样输出");
+        let compiled = std::fs::read_to_string(out_dir.join("app.js")).unwrap();
+        assert_eq!(compiled, compiler.compile_string("var  app = 1 ;\n").unwrap().code, "其他文件应正常编译");
+
+        assert!(!has_ignore_file_annotation("var x = 1;\n/* @lumen-ignore-file */\n"), "第一条语句之后的标注无效");
+    }
+
+    /// 删除程序中所有语句的转换
+    struct ClearProgram;
+
+    impl Transform for ClearProgram {
+        fn name(&self) -> &str {
+            "clear-program"
+        }
+
+        fn apply(&self, ir: &mut IR) -> Result<()> {
+            let statements: Vec<usize> = ir.get_root().children.iter().map(|child| child.0.id).collect();
+            for id in statements {
+                ir.remove_node(id);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_file_compiles_use_registered_transforms() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("src").join("app.js");
+        std::fs::create_dir_all(input.parent().unwrap()).unwrap();
+        std::fs::write(&input, "var app = 1;\n").unwrap();
+
+        let compiler = Compiler::new()
+            .with_transform(ClearProgram)
+            .with_transformers(vec!["clear-program".to_string()]);
+        assert_eq!(compiler.compile_string("var app = 1;\n").unwrap().code, "");
+        let output = dir.path().join("app.out.js");
+        assert_eq!(compiler.compile_file(&input, Some(&output)).unwrap().code, "", "单文件编译应使用注册的转换");
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "");
+        let mut written = Vec::new();
+        compiler.compile_reader("var app = 1;\n".as_bytes(), &mut written, Some("app.js")).unwrap();
+        assert!(written.is_empty());
+
+        let dist = dir.path().join("dist");
+        compiler.compile_dir(dir.path().join("src"), dist.clone()).unwrap();
+        assert_eq!(std::fs::read_to_string(dist.join("app.js")).unwrap(), "");
+    }
+
+    #[test]
+    fn test_preserve_structure_avoids_output_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        for (sub, code) in [("a", "var a = 1;\n/* @lumen-ignore-file */"), ("b", "/* @lumen-ignore-file */\nvar b = 2;\n")] {
+            std::fs::create_dir_all(src.join(sub)).unwrap();
+            std::fs::write(src.join(sub).join("x.ts"), code).unwrap();
+        }
+        let inputs = vec![src.join("a").join("x.ts"), src.join("b").join("x.ts")];
+
+        let out_dir = dir.path().join("dist");
+        Compiler::new().with_preserve_structure(true).compile_files(&inputs, Some(out_dir.clone())).unwrap();
+        let a = std::fs::read_to_string(out_dir.join("a").join("x.js")).unwrap();
+        let b = std::fs::read_to_string(out_dir.join("b").join("x.js")).unwrap();
+        assert_ne!(a, b, "同名文件应输出到不同的镜像路径");
+        assert!(b.contains("var b = 2;"));
+
+        let mirrored = dir.path().join("mirrored");
+        Compiler::new().compile_dir(src.clone(), mirrored.clone()).unwrap();
+        assert!(mirrored.join("a").join("x.js").is_file() && mirrored.join("b").join("x.js").is_file());
+    }
+
+    #[test]
+    fn test_compile_files_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs: Vec<PathBuf> = (0..3).map(|i| dir.path().join(format!("f{}.js", i))).collect();
+        for input in &inputs {
+            std::fs::write(input, "var x = 1;").unwrap();
+        }
+        let missing = dir.path().join("missing.js");
+        let all: Vec<PathBuf> = inputs.iter().cloned().chain([missing]).collect();
+
+        let mut reports = Vec::new();
+        Compiler::new().compile_files_with_progress(&all, None, |p| reports.push(p.clone())).unwrap();
+        let completed: Vec<usize> = reports.iter().map(|p| p.completed).collect();
+        assert_eq!(completed, vec![1, 2, 3, 4], "失败的文件也应报告进度");
+        assert_eq!(reports.last().unwrap().eta, Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_peak_memory_scales_with_input_size() {
+        let compiler = Compiler::new().with_cache(false);
+        let small = compiler.compile_string("var x = 1;").unwrap();
+        let large_source = format!("var s = \"{}\";", "x".repeat(100_000));
+        let large = compiler.compile_string(&large_source).unwrap();
+
+        let small_peak = small.peak_memory_bytes.expect("应估算峰值内存");
+        let large_peak = large.peak_memory_bytes.expect("应估算峰值内存");
+        assert!(small_peak >= "var x = 1;".len());
+        assert!(large_peak >= large_source.len());
+        assert!(large_peak > small_peak * 10, "峰值内存应随输入增大: {} vs {}", small_peak, large_peak);
+    }
+
+    #[test]
+    fn test_fingerprint_is_deterministic() {
+        let compile = || {
+            let mut options = CompileOptions::default();
+            options.extra_options.insert("a".to_string(), "1".to_string());
+            options.extra_options.insert("b".to_string(), "2".to_string());
+            Compiler::with_options(options).with_cache(false).with_fingerprint(true)
+                .compile_string("var x = 42;").unwrap().code
+        };
+        let first = compile();
+        let fingerprint = first.lines().last().unwrap();
+        assert!(fingerprint.starts_with(&format!("//# lumen {} ", env!("CARGO_PKG_VERSION"))), "{}", first);
+        for _ in 0..3 {
+            assert_eq!(compile(), first, "相同输入和配置应产生相同的指纹");
+        }
+
+        let other = Compiler::new().with_cache(false).with_fingerprint(true).with_target("es5")
+            .compile_string("var x = 42;").unwrap().code;
+        assert_ne!(other.lines().last(), Some(fingerprint), "配置不同时哈希应不同");
+
+        let minified = Compiler::new().with_fingerprint(true).with_minify(true)
+            .compile_string("var x = 42;").unwrap().code;
+        assert!(!minified.contains("//# lumen"), "压缩输出不追加指纹");
+    }
+
+    #[test]
+    fn test_shebang_preserved_as_first_line() {
+        let source = "#!/usr/bin/env node\nvar x = 42;\n";
+        for compiler in [Compiler::new(), Compiler::new().with_minify(true), Compiler::new().with_fingerprint(true)] {
+            let code = compiler.compile_string(source).unwrap().code;
+            assert_eq!(code.lines().next(), Some("#!/usr/bin/env node"), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_strict_directive_not_duplicated() {
+        // "use strict"; var x = 1;
+        let mut ir = IR::new();
+        let directive = ir.create_node(NodeType::StringLiteral);
+        ir.get_node_mut(directive).unwrap().set_value("value", NodeValue::String("use strict".to_string()));
+        ir.add_child(ir.root_id, directive);
+        let decl = var_decl(&mut ir, "var", "x", 1.0);
+        ir.add_child(ir.root_id, decl);
+
+        let generate = |strict: StrictMode, ir: &IR| {
+            CodeGenerator::new(CodegenOptions { strict, target: "es2015".to_string(), ..Default::default() }).generate(ir)
+        };
+        for strict in [StrictMode::Preserve, StrictMode::Always] {
+            let code = generate(strict, &ir);
+            assert_eq!(code.matches("use strict").count(), 1, "{:?}: {}", strict, code);
+        }
+        assert!(!generate(StrictMode::Never, &ir).contains("use strict"));
+
+        let mut sloppy = IR::new();
+        let decl = var_decl(&mut sloppy, "var", "x", 1.0);
+        sloppy.add_child(sloppy.root_id, decl);
+        assert_eq!(generate(StrictMode::Always, &sloppy), "\"use strict\";\nvar x = 1;\n");
+        assert_eq!(generate(StrictMode::Preserve, &sloppy), "var x = 1;\n", "不再随目标环境添加指令");
+    }
+
+    #[test]
+    fn test_minified_and_unminified_variants_in_one_pass() {
+        let mut ir = IR::new();
+        for (name, value) in [("a", 1.0), ("b", 2.0)] {
+            let decl = var_decl(&mut ir, "const", name, value);
+            ir.add_child(ir.root_id, decl);
+        }
+        let variants = vec![
+            CodegenOptions::default(),
+            CodegenOptions { minify: true, ..Default::default() },
+        ];
+        let results = Compiler::new().generate_variants("const a = 1; const b = 2;", ir, variants, Instant::now()).unwrap();
+
+        assert_eq!(results.len(), 2);
+        let (plain, minified) = (&results[0].code, &results[1].code);
+        assert_eq!(plain, "const a = 1;\nconst b = 2;\n");
+        assert!(minified.len() < plain.len(), "压缩版应更小: {:?}", minified);
+        // 两者只在空白上不同
+        assert_eq!(plain.split_whitespace().collect::<String>(), minified.split_whitespace().collect::<String>());
+        assert_eq!(results[1].output_size, minified.len());
+    }
+
+    #[test]
+    fn test_verify_output_catches_invalid_codegen() {
+        // 模拟有缺陷的重命名：生成的名字不是合法的标识符
+        let mut ir = IR::new();
+        let decl = var_decl(&mut ir, "var", "1total", 3.0);
+        ir.add_child(ir.root_id, decl);
+
+        let variants = || vec![CodegenOptions::default()];
+        let unchecked = Compiler::new().generate_variants("", ir.clone(), variants(), Instant::now()).unwrap();
+        assert_eq!(unchecked[0].code, "var 1total = 3;\n");
+
+        let err = Compiler::new().with_verify_output(true)
+            .generate_variants("", ir, variants(), Instant::now())
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("生成的代码无法重新解析"), "{}", err);
+        assert!(err.contains("(1:4)"), "错误应指出出错的位置: {}", err);
+
+        let valid = Compiler::new().with_verify_output(true).compile_string("let x = (1 + 2) * 3;").unwrap();
+        assert_eq!(valid.code, "let x = (1 + 2) * 3;\n");
+    }
+
+    #[test]
+    fn test_linked_legal_comments_written_to_sidecar() {
+        let mut ir = IR::new();
+        let decl = var_decl(&mut ir, "var", "lib", 1.0);
+        ir.add_leading_comment(decl, "! lib v1.0 | MIT License", true);
+        ir.add_leading_comment(decl, " 普通注释", true);
+        ir.add_child(ir.root_id, decl);
+        let other = var_decl(&mut ir, "var", "dep", 2.0);
+        ir.add_leading_comment(other, " @license Apache-2.0 ", true);
+        ir.add_child(ir.root_id, other);
+
+        let mut options = CompileOptions::default();
+        options.codegen_options.legal_comments = LegalComments::Linked;
+        let mut ctx = CompilerContext::new(options.clone());
+        let mut result = Compiler::generate_result(&options, "", &ir, &mut ctx, Instant::now());
+        assert!(!result.code.contains("MIT"), "链接模式下输出中不保留法律注释: {}", result.code);
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("bundle.js");
+        link_legal_comments(&output, &mut result).unwrap();
+        let sidecar = std::fs::read_to_string(dir.path().join("bundle.js.LEGAL.txt")).unwrap();
+        assert_eq!(sidecar, "/*! lib v1.0 | MIT License*/\n/* @license Apache-2.0 */\n");
+        assert!(result.code.ends_with("/*! For license information please see bundle.js.LEGAL.txt */\n"), "{}", result.code);
+
+        let eof = CodeGenerator::new(CodegenOptions { legal_comments: LegalComments::Eof, ..Default::default() }).generate(&ir);
+        assert_eq!(eof, "/* 普通注释*/\nvar lib = 1;\nvar dep = 2;\n/*! lib v1.0 | MIT License*/\n/* @license Apache-2.0 */\n");
+        let inline = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(inline, "/*! lib v1.0 | MIT License*/\n/* 普通注释*/\nvar lib = 1;\n/* @license Apache-2.0 */\nvar dep = 2;\n");
+        let legal_only = CodeGenerator::new(CodegenOptions { preserve_comments: false, ..Default::default() }).generate(&ir);
+        assert_eq!(legal_only, "/*! lib v1.0 | MIT License*/\nvar lib = 1;\n/* @license Apache-2.0 */\nvar dep = 2;\n");
+    }
+
+    /// 构造对象方法或访问器属性，方法体为 `{ <name>(<params>); }`
+    fn method_property(ir: &mut IR, kind: &str, key: &str, params: &[&str], call: &str) -> usize {
+        let function = ir.create_node(NodeType::FunctionDeclaration);
+        for param in params {
+            let param = ident(ir, param);
+            ir.add_child(function, param);
+        }
+        let callee = ident(ir, call);
+        let call = ir.create_node(NodeType::CallExpression);
+        ir.add_child(call, callee);
+        for param in params {
+            let arg = ident(ir, param);
+            ir.add_child(call, arg);
+        }
+        let body = ir.create_node(NodeType::BlockStatement);
+        ir.add_child(body, call);
+        ir.add_child(function, body);
+
+        let property = ir.create_node(NodeType::Property);
+        let node = ir.get_node_mut(property).unwrap();
+        node.set_value("kind", NodeValue::String(kind.to_string()));
+        node.set_value("method", NodeValue::Boolean(kind == "init"));
+        let key = ident(ir, key);
+        ir.add_child(property, key);
+        ir.add_child(property, function);
+        property
+    }
+
+    #[test]
+    fn test_object_accessors_and_methods_round_trip() {
+        let mut ir = IR::new();
+        let object = ir.create_node(NodeType::ObjectExpression);
+        for (kind, key, params, call) in [("get", "value", &[][..], "read"), ("set", "value", &["v"][..], "write"), ("init", "reset", &[][..], "clear")] {
+            let property = method_property(&mut ir, kind, key, params, call);
+            ir.add_child(object, property);
+        }
+        let name = ident(&mut ir, "counter");
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        ir.add_child(decl, name);
+        ir.add_child(decl, object);
+        ir.add_child(ir.root_id, decl);
+
+        let options = CodegenOptions::default();
+        let code = CodeGenerator::new(options.clone()).generate(&ir);
+        assert_eq!(
+            code,
+            "var counter = { get value() {\nread();\n}, set value(v) {\nwrite(v);\n}, reset() {\nclear();\n} };\n"
+        );
+        assert_eq!(ir.estimated_output_size(&options), code.len());
+
+        // 方法的种类保留在IR中
+        let object = ir.get_node(object).unwrap();
+        let kinds: Vec<_> = object.children.iter()
+            .map(|p| (p.0.get_string_value("kind").unwrap(), p.0.get_boolean_value("method").unwrap()))
+            .collect();
+        assert_eq!(kinds, vec![("get", false), ("set", false), ("init", true)]);
+    }
+
+    #[test]
+    fn test_bundle_entirely_from_memory_resolver() {
+        let resolver = MemoryResolver::new()
+            .with_module("/virtual/src/main.js", "import { factor } from './lib/math';\nimport '../config';\nvar answer = factor * 21;\n")
+            .with_module("/virtual/src/lib/math.js", "export const factor = 2;\n")
+            .with_module("/virtual/config/index.js", "var debug = false;\n");
+        assert_eq!(resolver.resolve("./lib/math", Path::new("/virtual/src/main.js")), Some(PathBuf::from("/virtual/src/lib/math.js")));
+        assert!(resolver.resolve("./missing", Path::new("/virtual/src/main.js")).is_none());
+
+        let output_dir = Path::new("/virtual/dist");
+        let compiler = Compiler::new().with_resolver(resolver);
+        let outputs = compiler.bundle_entries(vec![PathBuf::from("/virtual/src/./main.js")], output_dir).unwrap();
+
+        assert_eq!(outputs.len(), 1);
+        let bundle = &outputs[0];
+        assert_eq!(bundle.output_path, output_dir.join("main.js"));
+        assert_eq!(bundle.modules, ["/virtual/src/lib/math.js", "/virtual/config/index.js", "/virtual/src/main.js"].map(PathBuf::from));
+        assert!(bundle.code.contains("// 模块: /virtual/src/lib/math.js\nexport const factor = 2;"), "{}", bundle.code);
+        assert!(bundle.code.contains("var debug = false;") && bundle.code.contains("var answer = factor * 21;"), "{}", bundle.code);
+        assert!(!output_dir.exists(), "打包内存中的模块不应写入磁盘");
+
+        assert!(compiler.bundle_entries(vec![PathBuf::from("/virtual/other.js")], output_dir).is_err());
+    }
+
+    #[test]
+    fn test_entries_with_same_file_name_are_rejected() {
+        let resolver = MemoryResolver::new()
+            .with_module("/virtual/a/index.js", "var a = 1;\n")
+            .with_module("/virtual/b/index.js", "var b = 2;\n");
+        let compiler = Compiler::new().with_resolver(resolver);
+        let entries = vec![PathBuf::from("/virtual/a/index.js"), PathBuf::from("/virtual/b/index.js")];
+        let err = compiler.bundle_entries(entries, Path::new("/virtual/dist")).unwrap_err();
+        assert!(err.to_string().contains("/virtual/dist/index.js"), "{}", err);
+
+        let dir = tempfile::tempdir().unwrap();
+        let entries = vec![PathBuf::from("/virtual/a/index.js"), PathBuf::from("/virtual/b/index.js")];
+        assert!(compiler.compile_entries(entries, dir.path().join("dist")).is_err());
+        assert!(!dir.path().join("dist/index.js").exists(), "冲突时不应写入任何产物");
+    }
+
+    #[test]
+    fn test_external_imports_left_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.js");
+        std::fs::write(&main, "import React from 'react';\nimport { double } from './helper';\n").unwrap();
+        std::fs::write(dir.path().join("helper.js"), "export function double(n) { return n * 2; }\n").unwrap();
+        // 与外部依赖同名的本地文件也不应被解析
+        std::fs::create_dir(dir.path().join("react")).unwrap();
+        std::fs::write(dir.path().join("react").join("index.js"), "").unwrap();
+
+        let resolver = FsResolver::new().with_externals(vec!["react".to_string(), "./react".to_string()]);
+        assert!(resolver.resolve("./react", &main).is_none());
+        assert!(FsResolver::new().resolve("./react", &main).is_some());
+        let closure = resolver.dependency_closure(&main).unwrap();
+        assert_eq!(closure.externals, vec!["react".to_string()]);
+        let names: Vec<_> = closure.modules.iter().map(|m| m.file_name().unwrap().to_owned()).collect();
+        assert_eq!(names, vec!["helper.js", "main.js"]);
+
+        // import React from "react"; import { answer } from "./helper";
+        let mut ir = IR::new();
+        for (specifier, local, imported) in [("react", "React", "default"), ("./helper", "answer", "answer")] {
+            let import = ir.create_node(NodeType::ImportDeclaration);
+            ir.get_node_mut(import).unwrap().set_value("source", NodeValue::String(specifier.to_string()));
+            let ident = ir.create_node(NodeType::Identifier);
+            let node = ir.get_node_mut(ident).unwrap();
+            node.set_value("name", NodeValue::String(local.to_string()));
+            node.set_value("imported", NodeValue::String(imported.to_string()));
+            ir.add_child(import, ident);
+            ir.add_child(ir.root_id, import);
+        }
+        let inlined = inline_imports(&mut ir, |specifier| {
+            if resolver.is_external(specifier) {
+                return None;
+            }
+            let mut helper = IR::new();
+            let decl = var_decl(&mut helper, "const", "answer", 42.0);
+            let export = helper.create_node(NodeType::ExportDeclaration);
+            helper.add_child(export, decl);
+            helper.add_child(helper.root_id, export);
+            Some(helper)
+        });
+        assert_eq!(inlined, vec!["./helper".to_string()]);
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, "import React from \"react\";\nconst answer = 42;\n");
+    }
+
+    #[test]
+    fn test_cache_stats_count_repeated_compiles() {
+        let compiler = Compiler::new();
+        for source in ["var a = 1;", "var b = 2;", "var a = 1;", "var c = 3;", "var b = 2;", "var a = 1;"] {
+            compiler.compile_string(source).unwrap();
+        }
+
+        let stats = compiler.cache_stats();
+        assert_eq!(stats.misses, 3, "每个不同的输入首次编译都应未命中");
+        assert_eq!(stats.hits, 3, "重复的输入应命中缓存");
+        assert_eq!(stats.evictions, 0);
+
+        let disabled = Compiler::new().with_cache(false);
+        disabled.compile_string("var a = 1;").unwrap();
+        disabled.compile_string("var a = 1;").unwrap();
+        assert_eq!(disabled.cache_stats(), CacheStats::default(), "禁用缓存时不应记录统计");
+    }
+
+    #[test]
+    fn test_cache_evicts_within_byte_budget() {
+        // 每条输出11字节，最多容纳两条
+        let max_bytes = 25;
+        let compiler = Compiler::new().with_cache_max_bytes(max_bytes);
+        for source in ["var a = 1;", "var b = 2;", "var c = 3;", "var d = 4;"] {
+            compiler.compile_string(source).unwrap();
+            assert!(compiler.cache_stats().bytes <= max_bytes, "{:?}", compiler.cache_stats());
+        }
+
+        let stats = compiler.cache_stats();
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.bytes, 22);
+        assert!(!compiler.compile_string("var a = 1;").unwrap().from_cache, "被淘汰的结果应重新编译");
+        assert!(compiler.compile_string("var a = 1;").unwrap().from_cache);
+    }
+
+    #[test]
+    fn test_batch_compiles_share_the_compiler_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.js");
+        let b = dir.path().join("b.js");
+        std::fs::write(&a, "var a = 1;\n").unwrap();
+        std::fs::write(&b, "var b = 2;\n").unwrap();
+
+        let compiler = Compiler::new();
+        let results = compiler.compile_files(&[a.clone(), b.clone(), a.clone()], None).unwrap();
+        assert_eq!(results.iter().map(|r| r.from_cache).collect::<Vec<_>>(), vec![false, false, true]);
+        let stats = compiler.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2), "重复的输入文件应命中编译器的缓存");
+        assert!(stats.bytes > 0);
+
+        assert!(compiler.compile_file(&b, None).unwrap().from_cache);
+        assert_eq!(compiler.cache_stats().hits, 2);
+    }
+
+    #[test]
+    fn test_repeated_compile_reports_cache_hit() {
+        let compiler = Compiler::new();
+        let first = compiler.compile_string("var answer = 42;").unwrap();
+        let second = compiler.compile_string("var answer = 42;").unwrap();
+        assert!(!first.from_cache);
+        assert!(second.from_cache, "相同的输入和选项应命中缓存");
+        assert_eq!(first.code, second.code);
+
+        compiler.clear_cache();
+        assert_eq!(compiler.cache_stats().bytes, 0);
+        assert!(!compiler.compile_string("var answer = 42;").unwrap().from_cache, "清空后应重新编译");
+
+        let disabled = Compiler::new().with_cache(false);
+        disabled.compile_string("var answer = 42;").unwrap();
+        assert!(!disabled.compile_string("var answer = 42;").unwrap().from_cache, "禁用缓存时不应使用缓存");
+    }
+
+    #[test]
+    fn test_cache_hit_keeps_source_map_chunks_and_legal_comments() {
+        let source = "/*! lib v1.0 | MIT License */\nvar a = 1;\nvar b = 2;\nvar c = 3;\n";
+        let mut options = CompileOptions::default();
+        options.codegen_options.legal_comments = LegalComments::Linked;
+        let compiler = Compiler::with_options(options).with_sourcemap(true).with_max_chunk_bytes(12);
+        let first = compiler.compile_string(source).unwrap();
+        let second = compiler.compile_string(source).unwrap();
+        assert!(second.from_cache);
+        assert!(first.source_map.is_some());
+        assert_eq!(second.source_map, first.source_map, "命中缓存时应返回同样的source map");
+        assert!(first.chunks.len() > 1);
+        assert_eq!(second.chunks, first.chunks);
+        assert!(!first.legal_comments.is_empty());
+        assert_eq!(second.legal_comments, first.legal_comments);
+        assert_eq!(second.code, first.code);
+    }
+
+    #[test]
+    fn test_loaded_cache_hits_in_fresh_compiler() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("cache.json");
+
+        let compiler = Compiler::new();
+        let expected = compiler.compile_string("var answer = 42;").unwrap().code;
+        compiler.save_cache(&archive).unwrap();
+
+        let fresh = Compiler::new();
+        assert_eq!(fresh.load_cache(&archive).unwrap(), 1);
+        let result = fresh.compile_string("var answer = 42;").unwrap();
+        assert_eq!(result.code, expected);
+        let stats = fresh.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 0), "加载的缓存应在新编译器中命中");
+
+        std::fs::write(&archive, "not json").unwrap();
+        assert!(Compiler::new().load_cache(&archive).is_err());
+    }
+
+    #[test]
+    fn test_inline_sources_toggle() {
+        let source = "var x = 1;";
+
+        let mut options = CompileOptions::default();
+        options.codegen_options.sourcemap = true;
+        options.codegen_options.inline_sources = true;
+        let result = Compiler::with_options(options.clone()).compile_string(source).unwrap();
+        let map: SourceMap = serde_json::from_str(result.source_map.as_deref().unwrap()).unwrap();
+        assert_eq!(map.sources_content, Some(vec![Some(source.to_string())]));
+
+        options.codegen_options.inline_sources = false;
+        let result = Compiler::with_options(options).compile_string(source).unwrap();
+        let json = result.source_map.unwrap();
+        assert!(!json.contains("sourcesContent"), "关闭内联时不应包含sourcesContent: {}", json);
+        let map: SourceMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(map.sources.len(), 1);
+    }
+
+    #[test]
+    fn test_umd_output_has_all_branches() {
+        let ir = single_export_module();
+        let options = CodegenOptions {
+            output_format: OutputFormat::Umd { global_name: "MyLib".to_string() },
+            ..Default::default()
+        };
+        let code = CodeGenerator::new(options).generate(&ir);
+
+        assert!(code.contains("module.exports = factory();"), "缺少CommonJS分支: {}", code);
+        assert!(code.contains("define([], factory);"), "缺少AMD分支: {}", code);
+        assert!(code.contains("root.MyLib = factory();"), "缺少全局变量分支: {}", code);
+        assert!(code.contains("exports.answer = answer;"));
+        assert!(code.contains("return exports;"));
+
+        // 三个分支都在工厂函数定义之前，模块代码在工厂函数内部
+        let factory_pos = code.find("function () {").unwrap();
+        assert!(code.find("define(").unwrap() < factory_pos);
+        assert!(code.find("const answer = 42;").unwrap() > factory_pos);
+    }
+
+    #[test]
+    fn test_compile_entries_shared_dependency() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("shared.js"), "export const value = 1;").unwrap();
+        std::fs::write(src.join("a.js"), "import { value } from './shared';").unwrap();
+        std::fs::write(src.join("b.js"), "import { value } from './shared.js';").unwrap();
+
+        let out_dir = dir.path().join("dist");
+        let compiler = Compiler::new();
+        let outputs = compiler
+            .compile_entries(vec![src.join("a.js"), src.join("b.js")], &out_dir)
+            .unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        let shared = std::fs::canonicalize(src.join("shared.js")).unwrap();
+        for output in &outputs {
+            assert!(output.output_path.exists(), "每个入口都应该有输出文件");
+            assert_eq!(output.modules.len(), 2);
+            assert_eq!(output.modules[0], shared, "共享依赖应该排在入口之前");
+            assert!(output.code.contains("shared.js"));
+        }
+        assert_ne!(outputs[0].output_path, outputs[1].output_path);
+    }
+
+    #[test]
+    fn test_compile_entries_extracts_shared_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("common.js"), "export const value = 1;").unwrap();
+        std::fs::write(src.join("only_a.js"), "export const a = 2;").unwrap();
+        std::fs::write(src.join("a.js"), "import { value } from './common';\nimport { a } from './only_a';").unwrap();
+        std::fs::write(src.join("b.js"), "import { value } from './common';").unwrap();
+
+        let out_dir = dir.path().join("dist");
+        let compiler = Compiler::new().with_shared_chunks(2);
+        let outputs = compiler
+            .compile_entries(vec![src.join("a.js"), src.join("b.js")], &out_dir)
+            .unwrap();
+
+        let common = std::fs::canonicalize(src.join("common.js")).unwrap();
+        let only_a = std::fs::canonicalize(src.join("only_a.js")).unwrap();
+
+        let shared: Vec<_> = outputs.iter().filter(|o| o.kind == ChunkKind::Shared).collect();
+        assert_eq!(shared.len(), 1, "应该只生成一个共享chunk");
+        assert_eq!(shared[0].modules, vec![common.clone()]);
+
+        let entries: Vec<_> = outputs.iter().filter(|o| o.kind == ChunkKind::Entry).collect();
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            assert!(!entry.modules.contains(&common), "共享模块不应重复打包进入口");
+            assert_eq!(entry.shared_chunks, vec![shared[0].output_path.clone()]);
+            assert!(entry.code.contains("import \"./chunk-shared.js\";"));
+        }
+        assert!(entries[0].modules.contains(&only_a), "只被一个入口引用的模块应留在入口中");
+    }
+
+    #[test]
+    fn test_compile_entries_reports_circular_import() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.js"), "import { b } from './b';\nexport const a = 1;").unwrap();
+        std::fs::write(dir.path().join("b.js"), "import { a } from './a';\nexport const b = 2;").unwrap();
+
+        let a = std::fs::canonicalize(dir.path().join("a.js")).unwrap();
+        let b = std::fs::canonicalize(dir.path().join("b.js")).unwrap();
+        let out_dir = dir.path().join("dist");
+
+        let outputs = Compiler::new()
+            .compile_entries(vec![a.clone()], &out_dir)
+            .unwrap();
+        assert_eq!(
+            outputs[0].warnings,
+            vec![BundleWarning::CircularImport { members: vec![a.clone(), b.clone()] }]
+        );
+
+        let err = Compiler::new()
+            .with_fail_on_warning(true)
+            .compile_entries(vec![a.clone()], &out_dir)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a.js") && err.contains("b.js"), "错误信息应列出循环成员: {}", err);
+    }
+
+    #[test]
+    fn test_compile_function_in_isolation() {
+        let compiler = Compiler::with_options(CompileOptions { verify_output: true, ..Default::default() });
+        let result = compiler.compile_function("(a) => a + 1").unwrap();
+        assert_eq!(result.code, "(a) => a + 1;\n");
+
+        // 输出重新解析后仍是同一个带一个参数的箭头函数
+        let ir = JsParser::new(ParseOptions::default()).parse_string(&result.code).unwrap();
+        let root = ir.get_root();
+        let [function] = root.children.as_slice() else {
+            panic!("输出应只包含一个函数: {}", result.code);
+        };
+        assert_eq!(function.0.node_type, NodeType::ArrowFunctionExpression);
+        assert_eq!(function.0.children.len(), 2);
+        assert_eq!(function.0.children[1].0.node_type, NodeType::BinaryExpression);
+
+        let declaration = compiler.compile_function("function add(a, b) {\n  return a + b;\n}").unwrap();
+        assert_eq!(declaration.code, "function add(a, b) {\nreturn a + b;\n}\n");
+        assert!(compiler.compile_function("var x = 1;").is_err());
+        assert!(compiler.compile_function("(a) => a; (b) => b").is_err());
+    }
+
+    #[test]
+    fn test_source_map_maps_statements_to_original_lines() {
+        let source = "var a = 1;\n  var b = a + 2;\n";
+        let compiler = Compiler::new().with_sourcemap(true);
+        let result = compiler.compile_string(source).unwrap();
+        assert_eq!(result.code, "var a = 1;\nvar b = a + 2;\n");
+
+        let json = result.source_map.expect("应生成Source Map");
+        let map: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(map["version"], 3);
+        assert!(!map["mappings"].as_str().unwrap().is_empty());
+
+        let entries = decode_source_map(&json).unwrap();
+        let at = |line: usize, column: usize| entries.iter()
+            .find(|e| e.generated_line == line && e.generated_column == column)
+            .map(|e| (e.original_line, e.original_column));
+        assert_eq!(at(0, 0), Some((0, 0)));
+        assert_eq!(at(0, 8), Some((0, 8)), "初始值1");
+        // 第二行在源码中缩进了两个空格
+        assert_eq!(at(1, 0), Some((1, 2)));
+        assert_eq!(at(1, 8), Some((1, 10)), "初始值a + 2");
+
+        // 压缩后所有代码位于同一行，列号扣除删除的空白
+        let minified = Compiler::new().with_sourcemap(true).with_minify(true).compile_string(source).unwrap();
+        assert_eq!(minified.code, "var a=1;var b=a+2;");
+        let entries = decode_source_map(&minified.source_map.unwrap()).unwrap();
+        assert!(entries.iter().any(|e| (e.generated_line, e.generated_column, e.original_line, e.original_column) == (0, 8, 1, 2)));
+
+        // 降级为var的let/const长度不同，之后的列号仍然对应；字符串中的"const "不影响位置
+        let source = "let a = \"const \";\nconst b = a;\n";
+        let es5 = Compiler::new().with_sourcemap(true).with_target("es5").compile_string(source).unwrap();
+        assert_eq!(es5.code, "var a = \"const \";\nvar b = a;\n");
+        let entries = decode_source_map(&es5.source_map.unwrap()).unwrap();
+        let at = |line: usize, column: usize| entries.iter()
+            .find(|e| e.generated_line == line && e.generated_column == column)
+            .map(|e| (e.original_line, e.original_column));
+        assert_eq!(at(0, 8), Some((0, 8)), "初始值\"const \"");
+        assert_eq!(at(1, 8), Some((1, 10)), "初始值a");
+    }
+
+    #[test]
+    fn test_source_map_accounts_for_indentation() {
+        // 源码的函数体缩进四个空格，输出缩进两个空格
+        let source = "function f(a) {\n    return a + 1;\n}\n";
+        let result = Compiler::new().with_sourcemap(true).with_indent(2).compile_string(source).unwrap();
+        assert_eq!(result.code, "function f(a) {\n  return a + 1;\n}\n");
+
+        let entries = decode_source_map(&result.source_map.unwrap()).unwrap();
+        let at = |line: usize, column: usize| entries.iter()
+            .find(|e| e.generated_line == line && e.generated_column == column)
+            .map(|e| (e.original_line, e.original_column));
+        assert_eq!(at(1, 2), Some((1, 4)), "return语句应映射到缩进之后的位置");
+        assert_eq!(at(1, 9), Some((1, 11)), "返回值a + 1");
+        assert!(entries.iter().all(|e| e.generated_line != 1 || e.generated_column >= 2));
+    }
+
+    #[test]
+    fn test_template_literal_round_trip_and_es5_lowering() {
+        let source = "const s = `Hi ${name}, \\`${`n ${x + 1}`}\\``;\nconst t = `${a}`;\n";
+        let result = Compiler::new().compile_string(source).unwrap();
+        assert_eq!(result.code, source, "支持模板字符串的目标应原样输出");
+
+        let es5 = Compiler::new().with_target("es5").compile_string(source).unwrap();
+        assert_eq!(
+            es5.code,
+            "var s = \"Hi \" + name + \", `\" + (\"n \" + (x + 1)) + \"`\";\nvar t = \"\" + a;\n"
+        );
+
+        let restricted = Compiler::new().with_target("es5").with_restrict_to_target(true).compile_string("var t = `${a}`;");
+        assert!(restricted.unwrap_err().to_string().contains("模板字符串"));
+    }
+
+    #[test]
+    fn test_applied_transforms_lists_modifying_passes() {
+        let options = OptimizerOptions { minify: false, dce: false, ..Default::default() };
+        let result = Compiler::new().with_optimizer_options(options.clone()).compile_string("var x = 1 + 2;\n").unwrap();
+        assert_eq!(result.code, "var x = 3;\n");
+        assert_eq!(result.applied_transforms, vec!["ConstantFolding".to_string()], "未修改语法树和被禁用的优化器不应列出");
+
+        let unchanged = Compiler::new().with_optimizer_options(options).compile_string("var y = 1;\n").unwrap();
+        assert!(unchanged.applied_transforms.is_empty());
+        assert!(Compiler::new().compile_string("var x = 1 + 2;\n").unwrap().applied_transforms.is_empty());
+    }
+
+    #[test]
+    fn test_radix_and_bigint_literals_round_trip() {
+        let source = "var a = 0xff;\nvar b = 0o17;\nvar c = 0b1010;\nvar d = 0x10n;\n";
+        let ir = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+
+        let plain = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(plain, source, "未压缩时保留原来的写法");
+        let minified = CodeGenerator::new(CodegenOptions { minify: true, ..Default::default() }).generate(&ir);
+        assert_eq!(minified, "var a=255;var b=15;var c=10;var d=16n;");
+        assert_eq!(ir.estimated_output_size(&CodegenOptions::default()), plain.len());
+    }
+
+    #[test]
+    fn test_comments_preserved_and_banner_survives_minify() {
+        let source = "/*! mylib v2.0 | MIT */\n// 入口\nvar a = 1;\nfunction f() {\n/* 函数体 */\nreturn a;\n}\nvar s = \"/* 不是注释 */\";\n";
+        let ir = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+        assert_eq!(ir.get_root().children[0].0.leading_comments(), ["! mylib v2.0 | MIT ", " 入口"]);
+
+        let plain = CodeGenerator::new(CodegenOptions { minify: false, ..Default::default() }).generate(&ir);
+        assert_eq!(plain, source);
+        assert_eq!(ir.estimated_output_size(&CodegenOptions { minify: false, ..Default::default() }), plain.len());
+        let stripped = CodeGenerator::new(CodegenOptions { minify: false, preserve_comments: false, ..Default::default() }).generate(&ir);
+        assert!(stripped.starts_with("/*! mylib v2.0 | MIT */\nvar a = 1;\n"), "关闭preserve_comments时只保留法律注释: {}", stripped);
+        assert!(!stripped.contains("函数体"));
+
+        let minified = CodeGenerator::new(CodegenOptions { minify: true, ..Default::default() }).generate(&ir);
+        assert!(minified.starts_with("/*! mylib v2.0 | MIT */"), "压缩后应保留版权声明的原文: {}", minified);
+        assert!(!minified.contains("入口") && !minified.contains("函数体"), "压缩应删除普通注释: {}", minified);
+        assert!(minified.contains("\"/* 不是注释 */\""), "字符串中的空白不应被删除: {}", minified);
+    }
+
+    #[test]
+    fn test_keyword_operators_round_trip() {
+        let source = "new Foo(1);\n\"x\" in obj;\na instanceof B;\ndelete obj.x;\n\
+            typeof x === \"string\";\nvoid f();\nnew (f())();\n(typeof x).length;\n";
+        let ir = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+        let plain = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(plain, source);
+        assert_eq!(ir.estimated_output_size(&CodegenOptions::default()), plain.len());
+
+        // 有副作用的运算不被优化删除或折叠
+        let options = OptimizerOptions { minify: false, ..Default::default() };
+        let optimized = Compiler::new().with_optimizer_options(options).compile_string(source).unwrap();
+        assert_eq!(optimized.code, plain);
+    }
+
+    #[test]
+    fn test_phase_fractions_sum_to_one() {
+        let source = "var x = 1 + 2;\n".repeat(200);
+        let result = Compiler::new().compile_string(&source).unwrap();
+
+        let mut phases: Vec<&str> = result.phase_fractions.keys().map(String::as_str).collect();
+        phases.sort();
+        assert_eq!(phases, ["codegen", "optimize", "parse"]);
+        assert!(result.phase_fractions.values().all(|f| (0.0..=1.0).contains(f)), "{:?}", result.phase_fractions);
+        let sum: f64 = result.phase_fractions.values().sum();
+        assert!((sum - 1.0).abs() < 1e-9, "各阶段比例之和应为1: {}", sum);
+
+        let cached = Compiler::new().with_cache(true);
+        cached.compile_string(&source).unwrap();
+        assert!(cached.compile_string(&source).unwrap().phase_fractions.is_empty(), "缓存结果没有阶段耗时");
+    }
+
+    #[test]
+    fn test_phase_times_include_optimize() {
+        let source = "var x = 1 + 2;\nvar y = x * 3;\n".repeat(500);
+        let compiler = Compiler::new().with_cache(false).with_optimizer_options(OptimizerOptions::default());
+        let result = compiler.compile_string(&source).unwrap();
+
+        for phase in ["parse", "optimize", "codegen"] {
+            assert!(result.phase_times.contains_key(phase), "缺少{}阶段的耗时: {:?}", phase, result.phase_times);
+        }
+        let phases_ms: u64 = result.phase_times.values().sum();
+        assert!(phases_ms <= result.time_ms, "各阶段耗时之和不应超过总耗时: {:?} > {}", result.phase_times, result.time_ms);
+
+        let variants = compiler.compile_variants(&source, vec![CodegenOptions::default()]).unwrap();
+        assert!(variants[0].phase_times.contains_key("optimize"), "{:?}", variants[0].phase_times);
+    }
+
+    #[test]
+    fn test_cache_keys_distinct_for_random_sources() {
+        let compiler = Compiler::new();
+        // xorshift伪随机数，结果可以复现
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let mut sources = std::collections::HashSet::new();
+        let mut keys = std::collections::HashSet::new();
+        for _ in 0..5000 {
+            let len = (next() % 24) as usize;
+            let source: String = (0..len).map(|_| (b' ' + (next() % 95) as u8) as char).collect();
+            if sources.insert(source.clone()) {
+                let key = compiler.cache_key(&source);
+                assert_eq!(key.len(), 32);
+                assert!(keys.insert(key), "不同的源码得到了相同的缓存键: {:?}", source);
+            }
+        }
+        assert!(sources.len() > 4000);
+
+        assert_eq!(compiler.cache_key("var x = 1;"), Compiler::new().cache_key("var x = 1;"), "相同的输入和选项应得到相同的键");
+        assert_ne!(compiler.cache_key("var x = 1;"), Compiler::new().with_minify(true).cache_key("var x = 1;"));
+    }
+
+    #[test]
+    fn test_compile_within_deadline_returns_partial_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "var x = 1 + 2;\nvar y = x * 3;\n".repeat(300);
+        let inputs: Vec<PathBuf> = (0..200)
+            .map(|i| {
+                let path = dir.path().join(format!("file{}.js", i));
+                std::fs::write(&path, &source).unwrap();
+                path
+            })
+            .collect();
+        let out_dir = dir.path().join("out");
+
+        let compiler = Compiler::new().with_cache(false);
+        let outcomes = compiler.compile_files_within(&inputs, Some(out_dir.clone()), Duration::from_millis(5)).unwrap();
+        assert_eq!(outcomes.len(), inputs.len());
+
+        // 按顺序编译，截止之后的文件都没有开始
+        let compiled = outcomes.iter().take_while(|outcome| matches!(outcome, FileOutcome::Compiled(_))).count();
+        assert!(compiled < inputs.len(), "5毫秒内不应编译完全部文件");
+        assert!(outcomes[compiled..].iter().all(|outcome| matches!(outcome, FileOutcome::NotStarted)));
+
+        let expected = compiler.compile_string(&source).unwrap().code;
+        for (input, outcome) in inputs.iter().zip(&outcomes) {
+            let output = output_path_in_dir(input, &out_dir);
+            match outcome {
+                FileOutcome::Compiled(result) => {
+                    assert_eq!(result.code, expected);
+                    assert_eq!(std::fs::read_to_string(&output).unwrap(), expected, "输出文件应完整");
+                },
+                _ => assert!(!output.exists(), "未开始的文件不应有输出: {}", output.display()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_es5_lowers_declaration_kind_without_touching_text() {
+        let source = "let outlet = 1;\nconst s = \"const x\";\n";
+        let es5 = Compiler::new().with_cache(false).with_target("es5").compile_string(source).unwrap();
+        assert_eq!(es5.code, "var outlet = 1;\nvar s = \"const x\";\n");
+
+        // 支持块级作用域的目标保持原有的关键字
+        let es2015 = Compiler::new().with_cache(false).with_target("es2015").compile_string(source).unwrap();
+        assert_eq!(es2015.code, source);
+    }
+
+    #[test]
+    fn test_minified_output_reparses_to_same_program() {
+        let source = "var x = 1;\nlet y = x - -x;\nconst z = typeof y;\n";
+        let expected = Compiler::new().with_cache(false).compile_string(source).unwrap().code;
+        let minified = Compiler::new().with_cache(false).with_minify(true).compile_string(source).unwrap().code;
+        assert_eq!(minified, "var x=1;let y=x- -x;const z=typeof y;");
+        let ir = JsParser::new(ParseOptions::default()).parse_string(&minified).unwrap();
+        assert_eq!(CodeGenerator::new(CodegenOptions::default()).generate(&ir), expected, "压缩后的代码应能解析回同样的程序");
+
+        // 包装代码同样只删除不影响记号划分的空白
+        let iife = Compiler::new().with_cache(false).with_minify(true)
+            .with_output_format(OutputFormat::Iife { global_name: "Lib".to_string() })
+            .compile_string(source).unwrap().code;
+        assert_eq!(iife, "window.Lib=(function(){var exports={};var x=1;let y=x- -x;const z=typeof y;return exports;})();");
+    }
+
+    #[test]
+    fn test_string_literal_escapes_round_trip() {
+        let source = "var s = \"a\\\"b\\\\c\\nd\";\n";
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, source, "引号、反斜杠和换行应重新转义");
+        assert_eq!(ir.estimated_output_size(&CodegenOptions::default()), code.len());
+
+        // 单引号字符串统一输出为双引号
+        let result = Compiler::new().with_cache(false).compile_string("var t = 'it\\'s \"ok\"';\n").unwrap();
+        assert_eq!(result.code, "var t = \"it's \\\"ok\\\"\";\n");
+    }
+
+    #[test]
+    fn test_conditional_expression_round_trip_from_source() {
+        // 条件表达式右结合，作为条件时需要括号
+        let source = "var r = ok ? 1 : a ? b : c;\nvar s = (a ? b : c) ? x + 1 : -x;\n";
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let conditional = &ir.get_root().children[0].0.children[1].0;
+        assert_eq!(conditional.node_type, NodeType::ConditionalExpression);
+        assert_eq!(conditional.children[2].0.node_type, NodeType::ConditionalExpression, "a ? b : c是第一个条件表达式的else分支");
+        assert_eq!(CodeGenerator::new(CodegenOptions::default()).generate(&ir), source);
+
+        assert!(JsParser::new(ParseOptions::default()).parse_string("var r = a ? b;\n").is_err(), "缺少':'");
+    }
+
+    #[test]
+    fn test_sequence_expression_round_trip_from_source() {
+        let source = "a, b;\nf((a, b), c);\nvar x = (a, b + 1);\n";
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let statements = &ir.get_root().children;
+        assert_eq!(statements[0].0.node_type, NodeType::SequenceExpression);
+        assert_eq!(statements[1].0.children.len(), 3, "括号外的逗号分隔参数");
+        assert_eq!(CodeGenerator::new(CodegenOptions::default()).generate(&ir), source);
+    }
+
+    #[test]
+    fn test_loops_and_labels_round_trip_from_source() {
+        let source = concat!(
+            "for (var i = 0; i < n; next(i)) f(i);\n",
+            "for (;;) break;\n",
+            "for (init(), reset(n); i < j; step(i, j)) {\n}\n",
+            "loop: for (; ready();) {\nif_ready();\ncontinue loop;\n}\n",
+            "while (a, b) wait();\n",
+        );
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let statements = &ir.get_root().children;
+        assert_eq!(statements[0].0.node_type, NodeType::ForStatement);
+        assert_eq!(statements[0].0.children[0].0.node_type, NodeType::VariableDeclaration);
+        assert_eq!(statements[1].0.children[0].0.node_type, NodeType::Unknown, "省略的部分用Unknown节点占位");
+        assert_eq!(statements[3].0.get_string_value("label"), Some("loop"));
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, source);
+        assert_eq!(ir.estimated_output_size(&CodegenOptions::default()), code.len());
+
+        assert!(JsParser::new(ParseOptions::default()).parse_string("for (var i = 0, j = 1; ; ) {\n}\n").is_err());
+    }
+
+    #[test]
+    fn test_object_literals_round_trip_from_source() {
+        use lumen_optimizers::{Mangler, Optimizer};
+
+        let source = concat!(
+            "var o = { a: 1, b, \"c d\": 2, 3: x, [k + 1]: y, default: z, get: 4 };\n",
+            "var counter = { get value() {\nreturn n;\n}, set value(v) {\nwrite(v);\n}, reset() {\nclear();\n}, ",
+            "async load(url) {\nawait fetch(url);\n}, *items() {\nyield n;\n}, [key]() {\n} };\n",
+            "var empty = {};\n",
+        );
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert_eq!(code, source);
+
+        // 方法的种类保留在IR中
+        let counter = &ir.get_root().children[1].0.children[1].0;
+        let kinds: Vec<_> = counter.children.iter()
+            .map(|p| (p.0.get_string_value("kind").unwrap(), p.0.get_boolean_value("method").unwrap()))
+            .collect();
+        assert_eq!(kinds, vec![("get", false), ("set", false), ("init", true), ("init", true), ("init", true), ("init", true)]);
+
+        // 简写属性的值被重命名后写成完整形式
+        let mut ir = JsParser::new(ParseOptions::default()).parse_string("function f(value) {\nreturn { value };\n}\n").unwrap();
+        assert!(CodeGenerator::new(CodegenOptions::default()).generate(&ir).contains("return { value };"));
+        Mangler::new(OptimizerOptions::default()).optimize(&mut ir);
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert!(code.contains("return { value: a };"), "{}", code);
+        assert_eq!(ir.estimated_output_size(&CodegenOptions::default()), code.len());
+    }
+}
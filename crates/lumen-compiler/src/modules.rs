@@ -0,0 +1,271 @@
+//! 模块语法转换 - 按输出模块格式改写只在ES模块中有效的语法
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use anyhow::{Result, anyhow};
+
+use lumen_core::{IR, Node, NodeRef, NodeType, NodeValue, OutputFormat};
+
+/// 按输出格式改写`import.meta`和动态导入
+///
+/// ESM输出保持原样；CommonJS输出把`import.meta.url`、`import.meta.filename`、`import.meta.dirname`
+/// 改写为Node中的等价表达式，其余用法无法表达，返回错误；IIFE和UMD输出按经典脚本加载，不支持`import.meta`。
+/// 动态导入在CommonJS输出中改写为`Promise.resolve().then(() => require(x))`，其他格式保持原样。
+pub fn lower_module_syntax(ir: &mut IR, format: &OutputFormat) -> Result<()> {
+    if *format == OutputFormat::Esm {
+        return Ok(());
+    }
+    let root = ir.get_root();
+    let new_root = rewrite(ir, &root, &mut |ir, node| {
+        if node.node_type == NodeType::ImportExpression {
+            return Ok(rewrite_dynamic_import(ir, node, format));
+        }
+        rewrite_import_meta(ir, node, format)
+    })?;
+    ir.nodes.insert(ir.root_id, new_root);
+    Ok(())
+}
+
+/// CommonJS中的`import(x)`：`Promise.resolve().then(() => require(x))`
+fn rewrite_dynamic_import(ir: &mut IR, node: &Node, format: &OutputFormat) -> Option<NodeRef> {
+    if *format != OutputFormat::CommonJs {
+        return None;
+    }
+    let specifier = node.children.first()?.0.id;
+
+    let promise = identifier(ir, "Promise");
+    let resolve = identifier(ir, "resolve");
+    let resolve_member = node_with_children(ir, NodeType::MemberExpression, &[promise, resolve]);
+    let resolved = node_with_children(ir, NodeType::CallExpression, &[resolve_member]);
+    let then = identifier(ir, "then");
+    let then_member = node_with_children(ir, NodeType::MemberExpression, &[resolved, then]);
+
+    let require = identifier(ir, "require");
+    let require_call = node_with_children(ir, NodeType::CallExpression, &[require, specifier]);
+    let callback = node_with_children(ir, NodeType::ArrowFunctionExpression, &[require_call]);
+    let call = node_with_children(ir, NodeType::CallExpression, &[then_member, callback]);
+
+    let mut replacement = ir.nodes[&call].0.as_ref().clone();
+    replacement.location = node.location;
+    Some(NodeRef(Arc::new(replacement)))
+}
+
+/// `import.meta.<property>`的替换结果；不是`import.meta`时返回None
+fn rewrite_import_meta(ir: &mut IR, node: &Node, format: &OutputFormat) -> Result<Option<NodeRef>> {
+    if is_import_meta(node) {
+        // 成员访问之外的`import.meta`（如整体传参）
+        return Err(unsupported(format, "import.meta"));
+    }
+    let [object, property] = node.children.as_slice() else {
+        return Ok(None);
+    };
+    if node.node_type != NodeType::MemberExpression || !is_import_meta(&object.0) {
+        return Ok(None);
+    }
+    let name = match property.0.get_string_value("name") {
+        Some(name) if node.get_boolean_value("computed") != Some(true) => name,
+        _ => return Err(unsupported(format, "import.meta[...]")),
+    };
+    if *format != OutputFormat::CommonJs {
+        return Err(unsupported(format, &format!("import.meta.{}", name)));
+    }
+
+    let replacement = match name {
+        // require("url").pathToFileURL(__filename).href
+        "url" => {
+            let require = identifier(ir, "require");
+            let specifier = ir.create_node(NodeType::StringLiteral);
+            set_value(ir, specifier, "value", NodeValue::String("url".to_string()));
+            let require_call = node_with_children(ir, NodeType::CallExpression, &[require, specifier]);
+            let to_url = identifier(ir, "pathToFileURL");
+            let callee = node_with_children(ir, NodeType::MemberExpression, &[require_call, to_url]);
+            let filename = identifier(ir, "__filename");
+            let call = node_with_children(ir, NodeType::CallExpression, &[callee, filename]);
+            let href = identifier(ir, "href");
+            node_with_children(ir, NodeType::MemberExpression, &[call, href])
+        },
+        "filename" => identifier(ir, "__filename"),
+        "dirname" => identifier(ir, "__dirname"),
+        _ => return Err(unsupported(format, &format!("import.meta.{}", name))),
+    };
+    let mut replacement = ir.nodes[&replacement].0.as_ref().clone();
+    replacement.location = node.location;
+    Ok(Some(NodeRef(Arc::new(replacement))))
+}
+
+fn is_import_meta(node: &Node) -> bool {
+    node.node_type == NodeType::MetaProperty
+        && node.get_string_value("meta") == Some("import")
+        && node.get_string_value("property") == Some("meta")
+}
+
+fn unsupported(format: &OutputFormat, syntax: &str) -> anyhow::Error {
+    let format = match format {
+        OutputFormat::Esm => "ESM",
+        OutputFormat::CommonJs => "CommonJS",
+        OutputFormat::Iife { .. } => "IIFE",
+        OutputFormat::Umd { .. } => "UMD",
+    };
+    anyhow!("{} 输出格式不支持 {}", format, syntax)
+}
+
+fn identifier(ir: &mut IR, name: &str) -> usize {
+    let id = ir.create_node(NodeType::Identifier);
+    set_value(ir, id, "name", NodeValue::String(name.to_string()));
+    id
+}
+
+fn set_value(ir: &mut IR, id: usize, key: &str, value: NodeValue) {
+    if let Some(node) = ir.get_node_mut(id) {
+        node.set_value(key, value);
+    }
+}
+
+fn node_with_children(ir: &mut IR, node_type: NodeType, children: &[usize]) -> usize {
+    let id = ir.create_node(node_type);
+    for &child in children {
+        ir.add_child(id, child);
+    }
+    id
+}
+
+/// 自顶向下重建子树，`transform`返回Some时用返回的节点替换当前节点（不再处理其子节点），
+/// 被替换掉的节点从节点表中删除
+fn rewrite<F>(ir: &mut IR, node: &Node, transform: &mut F) -> Result<NodeRef>
+where
+    F: FnMut(&mut IR, &Node) -> Result<Option<NodeRef>>,
+{
+    if let Some(replacement) = transform(ir, node)? {
+        let mut kept = HashSet::new();
+        collect_ids(&replacement.0, &mut kept);
+        let mut dropped = HashSet::new();
+        collect_ids(node, &mut dropped);
+        for id in dropped.difference(&kept) {
+            ir.nodes.remove(id);
+        }
+        ir.nodes.insert(replacement.0.id, replacement.clone());
+        return Ok(replacement);
+    }
+
+    let mut new_node = node.clone();
+    new_node.children = node.children.iter()
+        .map(|child| rewrite(ir, &child.0, transform))
+        .collect::<Result<_>>()?;
+    let node_ref = NodeRef(Arc::new(new_node));
+    ir.nodes.insert(node.id, node_ref.clone());
+    Ok(node_ref)
+}
+
+fn collect_ids(node: &Node, ids: &mut HashSet<usize>) {
+    ids.insert(node.id);
+    for child in &node.children {
+        collect_ids(&child.0, ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::CodegenOptions;
+    use crate::CodeGenerator;
+
+    /// `var u = import.meta.<property>;`
+    fn import_meta_module(property: &str) -> IR {
+        let mut ir = IR::new();
+        let meta = ir.create_node(NodeType::MetaProperty);
+        set_value(&mut ir, meta, "meta", NodeValue::String("import".to_string()));
+        set_value(&mut ir, meta, "property", NodeValue::String("meta".to_string()));
+        let property = identifier(&mut ir, property);
+        let member = node_with_children(&mut ir, NodeType::MemberExpression, &[meta, property]);
+        let name = identifier(&mut ir, "u");
+        let decl = node_with_children(&mut ir, NodeType::VariableDeclaration, &[name, member]);
+        ir.add_child(ir.root_id, decl);
+        ir
+    }
+
+    fn generate(ir: &IR, format: OutputFormat) -> String {
+        CodeGenerator::new(CodegenOptions { output_format: format, ..Default::default() }).generate(ir)
+    }
+
+    #[test]
+    fn test_import_meta_url_by_output_format() {
+        let mut ir = import_meta_module("url");
+        lower_module_syntax(&mut ir, &OutputFormat::Esm).unwrap();
+        assert_eq!(generate(&ir, OutputFormat::Esm), "var u = import.meta.url;\n");
+
+        lower_module_syntax(&mut ir, &OutputFormat::CommonJs).unwrap();
+        assert_eq!(
+            generate(&ir, OutputFormat::CommonJs),
+            "var u = require(\"url\").pathToFileURL(__filename).href;\n"
+        );
+        assert!(!ir.nodes.values().any(|n| n.0.node_type == NodeType::MetaProperty), "旧节点应从节点表中删除");
+
+        let mut ir = import_meta_module("resolve");
+        let err = lower_module_syntax(&mut ir, &OutputFormat::CommonJs).unwrap_err();
+        assert!(err.to_string().contains("import.meta.resolve"), "{}", err);
+
+        let mut ir = import_meta_module("url");
+        let iife = OutputFormat::Iife { global_name: "Lib".to_string() };
+        assert!(lower_module_syntax(&mut ir, &iife).is_err(), "经典脚本中不能使用import.meta");
+    }
+
+    #[test]
+    fn test_import_meta_from_source() {
+        let parse = |source: &str| lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+        let source = "var u = import.meta.url;\nimport.meta.dirname.length;\n";
+        let mut ir = parse(source);
+        assert_eq!(generate(&ir, OutputFormat::Esm), source);
+        lower_module_syntax(&mut ir, &OutputFormat::CommonJs).unwrap();
+        assert_eq!(
+            generate(&ir, OutputFormat::CommonJs),
+            "var u = require(\"url\").pathToFileURL(__filename).href;\n__dirname.length;\n"
+        );
+
+        // 成员访问之外的import.meta
+        let mut ir = parse("var m = import.meta;\n");
+        assert!(is_import_meta(&ir.get_root().children[0].0.children[1].0));
+        let err = lower_module_syntax(&mut ir, &OutputFormat::CommonJs).unwrap_err();
+        assert!(err.to_string().contains("import.meta"), "{}", err);
+        assert!(lumen_parser::JsParser::new(Default::default()).parse_string("var x = import.target;\n").is_err());
+    }
+
+    #[test]
+    fn test_dynamic_import_downlevel_for_commonjs() {
+        // var page = import("./page");
+        let mut ir = IR::new();
+        let specifier = ir.create_node(NodeType::StringLiteral);
+        set_value(&mut ir, specifier, "value", NodeValue::String("./page".to_string()));
+        let import = node_with_children(&mut ir, NodeType::ImportExpression, &[specifier]);
+        let name = identifier(&mut ir, "page");
+        let decl = node_with_children(&mut ir, NodeType::VariableDeclaration, &[name, import]);
+        ir.add_child(ir.root_id, decl);
+
+        let umd = OutputFormat::Umd { global_name: "Lib".to_string() };
+        let mut preserved = ir.clone();
+        lower_module_syntax(&mut preserved, &umd).unwrap();
+        assert!(generate(&preserved, OutputFormat::Esm).contains("var page = import(\"./page\");"));
+
+        lower_module_syntax(&mut ir, &OutputFormat::CommonJs).unwrap();
+        assert_eq!(
+            generate(&ir, OutputFormat::CommonJs),
+            "var page = Promise.resolve().then(() => require(\"./page\"));\n"
+        );
+    }
+
+    #[test]
+    fn test_dynamic_import_from_source() {
+        let source = "var page = import(\"./page\");\nimport(\"./lazy\").then(start);\n";
+        let mut ir = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+        let statements = &ir.get_root().children;
+        assert_eq!(statements[0].0.children[1].0.node_type, NodeType::ImportExpression);
+        assert_eq!(statements[1].0.node_type, NodeType::CallExpression, "语句开头的import(...)不是导入声明");
+        assert_eq!(generate(&ir, OutputFormat::Esm), source);
+
+        lower_module_syntax(&mut ir, &OutputFormat::CommonJs).unwrap();
+        assert_eq!(
+            generate(&ir, OutputFormat::CommonJs),
+            "var page = Promise.resolve().then(() => require(\"./page\"));\nPromise.resolve().then(() => require(\"./lazy\")).then(start);\n"
+        );
+        assert!(lumen_parser::JsParser::new(Default::default()).parse_string("import(\"./a\", b);\n").is_err());
+    }
+}
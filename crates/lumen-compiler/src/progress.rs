@@ -0,0 +1,76 @@
+//! 批量编译进度 - 根据已用时间和剩余文件数估算剩余时间
+
+use std::time::Duration;
+
+/// 批量编译的进度，每完成一个文件（无论成功与否）报告一次
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProgress {
+    /// 已完成的文件数
+    pub completed: usize,
+    /// 文件总数
+    pub total: usize,
+    /// 从开始到现在的耗时
+    pub elapsed: Duration,
+    /// 平均吞吐量（文件/秒）
+    pub files_per_sec: f64,
+    /// 按平均吞吐量估算的剩余时间，还没有完成任何文件时为None
+    pub eta: Option<Duration>,
+}
+
+/// 进度跟踪器，时间由调用方传入，便于在测试中使用固定的时间线
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    total: usize,
+    completed: usize,
+}
+
+impl ProgressTracker {
+    pub fn new(total: usize) -> Self {
+        Self { total, completed: 0 }
+    }
+
+    /// 记录完成一个文件，`elapsed`为从批量编译开始到现在的耗时
+    pub fn record(&mut self, elapsed: Duration) -> BatchProgress {
+        self.completed = (self.completed + 1).min(self.total);
+        let seconds = elapsed.as_secs_f64();
+        let files_per_sec = if seconds > 0.0 { self.completed as f64 / seconds } else { 0.0 };
+
+        let remaining = self.total - self.completed;
+        let eta = if remaining == 0 {
+            Some(Duration::ZERO)
+        } else if files_per_sec > 0.0 {
+            Some(Duration::from_secs_f64(remaining as f64 / files_per_sec))
+        } else {
+            None
+        };
+
+        BatchProgress {
+            completed: self.completed,
+            total: self.total,
+            elapsed,
+            files_per_sec,
+            eta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_decreases_to_zero() {
+        // 每个文件耗时200毫秒
+        let mut tracker = ProgressTracker::new(5);
+        let reports: Vec<BatchProgress> = (1..=5)
+            .map(|i| tracker.record(Duration::from_millis(200 * i)))
+            .collect();
+
+        let etas: Vec<Duration> = reports.iter().map(|r| r.eta.unwrap()).collect();
+        assert!(etas.windows(2).all(|w| w[1] < w[0]), "剩余时间应单调递减: {:?}", etas);
+        assert_eq!(etas[0], Duration::from_millis(800));
+        assert_eq!(etas[4], Duration::ZERO);
+        assert!((reports[2].files_per_sec - 5.0).abs() < 1e-9);
+        assert_eq!(reports[4].completed, reports[4].total);
+    }
+}
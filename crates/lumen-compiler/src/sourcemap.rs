@@ -0,0 +1,312 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+
+/// Source Map v3 结构
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMap {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub names: Vec<String>,
+    pub mappings: String,
+    /// 内联的原始源码，仅在启用`inline_sources`时输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources_content: Option<Vec<Option<String>>>,
+}
+
+/// 单个映射段（行列均从0开始）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Mapping {
+    generated_line: usize,
+    generated_column: usize,
+    source: usize,
+    original_line: usize,
+    original_column: usize,
+}
+
+/// Source Map构建器
+#[derive(Debug, Clone, Default)]
+pub struct SourceMapBuilder {
+    file: Option<String>,
+    sources: Vec<String>,
+    sources_content: Vec<Option<String>>,
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置生成文件名
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// 登记源文件及其内容，返回源文件索引
+    pub fn add_source(&mut self, name: impl Into<String>, content: Option<String>) -> usize {
+        self.sources.push(name.into());
+        self.sources_content.push(content);
+        self.sources.len() - 1
+    }
+
+    /// 添加一个映射段
+    pub fn add_mapping(
+        &mut self,
+        generated_line: usize,
+        generated_column: usize,
+        source: usize,
+        original_line: usize,
+        original_column: usize,
+    ) {
+        self.mappings.push(Mapping {
+            generated_line,
+            generated_column,
+            source,
+            original_line,
+            original_column,
+        });
+    }
+
+    /// 构建Source Map，`inline_sources`为false时不输出`sourcesContent`
+    pub fn build(&self, inline_sources: bool) -> SourceMap {
+        let sources_content = if inline_sources {
+            Some(self.sources_content.clone())
+        } else {
+            None
+        };
+
+        SourceMap {
+            version: 3,
+            file: self.file.clone(),
+            sources: self.sources.clone(),
+            names: Vec::new(),
+            mappings: self.encode_mappings(),
+            sources_content,
+        }
+    }
+
+    /// 序列化为JSON字符串
+    pub fn to_json(&self, inline_sources: bool) -> String {
+        serde_json::to_string(&self.build(inline_sources))
+            .expect("Source Map序列化不应失败")
+    }
+
+    /// 按VLQ编码映射段，字段均相对于前一个段
+    fn encode_mappings(&self) -> String {
+        let mut mappings = self.mappings.clone();
+        mappings.sort_by_key(|m| (m.generated_line, m.generated_column));
+
+        let mut out = String::new();
+        let mut line = 0;
+        let mut prev_column = 0i64;
+        let mut prev_source = 0i64;
+        let mut prev_original_line = 0i64;
+        let mut prev_original_column = 0i64;
+        let mut first_in_line = true;
+
+        for m in &mappings {
+            while line < m.generated_line {
+                out.push(';');
+                line += 1;
+                prev_column = 0;
+                first_in_line = true;
+            }
+            if !first_in_line {
+                out.push(',');
+            }
+            first_in_line = false;
+
+            encode_vlq(m.generated_column as i64 - prev_column, &mut out);
+            encode_vlq(m.source as i64 - prev_source, &mut out);
+            encode_vlq(m.original_line as i64 - prev_original_line, &mut out);
+            encode_vlq(m.original_column as i64 - prev_original_column, &mut out);
+
+            prev_column = m.generated_column as i64;
+            prev_source = m.source as i64;
+            prev_original_line = m.original_line as i64;
+            prev_original_column = m.original_column as i64;
+        }
+
+        out
+    }
+}
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 VLQ编码
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut vlq = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+    loop {
+        let mut digit = (vlq & 0b11111) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64_CHARS[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// Base64 VLQ解码，返回解码出的值序列
+fn decode_vlq_segment(segment: &str) -> Result<Vec<i64>> {
+    let mut values = Vec::new();
+    let mut value = 0i64;
+    let mut shift = 0;
+
+    for c in segment.bytes() {
+        let digit = BASE64_CHARS.iter().position(|&b| b == c)
+            .ok_or_else(|| anyhow!("无效的VLQ字符: '{}'", c as char))? as i64;
+        value |= (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+            // 映射中的值都是32位整数，继续移位会溢出
+            if shift > 32 {
+                return Err(anyhow!("VLQ值超出32位: '{}'", segment));
+            }
+            continue;
+        }
+        let negative = value & 1 == 1;
+        value >>= 1;
+        values.push(if negative { -value } else { value });
+        value = 0;
+        shift = 0;
+    }
+
+    if shift != 0 {
+        return Err(anyhow!("VLQ段不完整: '{}'", segment));
+    }
+    Ok(values)
+}
+
+/// 解码后的单条映射（行列均从0开始）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingEntry {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    /// 源文件名，只有生成位置没有对应源码时为None
+    pub source: Option<String>,
+    pub original_line: usize,
+    pub original_column: usize,
+    pub name: Option<String>,
+}
+
+impl fmt::Display for MappingEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 与主流工具保持一致：行号从1开始，列号从0开始
+        write!(f, "{}:{}", self.generated_line + 1, self.generated_column)?;
+        if let Some(source) = &self.source {
+            write!(f, " -> {}:{}:{}", source, self.original_line + 1, self.original_column)?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析Source Map v3 JSON，把`mappings`解码为可读的映射条目
+pub fn decode_source_map(json: &str) -> Result<Vec<MappingEntry>> {
+    let map: SourceMap = serde_json::from_str(json)
+        .map_err(|e| anyhow!("解析Source Map失败: {}", e))?;
+    if map.version != 3 {
+        return Err(anyhow!("不支持的Source Map版本: {}", map.version));
+    }
+
+    let mut entries = Vec::new();
+    let mut source = 0i64;
+    let mut original_line = 0i64;
+    let mut original_column = 0i64;
+    let mut name = 0i64;
+
+    for (generated_line, line) in map.mappings.split(';').enumerate() {
+        let mut generated_column = 0i64;
+
+        for segment in line.split(',').filter(|s| !s.is_empty()) {
+            let fields = decode_vlq_segment(segment)?;
+            generated_column += fields[0];
+
+            let mut entry = MappingEntry {
+                generated_line,
+                generated_column: generated_column as usize,
+                source: None,
+                original_line: 0,
+                original_column: 0,
+                name: None,
+            };
+
+            match fields.len() {
+                1 => {},
+                4 | 5 => {
+                    source += fields[1];
+                    original_line += fields[2];
+                    original_column += fields[3];
+                    entry.source = Some(map.sources.get(source as usize).cloned()
+                        .ok_or_else(|| anyhow!("源文件索引越界: {}", source))?);
+                    entry.original_line = original_line as usize;
+                    entry.original_column = original_column as usize;
+
+                    if let Some(delta) = fields.get(4) {
+                        name += delta;
+                        entry.name = Some(map.names.get(name as usize).cloned()
+                            .ok_or_else(|| anyhow!("名称索引越界: {}", name))?);
+                    }
+                },
+                n => return Err(anyhow!("映射段字段数无效: {} ('{}')", n, segment)),
+            }
+
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_mappings() {
+        let mut builder = SourceMapBuilder::new();
+        let src = builder.add_source("a.js", None);
+        builder.add_mapping(0, 0, src, 0, 0);
+        builder.add_mapping(0, 4, src, 0, 4);
+        builder.add_mapping(1, 0, src, 2, 0);
+
+        assert_eq!(builder.build(false).mappings, "AAAA,IAAI;AAEJ");
+    }
+
+    #[test]
+    fn test_decode_source_map() {
+        let json = r#"{"version":3,"sources":["a.js"],"names":["foo"],"mappings":"AAAA,IAAIA;;AAEJ"}"#;
+        let entries = decode_source_map(json).unwrap();
+
+        let positions: Vec<_> = entries.iter()
+            .map(|e| (e.generated_line, e.generated_column, e.original_line, e.original_column))
+            .collect();
+        assert_eq!(positions, vec![(0, 0, 0, 0), (0, 4, 0, 4), (2, 0, 2, 0)]);
+        assert_eq!(entries[1].name.as_deref(), Some("foo"));
+        assert!(entries.iter().all(|e| e.source.as_deref() == Some("a.js")));
+        assert_eq!(entries[2].to_string(), "3:0 -> a.js:3:0");
+    }
+
+    #[test]
+    fn test_decode_rejects_overlong_vlq() {
+        // 七个续位字符之后仍有续位
+        let err = decode_vlq_segment("gggggggggggggA").unwrap_err();
+        assert!(err.to_string().contains("32位"), "{}", err);
+        let json = r#"{"version":3,"sources":["a.js"],"names":[],"mappings":"AAAA,gggggggggggggA"}"#;
+        assert!(decode_source_map(json).is_err());
+
+        // 32位范围内的最大值仍能解码
+        assert_eq!(decode_vlq_segment("+/////D").unwrap(), vec![i32::MAX as i64]);
+    }
+}
@@ -0,0 +1,159 @@
+//! 自定义转换 - 在代码优化之后、代码生成之前按名字依次运行的语法树转换
+//!
+//! `CompileOptions.transformers`中的名字在`TransformRegistry`中查找对应的实现，
+//! 内置的转换有`strip-console`，也可以通过`Compiler::with_transform`注册自己的实现。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use lumen_core::{IR, Node, NodeType};
+
+/// 语法树转换
+pub trait Transform: Send + Sync {
+    /// 在配置中引用该转换时使用的名字
+    fn name(&self) -> &str;
+
+    /// 就地修改语法树，出错时中止编译
+    fn apply(&self, ir: &mut IR) -> Result<()>;
+}
+
+/// 配置中引用了没有注册的转换
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("未知的转换器: {0}")]
+pub struct UnknownTransform(pub String);
+
+/// 转换名到实现的映射
+#[derive(Clone)]
+pub struct TransformRegistry {
+    transforms: HashMap<String, Arc<dyn Transform>>,
+}
+
+impl Default for TransformRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransformRegistry {
+    /// 只包含内置转换的注册表
+    pub fn new() -> Self {
+        let mut registry = Self { transforms: HashMap::new() };
+        registry.register(StripConsole);
+        registry
+    }
+
+    /// 按`name()`注册转换，同名的已有转换被替换
+    pub fn register<T: Transform + 'static>(&mut self, transform: T) {
+        self.transforms.insert(transform.name().to_string(), Arc::new(transform));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Transform>> {
+        self.transforms.get(name).cloned()
+    }
+
+    /// 按顺序查找`names`对应的转换，任何一个名字没有注册时报错
+    pub fn resolve(&self, names: &[String]) -> std::result::Result<Vec<Arc<dyn Transform>>, UnknownTransform> {
+        names.iter()
+            .map(|name| self.get(name).ok_or_else(|| UnknownTransform(name.clone())))
+            .collect()
+    }
+}
+
+/// 删除作为语句出现的`console.*(...)`调用
+///
+/// 只处理程序和语句块中直接出现的调用语句，作为表达式一部分的调用（如`var x = console.log(1)`）保持不变。
+pub struct StripConsole;
+
+impl Transform for StripConsole {
+    fn name(&self) -> &str {
+        "strip-console"
+    }
+
+    fn apply(&self, ir: &mut IR) -> Result<()> {
+        let mut calls = Vec::new();
+        collect_console_statements(&ir.get_root(), &mut calls);
+        for id in calls {
+            ir.remove_node(id);
+        }
+        Ok(())
+    }
+}
+
+/// 收集语句列表中的`console.*(...)`调用语句，被删除的语句内部不再查找
+fn collect_console_statements(node: &Node, calls: &mut Vec<usize>) {
+    let holds_statements = matches!(node.node_type, NodeType::Program | NodeType::BlockStatement);
+    for child in &node.children {
+        if holds_statements && is_console_call(&child.0) {
+            calls.push(child.0.id);
+        } else {
+            collect_console_statements(&child.0, calls);
+        }
+    }
+}
+
+/// 被调用者是否是`console`上的成员
+fn is_console_call(node: &Node) -> bool {
+    if node.node_type != NodeType::CallExpression {
+        return false;
+    }
+    let Some(callee) = node.children.first() else {
+        return false;
+    };
+    callee.0.node_type == NodeType::MemberExpression
+        && callee.0.children.first().is_some_and(|object| {
+            object.0.node_type == NodeType::Identifier && object.0.get_string_value("name") == Some("console")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CodeGenerator, Compiler};
+    use lumen_core::{CodegenOptions, NodeValue, test_utils::ident};
+
+    /// 构造调用 `<object>.<method>(x)`
+    fn method_call(ir: &mut IR, object: &str, method: &str) -> usize {
+        let (object, property, arg) = (ident(ir, object), ident(ir, method), ident(ir, "x"));
+        let member = ir.create_node(NodeType::MemberExpression);
+        ir.add_child(member, object);
+        ir.add_child(member, property);
+        let call = ir.create_node(NodeType::CallExpression);
+        ir.add_child(call, member);
+        ir.add_child(call, arg);
+        call
+    }
+
+    #[test]
+    fn test_strip_console_removes_console_call_statements() {
+        let mut ir = IR::new();
+        let log = method_call(&mut ir, "console", "log");
+        ir.add_child(ir.root_id, log);
+        let other = method_call(&mut ir, "logger", "log");
+        ir.add_child(ir.root_id, other);
+
+        // 函数体内的调用同样删除：function f() { console.warn(x); }
+        let body = ir.create_node(NodeType::BlockStatement);
+        let warn = method_call(&mut ir, "console", "warn");
+        ir.add_child(body, warn);
+        let function = ir.create_node(NodeType::FunctionDeclaration);
+        ir.get_node_mut(function).unwrap().set_value("name", NodeValue::String("f".to_string()));
+        ir.add_child(function, body);
+        ir.add_child(ir.root_id, function);
+
+        let registry = TransformRegistry::new();
+        for transform in registry.resolve(&["strip-console".to_string()]).unwrap() {
+            transform.apply(&mut ir).unwrap();
+        }
+        let code = CodeGenerator::new(CodegenOptions::default()).generate(&ir);
+        assert!(!code.contains("console"), "{}", code);
+        assert!(code.contains("logger.log(x);"), "不是console的调用应保留: {}", code);
+        assert!(ir.get_node(warn).is_none(), "节点表应与语法树保持一致");
+
+        // 未注册的转换名在编译时报错
+        assert_eq!(registry.resolve(&["strip-debugger".to_string()]).err(), Some(UnknownTransform("strip-debugger".to_string())));
+        let compiler = Compiler::new().with_cache(false).with_transformers(vec!["strip-debugger".to_string()]);
+        let err = compiler.compile_string("var x = 1;\n").unwrap_err();
+        assert!(err.downcast_ref::<UnknownTransform>().is_some(), "{}", err);
+    }
+}
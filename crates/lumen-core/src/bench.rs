@@ -0,0 +1,136 @@
+//! 性能测试辅助 - 各基准测试程序共用的运行次数、预热和统计
+
+use std::time::Duration;
+
+/// 运行次数和预热次数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// 计入统计的运行次数
+    pub runs: usize,
+    /// 正式运行之前的预热次数，预热的结果不计入统计
+    pub warmup: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { runs: 5, warmup: 1 }
+    }
+}
+
+impl BenchOptions {
+    /// 从命令行参数中取出`--runs N`和`--warmup W`，返回选项和其余的参数
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<(Self, Vec<String>), String> {
+        let mut options = Self::default();
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let target = match arg.as_str() {
+                "--runs" => &mut options.runs,
+                "--warmup" => &mut options.warmup,
+                _ => {
+                    rest.push(arg);
+                    continue;
+                },
+            };
+            let value = args.next().ok_or_else(|| format!("{}缺少参数值", arg))?;
+            *target = value.parse().map_err(|_| format!("{}的参数值无效: {}", arg, value))?;
+        }
+        if options.runs == 0 {
+            return Err("--runs至少为1".to_string());
+        }
+        Ok((options, rest))
+    }
+}
+
+/// 当前是第几次运行，序号从1开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Iteration {
+    Warmup(usize),
+    Measured(usize),
+}
+
+/// 正式运行耗时的统计（毫秒）
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchStats {
+    /// 每次正式运行的耗时，不含预热
+    pub samples_ms: Vec<f64>,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    /// 样本标准差，只有一次运行时为0
+    pub stddev_ms: f64,
+}
+
+impl BenchStats {
+    pub fn from_samples(samples_ms: Vec<f64>) -> Self {
+        let count = samples_ms.len();
+        if count == 0 {
+            return Self { samples_ms, mean_ms: 0.0, median_ms: 0.0, stddev_ms: 0.0 };
+        }
+
+        let mean_ms = samples_ms.iter().sum::<f64>() / count as f64;
+        let mut sorted = samples_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        let median_ms = if count.is_multiple_of(2) {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        } else {
+            sorted[count / 2]
+        };
+        let stddev_ms = if count > 1 {
+            let variance = samples_ms.iter().map(|s| (s - mean_ms).powi(2)).sum::<f64>() / (count - 1) as f64;
+            variance.sqrt()
+        } else {
+            0.0
+        };
+
+        Self { samples_ms, mean_ms, median_ms, stddev_ms }
+    }
+}
+
+/// 先运行`warmup`次预热，再运行`runs`次并统计耗时
+///
+/// `run`返回本次运行的耗时，由调用方决定计时的范围；出错时立即停止并返回错误。
+pub fn measure<F, E>(options: &BenchOptions, mut run: F) -> Result<BenchStats, E>
+where
+    F: FnMut(Iteration) -> Result<Duration, E>,
+{
+    for i in 1..=options.warmup {
+        run(Iteration::Warmup(i))?;
+    }
+    let samples_ms = (1..=options.runs)
+        .map(|i| run(Iteration::Measured(i)).map(|elapsed| elapsed.as_secs_f64() * 1000.0))
+        .collect::<Result<Vec<_>, E>>()?;
+    Ok(BenchStats::from_samples(samples_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_exclude_warmup_iterations() {
+        let args = ["input.js", "--runs", "4", "--warmup", "2"].map(String::from);
+        let (options, rest) = BenchOptions::from_args(args).unwrap();
+        assert_eq!(options, BenchOptions { runs: 4, warmup: 2 });
+        assert_eq!(rest, vec!["input.js".to_string()]);
+
+        // 预热的两次很慢，正式运行依次为10、20、30、40毫秒
+        let mut iterations = Vec::new();
+        let stats = measure(&options, |iteration| {
+            iterations.push(iteration);
+            Ok::<_, ()>(match iteration {
+                Iteration::Warmup(_) => Duration::from_secs(1),
+                Iteration::Measured(i) => Duration::from_millis(10 * i as u64),
+            })
+        }).unwrap();
+
+        assert_eq!(iterations.len(), 6);
+        assert_eq!(&iterations[..2], &[Iteration::Warmup(1), Iteration::Warmup(2)]);
+        assert_eq!(stats.samples_ms, vec![10.0, 20.0, 30.0, 40.0], "统计不应包含预热");
+        assert!((stats.mean_ms - 25.0).abs() < 1e-9);
+        assert!((stats.median_ms - 25.0).abs() < 1e-9);
+        assert!((stats.stddev_ms - (500.0f64 / 3.0).sqrt()).abs() < 1e-9);
+
+        assert!(BenchOptions::from_args(["--runs".to_string()]).is_err());
+        assert!(BenchOptions::from_args(["--runs", "0"].map(String::from)).is_err());
+    }
+}
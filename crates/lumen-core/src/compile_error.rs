@@ -0,0 +1,144 @@
+//! 编译错误的详细信息 - 位置、代码片段和高亮范围，供解析器和编译器共用
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::paths::display_path;
+
+/// 编译错误的详细信息
+#[derive(Debug, Clone)]
+pub struct CompileErrorInfo {
+    /// 源文件路径
+    pub file: Option<String>,
+    /// 显示路径时的基准目录，位于其下的文件显示相对路径
+    pub root_dir: Option<PathBuf>,
+    /// 错误所在行号
+    pub line: Option<usize>,
+    /// 错误所在列号
+    pub column: Option<usize>,
+    /// 错误代码
+    pub code: Option<String>,
+    /// 错误消息
+    pub message: String,
+    /// 相关代码片段
+    pub snippet: Option<String>,
+    /// 高亮范围（开始位置）
+    pub highlight_start: Option<usize>,
+    /// 高亮范围（结束位置）
+    pub highlight_end: Option<usize>,
+}
+
+impl CompileErrorInfo {
+    /// 创建一个新的编译错误信息
+    pub fn new(message: &str) -> Self {
+        Self {
+            file: None,
+            root_dir: None,
+            line: None,
+            column: None,
+            code: None,
+            message: message.to_string(),
+            snippet: None,
+            highlight_start: None,
+            highlight_end: None,
+        }
+    }
+
+    /// 设置源文件路径
+    pub fn with_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    /// 设置显示路径时的基准目录
+    pub fn with_root_dir(mut self, root_dir: &Path) -> Self {
+        self.root_dir = Some(root_dir.to_path_buf());
+        self
+    }
+
+    /// 用于显示的源文件路径
+    pub fn display_file(&self) -> Option<String> {
+        let file = self.file.as_ref()?;
+        Some(display_path(Path::new(file), self.root_dir.as_deref()))
+    }
+
+    /// 设置错误位置
+    pub fn with_position(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    /// 设置错误代码
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// 设置代码片段
+    pub fn with_snippet(mut self, snippet: &str) -> Self {
+        self.snippet = Some(snippet.to_string());
+        self
+    }
+
+    /// 设置高亮范围
+    pub fn with_highlight(mut self, start: usize, end: usize) -> Self {
+        self.highlight_start = Some(start);
+        self.highlight_end = Some(end);
+        self
+    }
+}
+
+impl fmt::Display for CompileErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 错误位置信息
+        if let (Some(file), Some(line), Some(column)) = (self.display_file(), self.line, self.column) {
+            writeln!(f, "错误: {}:{}:{}", file, line, column)?;
+        } else if let Some(file) = self.display_file() {
+            writeln!(f, "错误: {}", file)?;
+        } else {
+            writeln!(f, "错误:")?;
+        }
+
+        // 错误代码和消息
+        if let Some(code) = &self.code {
+            writeln!(f, "[{}] {}", code, self.message)?;
+        } else {
+            writeln!(f, "{}", self.message)?;
+        }
+
+        // 代码片段
+        if let Some(snippet) = &self.snippet {
+            writeln!(f, "\n{}", snippet)?;
+
+            // 高亮错误位置
+            if let (Some(start), Some(end)) = (self.highlight_start, self.highlight_end) {
+                let spaces = " ".repeat(start);
+                let markers = "^".repeat(end - start);
+                writeln!(f, "{}{}", spaces, markers)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_path_relative_to_root_dir() {
+        let root = Path::new("/home/ci/project");
+        let info = CompileErrorInfo::new("意外的符号 ';'")
+            .with_file("/home/ci/project/src/app.ts")
+            .with_position(3, 8)
+            .with_root_dir(root);
+        let expected = Path::new("src").join("app.ts").display().to_string();
+        assert!(info.to_string().starts_with(&format!("错误: {}:3:8\n", expected)), "{}", info);
+
+        // 不在基准目录下的文件保持原路径
+        let outside = CompileErrorInfo::new("找不到模块").with_file("/tmp/other.js").with_root_dir(root);
+        assert!(outside.to_string().starts_with("错误: /tmp/other.js\n"));
+    }
+}
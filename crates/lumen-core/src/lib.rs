@@ -3,6 +3,15 @@ use std::fmt;
 use std::sync::Arc;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 
+pub mod bench;
+mod compile_error;
+pub mod paths;
+mod size;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
+pub use compile_error::CompileErrorInfo;
+
 /// 节点类型枚举
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NodeType {
@@ -21,18 +30,43 @@ pub enum NodeType {
     BinaryExpression,
     UnaryExpression,
     CallExpression,
+    /// `new`表达式，第一个子节点是构造函数，其余为参数
+    NewExpression,
     MemberExpression,
     ArrowFunctionExpression,
     ObjectExpression,
+    /// 对象字面量的属性，子节点为键和值；`computed`为true时键是计算属性名`[expr]`
+    ///
+    /// `kind`为`get`/`set`的访问器和`method`为true的简写方法`foo() {}`，值是没有名字的函数声明节点。
+    Property,
     ArrayExpression,
+    ConditionalExpression,
+    SequenceExpression,
+    AwaitExpression,
+    YieldExpression,
+    /// 元属性，如`import.meta`（`meta`和`property`两个字符串值）
+    MetaProperty,
+    /// 动态导入`import(specifier)`，唯一的子节点是模块说明符
+    ImportExpression,
+    /// 模板字符串，子节点依次为字符串片段和插值表达式，首尾都是字符串片段
+    ///
+    /// 字符串片段是`StringLiteral`节点，`value`为转义后的内容，`raw`为源码中的原始文本。
+    TemplateLiteral,
     
     // 语句
     BlockStatement,
     IfStatement,
     ForStatement,
     WhileStatement,
+    WithStatement,
     TryStatement,
     ReturnStatement,
+    /// 带标签的语句`label: body`，`label`为标签名，唯一的子节点是语句体
+    LabeledStatement,
+    /// `break`语句，可选的`label`为跳出的标签
+    BreakStatement,
+    /// `continue`语句，可选的`label`为继续的标签
+    ContinueStatement,
     
     // 字面量
     StringLiteral,
@@ -119,6 +153,8 @@ impl<'de> Deserialize<'de> for NodeRef {
 pub enum NodeValue {
     String(String),
     Number(f64),
+    /// BigInt字面量的十进制数字（不含后缀`n`），f64无法精确表示任意大小的整数
+    BigInt(String),
     Boolean(bool),
     Null,
     Array(Vec<NodeRef>),
@@ -190,6 +226,21 @@ impl Node {
             _ => None,
         }
     }
+    
+    /// 获取节点的前导注释内容（不含注释符号）
+    pub fn leading_comments(&self) -> Vec<&str> {
+        match self.get_value("leading_comments") {
+            Some(NodeValue::Array(comments)) => comments.iter()
+                .filter_map(|c| c.0.get_string_value("value"))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    
+    /// 前导注释中是否包含指定标注，如 `@lumen-ignore`
+    pub fn has_annotation(&self, annotation: &str) -> bool {
+        self.leading_comments().iter().any(|c| c.trim() == annotation)
+    }
 }
 
 /// Lumen中间表示（IR）
@@ -231,6 +282,31 @@ impl IR {
         self.nodes.get(&id).cloned().map(|node_ref| node_ref.0)
     }
     
+    /// 获取节点的可变引用，节点被共享时会先克隆（写时复制）
+    ///
+    /// 注意：已挂到父节点`children`中的是节点的快照，修改应在`add_child`之前完成。
+    pub fn get_node_mut(&mut self, id: usize) -> Option<&mut Node> {
+        self.nodes.get_mut(&id).map(|node_ref| Arc::make_mut(&mut node_ref.0))
+    }
+    
+    /// 为节点添加一条前导注释，注释以`Comment`节点的形式保存在`leading_comments`中
+    pub fn add_leading_comment(&mut self, node_id: usize, text: &str, block: bool) {
+        let comment_id = self.create_node(NodeType::Comment);
+        if let Some(node) = self.get_node_mut(comment_id) {
+            node.set_value("value", NodeValue::String(text.to_string()));
+            node.set_value("block", NodeValue::Boolean(block));
+            node.parent = Some(node_id);
+        }
+        let comment = self.nodes[&comment_id].clone();
+        
+        if let Some(node) = self.get_node_mut(node_id) {
+            match node.values.get_mut("leading_comments") {
+                Some(NodeValue::Array(comments)) => comments.push(comment),
+                _ => node.set_value("leading_comments", NodeValue::Array(vec![comment])),
+            }
+        }
+    }
+    
     pub fn create_node(&mut self, node_type: NodeType) -> usize {
         let id = self.next_id;
         self.next_id += 1;
@@ -271,6 +347,80 @@ impl IR {
         }
     }
     
+    /// 可变地访问节点表中的每个节点，访问顺序不确定
+    ///
+    /// 节点通过`Arc::make_mut`取得可变引用，仍被共享的节点（例如同时出现在父节点的子节点列表中）
+    /// 会先被克隆。访问结束后按节点表重建从根节点出发的子节点引用，使树上的快照与节点表一致。
+    pub fn visit_mut<F>(&mut self, mut visitor: F)
+    where
+        F: FnMut(&mut Node),
+    {
+        for node_ref in self.nodes.values_mut() {
+            visitor(Arc::make_mut(&mut node_ref.0));
+        }
+        self.relink(self.root_id);
+    }
+    
+    /// 自底向上用节点表中的节点替换子节点引用，返回更新后的节点；不在节点表中的子节点保持原样
+    fn relink(&mut self, id: usize) -> Option<NodeRef> {
+        let node = self.nodes.get(&id)?.0.clone();
+        let children: Vec<NodeRef> = node.children.iter()
+            .map(|child| self.relink(child.0.id).unwrap_or_else(|| child.clone()))
+            .collect();
+        let node_ref = self.nodes.get_mut(&id)?;
+        Arc::make_mut(&mut node_ref.0).children = children;
+        Some(node_ref.clone())
+    }
+    
+    /// 删除节点及其所有子孙节点（包括前导注释），返回删除的节点
+    ///
+    /// 节点同时从父节点的`children`中移除，根节点到父节点路径上的各个节点替换为更新后的副本，
+    /// 树与节点表保持一致。根节点不能删除；节点不存在时不做任何修改，返回None。
+    pub fn remove_node(&mut self, id: usize) -> Option<Arc<Node>> {
+        if id == self.root_id || !self.nodes.contains_key(&id) {
+            return None;
+        }
+        let root = self.get_root();
+        // 不在树上的节点只从节点表中删除
+        let removed = match child_path(&root, id) {
+            Some(path) => self.detach(&root, &path).1,
+            None => self.nodes[&id].0.clone(),
+        };
+        
+        let mut stack = vec![removed.clone()];
+        while let Some(node) = stack.pop() {
+            self.nodes.remove(&node.id);
+            stack.extend(node.children.iter().map(|child| child.0.clone()));
+            if let Some(NodeValue::Array(comments)) = node.get_value("leading_comments") {
+                stack.extend(comments.iter().map(|comment| comment.0.clone()));
+            }
+        }
+        Some(removed)
+    }
+    
+    /// 按子节点下标路径删除节点，返回更新后的`node`和删除的节点；沿途更新的节点写回节点表
+    fn detach(&mut self, node: &Node, path: &[usize]) -> (NodeRef, Arc<Node>) {
+        let mut updated = node.clone();
+        let removed = match path {
+            [index] => updated.children.remove(*index).0,
+            [index, rest @ ..] => {
+                let (child, removed) = self.detach(&node.children[*index].0, rest);
+                updated.children[*index] = child;
+                removed
+            },
+            [] => unreachable!("路径至少包含一个下标"),
+        };
+        let updated = NodeRef(Arc::new(updated));
+        self.nodes.insert(updated.0.id, updated.clone());
+        (updated, removed)
+    }
+    
+    /// 程序开头的指令序言中是否有`"use strict"`
+    pub fn has_strict_directive(&self) -> bool {
+        let statements = &self.get_root().children;
+        (0..statements.len()).any(|i| is_strict_prologue(statements, i))
+    }
+    
     pub fn to_json(&self) -> String {
         // 简单实现，实际项目中应使用serde等库
         let mut json = String::new();
@@ -282,14 +432,293 @@ impl IR {
     }
 }
 
+/// 从`node`到`id`节点的子节点下标路径，`id`不在子树中时返回None
+fn child_path(node: &Node, id: usize) -> Option<Vec<usize>> {
+    node.children.iter().enumerate().find_map(|(i, child)| {
+        if child.0.id == id {
+            return Some(vec![i]);
+        }
+        let mut path = child_path(&child.0, id)?;
+        path.insert(0, i);
+        Some(path)
+    })
+}
+
+/// 语句是否为`"use strict"`指令（作为语句出现的字符串字面量）
+pub fn is_strict_directive(node: &Node) -> bool {
+    node.node_type == NodeType::StringLiteral && node.get_string_value("value") == Some("use strict")
+}
+
+/// 第`index`条语句是否为指令序言（开头连续的字符串字面量语句）中的`"use strict"`
+pub fn is_strict_prologue(statements: &[NodeRef], index: usize) -> bool {
+    statements.get(..=index).is_some_and(|prologue| {
+        prologue.iter().all(|stmt| stmt.0.node_type == NodeType::StringLiteral)
+            && is_strict_directive(&prologue[index].0)
+    })
+}
+
+/// 数值的JS写法，`NaN`和正负无穷分别写为`NaN`、`Infinity`和`-Infinity`
+pub fn format_number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// 数值最短的JS写法，压缩时使用
+///
+/// 在十进制、省略前导零的小数（`.5`）、科学计数法（`1e6`）和十六进制（`0x...`，只用于整数）中
+/// 选最短的一种，长度相同时保留十进制。
+pub fn shortest_number(value: f64) -> String {
+    if !value.is_finite() {
+        return format_number(value);
+    }
+    if value < 0.0 {
+        return format!("-{}", shortest_number(-value));
+    }
+
+    let decimal = format_number(value);
+    let mut candidates = vec![decimal.strip_prefix("0.").map_or_else(|| decimal.clone(), |fraction| format!(".{}", fraction))];
+    candidates.push(format!("{:e}", value));
+    // 超出安全整数范围的整数转为u64时可能不精确，不考虑十六进制
+    if value.fract() == 0.0 && value <= 9007199254740991.0 {
+        candidates.push(format!("0x{:x}", value as u64));
+    }
+    candidates.into_iter().min_by_key(|candidate| candidate.len()).unwrap()
+}
+
+/// 数值字面量节点的JS写法
+///
+/// 源码中的十六进制、八进制、二进制和BigInt字面量记录在`raw`中，不压缩时按原样输出；
+/// 压缩时数值使用最短写法，BigInt使用十进制加后缀`n`。
+pub fn numeric_literal_text(node: &Node, minify: bool) -> Option<String> {
+    let raw = node.get_string_value("raw").filter(|_| !minify);
+    match node.get_value("value")? {
+        _ if raw.is_some() => raw.map(str::to_string),
+        NodeValue::Number(value) if minify => Some(shortest_number(*value)),
+        NodeValue::Number(value) => Some(format_number(*value)),
+        NodeValue::BigInt(digits) => Some(format!("{}n", digits)),
+        _ => None,
+    }
+}
+
+/// 一元运算符和运算数之间是否需要空格
+///
+/// 关键字运算符（`typeof`、`void`、`delete`）总是需要；`-`、`+`后面紧跟同一个符号时
+/// 会被读成`--`、`++`，如`- -x`、`- -3`（折叠得到的负数字面量）。
+pub fn space_after_unary(operator: &str, operand: &Node) -> bool {
+    if !operator.is_empty() && operator.chars().all(|c| c.is_ascii_alphabetic()) {
+        return true;
+    }
+    let starts_with = |c: char| match operand.node_type {
+        NodeType::UnaryExpression => operand.get_string_value("operator").is_some_and(|op| op.starts_with(c)),
+        NodeType::NumericLiteral => c == '-' && operand.get_number_value("value").is_some_and(|n| n.is_sign_negative()),
+        _ => false,
+    };
+    matches!(operator, "-" | "+") && starts_with(operator.chars().next().unwrap_or_default())
+}
+
+/// 把字符串内容写成双引号字符串字面量
+pub fn quote_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{2028}' => quoted.push_str("\\u2028"),
+            '\u{2029}' => quoted.push_str("\\u2029"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// 删除`before`与`after`之间的空白后两个记号是否会粘在一起，如`var x`、`a - -b`
+pub fn separates_tokens(before: char, after: char) -> bool {
+    let word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    (word(before) && word(after)) || (before == after && matches!(before, '+' | '-'))
+}
+
+/// 属性能否按简写`{ a }`输出：解析时是简写，且键和值仍是同名的标识符（值被重命名后需要写成`a: b`）
+pub fn is_shorthand_property(property: &Node) -> bool {
+    let [key, value] = property.children.as_slice() else {
+        return false;
+    };
+    property.get_boolean_value("shorthand") == Some(true)
+        && value.0.node_type == NodeType::Identifier
+        && key.0.get_string_value("name").is_some_and(|name| value.0.get_string_value("name") == Some(name))
+}
+
+/// 法律注释（`/*!`开头或包含`@license`、`@preserve`的注释）的输出方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegalComments {
+    /// 保留在所在的顶层语句之前
+    #[default]
+    Inline,
+    /// 去重后集中放到文件末尾
+    Eof,
+    /// 写入单独的`<输出文件>.LEGAL.txt`，输出中只保留指向该文件的注释
+    Linked,
+    /// 全部删除
+    None,
+}
+
+/// 注释内容是否为法律注释
+pub fn is_legal_comment(text: &str) -> bool {
+    text.starts_with('!') || text.contains("@license") || text.contains("@preserve")
+}
+
+/// `Comment`节点带注释符号的写法，`block`为false时是行注释
+pub fn comment_source(comment: &Node) -> Option<String> {
+    let text = comment.get_string_value("value")?;
+    if comment.get_boolean_value("block") == Some(false) {
+        Some(format!("//{}", text))
+    } else {
+        Some(format!("/*{}*/", text))
+    }
+}
+
+/// 子树中所有节点前导注释里的法律注释，按出现顺序带注释符号返回
+pub fn legal_comments_in(node: &Node) -> Vec<String> {
+    let mut comments = Vec::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if let Some(NodeValue::Array(leading)) = node.get_value("leading_comments") {
+            for comment in leading {
+                if comment.0.get_string_value("value").is_some_and(is_legal_comment) {
+                    comments.extend(comment_source(&comment.0));
+                }
+            }
+        }
+        stack.extend(node.children.iter().rev().map(|c| c.0.as_ref()));
+    }
+    comments
+}
+
+/// 严格模式指令的处理方式，与目标环境无关
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrictMode {
+    /// 保留源码中已有的指令，不额外添加
+    #[default]
+    Preserve,
+    /// 源码中没有指令时在输出开头添加`"use strict";`
+    Always,
+    /// 删除程序开头的`"use strict"`指令
+    Never,
+}
+
+/// 输出模块格式
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum OutputFormat {
+    /// ES模块，保留import/export
+    #[default]
+    Esm,
+    /// 立即执行函数，导出对象赋值给`window.<global_name>`
+    Iife { global_name: String },
+    /// 通用模块定义，依次尝试CommonJS、AMD，最后回退到全局变量`<global_name>`
+    Umd { global_name: String },
+    /// CommonJS模块，导出赋值给`exports`对象
+    CommonJs,
+}
+
+impl OutputFormat {
+    /// 是否需要把导出改写为对`exports`对象的赋值
+    pub fn wraps_exports(&self) -> bool {
+        !matches!(self, OutputFormat::Esm)
+    }
+}
+
+/// 目标环境支持的语法特性，不支持的特性会在代码生成时降级
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetFeatures {
+    /// 箭头函数（ES2015）
+    pub arrow_functions: bool,
+    /// const/let块级作用域（ES2015）
+    pub block_scoping: bool,
+    /// class语法（ES2015）
+    pub classes: bool,
+    /// async/await（ES2017）
+    pub async_await: bool,
+    /// 可选链`?.`（ES2020）
+    pub optional_chaining: bool,
+    /// 模板字符串（ES2015）
+    pub template_literals: bool,
+}
+
+impl TargetFeatures {
+    /// 所有特性均支持
+    pub fn all() -> Self {
+        Self {
+            arrow_functions: true,
+            block_scoping: true,
+            classes: true,
+            async_await: true,
+            optional_chaining: true,
+            template_literals: true,
+        }
+    }
+
+    /// 所有特性均不支持（ES5）
+    pub fn none() -> Self {
+        Self {
+            arrow_functions: false,
+            block_scoping: false,
+            classes: false,
+            async_await: false,
+            optional_chaining: false,
+            template_literals: false,
+        }
+    }
+
+    /// 根据目标字符串展开默认特性集，未知目标视为esnext
+    pub fn for_target(target: &str) -> Self {
+        let year = match target.to_lowercase().as_str() {
+            "es5" => return Self::none(),
+            "es6" => 2015,
+            t => t.strip_prefix("es")
+                .and_then(|y| y.parse::<u32>().ok())
+                .unwrap_or(u32::MAX),
+        };
+
+        Self {
+            arrow_functions: year >= 2015,
+            block_scoping: year >= 2015,
+            classes: year >= 2015,
+            async_await: year >= 2017,
+            optional_chaining: year >= 2020,
+            template_literals: year >= 2015,
+        }
+    }
+}
+
 /// 代码生成选项
 #[derive(Debug, Clone)]
 pub struct CodegenOptions {
     pub minify: bool,
     pub sourcemap: bool,
     pub target: String,
+    /// 覆盖`target`展开的默认特性集，None时由`target`决定
+    pub target_features: Option<TargetFeatures>,
     pub inline_sources: bool,
+    /// 不压缩时在语句之前输出源码中的注释，法律注释的输出方式由`legal_comments`决定
     pub preserve_comments: bool,
+    pub output_format: OutputFormat,
+    /// 单个输出文件的最大字节数，超出时在语句边界拆分为多个chunk
+    pub max_chunk_bytes: Option<usize>,
+    /// 严格模式指令的处理方式
+    pub strict: StrictMode,
+    /// 法律注释的输出方式
+    pub legal_comments: LegalComments,
+    /// 未压缩时代码块每层缩进的空格数，0表示不缩进
+    pub indent: usize,
 }
 
 impl Default for CodegenOptions {
@@ -298,12 +727,25 @@ impl Default for CodegenOptions {
             minify: false,
             sourcemap: false,
             target: "es2020".to_string(),
+            target_features: None,
             inline_sources: false,
             preserve_comments: true,
+            output_format: OutputFormat::default(),
+            max_chunk_bytes: None,
+            strict: StrictMode::Preserve,
+            legal_comments: LegalComments::Inline,
+            indent: 0,
         }
     }
 }
 
+impl CodegenOptions {
+    /// 实际生效的目标特性集
+    pub fn features(&self) -> TargetFeatures {
+        self.target_features.unwrap_or_else(|| TargetFeatures::for_target(&self.target))
+    }
+}
+
 // 错误处理模块
 pub mod error {
     use thiserror::Error;
@@ -333,4 +775,84 @@ pub mod types {
         pub id: usize,
         pub node_type: NodeType,
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visit_mut_updates_nodes_and_tree() {
+        // var a = 1; var b = 2;
+        let mut ir = IR::new();
+        for (name, value) in [("a", 1.0), ("b", 2.0)] {
+            let decl = ir.create_node(NodeType::VariableDeclaration);
+            let ident = ir.create_node(NodeType::Identifier);
+            let lit = ir.create_node(NodeType::NumericLiteral);
+            ir.get_node_mut(ident).unwrap().set_value("name", NodeValue::String(name.to_string()));
+            ir.get_node_mut(lit).unwrap().set_value("value", NodeValue::Number(value));
+            ir.add_child(decl, ident);
+            ir.add_child(decl, lit);
+            ir.add_child(ir.root_id, decl);
+        }
+
+        let mut visited = 0;
+        ir.visit_mut(|node| {
+            visited += 1;
+            if let Some(n) = node.get_number_value("value") {
+                node.set_value("value", NodeValue::Number(n * 10.0));
+            }
+        });
+        assert_eq!(visited, ir.nodes.len());
+
+        // 从根节点出发遍历看到的是修改后的节点
+        let mut values = Vec::new();
+        ir.visit(|node| values.extend(node.get_number_value("value")));
+        assert_eq!(values, vec![10.0, 20.0]);
+        let root = ir.get_root();
+        let lit = &root.children[1].0.children[1];
+        assert!(Arc::ptr_eq(&lit.0, &ir.nodes[&lit.0.id].0), "树上的子节点应与节点表中的是同一个节点");
+    }
+
+    #[test]
+    fn test_remove_node_detaches_subtree() {
+        // var a = 1; /* 注释 */ var b = 2;
+        let mut ir = IR::new();
+        let mut decls = Vec::new();
+        for name in ["a", "b"] {
+            let decl = ir.create_node(NodeType::VariableDeclaration);
+            let ident = ir.create_node(NodeType::Identifier);
+            let lit = ir.create_node(NodeType::NumericLiteral);
+            ir.get_node_mut(ident).unwrap().set_value("name", NodeValue::String(name.to_string()));
+            if name == "b" {
+                ir.add_leading_comment(decl, " 注释 ", true);
+            }
+            ir.add_child(decl, ident);
+            ir.add_child(decl, lit);
+            ir.add_child(ir.root_id, decl);
+            decls.push((decl, lit));
+        }
+        let (decl_a, lit_a) = decls[0];
+        let (decl_b, _) = decls[1];
+
+        // 删除嵌套的字面量：父节点在节点表和树上都只剩一个子节点
+        let removed = ir.remove_node(lit_a).unwrap();
+        assert_eq!(removed.node_type, NodeType::NumericLiteral);
+        assert!(ir.get_node(lit_a).is_none());
+        assert_eq!(ir.get_node(decl_a).unwrap().children.len(), 1);
+        assert_eq!(ir.get_root().children[0].0.children.len(), 1);
+        assert!(Arc::ptr_eq(&ir.get_root().children[0].0, &ir.nodes[&decl_a].0));
+
+        // 删除整条语句时子孙节点和前导注释一并删除
+        let nodes_before = ir.nodes.len();
+        ir.remove_node(decl_b).unwrap();
+        assert_eq!(ir.nodes.len(), nodes_before - 4);
+        assert_eq!(ir.get_root().children.len(), 1);
+        assert_eq!(ir.get_root().children[0].0.id, decl_a);
+
+        // 根节点和不存在的节点不做修改
+        assert!(ir.remove_node(ir.root_id).is_none());
+        assert!(ir.remove_node(decl_b).is_none());
+        assert_eq!(ir.nodes.len(), nodes_before - 4);
+    }
+}
@@ -0,0 +1,98 @@
+//! 输出路径计算 - 统一处理`/`与`\`混用的输入路径，保证各平台得到相同的输出文件名
+
+use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
+use anyhow::{Result, anyhow};
+
+/// 把路径中的`/`和`\`都视为分隔符，按当前平台的分隔符重新拼接
+///
+/// 同时去掉空段和`.`段；保留开头的根目录和Windows盘符（如`C:`）。
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    let mut normalized = PathBuf::new();
+    if text.starts_with(['/', '\\']) {
+        normalized.push(MAIN_SEPARATOR_STR);
+    }
+
+    for (i, segment) in text.split(['/', '\\']).filter(|s| !s.is_empty() && *s != ".").enumerate() {
+        if i == 0 && segment.len() == 2 && segment.ends_with(':') {
+            // 盘符后必须带分隔符，否则在Windows上表示相对于该盘当前目录的路径
+            normalized.push(format!("{}{}", segment, MAIN_SEPARATOR_STR));
+        } else {
+            normalized.push(segment);
+        }
+    }
+    normalized
+}
+
+/// 用于错误信息和日志的路径：位于`root_dir`下时显示相对路径，否则显示原路径
+pub fn display_path(path: &Path, root_dir: Option<&Path>) -> String {
+    let normalized = normalize_path(path);
+    root_dir
+        .and_then(|root| normalized.strip_prefix(normalize_path(root)).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map_or_else(|| path.display().to_string(), |relative| relative.display().to_string())
+}
+
+/// 批量编译时的输出路径：`<output_dir>/<输入文件名>.js`
+pub fn output_path_in_dir(input: &Path, output_dir: &Path) -> PathBuf {
+    let input = normalize_path(input);
+    let mut path = normalize_path(output_dir).join(input.file_name().unwrap_or_default());
+    path.set_extension("js");
+    path
+}
+
+/// 保留目录结构的输出路径：`input`相对于`base_dir`的路径放到`output_dir`下，扩展名改为`.js`
+pub fn relative_output_path(input: &Path, base_dir: &Path, output_dir: &Path) -> Result<PathBuf> {
+    let input = normalize_path(input);
+    let base_dir = normalize_path(base_dir);
+    let relative = input.strip_prefix(&base_dir)
+        .map_err(|_| anyhow!("文件 {} 不在目录 {} 中", input.display(), base_dir.display()))?;
+
+    let mut path = normalize_path(output_dir).join(relative);
+    path.set_extension("js");
+    Ok(path)
+}
+
+/// 所有输入文件所在目录的最长公共前缀，用作保留目录结构时的默认基准目录
+pub fn common_base_dir<P: AsRef<Path>>(inputs: &[P]) -> PathBuf {
+    let mut dirs = inputs.iter()
+        .map(|input| normalize_path(input.as_ref()).parent().map(Path::to_path_buf).unwrap_or_default());
+    let Some(first) = dirs.next() else {
+        return PathBuf::new();
+    };
+    dirs.fold(first, |base, dir| {
+        base.components()
+            .zip(dir.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_separators_give_consistent_output_paths() {
+        let expected = Path::new("dist").join("app.js");
+        for input in ["src/app.ts", "src\\app.ts", "./src/app.ts", ".\\src\\app.ts", "src//app.ts"] {
+            assert_eq!(output_path_in_dir(Path::new(input), Path::new("dist")), expected, "输入: {}", input);
+        }
+        assert_eq!(output_path_in_dir(Path::new("src/app.ts"), Path::new("dist\\")), expected);
+
+        let expected = Path::new("dist").join("components").join("button.js");
+        for (input, base) in [
+            ("proj/src/components/button.tsx", "proj/src"),
+            ("proj\\src\\components\\button.tsx", "proj/src"),
+            ("proj/src\\components/button.tsx", "proj\\src\\"),
+        ] {
+            let path = relative_output_path(Path::new(input), Path::new(base), Path::new("dist")).unwrap();
+            assert_eq!(path, expected, "输入: {} 基准目录: {}", input, base);
+        }
+        assert!(relative_output_path(Path::new("other/a.js"), Path::new("proj/src"), Path::new("dist")).is_err());
+
+        let drive = normalize_path(Path::new("C:\\work/src\\a.js"));
+        assert_eq!(drive, Path::new(&format!("C:{0}work{0}src{0}a.js", MAIN_SEPARATOR_STR)));
+    }
+}
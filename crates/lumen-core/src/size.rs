@@ -0,0 +1,592 @@
+use std::mem::size_of;
+use std::sync::Arc;
+
+use crate::{CodegenOptions, IR, Node, NodeRef, NodeType, NodeValue, OutputFormat, StrictMode, comment_source, is_shorthand_property, is_strict_prologue, quote_string, separates_tokens, space_after_unary};
+
+impl IR {
+    /// 估算代码生成输出的字节数
+    ///
+    /// 按代码生成器的输出格式累加每个节点产生的记号长度，不实际拼接代码；压缩时不计空白。
+    /// 目标降级只计入箭头函数和const/let，async降级等少见的改写按原样估算。
+    pub fn estimated_output_size(&self, options: &CodegenOptions) -> usize {
+        let features = options.features();
+        let mut estimator = SizeEstimator {
+            arrow_functions: features.arrow_functions,
+            block_scoping: features.block_scoping,
+            wraps_exports: options.output_format.wraps_exports(),
+            minify: options.minify,
+            comments: options.preserve_comments && !options.minify,
+            tokens: 0,
+            whitespace: 0,
+            previous: None,
+            pending_space: false,
+        };
+
+        if options.strict == StrictMode::Always && !self.has_strict_directive() {
+            estimator.text("\"use strict\";\n");
+        }
+        match &options.output_format {
+            OutputFormat::Esm | OutputFormat::CommonJs => {},
+            OutputFormat::Iife { global_name } => {
+                estimator.text("window.");
+                estimator.text(global_name);
+                estimator.text(" = (function () {\nvar exports = {};\nreturn exports;\n})();\n");
+            },
+            OutputFormat::Umd { global_name } => {
+                estimator.text(UMD_WRAPPER);
+                estimator.text(global_name);
+            },
+        }
+        let statements = &self.get_root().children;
+        for (i, stmt) in statements.iter().enumerate() {
+            if options.strict == StrictMode::Never && is_strict_prologue(statements, i) {
+                continue;
+            }
+            estimator.statement(&stmt.0);
+        }
+
+        if options.minify {
+            estimator.tokens
+        } else {
+            estimator.tokens + estimator.whitespace
+        }
+    }
+}
+
+impl IR {
+    /// 估算IR占用的堆内存字节数
+    ///
+    /// 按节点表中每个节点的结构体大小、Arc头、子节点列表和属性值的容量累加，不计哈希表的空槽。
+    pub fn estimated_memory_bytes(&self) -> usize {
+        let arc_header = size_of::<Arc<Node>>() * 2;
+        self.nodes.values()
+            .map(|node| {
+                let node = &node.0;
+                let values: usize = node.values.iter()
+                    .map(|(key, value)| size_of::<(String, NodeValue)>() + key.capacity() + value_heap_bytes(value))
+                    .sum();
+                size_of::<(usize, NodeRef)>()
+                    + arc_header
+                    + size_of::<Node>()
+                    + node.children.capacity() * size_of::<NodeRef>()
+                    + values
+            })
+            .sum()
+    }
+}
+
+/// 属性值自身持有的堆内存（子节点引用只计指针，节点本身在节点表中单独计算）
+fn value_heap_bytes(value: &NodeValue) -> usize {
+    match value {
+        NodeValue::String(s) | NodeValue::BigInt(s) => s.capacity(),
+        NodeValue::Array(items) => items.capacity() * size_of::<NodeRef>(),
+        NodeValue::Object(map) => map.keys().map(|k| k.capacity() + size_of::<(String, NodeRef)>()).sum(),
+        NodeValue::Number(_) | NodeValue::Boolean(_) | NodeValue::Null => 0,
+    }
+}
+
+/// UMD包装中除全局名以外的固定部分
+const UMD_WRAPPER: &str = concat!(
+    "(function (root, factory) {\n",
+    "if (typeof module === \"object\" && module.exports) {\n",
+    "module.exports = factory();\n",
+    "} else if (typeof define === \"function\" && define.amd) {\n",
+    "define([], factory);\n",
+    "} else {\n",
+    "root. = factory();\n",
+    "}\n",
+    "})(typeof self !== \"undefined\" ? self : this, function () {\n",
+    "var exports = {};\n",
+    "return exports;\n",
+    "});\n",
+);
+
+/// 按代码生成器的格式累计记号和空白的字节数
+struct SizeEstimator {
+    arrow_functions: bool,
+    block_scoping: bool,
+    wraps_exports: bool,
+    /// 压缩时数值按最短写法输出
+    minify: bool,
+    /// 语句的前导注释在原处输出
+    comments: bool,
+    tokens: usize,
+    whitespace: usize,
+    /// 上一个非空白字符，以及它之后是否有空白，用于计算压缩时保留的空格
+    previous: Option<char>,
+    pending_space: bool,
+}
+
+impl SizeEstimator {
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if matches!(c, ' ' | '\n' | '\t') {
+                self.whitespace += 1;
+                self.pending_space = true;
+                continue;
+            }
+            // 压缩时两个记号之间保留一个空白
+            if std::mem::take(&mut self.pending_space) && self.previous.is_some_and(|before| separates_tokens(before, c)) {
+                self.whitespace -= 1;
+                self.tokens += 1;
+            }
+            self.previous = Some(c);
+            self.tokens += c.len_utf8();
+        }
+    }
+
+    fn statement(&mut self, node: &Node) {
+        if let (true, Some(NodeValue::Array(comments))) = (self.comments, node.get_value("leading_comments")) {
+            for comment in comments.iter().filter_map(|c| comment_source(&c.0)) {
+                self.text(&comment);
+                self.text("\n");
+            }
+        }
+        match node.node_type {
+            NodeType::VariableDeclaration => {
+                self.declarator(node);
+                self.text(";\n");
+            },
+            NodeType::ImportDeclaration => self.import(node),
+            NodeType::ExportDeclaration => self.export(node),
+            NodeType::BlockStatement => {
+                self.text("{\n");
+                for stmt in &node.children {
+                    self.statement(&stmt.0);
+                }
+                self.text("}\n");
+            },
+            NodeType::FunctionDeclaration => self.function(node),
+            NodeType::ReturnStatement => {
+                self.text("return");
+                if let Some(arg) = node.children.first() {
+                    self.text(" ");
+                    self.expression(&arg.0);
+                }
+                self.text(";\n");
+            },
+            NodeType::IfStatement => {
+                let [test, consequent, rest @ ..] = node.children.as_slice() else {
+                    return;
+                };
+                self.text("if () ");
+                self.expression(&test.0);
+                self.body(&consequent.0);
+                if let Some(alternate) = rest.first() {
+                    // 前一个分支结尾的换行被替换为" else "
+                    self.whitespace = self.whitespace.saturating_sub(1);
+                    self.text(" else ");
+                    self.body(&alternate.0);
+                }
+            },
+            NodeType::WhileStatement => {
+                let [test, body] = node.children.as_slice() else {
+                    return;
+                };
+                self.text("while () ");
+                self.loop_clause(&test.0);
+                self.body(&body.0);
+            },
+            NodeType::ForStatement => {
+                let [init, test, update, body] = node.children.as_slice() else {
+                    return;
+                };
+                self.text("for (");
+                self.loop_clause(&init.0);
+                for clause in [test, update] {
+                    self.text(";");
+                    if clause.0.node_type != NodeType::Unknown {
+                        self.text(" ");
+                        self.loop_clause(&clause.0);
+                    }
+                }
+                self.text(") ");
+                self.body(&body.0);
+            },
+            NodeType::LabeledStatement => {
+                let Some(body) = node.children.first() else {
+                    return;
+                };
+                self.text(node.get_string_value("label").unwrap_or(""));
+                self.text(": ");
+                self.statement(&body.0);
+            },
+            NodeType::BreakStatement | NodeType::ContinueStatement => {
+                self.text(if node.node_type == NodeType::BreakStatement { "break" } else { "continue" });
+                if let Some(label) = node.get_string_value("label") {
+                    self.text(" ");
+                    self.text(label);
+                }
+                self.text(";\n");
+            },
+            NodeType::SequenceExpression => {
+                self.separated(&node.children);
+                self.text(";\n");
+            },
+            _ => {
+                let before = self.tokens;
+                self.expression(node);
+                if self.tokens > before {
+                    self.text(";\n");
+                }
+            },
+        }
+    }
+
+    /// 不含结尾分号的变量声明
+    fn declarator(&mut self, node: &Node) {
+        let kind = node.get_string_value("kind").unwrap_or("var");
+        self.text(if self.block_scoping { kind } else { "var" });
+        self.text(" ");
+        if let Some(name) = node.children.first() {
+            self.expression(&name.0);
+        }
+        if let Some(init) = node.children.get(1) {
+            self.text(" = ");
+            self.expression(&init.0);
+        }
+    }
+
+    fn loop_clause(&mut self, node: &Node) {
+        match node.node_type {
+            NodeType::Unknown => {},
+            NodeType::VariableDeclaration => self.declarator(node),
+            NodeType::SequenceExpression => self.separated(&node.children),
+            _ => self.expression(node),
+        }
+    }
+
+    /// 控制语句的语句体，只包含一条非声明语句的代码块省略花括号
+    fn body(&mut self, node: &Node) {
+        if node.node_type == NodeType::BlockStatement {
+            if let [only] = node.children.as_slice() {
+                let is_declaration = matches!(
+                    only.0.node_type,
+                    NodeType::VariableDeclaration | NodeType::FunctionDeclaration | NodeType::ClassDeclaration
+                );
+                if !is_declaration {
+                    self.statement(&only.0);
+                    return;
+                }
+            }
+        }
+        self.statement(node);
+    }
+
+    fn function(&mut self, node: &Node) {
+        let Some((body, params)) = node.children.split_last() else {
+            return;
+        };
+        if node.get_boolean_value("async") == Some(true) {
+            self.text("async ");
+        }
+        self.text(if node.get_boolean_value("generator") == Some(true) { "function* " } else { "function " });
+        self.text(node.get_string_value("name").unwrap_or(""));
+        self.text("() ");
+        self.params(params);
+        self.statement(&body.0);
+    }
+
+    fn export(&mut self, node: &Node) {
+        if node.get_boolean_value("default") == Some(true) {
+            if let Some(expr) = node.children.first() {
+                self.text(if self.wraps_exports { "exports.default = " } else { "export default " });
+                self.expression(&expr.0);
+                self.text(";\n");
+            }
+            return;
+        }
+
+        let mut specifiers = 0;
+        for child in &node.children {
+            let child = &child.0;
+            if child.node_type == NodeType::Identifier {
+                let local = child.get_string_value("name").unwrap_or("");
+                let exported = child.get_string_value("exported").unwrap_or(local);
+                if self.wraps_exports {
+                    // exports.exported = local;\n
+                    self.text("exports. = ;\n");
+                    self.text(exported);
+                    self.text(local);
+                } else {
+                    self.text(local);
+                    if exported != local {
+                        self.text(" as ");
+                        self.text(exported);
+                    }
+                }
+                specifiers += 1;
+            } else {
+                if !self.wraps_exports {
+                    self.text("export ");
+                }
+                self.statement(child);
+                let declared = match child.node_type {
+                    NodeType::VariableDeclaration => child.children.first().and_then(|c| c.0.get_string_value("name")),
+                    NodeType::FunctionDeclaration | NodeType::ClassDeclaration => child.get_string_value("name"),
+                    _ => None,
+                };
+                if let (true, Some(name)) = (self.wraps_exports, declared) {
+                    self.text("exports. = ;\n");
+                    self.text(name);
+                    self.text(name);
+                }
+            }
+        }
+        if specifiers > 0 && !self.wraps_exports {
+            // export { a, b };\n
+            self.text("export {  };\n");
+            self.text(&", ".repeat(specifiers - 1));
+        }
+    }
+
+    fn expression(&mut self, node: &Node) {
+        match node.node_type {
+            NodeType::NumericLiteral => {
+                if let Some(text) = crate::numeric_literal_text(node, self.minify) {
+                    self.text(&text);
+                }
+            },
+            NodeType::StringLiteral => {
+                if let Some(value) = node.get_string_value("value") {
+                    self.text(&quote_string(value));
+                }
+            },
+            NodeType::TemplateLiteral => {
+                self.text("``");
+                for (i, part) in node.children.iter().enumerate() {
+                    if i % 2 == 0 {
+                        self.text(part.0.get_string_value("raw").unwrap_or(""));
+                    } else {
+                        self.text("${}");
+                        self.expression(&part.0);
+                    }
+                }
+            },
+            NodeType::BooleanLiteral => match node.get_boolean_value("value") {
+                Some(true) => self.text("true"),
+                Some(false) => self.text("false"),
+                None => {},
+            },
+            NodeType::NullLiteral => self.text("null"),
+            NodeType::Identifier => self.text(node.get_string_value("name").unwrap_or("")),
+            NodeType::ArrowFunctionExpression => self.arrow_function(node),
+            NodeType::BinaryExpression => {
+                if let [left, right] = node.children.as_slice() {
+                    self.operand(&left.0);
+                    self.text(" ");
+                    self.text(node.get_string_value("operator").unwrap_or("+"));
+                    self.text(" ");
+                    self.operand(&right.0);
+                }
+            },
+            NodeType::MemberExpression => {
+                if let [object, property] = node.children.as_slice() {
+                    self.callee(&object.0);
+                    self.text(if node.get_boolean_value("computed") == Some(true) { "[]" } else { "." });
+                    self.expression(&property.0);
+                }
+            },
+            NodeType::CallExpression => {
+                if let Some((callee, args)) = node.children.split_first() {
+                    self.callee(&callee.0);
+                    self.text("()");
+                    self.separated(args);
+                }
+            },
+            NodeType::NewExpression => {
+                if let Some((callee, args)) = node.children.split_first() {
+                    self.text("new ()");
+                    if callee.0.node_type == NodeType::CallExpression {
+                        self.text("()");
+                    }
+                    self.callee(&callee.0);
+                    self.separated(args);
+                }
+            },
+            NodeType::ObjectExpression if node.children.is_empty() => self.text("{}"),
+            NodeType::ObjectExpression => {
+                self.text("{  }");
+                self.separated(&node.children);
+            },
+            NodeType::Property => {
+                if let [key, value] = node.children.as_slice() {
+                    if node.get_boolean_value("computed") == Some(true) {
+                        self.text("[]");
+                    }
+                    self.expression(&key.0);
+                    let kind = node.get_string_value("kind").unwrap_or("init");
+                    if kind == "init" && node.get_boolean_value("method") != Some(true) {
+                        if !is_shorthand_property(node) {
+                            self.text(": ");
+                            self.expression(&value.0);
+                        }
+                    } else {
+                        if kind != "init" {
+                            self.text(kind);
+                            self.text(" ");
+                        }
+                        self.method(&value.0);
+                    }
+                }
+            },
+            NodeType::ConditionalExpression => {
+                if let [test, consequent, alternate] = node.children.as_slice() {
+                    self.operand(&test.0);
+                    self.text(" ?  : ");
+                    self.expression(&consequent.0);
+                    self.expression(&alternate.0);
+                }
+            },
+            NodeType::SequenceExpression => {
+                self.text("()");
+                self.separated(&node.children);
+            },
+            NodeType::UnaryExpression => {
+                let operator = node.get_string_value("operator").unwrap_or("");
+                self.text(operator);
+                if let Some(arg) = node.children.first() {
+                    if space_after_unary(operator, &arg.0) {
+                        self.text(" ");
+                    }
+                    self.operand(&arg.0);
+                }
+            },
+            NodeType::YieldExpression => {
+                self.text(if node.get_boolean_value("delegate") == Some(true) { "yield*" } else { "yield" });
+                if let Some(arg) = node.children.first() {
+                    self.text(" ");
+                    self.operand(&arg.0);
+                }
+            },
+            NodeType::MetaProperty => {
+                self.text(node.get_string_value("meta").unwrap_or("import"));
+                self.text(".");
+                self.text(node.get_string_value("property").unwrap_or("meta"));
+            },
+            NodeType::ImportExpression => {
+                self.text("import()");
+                self.separated(&node.children);
+            },
+            NodeType::AwaitExpression => {
+                self.text("await ");
+                if let Some(arg) = node.children.first() {
+                    self.operand(&arg.0);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// 复合表达式作为运算数时需要额外的一对括号
+    fn operand(&mut self, node: &Node) {
+        let needs_parens = matches!(
+            node.node_type,
+            NodeType::BinaryExpression
+                | NodeType::ConditionalExpression
+                | NodeType::ArrowFunctionExpression
+                | NodeType::AwaitExpression
+                | NodeType::YieldExpression
+        );
+        if needs_parens {
+            self.text("()");
+        }
+        self.expression(node);
+    }
+
+    /// 成员访问的对象和被调用者，一元表达式也需要括号
+    fn callee(&mut self, node: &Node) {
+        if node.node_type == NodeType::UnaryExpression {
+            self.text("()");
+        }
+        self.operand(node);
+    }
+
+    fn arrow_function(&mut self, node: &Node) {
+        let Some((body, params)) = node.children.split_last() else {
+            return;
+        };
+        self.text(if self.arrow_functions { "() => " } else { "function () " });
+        self.params(params);
+        if body.0.node_type == NodeType::BlockStatement {
+            self.statement(&body.0);
+            // 内联代码块结尾不换行
+            self.whitespace = self.whitespace.saturating_sub(1);
+        } else if self.arrow_functions {
+            self.expression(&body.0);
+        } else {
+            self.text("{\nreturn ;\n}");
+            self.expression(&body.0);
+        }
+    }
+
+    /// 对象方法和访问器的`[async ][*](<参数>) { ... }`部分，不含键
+    fn method(&mut self, function: &Node) {
+        let Some((body, params)) = function.children.split_last() else {
+            return;
+        };
+        if function.get_boolean_value("async") == Some(true) {
+            self.text("async ");
+        }
+        if function.get_boolean_value("generator") == Some(true) {
+            self.text("*");
+        }
+        self.text("() ");
+        self.params(params);
+        self.statement(&body.0);
+        // 内联代码块结尾不换行
+        self.whitespace = self.whitespace.saturating_sub(1);
+    }
+
+    fn params(&mut self, params: &[NodeRef]) {
+        for param in params {
+            self.text(param.0.get_string_value("name").unwrap_or(""));
+        }
+        self.text(&", ".repeat(params.len().saturating_sub(1)));
+    }
+
+    /// `import <默认>, * as <命名空间>, { <a>, <b as c> } from "<source>";`
+    fn import(&mut self, node: &Node) {
+        let mut clauses = 0usize;
+        let mut named = 0;
+        self.text("import ");
+        for ident in &node.children {
+            let Some(local) = ident.0.get_string_value("name") else {
+                continue;
+            };
+            match ident.0.get_string_value("imported").unwrap_or(local) {
+                "default" => clauses += 1,
+                "*" => {
+                    self.text("* as ");
+                    clauses += 1;
+                },
+                imported if imported == local => named += 1,
+                imported => {
+                    self.text(imported);
+                    self.text(" as ");
+                    named += 1;
+                },
+            }
+            self.text(local);
+        }
+        if named > 0 {
+            self.text("{  }");
+            self.text(&", ".repeat(named - 1));
+            clauses += 1;
+        }
+        self.text(&", ".repeat(clauses.saturating_sub(1)));
+        if clauses > 0 {
+            self.text(" from ");
+        }
+        self.text("\"\";\n");
+        self.text(node.get_string_value("source").unwrap_or(""));
+    }
+
+    /// 逗号分隔的表达式（不含括号）
+    fn separated(&mut self, nodes: &[NodeRef]) {
+        for node in nodes {
+            self.expression(&node.0);
+        }
+        self.text(&", ".repeat(nodes.len().saturating_sub(1)));
+    }
+}
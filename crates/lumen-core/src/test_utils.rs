@@ -0,0 +1,10 @@
+//! 测试辅助函数 - 各crate的测试中手工构造语法树时使用，通过`test-utils`特性对其他crate开放
+
+use crate::{IR, NodeType, NodeValue};
+
+/// 创建名为`name`的标识符节点，返回节点ID
+pub fn ident(ir: &mut IR, name: &str) -> usize {
+    let id = ir.create_node(NodeType::Identifier);
+    ir.get_node_mut(id).unwrap().set_value("name", NodeValue::String(name.to_string()));
+    id
+}
@@ -0,0 +1,292 @@
+//! 诊断信息 - 只检查不生成代码，按LSP的格式报告语法错误和作用域分析的警告
+
+use std::time::Instant;
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+
+use lumen_core::{IR, SourceLocation};
+use lumen_parser::{JsParser, ParseError, ParseOptions};
+
+use crate::{LintOptions, analyze_dynamic_scopes, build_scope_tree, lint};
+
+/// 诊断的严重程度，序列化为LSP的`DiagnosticSeverity`数值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// LSP位置：行号和列号都从0开始，列号按UTF-16码元计数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Position {
+    pub fn new(line: u32, character: u32) -> Self {
+        Self { line, character }
+    }
+}
+
+/// LSP范围，结束位置不包含
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// 一条诊断信息
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    /// 诊断代码，如`syntax-error`、`unused-variable`
+    pub code: String,
+    pub message: String,
+    /// 固定为`lumen`
+    pub source: String,
+}
+
+impl Diagnostic {
+    fn new(range: Range, severity: Severity, code: &str, message: String) -> Self {
+        Self { range, severity, code: code.to_string(), message, source: "lumen".to_string() }
+    }
+}
+
+/// `analyze`的选项
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    pub parse_options: ParseOptions,
+    pub lint_options: LintOptions,
+}
+
+/// 源码的统计信息
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalysisStats {
+    /// 源码行数
+    pub lines: usize,
+    /// IR节点数
+    pub nodes: usize,
+    /// 作用域数（包括顶层作用域）
+    pub scopes: usize,
+    /// 声明的名字数
+    pub bindings: usize,
+    /// 解析耗时（毫秒）
+    pub parse_time_ms: u64,
+}
+
+/// 分析结果：解析得到的IR、诊断信息和统计信息
+#[derive(Debug, Clone)]
+pub struct AnalysisResult {
+    /// 解析失败时为只有根节点的空IR
+    pub ir: IR,
+    pub diagnostics: Vec<Diagnostic>,
+    pub stats: AnalysisStats,
+}
+
+/// 解析源码、运行检查并统计，不进行优化和代码生成，供编辑器等工具使用
+pub fn analyze(source: &str, options: &AnalyzeOptions) -> AnalysisResult {
+    let start = Instant::now();
+    let parsed = JsParser::new(options.parse_options.clone()).parse_string(source);
+    let parse_time_ms = start.elapsed().as_millis() as u64;
+    let mut result = analyze_parsed(source, parsed, &options.lint_options);
+    result.stats.parse_time_ms = parse_time_ms;
+    result
+}
+
+/// 对已经解析的结果进行检查和统计
+fn analyze_parsed(source: &str, parsed: Result<IR>, lint_options: &LintOptions) -> AnalysisResult {
+    let (ir, diagnostics) = match parsed {
+        Ok(ir) => {
+            let diagnostics = collect_diagnostics(source, Some(&ir), &[], lint_options);
+            (ir, diagnostics)
+        },
+        Err(error) => {
+            let parse_error = error.downcast_ref::<ParseError>().cloned()
+                .unwrap_or_else(|| ParseError::new(&error.to_string(), 1, 0));
+            (IR::new(), collect_diagnostics(source, None, &[parse_error], lint_options))
+        },
+    };
+
+    let tree = build_scope_tree(&ir);
+    let stats = AnalysisStats {
+        lines: source.lines().count(),
+        nodes: ir.nodes.len(),
+        scopes: tree.scopes.len(),
+        bindings: tree.bindings().count(),
+        parse_time_ms: 0,
+    };
+    AnalysisResult { ir, diagnostics, stats }
+}
+
+/// 解析源码并返回诊断信息，不进行优化和代码生成
+pub fn diagnose(source: &str, filename: &str) -> Vec<Diagnostic> {
+    let options = AnalyzeOptions {
+        parse_options: ParseOptions {
+            filename: Some(filename.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    analyze(source, &options).diagnostics
+}
+
+/// 汇总语法错误和IR上的检查结果
+///
+/// 可恢复的解析会同时给出IR和语法错误，两者都会报告；解析失败时`ir`为None。
+pub fn collect_diagnostics(source: &str, ir: Option<&IR>, parse_errors: &[ParseError], lint_options: &LintOptions) -> Vec<Diagnostic> {
+    let lines = LineIndex::new(source);
+    let mut diagnostics: Vec<Diagnostic> = parse_errors.iter()
+        .map(|error| {
+            let start = lines.position(error.line, error.column);
+            // 语法错误只有起始位置，标记到下一个字符
+            let end = lines.position(error.line, lines.next_char(error.line, error.column));
+            Diagnostic::new(Range { start, end }, Severity::Error, "syntax-error", error.message.clone())
+        })
+        .collect();
+
+    if let Some(ir) = ir {
+        let scopes = analyze_dynamic_scopes(ir);
+        for (message, id) in scopes.warnings.iter().zip(&scopes.warning_nodes) {
+            let range = lines.range(ir.get_node(*id).and_then(|n| n.location));
+            diagnostics.push(Diagnostic::new(range, Severity::Warning, "dynamic-scope", message.clone()));
+        }
+        for lint in lint(ir, lint_options) {
+            let range = lines.range(ir.get_node(lint.node_id).and_then(|n| n.location));
+            diagnostics.push(Diagnostic::new(range, Severity::Warning, lint.rule, lint.message));
+        }
+    }
+
+    diagnostics.sort_by_key(|d| (d.range.start.line, d.range.start.character));
+    diagnostics
+}
+
+/// 把`SourceLocation`（行号从1开始，列号为行内字节偏移）转换为LSP位置
+struct LineIndex<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { lines: source.split('\n').collect() }
+    }
+
+    fn position(&self, line: usize, column: usize) -> Position {
+        let text = self.lines.get(line.saturating_sub(1)).copied().unwrap_or("");
+        let prefix = text.get(..column.min(text.len())).unwrap_or(text);
+        Position::new(line.saturating_sub(1) as u32, prefix.encode_utf16().count() as u32)
+    }
+
+    /// `column`处字符之后的字节偏移，位于行尾时不移动
+    fn next_char(&self, line: usize, column: usize) -> usize {
+        let text = self.lines.get(line.saturating_sub(1)).copied().unwrap_or("");
+        text.get(column..).and_then(|rest| rest.chars().next()).map_or(column, |c| column + c.len_utf8())
+    }
+
+    /// 节点没有位置信息时报告在文件开头
+    fn range(&self, location: Option<SourceLocation>) -> Range {
+        match location {
+            Some(loc) => Range {
+                start: self.position(loc.start_line, loc.start_column),
+                end: self.position(loc.end_line, loc.end_column),
+            },
+            None => Range { start: Position::new(0, 0), end: Position::new(0, 0) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::{NodeType, NodeValue};
+
+    #[test]
+    fn test_syntax_error_and_unused_variable_ranges() {
+        // 第一行的中文字符串在UTF-16中每个字符占一个码元，而在UTF-8中占三个字节
+        let source = "const s = \"你好\"; console.log(s);\nconst unused = 1;\nlet x = ;\n";
+
+        let mut ir = IR::new();
+        for (line, name, start, end) in [(1, "s", 6, 7), (2, "unused", 6, 12)] {
+            let ident = ir.create_node(NodeType::Identifier);
+            let decl = ir.create_node(NodeType::VariableDeclaration);
+            let node = ir.get_node_mut(ident).unwrap();
+            node.set_value("name", NodeValue::String(name.to_string()));
+            node.location = Some(SourceLocation::new(line, start, line, end));
+            ir.add_child(decl, ident);
+            ir.add_child(ir.root_id, decl);
+        }
+        // console.log(s) 中对s的引用
+        let reference = ir.create_node(NodeType::Identifier);
+        ir.get_node_mut(reference).unwrap().set_value("name", NodeValue::String("s".to_string()));
+        let call = ir.create_node(NodeType::CallExpression);
+        ir.add_child(call, reference);
+        ir.add_child(ir.root_id, call);
+
+        let error = ParseError::new("意外的符号 ';'", 3, 8);
+        let diagnostics = collect_diagnostics(source, Some(&ir), &[error], &LintOptions::default());
+        assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+
+        let unused = &diagnostics[0];
+        assert_eq!(unused.code, "unused-variable");
+        assert_eq!(unused.severity, Severity::Warning);
+        assert_eq!(unused.range, Range { start: Position::new(1, 6), end: Position::new(1, 12) });
+
+        let syntax = &diagnostics[1];
+        assert_eq!(syntax.code, "syntax-error");
+        assert_eq!(syntax.severity, Severity::Error);
+        assert_eq!(syntax.range, Range { start: Position::new(2, 8), end: Position::new(2, 9) });
+
+        // 第一行中位于中文字符串之后的位置按UTF-16计数
+        let lines = LineIndex::new(source);
+        assert_eq!(lines.position(1, source.find(';').unwrap()), Position::new(0, 14));
+    }
+
+    #[test]
+    fn test_analyze_returns_ir_diagnostics_and_stats() {
+        // function f(a) { let unused = 1; return a; }
+        let source = "function f(a) {\n  let unused = 1;\n  return a;\n}\n";
+        let mut ir = IR::new();
+        let param = ir.create_node(NodeType::Identifier);
+        ir.get_node_mut(param).unwrap().set_value("name", NodeValue::String("a".to_string()));
+        let name = ir.create_node(NodeType::Identifier);
+        let node = ir.get_node_mut(name).unwrap();
+        node.set_value("name", NodeValue::String("unused".to_string()));
+        node.location = Some(SourceLocation::new(2, 6, 2, 12));
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String("let".to_string()));
+        ir.add_child(decl, name);
+        let reference = ir.create_node(NodeType::Identifier);
+        ir.get_node_mut(reference).unwrap().set_value("name", NodeValue::String("a".to_string()));
+        let ret = ir.create_node(NodeType::ReturnStatement);
+        ir.add_child(ret, reference);
+        let body = ir.create_node(NodeType::BlockStatement);
+        ir.add_child(body, decl);
+        ir.add_child(body, ret);
+        let function = ir.create_node(NodeType::FunctionDeclaration);
+        ir.get_node_mut(function).unwrap().set_value("name", NodeValue::String("f".to_string()));
+        ir.add_child(function, param);
+        ir.add_child(function, body);
+        ir.add_child(ir.root_id, function);
+        let node_count = ir.nodes.len();
+
+        let result = analyze_parsed(source, Ok(ir), &LintOptions::default());
+        assert_eq!(result.ir.get_root().children.len(), 1);
+        assert_eq!(result.diagnostics.len(), 1, "{:?}", result.diagnostics);
+        assert_eq!(result.diagnostics[0].code, "unused-variable");
+        assert_eq!(result.diagnostics[0].range.start, Position::new(1, 6));
+        assert_eq!(result.stats, AnalysisStats { lines: 4, nodes: node_count, scopes: 2, bindings: 3, parse_time_ms: 0 });
+
+        let failed = analyze_parsed(source, Err(ParseError::new("意外的文件结尾", 4, 1).into()), &LintOptions::default());
+        assert_eq!(failed.diagnostics[0].severity, Severity::Error);
+        assert_eq!(failed.stats.nodes, 1, "解析失败时只有根节点");
+    }
+}
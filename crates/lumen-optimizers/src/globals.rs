@@ -0,0 +1,107 @@
+//! 运行环境的全局名字预设，用于判断未声明的引用是否为已知全局变量
+
+use std::collections::HashSet;
+
+/// 运行环境预设
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlobalsEnv {
+    #[default]
+    Browser,
+    Node,
+    WebWorker,
+    /// 只有ES2021的语言内置对象，不假定任何宿主环境
+    Es2021,
+}
+
+impl GlobalsEnv {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "browser" => Some(Self::Browser),
+            "node" => Some(Self::Node),
+            "worker" | "webworker" => Some(Self::WebWorker),
+            "es2021" => Some(Self::Es2021),
+            _ => None,
+        }
+    }
+
+    /// 该环境下的全局名字，包括所有环境共有的语言内置对象
+    pub fn globals(&self) -> HashSet<&'static str> {
+        let host: &[&[&str]] = match self {
+            Self::Browser => &[HOST_GLOBALS, BROWSER_GLOBALS],
+            Self::Node => &[HOST_GLOBALS, NODE_GLOBALS],
+            Self::WebWorker => &[HOST_GLOBALS, WORKER_GLOBALS],
+            Self::Es2021 => &[],
+        };
+        BUILTIN_GLOBALS.iter().chain(host.iter().copied().flatten()).copied().collect()
+    }
+
+    /// 预设的全局名字与用户提供的名字合并
+    pub fn globals_with(&self, extra: &[String]) -> HashSet<String> {
+        self.globals().into_iter()
+            .map(String::from)
+            .chain(extra.iter().cloned())
+            .collect()
+    }
+}
+
+/// ECMAScript内置的全局名字
+const BUILTIN_GLOBALS: &[&str] = &[
+    "globalThis", "undefined", "NaN", "Infinity", "eval", "arguments",
+    "Object", "Function", "Array", "String", "Number", "Boolean", "Symbol", "BigInt",
+    "Math", "JSON", "Date", "RegExp", "Error", "TypeError", "RangeError", "SyntaxError",
+    "ReferenceError", "EvalError", "URIError", "AggregateError",
+    "Map", "Set", "WeakMap", "WeakSet", "WeakRef", "FinalizationRegistry",
+    "Promise", "Proxy", "Reflect", "ArrayBuffer", "SharedArrayBuffer", "DataView", "Atomics",
+    "Int8Array", "Uint8Array", "Uint8ClampedArray", "Int16Array", "Uint16Array",
+    "Int32Array", "Uint32Array", "Float32Array", "Float64Array", "BigInt64Array", "BigUint64Array",
+    "parseInt", "parseFloat", "isNaN", "isFinite",
+    "encodeURI", "encodeURIComponent", "decodeURI", "decodeURIComponent",
+    "console",
+];
+
+/// 浏览器、Node和Worker都提供的定时器和网络相关全局名字
+const HOST_GLOBALS: &[&str] = &[
+    "setTimeout", "clearTimeout", "setInterval", "clearInterval", "queueMicrotask",
+    "structuredClone", "URL", "URLSearchParams", "TextEncoder", "TextDecoder",
+    "AbortController", "AbortSignal", "fetch", "Request", "Response", "Headers",
+];
+
+const BROWSER_GLOBALS: &[&str] = &[
+    "window", "self", "document", "navigator", "location", "history", "screen",
+    "localStorage", "sessionStorage", "alert", "confirm", "prompt",
+    "requestAnimationFrame", "cancelAnimationFrame", "getComputedStyle", "matchMedia",
+    "XMLHttpRequest", "WebSocket", "Worker", "Blob", "File", "FileReader", "FormData",
+    "Event", "CustomEvent", "EventTarget", "Node", "Element", "HTMLElement",
+    "MutationObserver", "IntersectionObserver", "ResizeObserver", "customElements",
+];
+
+const NODE_GLOBALS: &[&str] = &[
+    "global", "process", "Buffer", "require", "module", "exports",
+    "__dirname", "__filename", "setImmediate", "clearImmediate",
+];
+
+const WORKER_GLOBALS: &[&str] = &[
+    "self", "postMessage", "importScripts", "onmessage", "close",
+    "WebSocket", "Blob", "File", "FileReader", "FormData", "Event", "EventTarget",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_preset_knows_process_and_buffer() {
+        let node = GlobalsEnv::Node.globals();
+        assert!(node.contains("process") && node.contains("Buffer"));
+
+        let browser = GlobalsEnv::Browser.globals();
+        assert!(!browser.contains("process") && !browser.contains("Buffer"));
+        assert!(browser.contains("document"));
+
+        let es = GlobalsEnv::Es2021.globals();
+        assert!(es.contains("WeakRef") && !es.contains("setTimeout"));
+
+        let merged = GlobalsEnv::Browser.globals_with(&["jQuery".to_string()]);
+        assert!(merged.contains("jQuery") && merged.contains("window"));
+    }
+}
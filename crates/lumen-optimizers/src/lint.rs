@@ -0,0 +1,213 @@
+//! 代码检查 - 基于作用域树报告问题，只产生警告，不修改IR
+
+use std::collections::HashMap;
+
+use lumen_core::{IR, Node, NodeType};
+
+use crate::{BindingKind, GlobalsEnv, ScopeAnalysis, ScopeTree, analyze_dynamic_scopes, build_scope_tree};
+
+/// 检查选项
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// 运行环境，决定哪些全局名字是已知的
+    pub env: GlobalsEnv,
+    /// 额外的已知全局名字
+    pub globals: Vec<String>,
+}
+
+/// 一条检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintMessage {
+    /// 规则名称，用作诊断代码
+    pub rule: &'static str,
+    pub message: String,
+    /// 问题所在的节点
+    pub node_id: usize,
+}
+
+/// 未使用的变量和导入
+///
+/// 以下划线开头的名字表示有意不使用，不报告；被导出的名字和受`eval`/`with`影响的作用域中的名字也不报告。
+pub fn unused_bindings(tree: &ScopeTree, scopes: &ScopeAnalysis) -> Vec<LintMessage> {
+    tree.bindings()
+        .filter(|(scope_id, _)| !scopes.is_dynamic(*scope_id))
+        .map(|(_, binding)| binding)
+        .filter(|b| b.references == 0 && !b.exported && !b.name.starts_with('_'))
+        .filter_map(|b| {
+            let message = match b.kind {
+                BindingKind::Var | BindingKind::Let | BindingKind::Const => format!("变量 '{}' 已声明但从未使用", b.name),
+                BindingKind::Import => format!("导入的 '{}' 从未使用", b.name),
+                _ => return None,
+            };
+            Some(LintMessage { rule: "unused-variable", message, node_id: b.node_id })
+        })
+        .collect()
+}
+
+/// 引用了未声明、未导入且不是已知全局变量的名字，通常是拼写错误
+///
+/// 受`eval`/`with`影响的作用域中的引用可能在运行时才有绑定，不报告。
+pub fn undefined_references(ir: &IR, tree: &ScopeTree, scopes: &ScopeAnalysis, options: &LintOptions) -> Vec<LintMessage> {
+    let globals = options.env.globals_with(&options.globals);
+    tree.unresolved.iter()
+        .filter(|(_, scope_id)| !scopes.is_dynamic(*scope_id))
+        .filter_map(|(node_id, _)| {
+            let node = ir.get_node(*node_id)?;
+            let name = node.get_string_value("name")?;
+            if globals.contains(name) {
+                return None;
+            }
+            Some(LintMessage { rule: "no-undef", message: format!("'{}' 未定义", name), node_id: *node_id })
+        })
+        .collect()
+}
+
+/// 同一个对象字面量中重复的键，报告在后出现的键上，消息中给出第一次出现的位置
+///
+/// 计算属性名在运行时才能确定，不参与比较；同一个键的getter和setter不算重复。
+pub fn duplicate_keys(ir: &IR) -> Vec<LintMessage> {
+    let mut messages = Vec::new();
+    ir.visit(|node| {
+        if node.node_type != NodeType::ObjectExpression {
+            return;
+        }
+        let mut seen: HashMap<String, Vec<(&str, &Node)>> = HashMap::new();
+        for property in &node.children {
+            let property = &property.0;
+            if property.node_type != NodeType::Property || property.get_boolean_value("computed") == Some(true) {
+                continue;
+            }
+            let Some(key) = property.children.first().map(|k| &k.0) else {
+                continue;
+            };
+            let Some(name) = static_key(key) else {
+                continue;
+            };
+            let kind = property.get_string_value("kind").unwrap_or("init");
+            let earlier = seen.entry(name.clone()).or_default();
+            let conflict = earlier.iter().find(|(other, _)| {
+                *other == kind || *other == "init" || kind == "init"
+            });
+            if let Some((_, first)) = conflict {
+                let position = first.location
+                    .map(|loc| format!("（首次定义于 {}:{}）", loc.start_line, loc.start_column + 1))
+                    .unwrap_or_default();
+                messages.push(LintMessage {
+                    rule: "duplicate-key",
+                    message: format!("对象字面量中的键 '{}' 重复{}", name, position),
+                    node_id: key.id,
+                });
+            }
+            earlier.push((kind, key));
+        }
+    });
+    messages
+}
+
+/// 非计算属性名的键，数字键按JavaScript的属性名规则转换为字符串（`1`与`"1"`是同一个键）
+fn static_key(key: &Node) -> Option<String> {
+    match key.node_type {
+        NodeType::Identifier => key.get_string_value("name").map(String::from),
+        NodeType::StringLiteral => key.get_string_value("value").map(String::from),
+        NodeType::NumericLiteral => key.get_number_value("value").map(|n| n.to_string()),
+        _ => None,
+    }
+}
+
+/// 运行所有检查规则
+pub fn lint(ir: &IR, options: &LintOptions) -> Vec<LintMessage> {
+    let tree = build_scope_tree(ir);
+    let scopes = analyze_dynamic_scopes(ir);
+    let mut messages = unused_bindings(&tree, &scopes);
+    messages.extend(undefined_references(ir, &tree, &scopes, options));
+    messages.extend(duplicate_keys(ir));
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::{NodeValue, SourceLocation, test_utils::ident};
+
+    fn const_decl(ir: &mut IR, name: &str) -> usize {
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String("const".to_string()));
+        let name = ident(ir, name);
+        let init = ir.create_node(NodeType::NumericLiteral);
+        ir.get_node_mut(init).unwrap().set_value("value", NodeValue::Number(1.0));
+        ir.add_child(decl, name);
+        ir.add_child(decl, init);
+        decl
+    }
+
+    #[test]
+    fn test_unused_const_warns_unless_underscored() {
+        let mut ir = IR::new();
+        let foo = const_decl(&mut ir, "foo");
+        let ignored = const_decl(&mut ir, "_foo");
+        let used = const_decl(&mut ir, "bar");
+        let reference = ident(&mut ir, "bar");
+        // import { helper } from "./util"; 未被使用
+        let import = ir.create_node(NodeType::ImportDeclaration);
+        let helper = ident(&mut ir, "helper");
+        ir.add_child(import, helper);
+        for id in [foo, ignored, used, reference, import] {
+            ir.add_child(ir.root_id, id);
+        }
+
+        let messages = lint(&ir, &LintOptions::default());
+        let names: Vec<&str> = messages.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", names);
+        assert!(messages.iter().all(|m| m.rule == "unused-variable"));
+        assert_eq!(messages[0].message, "变量 'foo' 已声明但从未使用");
+        assert_eq!(messages[1].message, "导入的 'helper' 从未使用");
+        assert_eq!(messages[1].node_id, helper);
+    }
+
+    /// 构造 `<object>.title;`
+    fn member_statement(ir: &mut IR, object: &str) -> usize {
+        let object = ident(ir, object);
+        let property = ident(ir, "title");
+        let member = ir.create_node(NodeType::MemberExpression);
+        ir.add_child(member, object);
+        ir.add_child(member, property);
+        member
+    }
+
+    #[test]
+    fn test_duplicate_object_keys() {
+        let source = "({ a: 1, \"a\": 2 });\n({ [k]: 1, [k]: 2 });\n({ 1: 1, get v() {}, set v(x) {}, \"1\": 2 });\n";
+        let ir = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+
+        let messages = duplicate_keys(&ir);
+        let texts: Vec<&str> = messages.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(texts, vec![
+            "对象字面量中的键 'a' 重复（首次定义于 1:4）",
+            "对象字面量中的键 '1' 重复（首次定义于 3:4）",
+        ], "计算属性名和同名的getter/setter不应报告");
+        assert!(messages.iter().all(|m| m.rule == "duplicate-key"));
+        let second = ir.get_node(messages[0].node_id).unwrap();
+        assert_eq!(second.location, Some(SourceLocation::new(1, 9, 1, 12)), "应报告在第二次出现的位置");
+        assert!(!lint(&ir, &LintOptions::default()).iter().any(|m| m.message == "'a' 未定义"), "属性名不是引用");
+    }
+
+    #[test]
+    fn test_no_undef_flags_typo_under_browser_preset() {
+        let mut ir = IR::new();
+        let typo = member_statement(&mut ir, "documnet");
+        let correct = member_statement(&mut ir, "document");
+        ir.add_child(ir.root_id, typo);
+        ir.add_child(ir.root_id, correct);
+
+        let options = LintOptions { env: GlobalsEnv::Browser, globals: Vec::new() };
+        let messages = lint(&ir, &options);
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert_eq!(messages[0].rule, "no-undef");
+        assert_eq!(messages[0].message, "'documnet' 未定义");
+
+        // Node环境中没有document
+        let options = LintOptions { env: GlobalsEnv::Node, globals: vec!["documnet".to_string()] };
+        let names: Vec<String> = lint(&ir, &options).into_iter().map(|m| m.message).collect();
+        assert_eq!(names, vec!["'document' 未定义".to_string()]);
+    }
+}
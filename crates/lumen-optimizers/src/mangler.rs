@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use log::debug;
+
+use lumen_core::{IR, Node, NodeRef, NodeType, NodeValue};
+
+use crate::{analyze_dynamic_scopes, collect_protected_nodes, estimate_code_size, size_delta, OptimizationLevel, OptimizationResult, Optimizer, OptimizerOptions};
+use crate::scope::ScopeAnalysis;
+
+/// 生成短名字时需要跳过的关键字
+pub(crate) const KEYWORDS: &[&str] = &[
+    "do", "if", "in", "for", "let", "new", "try", "var", "case", "else", "enum", "eval",
+    "null", "this", "true", "void", "with", "break", "catch", "class", "const", "false",
+    "super", "throw", "while", "yield", "delete", "export", "import", "return", "switch",
+    "typeof", "default", "extends", "finally", "package", "private", "continue", "debugger",
+    "function", "arguments", "interface", "protected", "implements", "instanceof",
+];
+
+/// 名字混淆器 - 把函数作用域内声明的名字替换为短名字
+///
+/// 顶层声明可能被其他脚本引用，始终保留。`keep_fnames`、`keep_classnames`和`reserved`
+/// 中的名字不会被替换，也不会被用作生成的名字。受`eval`/`with`影响的动态作用域整体跳过。
+pub struct Mangler {
+    options: OptimizerOptions,
+}
+
+impl Mangler {
+    pub fn new(options: OptimizerOptions) -> Self {
+        Self { options }
+    }
+
+    /// 名字是否必须保留
+    fn is_reserved(&self, name: &str, globals: &HashSet<String>) -> bool {
+        self.options.reserved.iter().any(|r| r == name) || globals.contains(name)
+    }
+
+    /// 为每个函数作用域分配新名字，返回 函数节点ID -> (旧名字 -> 新名字)
+    fn plan_renames(
+        &self,
+        ir: &IR,
+        protected: &HashSet<usize>,
+        scopes: &ScopeAnalysis,
+    ) -> HashMap<usize, HashMap<String, String>> {
+        // 生成的名字不能与程序中已有的任何名字冲突
+        let mut taken: HashSet<String> = HashSet::new();
+        ir.visit(|node| {
+            if let Some(name) = node.get_string_value("name") {
+                taken.insert(name.to_string());
+            }
+        });
+        taken.extend(self.options.reserved.iter().cloned());
+        let globals = self.options.globals();
+
+        let mut generator = NameGenerator::default();
+        let mut plans = HashMap::new();
+        let mut stack = vec![ir.get_root()];
+
+        while let Some(node) = stack.pop() {
+            if is_function(&node) && !protected.contains(&node.id) && !scopes.is_dynamic(node.id) {
+                let mut renames = HashMap::new();
+                for decl in scope_declarations(&node) {
+                    let Some(name) = decl_name(decl) else { continue };
+                    let keep = self.is_reserved(name, &globals)
+                        || (self.options.keep_fnames && decl.node_type == NodeType::FunctionDeclaration)
+                        || (self.options.keep_classnames && decl.node_type == NodeType::ClassDeclaration);
+                    if keep || renames.contains_key(name) {
+                        continue;
+                    }
+                    renames.insert(name.to_string(), generator.next(&taken));
+                }
+                if !renames.is_empty() {
+                    plans.insert(node.id, renames);
+                }
+            }
+            for child in &node.children {
+                stack.push(child.0.clone());
+            }
+        }
+
+        plans
+    }
+}
+
+impl Optimizer for Mangler {
+    fn name(&self) -> &'static str {
+        "Mangler"
+    }
+
+    fn description(&self) -> &'static str {
+        "将局部变量、参数和内部函数名替换为短名字"
+    }
+
+    fn optimize(&self, ir: &mut IR) -> OptimizationResult {
+        let start = std::time::Instant::now();
+        let original_size = estimate_code_size(ir);
+        debug!("开始执行名字混淆");
+
+        let protected = collect_protected_nodes(ir);
+        let scopes = analyze_dynamic_scopes(ir);
+        let plans = self.plan_renames(ir, &protected, &scopes);
+        let renamed: usize = plans.values().map(|p| p.len()).sum();
+
+        if !plans.is_empty() {
+            let root = ir.get_root();
+            let mut rewriter = Rewriter { plans: &plans, scopes: Vec::new(), ir };
+            let new_root = rewriter.rewrite(&root, false);
+            ir.nodes.insert(ir.root_id, new_root);
+        }
+
+        let mut details = HashMap::new();
+        details.insert("renamed".to_string(), renamed.to_string());
+
+        OptimizationResult {
+            optimizer: self.name().to_string(),
+            success: true,
+            time_ms: start.elapsed().as_millis() as u64,
+            nodes_delta: 0,
+            size_delta: size_delta(original_size, ir),
+            details,
+            warnings: scopes.warnings,
+        }
+    }
+
+    fn is_applicable_for_level(&self, level: OptimizationLevel) -> bool {
+        !matches!(level, OptimizationLevel::None)
+    }
+}
+
+/// 按作用域改写标识符
+struct Rewriter<'a> {
+    plans: &'a HashMap<usize, HashMap<String, String>>,
+    scopes: Vec<&'a HashMap<String, String>>,
+    ir: &'a mut IR,
+}
+
+impl<'a> Rewriter<'a> {
+    /// 重建子树并同步更新节点表；`is_property`表示该节点是非计算属性名，不参与改写
+    fn rewrite(&mut self, node: &Node, is_property: bool) -> NodeRef {
+        let scope = self.plans.get(&node.id);
+        let mut new_node = node.clone();
+
+        // 函数自己的名字属于外层作用域，先于进入函数作用域改写
+        self.rename_value(&mut new_node, is_property);
+        if let Some(scope) = scope {
+            self.scopes.push(scope);
+        }
+
+        let computed = node.get_boolean_value("computed") == Some(true);
+        new_node.children = node.children.iter().enumerate()
+            .map(|(i, child)| {
+                let is_property = (node.node_type == NodeType::MemberExpression && i == 1 && !computed)
+                    || (node.node_type == NodeType::Property && i == 0 && !computed);
+                self.rewrite(&child.0, is_property)
+            })
+            .collect();
+
+        if scope.is_some() {
+            self.scopes.pop();
+        }
+
+        let node_ref = NodeRef(Arc::new(new_node));
+        self.ir.nodes.insert(node.id, node_ref.clone());
+        node_ref
+    }
+
+    fn rename_value(&self, node: &mut Node, is_property: bool) {
+        let renamable = matches!(
+            node.node_type,
+            NodeType::Identifier | NodeType::FunctionDeclaration | NodeType::ClassDeclaration
+        );
+        if !renamable || is_property {
+            return;
+        }
+        let Some(name) = node.get_string_value("name") else { return };
+        let new_name = self.scopes.iter().rev().find_map(|s| s.get(name));
+        if let Some(new_name) = new_name {
+            node.set_value("name", NodeValue::String(new_name.clone()));
+        }
+    }
+}
+
+fn is_function(node: &Node) -> bool {
+    matches!(node.node_type, NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression)
+}
+
+/// 声明节点引入的名字
+fn decl_name(node: &Node) -> Option<&str> {
+    match node.node_type {
+        NodeType::VariableDeclaration => node.children.first().and_then(|c| c.0.get_string_value("name")),
+        _ => node.get_string_value("name"),
+    }
+}
+
+/// 函数作用域内的声明：参数、函数体中的变量/函数/类声明（不进入嵌套函数）
+fn scope_declarations(func: &Node) -> Vec<&Node> {
+    fn collect<'n>(node: &'n Node, out: &mut Vec<&'n Node>) {
+        for child in &node.children {
+            let child = &child.0;
+            match child.node_type {
+                NodeType::VariableDeclaration => {
+                    out.push(child);
+                    collect(child, out);
+                },
+                // 嵌套函数的名字属于当前作用域，其内部属于新的作用域
+                NodeType::FunctionDeclaration | NodeType::ClassDeclaration => out.push(child),
+                NodeType::ArrowFunctionExpression => {},
+                _ => collect(child, out),
+            }
+        }
+    }
+
+    let mut decls = Vec::new();
+    if let Some((body, params)) = func.children.split_last() {
+        decls.extend(params.iter().map(|p| p.0.as_ref()));
+        if body.0.node_type == NodeType::BlockStatement {
+            collect(&body.0, &mut decls);
+        }
+    }
+    decls
+}
+
+/// 按 a, b, ..., Z, aa, ab, ... 的顺序生成短名字
+#[derive(Default)]
+struct NameGenerator {
+    counter: usize,
+}
+
+impl NameGenerator {
+    const ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+    fn next(&mut self, taken: &HashSet<String>) -> String {
+        loop {
+            let name = Self::encode(self.counter);
+            self.counter += 1;
+            if !taken.contains(&name) && !KEYWORDS.contains(&name.as_str()) {
+                return name;
+            }
+        }
+    }
+
+    fn encode(mut n: usize) -> String {
+        let base = Self::ALPHABET.len();
+        let mut name = vec![Self::ALPHABET[n % base]];
+        n /= base;
+        while n > 0 {
+            n -= 1;
+            name.push(Self::ALPHABET[n % base]);
+            n /= base;
+        }
+        name.reverse();
+        String::from_utf8(name).expect("字母表只包含ASCII字符")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::test_utils::ident;
+
+    /// function outer(input) { var counter = input; function helper() {} helper(counter); }
+    ///
+    /// `with_eval`为true时在函数体末尾追加 `eval("counter");`
+    fn nested_function_module(with_eval: bool) -> IR {
+        let mut ir = IR::new();
+
+        let decl = ir.create_node(NodeType::VariableDeclaration);
+        ir.get_node_mut(decl).unwrap().set_value("kind", NodeValue::String("var".to_string()));
+        let (counter, input) = (ident(&mut ir, "counter"), ident(&mut ir, "input"));
+        ir.add_child(decl, counter);
+        ir.add_child(decl, input);
+
+        let helper_body = ir.create_node(NodeType::BlockStatement);
+        let helper = ir.create_node(NodeType::FunctionDeclaration);
+        ir.get_node_mut(helper).unwrap().set_value("name", NodeValue::String("helper".to_string()));
+        ir.add_child(helper, helper_body);
+
+        let call = ir.create_node(NodeType::CallExpression);
+        let (callee, arg) = (ident(&mut ir, "helper"), ident(&mut ir, "counter"));
+        ir.add_child(call, callee);
+        ir.add_child(call, arg);
+
+        let body = ir.create_node(NodeType::BlockStatement);
+        for stmt in [decl, helper, call] {
+            ir.add_child(body, stmt);
+        }
+        if with_eval {
+            let eval = ident(&mut ir, "eval");
+            let code = ir.create_node(NodeType::StringLiteral);
+            ir.get_node_mut(code).unwrap().set_value("value", NodeValue::String("counter".to_string()));
+            let eval_call = ir.create_node(NodeType::CallExpression);
+            ir.add_child(eval_call, eval);
+            ir.add_child(eval_call, code);
+            ir.add_child(body, eval_call);
+        }
+        let outer = ir.create_node(NodeType::FunctionDeclaration);
+        ir.get_node_mut(outer).unwrap().set_value("name", NodeValue::String("outer".to_string()));
+        let param = ident(&mut ir, "input");
+        ir.add_child(outer, param);
+        ir.add_child(outer, body);
+        ir.add_child(ir.root_id, outer);
+        ir
+    }
+
+    fn all_names(ir: &IR) -> HashSet<String> {
+        let mut names = HashSet::new();
+        ir.visit(|node| {
+            if let Some(name) = node.get_string_value("name") {
+                names.insert(name.to_string());
+            }
+        });
+        names
+    }
+
+    #[test]
+    fn test_eval_scope_is_not_mangled() {
+        let mut ir = nested_function_module(true);
+        let result = Mangler::new(OptimizerOptions::default()).optimize(&mut ir);
+
+        let names = all_names(&ir);
+        assert!(names.contains("counter") && names.contains("input"), "包含eval的函数中局部变量不应被混淆: {:?}", names);
+        assert_eq!(result.details["renamed"], "0");
+        assert_eq!(result.warnings.len(), 1, "应产生一条eval警告");
+        assert!(result.warnings[0].contains("eval") && result.warnings[0].contains("outer"));
+    }
+
+    #[test]
+    fn test_keep_fnames_preserves_function_names() {
+        let mut ir = nested_function_module(false);
+        let options = OptimizerOptions { keep_fnames: true, ..Default::default() };
+        Mangler::new(options).optimize(&mut ir);
+
+        let names = all_names(&ir);
+        assert!(names.contains("outer"), "顶层函数名应保留");
+        assert!(names.contains("helper"), "启用keep_fnames时内部函数名应保留: {:?}", names);
+        assert!(!names.contains("counter") && !names.contains("input"), "局部变量和参数应被混淆: {:?}", names);
+
+        // 调用处与声明处应使用同一个新名字
+        let body = &ir.get_root().children[0].0.children[1].0;
+        let decl_name = body.children[0].0.children[0].0.get_string_value("name").unwrap().to_string();
+        let arg_name = body.children[2].0.children[1].0.get_string_value("name").unwrap().to_string();
+        assert_eq!(decl_name, arg_name);
+        // 节点表中的节点也应同步更新
+        let call = ir.get_node(body.children[2].0.id).unwrap();
+        assert_eq!(call.children[1].0.get_string_value("name"), Some(arg_name.as_str()));
+
+        let mut ir = nested_function_module(false);
+        Mangler::new(OptimizerOptions::default()).optimize(&mut ir);
+        assert!(!all_names(&ir).contains("helper"), "未启用keep_fnames时内部函数名应被混淆");
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use log::debug;
+
+use lumen_core::{IR, Node, NodeRef, NodeType, NodeValue};
+
+use crate::mangler::KEYWORDS;
+use crate::{collect_protected_nodes, estimate_code_size, rewrite_tree, size_delta, OptimizationLevel, OptimizationResult, Optimizer, OptimizerOptions};
+
+/// 属性访问规范化 - 把键为字符串字面量的计算属性访问改写为更短的形式
+///
+/// `a["foo"]`改写为`a.foo`；键是关键字或不是合法标识符时保留计算属性访问。
+/// 启用压缩时`a["1"]`改写为`a[1]`，只处理规范形式的非负整数，`"01"`等保持不变。
+pub struct PropertyAccessNormalization {
+    options: OptimizerOptions,
+}
+
+impl PropertyAccessNormalization {
+    pub fn new(options: OptimizerOptions) -> Self {
+        Self { options }
+    }
+
+    /// 改写计算属性访问，返回替换后的成员表达式
+    fn normalize(&self, node: &Node, next_id: &mut usize) -> Option<NodeRef> {
+        if node.node_type != NodeType::MemberExpression || node.get_boolean_value("computed") != Some(true) {
+            return None;
+        }
+        let [object, property] = node.children.as_slice() else {
+            return None;
+        };
+        if property.0.node_type != NodeType::StringLiteral {
+            return None;
+        }
+        let key = property.0.get_string_value("value")?;
+
+        let (new_property, computed) = if is_dot_accessible(key) {
+            let mut ident = Node::new(*next_id, NodeType::Identifier);
+            ident.set_value("name", NodeValue::String(key.to_string()));
+            (ident, false)
+        } else if self.options.minify && is_canonical_index(key) {
+            let mut number = Node::new(*next_id, NodeType::NumericLiteral);
+            number.set_value("value", NodeValue::Number(key.parse().ok()?));
+            (number, true)
+        } else {
+            return None;
+        };
+        *next_id += 1;
+
+        let mut new_property = new_property;
+        new_property.location = property.0.location;
+        let mut new_node = node.clone();
+        new_node.set_value("computed", NodeValue::Boolean(computed));
+        new_node.children = vec![object.clone(), NodeRef(Arc::new(new_property))];
+        Some(NodeRef(Arc::new(new_node)))
+    }
+}
+
+/// 键能否写成点号访问：ASCII标识符且不是关键字
+fn is_dot_accessible(key: &str) -> bool {
+    let mut chars = key.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_ascii_alphabetic() || first == '_' || first == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !KEYWORDS.contains(&key)
+}
+
+/// 键是否是规范形式的非负整数，数值转回字符串后与原来的键相同
+fn is_canonical_index(key: &str) -> bool {
+    // 超过15位时数值可能无法精确表示
+    !key.is_empty()
+        && key.len() <= 15
+        && key.bytes().all(|b| b.is_ascii_digit())
+        && (key == "0" || !key.starts_with('0'))
+}
+
+impl Optimizer for PropertyAccessNormalization {
+    fn name(&self) -> &'static str {
+        "PropertyAccessNormalization"
+    }
+
+    fn description(&self) -> &'static str {
+        "把字符串键的计算属性访问改写为点号访问或数字键"
+    }
+
+    fn optimize(&self, ir: &mut IR) -> OptimizationResult {
+        let start = std::time::Instant::now();
+        let original_nodes_count = ir.nodes.len();
+        let original_size = estimate_code_size(ir);
+
+        debug!("开始执行属性访问规范化");
+
+        let protected = collect_protected_nodes(ir);
+        let mut next_id = ir.next_id;
+        let mut normalized = 0;
+        rewrite_tree(ir, &protected, |node| {
+            let result = self.normalize(node, &mut next_id);
+            if result.is_some() {
+                normalized += 1;
+            }
+            result
+        });
+        ir.next_id = next_id;
+
+        let mut details = HashMap::new();
+        details.insert("normalized_accesses".to_string(), normalized.to_string());
+
+        OptimizationResult {
+            optimizer: self.name().to_string(),
+            success: true,
+            time_ms: start.elapsed().as_millis() as u64,
+            nodes_delta: (ir.nodes.len() as isize) - (original_nodes_count as isize),
+            size_delta: size_delta(original_size, ir),
+            details,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn is_applicable_for_level(&self, level: OptimizationLevel) -> bool {
+        !matches!(level, OptimizationLevel::None | OptimizationLevel::Basic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_compiler::CodeGenerator;
+    use lumen_core::CodegenOptions;
+
+    /// 构造表达式语句 `a[<key>];`
+    fn add_computed_access(ir: &mut IR, key: &str) {
+        let object = ir.create_node(NodeType::Identifier);
+        ir.get_node_mut(object).unwrap().set_value("name", NodeValue::String("a".to_string()));
+        let property = ir.create_node(NodeType::StringLiteral);
+        ir.get_node_mut(property).unwrap().set_value("value", NodeValue::String(key.to_string()));
+        let member = ir.create_node(NodeType::MemberExpression);
+        ir.get_node_mut(member).unwrap().set_value("computed", NodeValue::Boolean(true));
+        ir.add_child(member, object);
+        ir.add_child(member, property);
+        ir.add_child(ir.root_id, member);
+    }
+
+    #[test]
+    fn test_string_keys_become_dotted_access() {
+        let build = || {
+            let mut ir = IR::new();
+            for key in ["foo", "default", "1x", "$el", "1", "01"] {
+                add_computed_access(&mut ir, key);
+            }
+            ir
+        };
+        let generate = |ir: &IR| CodeGenerator::new(CodegenOptions::default()).generate(ir);
+
+        let mut ir = build();
+        let options = OptimizerOptions { minify: false, ..Default::default() };
+        let result = PropertyAccessNormalization::new(options).optimize(&mut ir);
+        assert_eq!(result.details["normalized_accesses"], "2");
+        assert_eq!(
+            generate(&ir),
+            "a.foo;\na[\"default\"];\na[\"1x\"];\na.$el;\na[\"1\"];\na[\"01\"];\n",
+            "关键字和非法标识符应保留计算属性访问"
+        );
+
+        // 压缩时规范形式的整数键改写为数字
+        let mut ir = build();
+        let result = PropertyAccessNormalization::new(OptimizerOptions::default()).optimize(&mut ir);
+        assert_eq!(result.details["normalized_accesses"], "3");
+        assert!(result.size_delta < 0);
+        assert!(generate(&ir).contains("a[1];\na[\"01\"];"), "{}", generate(&ir));
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet};
+use log::warn;
+
+use lumen_core::{IR, Node, NodeType};
+
+/// 动态作用域分析结果
+///
+/// 直接调用`eval`或使用`with`语句的作用域中，名字的绑定在编译期无法确定。由于`eval`
+/// 可以访问所有外层作用域的变量，包含它的函数以及所有外层函数（直到顶层）都被标记为动态。
+#[derive(Debug, Clone, Default)]
+pub struct ScopeAnalysis {
+    /// 动态作用域对应的节点ID（函数节点，顶层作用域为根节点）
+    pub dynamic_scopes: HashSet<usize>,
+    /// 检测到的问题，每处`eval`/`with`一条
+    pub warnings: Vec<String>,
+    /// 与`warnings`一一对应的`eval`调用或`with`语句节点ID
+    pub warning_nodes: Vec<usize>,
+}
+
+impl ScopeAnalysis {
+    /// 作用域是否为动态作用域，依赖作用域分析的优化应跳过这些作用域
+    pub fn is_dynamic(&self, scope_id: usize) -> bool {
+        self.dynamic_scopes.contains(&scope_id)
+    }
+}
+
+/// 查找被`eval`/`with`影响的作用域
+pub fn analyze_dynamic_scopes(ir: &IR) -> ScopeAnalysis {
+    let mut analysis = ScopeAnalysis::default();
+    let root = ir.get_root();
+    let mut scopes = vec![(root.id, None)];
+    visit(&root, &mut scopes, &mut analysis);
+    analysis
+}
+
+fn visit<'a>(node: &'a Node, scopes: &mut Vec<(usize, Option<&'a str>)>, analysis: &mut ScopeAnalysis) {
+    let reason = match node.node_type {
+        NodeType::WithStatement => Some("with语句"),
+        NodeType::CallExpression if is_direct_eval(node) => Some("eval"),
+        _ => None,
+    };
+    if let Some(reason) = reason {
+        let scope_name = scopes.last().and_then(|(_, name)| *name).unwrap_or("<顶层>");
+        let message = format!("作用域 '{}' 中使用了{}，该作用域及其外层作用域将跳过混淆和死代码消除", scope_name, reason);
+        warn!("{}", message);
+        analysis.warnings.push(message);
+        analysis.warning_nodes.push(node.id);
+        analysis.dynamic_scopes.extend(scopes.iter().map(|(id, _)| *id));
+    }
+
+    let is_scope = matches!(node.node_type, NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression);
+    if is_scope {
+        scopes.push((node.id, node.get_string_value("name")));
+    }
+    for child in &node.children {
+        visit(&child.0, scopes, analysis);
+    }
+    if is_scope {
+        scopes.pop();
+    }
+}
+
+/// 直接调用`eval(...)`，被调用者是名为eval的标识符
+fn is_direct_eval(call: &Node) -> bool {
+    call.children.first()
+        .is_some_and(|callee| callee.0.node_type == NodeType::Identifier && callee.0.get_string_value("name") == Some("eval"))
+}
+
+/// 绑定的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Var,
+    Let,
+    Const,
+    Function,
+    Class,
+    Param,
+    Import,
+}
+
+/// 作用域中声明的一个名字
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub kind: BindingKind,
+    /// 声明处的节点：变量、参数和导入为标识符节点，函数和类为声明节点
+    pub node_id: usize,
+    /// 被引用的次数（包括被导出）
+    pub references: usize,
+    /// 是否被导出
+    pub exported: bool,
+}
+
+/// 一个作用域：程序、函数（箭头函数）或代码块
+#[derive(Debug, Clone)]
+pub struct Scope {
+    /// 创建该作用域的节点（顶层作用域为根节点）
+    pub node_id: usize,
+    pub parent: Option<usize>,
+    pub bindings: Vec<Binding>,
+}
+
+/// 作用域树，`scopes[0]`为顶层作用域
+///
+/// `var`提升到最近的函数作用域，`let`/`const`/`class`属于最近的代码块；函数体代码块与函数共用一个作用域。
+/// 导入声明`ImportDeclaration`的每个标识符子节点是一个本地绑定。
+#[derive(Debug, Clone, Default)]
+pub struct ScopeTree {
+    pub scopes: Vec<Scope>,
+    /// 在任何作用域中都找不到声明的引用：(标识符节点ID, 引用所在作用域的节点ID)
+    ///
+    /// `typeof x`中对未声明名字的引用是合法的，不会记录。
+    pub unresolved: Vec<(usize, usize)>,
+}
+
+impl ScopeTree {
+    /// 所有作用域中的绑定及其所在作用域的节点ID
+    pub fn bindings(&self) -> impl Iterator<Item = (usize, &Binding)> {
+        self.scopes.iter().flat_map(|scope| scope.bindings.iter().map(move |b| (scope.node_id, b)))
+    }
+}
+
+/// 构建作用域树并解析所有标识符引用
+pub fn build_scope_tree(ir: &IR) -> ScopeTree {
+    let root = ir.get_root();
+    let mut builder = ScopeBuilder { tree: ScopeTree::default(), scope_of_node: HashMap::new() };
+    builder.tree.scopes.push(Scope { node_id: root.id, parent: None, bindings: Vec::new() });
+    builder.scope_of_node.insert(root.id, 0);
+
+    // 第一遍收集声明（处理提升），第二遍解析引用
+    builder.declare(&root, 0, 0);
+    builder.resolve(&root, 0);
+    builder.tree
+}
+
+struct ScopeBuilder {
+    tree: ScopeTree,
+    /// 创建作用域的节点ID -> 作用域下标
+    scope_of_node: HashMap<usize, usize>,
+}
+
+impl ScopeBuilder {
+    fn add_scope(&mut self, node_id: usize, parent: usize) -> usize {
+        self.tree.scopes.push(Scope { node_id, parent: Some(parent), bindings: Vec::new() });
+        let index = self.tree.scopes.len() - 1;
+        self.scope_of_node.insert(node_id, index);
+        index
+    }
+
+    fn bind(&mut self, scope: usize, name: &str, kind: BindingKind, node_id: usize, exported: bool) {
+        self.tree.scopes[scope].bindings.push(Binding {
+            name: name.to_string(),
+            kind,
+            node_id,
+            references: 0,
+            exported,
+        });
+    }
+
+    /// `block`为当前代码块作用域，`function`为当前函数作用域
+    fn declare(&mut self, node: &Node, block: usize, function: usize) {
+        self.declare_node(node, block, function, false);
+    }
+
+    fn declare_node(&mut self, node: &Node, block: usize, function: usize, exported: bool) {
+        match node.node_type {
+            NodeType::VariableDeclaration => {
+                let (kind, scope) = match node.get_string_value("kind") {
+                    Some("let") => (BindingKind::Let, block),
+                    Some("const") => (BindingKind::Const, block),
+                    _ => (BindingKind::Var, function),
+                };
+                if let Some(ident) = node.children.first() {
+                    if let Some(name) = ident.0.get_string_value("name") {
+                        self.bind(scope, name, kind, ident.0.id, exported);
+                    }
+                }
+                for init in node.children.iter().skip(1) {
+                    self.declare(&init.0, block, function);
+                }
+            },
+            NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression => {
+                if let Some(name) = node.get_string_value("name").filter(|_| node.node_type == NodeType::FunctionDeclaration) {
+                    self.bind(block, name, BindingKind::Function, node.id, exported);
+                }
+                let scope = self.add_scope(node.id, block);
+                let Some((body, params)) = node.children.split_last() else {
+                    return;
+                };
+                for param in params {
+                    if let Some(name) = param.0.get_string_value("name") {
+                        self.bind(scope, name, BindingKind::Param, param.0.id, false);
+                    }
+                }
+                if body.0.node_type == NodeType::BlockStatement {
+                    self.scope_of_node.insert(body.0.id, scope);
+                    for stmt in &body.0.children {
+                        self.declare(&stmt.0, scope, scope);
+                    }
+                } else {
+                    self.declare(&body.0, scope, scope);
+                }
+            },
+            NodeType::ClassDeclaration => {
+                if let Some(name) = node.get_string_value("name") {
+                    self.bind(block, name, BindingKind::Class, node.id, exported);
+                }
+                for child in &node.children {
+                    self.declare(&child.0, block, function);
+                }
+            },
+            NodeType::ImportDeclaration => {
+                for ident in &node.children {
+                    if let Some(name) = ident.0.get_string_value("name") {
+                        self.bind(block, name, BindingKind::Import, ident.0.id, false);
+                    }
+                }
+            },
+            NodeType::ExportDeclaration => {
+                for child in &node.children {
+                    // 导出说明符是对本地名字的引用，在解析阶段处理
+                    if child.0.node_type != NodeType::Identifier {
+                        self.declare_node(&child.0, block, function, true);
+                    }
+                }
+            },
+            NodeType::BlockStatement => {
+                let scope = self.add_scope(node.id, block);
+                for stmt in &node.children {
+                    self.declare(&stmt.0, scope, function);
+                }
+            },
+            _ => {
+                for child in &node.children {
+                    self.declare(&child.0, block, function);
+                }
+            },
+        }
+    }
+
+    fn resolve(&mut self, node: &Node, scope: usize) {
+        let scope = self.scope_of_node.get(&node.id).copied().unwrap_or(scope);
+        match node.node_type {
+            NodeType::Identifier => {
+                if let Some(name) = node.get_string_value("name") {
+                    self.reference(scope, name, Some(node.id));
+                }
+            },
+            NodeType::VariableDeclaration => {
+                for init in node.children.iter().skip(1) {
+                    self.resolve(&init.0, scope);
+                }
+            },
+            NodeType::FunctionDeclaration | NodeType::ArrowFunctionExpression => {
+                if let Some(body) = node.children.last() {
+                    self.resolve(&body.0, scope);
+                }
+            },
+            NodeType::ImportDeclaration => {},
+            NodeType::UnaryExpression if node.get_string_value("operator") == Some("typeof") => {
+                match node.children.first() {
+                    Some(arg) if arg.0.node_type == NodeType::Identifier => {
+                        if let Some(name) = arg.0.get_string_value("name") {
+                            self.reference(scope, name, None);
+                        }
+                    },
+                    Some(arg) => self.resolve(&arg.0, scope),
+                    None => {},
+                }
+            },
+            NodeType::MemberExpression => {
+                if let [object, property] = node.children.as_slice() {
+                    self.resolve(&object.0, scope);
+                    // 非计算属性`a.b`中的b不是引用
+                    if node.get_boolean_value("computed") == Some(true) {
+                        self.resolve(&property.0, scope);
+                    }
+                }
+            },
+            // 非计算属性名`{ a: 1 }`中的a不是引用
+            NodeType::Property if node.get_boolean_value("computed") != Some(true) => {
+                for value in node.children.iter().skip(1) {
+                    self.resolve(&value.0, scope);
+                }
+            },
+            _ => {
+                for child in &node.children {
+                    self.resolve(&child.0, scope);
+                }
+            },
+        }
+    }
+
+    /// 从内向外查找名字，找到时增加引用计数；找不到且给出了`node_id`时记录为未解析的引用
+    fn reference(&mut self, scope: usize, name: &str, node_id: Option<usize>) {
+        let scope_node = self.tree.scopes[scope].node_id;
+        let mut current = Some(scope);
+        while let Some(index) = current {
+            let scope = &mut self.tree.scopes[index];
+            if let Some(binding) = scope.bindings.iter_mut().find(|b| b.name == name) {
+                binding.references += 1;
+                return;
+            }
+            current = scope.parent;
+        }
+        if let Some(node_id) = node_id {
+            self.tree.unresolved.push((node_id, scope_node));
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use lumen_core::{CodegenOptions, IR};
+
+/// 估算IR生成代码的字节数，用于计算优化器的`size_delta`
+///
+/// 按默认代码生成选项估算，见`IR::estimated_output_size`。
+pub fn estimate_code_size(ir: &IR) -> usize {
+    ir.estimated_output_size(&CodegenOptions::default())
+}
+
+/// 优化前估算的大小与IR当前估算大小之差，缩小时为负数
+pub fn size_delta(original_size: usize, ir: &IR) -> isize {
+    estimate_code_size(ir) as isize - original_size as isize
+}
@@ -1,18 +1,21 @@
 use lumen_parser::{JsParser, ParseOptions};
 use lumen_core::IR;
+use lumen_core::bench::{BenchOptions, Iteration, measure};
 use std::path::Path;
 use std::time::Instant;
 use std::fs;
 use std::env;
 
 fn main() {
-    // 获取命令行参数
-    let args: Vec<String> = env::args().collect();
-    let file_path = if args.len() > 1 {
-        &args[1]
-    } else {
-        "../../tests/compile_speed.js"
+    // 获取命令行参数：[文件] [--runs N] [--warmup W]
+    let (options, args) = match BenchOptions::from_args(env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
     };
+    let file_path = args.first().map(String::as_str).unwrap_or("../../tests/compile_speed.js");
     
     println!("Lumen 解析器性能测试");
     println!("===================");
@@ -30,13 +33,9 @@ fn main() {
     println!("测试文件: {}", file_path);
     println!("文件大小: {} 字节", js_code.len());
     
-    // 运行多次以获取平均值
-    let runs = 5;
-    let mut total_ms = 0.0;
-    
-    println!("\n运行 {} 次测试...", runs);
+    println!("\n预热 {} 次，运行 {} 次测试...", options.warmup, options.runs);
     
-    for i in 1..=runs {
+    let result = measure(&options, |iteration| {
         // 创建解析器
         let parser = JsParser::new(ParseOptions::default());
         
@@ -44,27 +43,28 @@ fn main() {
         let start = Instant::now();
         let result = parser.parse_string(&js_code);
         let elapsed = start.elapsed();
-        let ms = elapsed.as_secs_f64() * 1000.0;
-        total_ms += ms;
         
         // 报告结果
-        match result {
-            Ok(ir) => {
-                println!("运行 #{}: 耗时={:.2}ms, 节点数={}", 
-                    i, ms, ir.nodes.len());
-            },
-            Err(e) => {
-                eprintln!("解析错误: {}", e);
-                return;
-            }
+        let ir = result?;
+        if let Iteration::Measured(i) = iteration {
+            println!("运行 #{}: 耗时={:.2}ms, 节点数={}", 
+                i, elapsed.as_secs_f64() * 1000.0, ir.nodes.len());
         }
-    }
+        Ok::<_, anyhow::Error>(elapsed)
+    });
+    let stats = match result {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("解析错误: {}", e);
+            return;
+        }
+    };
     
-    let avg_ms = total_ms / runs as f64;
+    let avg_ms = stats.mean_ms;
     let throughput = (js_code.len() as f64 / 1024.0 / 1024.0) / (avg_ms / 1000.0);
     
     println!("\n结果摘要:");
-    println!("平均解析时间: {:.2} ms", avg_ms);
+    println!("解析时间: 平均={:.2}ms, 中位数={:.2}ms, 标准差={:.2}ms", stats.mean_ms, stats.median_ms, stats.stddev_ms);
     println!("解析吞吐量: {:.2} MB/s", throughput);
     
     // 与其他解析器比较
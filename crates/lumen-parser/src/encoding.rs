@@ -0,0 +1,91 @@
+//! 源文件编码 - 按字节顺序标记识别UTF-16文件并转换为UTF-8
+
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+/// 源文件的字符编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceEncoding {
+    /// 根据字节顺序标记识别UTF-16 LE/BE，没有标记时按UTF-8读取
+    #[default]
+    Auto,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl SourceEncoding {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "auto" => Some(Self::Auto),
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-16le" | "utf16le" => Some(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Self::Utf16Be),
+            _ => None,
+        }
+    }
+}
+
+const UTF16_LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF16_BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+/// 把源文件的字节解码为字符串
+///
+/// UTF-16的字节顺序标记在转换时去掉；UTF-8的字节顺序标记保留，由词法分析处理。
+pub fn decode_source(bytes: &[u8], encoding: SourceEncoding) -> Result<String> {
+    let encoding = match encoding {
+        SourceEncoding::Auto if bytes.starts_with(&UTF16_LE_BOM) => SourceEncoding::Utf16Le,
+        SourceEncoding::Auto if bytes.starts_with(&UTF16_BE_BOM) => SourceEncoding::Utf16Be,
+        SourceEncoding::Auto => SourceEncoding::Utf8,
+        other => other,
+    };
+    match encoding {
+        SourceEncoding::Utf16Le => decode_utf16(bytes, &UTF16_LE_BOM, u16::from_le_bytes),
+        SourceEncoding::Utf16Be => decode_utf16(bytes, &UTF16_BE_BOM, u16::from_be_bytes),
+        _ => String::from_utf8(bytes.to_vec()).map_err(|e| anyhow!("源文件不是有效的UTF-8: {}", e)),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], bom: &[u8], to_unit: fn([u8; 2]) -> u16) -> Result<String> {
+    let bytes = bytes.strip_prefix(bom).unwrap_or(bytes);
+    if !bytes.len().is_multiple_of(2) {
+        return Err(anyhow!("UTF-16源文件的字节数不是偶数"));
+    }
+    let units = bytes.chunks_exact(2).map(|pair| to_unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|e| anyhow!("源文件不是有效的UTF-16: {}", e))
+}
+
+/// 读取源文件并按`encoding`解码
+pub fn read_source<P: AsRef<Path>>(path: P, encoding: SourceEncoding) -> Result<String> {
+    let bytes = std::fs::read(path.as_ref())?;
+    decode_source(&bytes, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsParser, ParseOptions};
+
+    #[test]
+    fn test_utf16_le_file_parses_like_utf8() {
+        let source = "var 名 = \"你好\";\n";
+        let dir = tempfile::tempdir().unwrap();
+        let utf8_path = dir.path().join("utf8.js");
+        let utf16_path = dir.path().join("utf16.js");
+        std::fs::write(&utf8_path, source).unwrap();
+        let mut utf16 = UTF16_LE_BOM.to_vec();
+        utf16.extend(source.encode_utf16().flat_map(u16::to_le_bytes));
+        std::fs::write(&utf16_path, utf16).unwrap();
+
+        assert_eq!(read_source(&utf16_path, SourceEncoding::Auto).unwrap(), source);
+        assert!(read_source(&utf16_path, SourceEncoding::Utf8).is_err(), "指定UTF-8时不转换");
+
+        let parser = JsParser::new(ParseOptions::default());
+        let from_utf8 = parser.parse_file(&utf8_path).unwrap();
+        let from_utf16 = parser.parse_file(&utf16_path).unwrap();
+        assert_eq!(from_utf16.nodes.len(), from_utf8.nodes.len());
+        assert_eq!(format!("{:?}", from_utf16.get_root()), format!("{:?}", from_utf8.get_root()));
+    }
+}
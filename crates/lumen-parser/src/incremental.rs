@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use log::debug;
+use anyhow::{Result, anyhow};
+
+use lumen_core::{IR, Node, NodeRef, NodeValue, SourceLocation};
+
+use crate::{JsParser, ParseOptions};
+
+/// 一次文本编辑：把旧源码中`start..end`（字节偏移）替换为`text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(start: usize, end: usize, text: &str) -> Self {
+        Self { start, end, text: text.to_string() }
+    }
+
+    /// 应用编辑，返回新源码
+    pub fn apply(&self, source: &str) -> Result<String> {
+        if self.start > self.end || self.end > source.len()
+            || !source.is_char_boundary(self.start) || !source.is_char_boundary(self.end)
+        {
+            return Err(anyhow!("无效的编辑范围: {}..{}", self.start, self.end));
+        }
+        Ok(format!("{}{}{}", &source[..self.start], self.text, &source[self.end..]))
+    }
+}
+
+type ParseFn = dyn Fn(&str) -> Result<IR> + Send + Sync;
+
+/// 增量解析器 - 只重新解析受编辑影响的顶层语句，并拼接回已有的IR
+///
+/// 依赖节点上的`SourceLocation`（行号从1开始，列号为行内字节偏移，结束位置不包含）确定语句范围；
+/// 顶层语句缺少位置信息时退化为完整解析。未受影响的语句保留原有节点ID，位于编辑之后的语句只平移位置。
+pub struct IncrementalParser {
+    parse: Box<ParseFn>,
+}
+
+impl IncrementalParser {
+    pub fn new(options: ParseOptions) -> Self {
+        let parser = JsParser::new(options);
+        Self::with_parser(move |source| parser.parse_string(source))
+    }
+
+    /// 使用自定义的解析函数
+    pub fn with_parser<F>(parse: F) -> Self
+    where
+        F: Fn(&str) -> Result<IR> + Send + Sync + 'static,
+    {
+        Self { parse: Box::new(parse) }
+    }
+
+    /// 对`old_source`应用编辑，更新`ir`并返回新源码
+    pub fn reparse(&self, ir: &mut IR, old_source: &str, edit: &TextEdit) -> Result<String> {
+        let new_source = edit.apply(old_source)?;
+        let old_lines = LineIndex::new(old_source);
+
+        let statements: Vec<Arc<Node>> = ir.get_root().children.iter().map(|c| c.0.clone()).collect();
+        let spans: Option<Vec<(usize, usize)>> = statements.iter()
+            .map(|s| s.location.map(|loc| old_lines.span(&loc)))
+            .collect();
+        let Some(spans) = spans else {
+            debug!("顶层语句缺少位置信息，执行完整解析");
+            return self.full_reparse(ir, new_source);
+        };
+
+        // 受影响的语句：与编辑范围相交或相邻的语句
+        let affected: Vec<usize> = spans.iter().enumerate()
+            .filter(|(_, (start, end))| *start <= edit.end && edit.start <= *end)
+            .map(|(i, _)| i)
+            .collect();
+        let (Some(&first), Some(&last)) = (affected.first(), affected.last()) else {
+            debug!("编辑位于语句之间，执行完整解析");
+            return self.full_reparse(ir, new_source);
+        };
+
+        // 在新源码中重新解析受影响的区域
+        let region_start = spans[first].0.min(edit.start);
+        let old_region_end = spans[last].1.max(edit.end);
+        let delta = edit.text.len() as isize - (edit.end - edit.start) as isize;
+        let new_region_end = (old_region_end as isize + delta) as usize;
+        let fragment = (self.parse)(&new_source[region_start..new_region_end])?;
+
+        let new_lines = LineIndex::new(&new_source);
+        let (region_line, region_column) = new_lines.position(region_start);
+
+        // 删除旧语句的节点
+        for stmt in &statements[first..=last] {
+            remove_subtree(ir, stmt);
+        }
+
+        // 把片段的节点重新编号并平移到区域所在位置，再拼接到根节点下
+        let mut id_map = HashMap::new();
+        let new_statements: Vec<NodeRef> = fragment.get_root().children.iter()
+            .map(|stmt| import_subtree(ir, &stmt.0, &mut id_map, &|loc| shift_into_region(loc, region_line, region_column)))
+            .collect();
+
+        // 编辑之后的语句保持节点ID，只平移位置
+        let (old_end_line, old_end_column) = old_lines.position(old_region_end);
+        let (new_end_line, new_end_column) = new_lines.position(new_region_end);
+        let after: Vec<NodeRef> = statements[last + 1..].iter()
+            .map(|stmt| {
+                let shift = |loc: SourceLocation| shift_after_edit(loc, (old_end_line, old_end_column), (new_end_line, new_end_column));
+                relocate_subtree(ir, stmt, &shift)
+            })
+            .collect();
+
+        let root_id = ir.root_id;
+        if let Some(root) = ir.get_node_mut(root_id) {
+            root.children.truncate(first);
+            root.children.extend(new_statements);
+            root.children.extend(after);
+        }
+
+        debug!("增量解析完成，重新解析了第 {}..={} 条语句", first, last);
+        Ok(new_source)
+    }
+
+    fn full_reparse(&self, ir: &mut IR, new_source: String) -> Result<String> {
+        let source_path = ir.source_path.clone();
+        *ir = (self.parse)(&new_source)?;
+        ir.source_path = source_path;
+        Ok(new_source)
+    }
+}
+
+/// 行首字节偏移索引
+struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self { line_starts }
+    }
+
+    fn offset(&self, line: usize, column: usize) -> usize {
+        self.line_starts.get(line.saturating_sub(1)).copied().unwrap_or(0) + column
+    }
+
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        (line, offset - self.line_starts[line - 1])
+    }
+
+    fn span(&self, loc: &SourceLocation) -> (usize, usize) {
+        (self.offset(loc.start_line, loc.start_column), self.offset(loc.end_line, loc.end_column))
+    }
+}
+
+/// 片段内的位置平移到所在区域：第一行需要加上区域起始列
+fn shift_into_region(loc: SourceLocation, line: usize, column: usize) -> SourceLocation {
+    let shift = |l: usize, c: usize| (l + line - 1, if l == 1 { c + column } else { c });
+    let (start_line, start_column) = shift(loc.start_line, loc.start_column);
+    let (end_line, end_column) = shift(loc.end_line, loc.end_column);
+    SourceLocation::new(start_line, start_column, end_line, end_column)
+}
+
+/// 编辑区域之后的位置：与区域结束在同一行的列随之平移，之后的行只平移行号
+fn shift_after_edit(loc: SourceLocation, old_end: (usize, usize), new_end: (usize, usize)) -> SourceLocation {
+    let shift = |l: usize, c: usize| {
+        if l == old_end.0 {
+            (new_end.0, c + new_end.1 - old_end.1)
+        } else {
+            (l + new_end.0 - old_end.0, c)
+        }
+    };
+    let (start_line, start_column) = shift(loc.start_line, loc.start_column);
+    let (end_line, end_column) = shift(loc.end_line, loc.end_column);
+    SourceLocation::new(start_line, start_column, end_line, end_column)
+}
+
+fn remove_subtree(ir: &mut IR, node: &Node) {
+    ir.nodes.remove(&node.id);
+    for child in &node.children {
+        remove_subtree(ir, &child.0);
+    }
+}
+
+/// 把另一个IR中的子树以新ID导入，返回导入后的节点
+fn import_subtree(
+    ir: &mut IR,
+    node: &Node,
+    id_map: &mut HashMap<usize, usize>,
+    shift: &dyn Fn(SourceLocation) -> SourceLocation,
+) -> NodeRef {
+    let new_id = ir.next_id;
+    ir.next_id += 1;
+    id_map.insert(node.id, new_id);
+
+    let mut new_node = node.clone();
+    new_node.id = new_id;
+    new_node.parent = node.parent.and_then(|p| id_map.get(&p).copied());
+    new_node.location = node.location.map(shift);
+    if let Some(NodeValue::Array(comments)) = node.get_value("leading_comments") {
+        let comments = comments.iter().map(|c| import_subtree(ir, &c.0, id_map, shift)).collect();
+        new_node.set_value("leading_comments", NodeValue::Array(comments));
+    }
+    new_node.children = node.children.iter()
+        .map(|child| import_subtree(ir, &child.0, id_map, shift))
+        .collect();
+
+    let node_ref = NodeRef(Arc::new(new_node));
+    ir.nodes.insert(new_id, node_ref.clone());
+    node_ref
+}
+
+/// 保持ID不变地平移子树中所有节点的位置
+fn relocate_subtree(ir: &mut IR, node: &Node, shift: &dyn Fn(SourceLocation) -> SourceLocation) -> NodeRef {
+    let mut new_node = node.clone();
+    new_node.location = node.location.map(shift);
+    new_node.children = node.children.iter()
+        .map(|child| relocate_subtree(ir, &child.0, shift))
+        .collect();
+
+    let node_ref = NodeRef(Arc::new(new_node));
+    ir.nodes.insert(node.id, node_ref.clone());
+    node_ref
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::NodeType;
+
+    /// 测试用的简易解析器：每个非空行是一条 `name = value;` 语句
+    fn line_parser(source: &str) -> Result<IR> {
+        let mut ir = IR::new();
+        for (i, line) in source.lines().enumerate() {
+            let text = line.trim_end();
+            if text.is_empty() {
+                continue;
+            }
+            let (name, value) = text.trim_end_matches(';').split_once('=')
+                .ok_or_else(|| anyhow!("无法解析: {}", text))?;
+            let location = SourceLocation::new(i + 1, 0, i + 1, text.len());
+
+            let ident = ir.create_node(NodeType::Identifier);
+            let lit = ir.create_node(NodeType::NumericLiteral);
+            let decl = ir.create_node(NodeType::VariableDeclaration);
+            ir.get_node_mut(ident).unwrap().set_value("name", NodeValue::String(name.trim().to_string()));
+            ir.get_node_mut(lit).unwrap().set_value("value", NodeValue::Number(value.trim().parse()?));
+            ir.get_node_mut(decl).unwrap().location = Some(location);
+            ir.add_child(decl, ident);
+            ir.add_child(decl, lit);
+            ir.add_child(ir.root_id, decl);
+        }
+        Ok(ir)
+    }
+
+    fn statement_ids(ir: &IR) -> Vec<usize> {
+        ir.get_root().children.iter().map(|c| c.0.id).collect()
+    }
+
+    #[test]
+    fn test_reparse_only_edited_statement() {
+        let source = "a = 1;\nb = 2;\nc = 3;\n";
+        let mut ir = line_parser(source).unwrap();
+        let before = statement_ids(&ir);
+
+        // 把 b = 2 改为 b = 42
+        let offset = source.find('2').unwrap();
+        let parser = IncrementalParser::with_parser(line_parser);
+        let edit = TextEdit::new(offset, offset + 1, "42");
+        let new_source = parser.reparse(&mut ir, source, &edit).unwrap();
+        assert_eq!(new_source, "a = 1;\nb = 42;\nc = 3;\n");
+
+        let after = statement_ids(&ir);
+        assert_eq!(after.len(), 3);
+        assert_eq!(after[0], before[0], "编辑之前的语句应保留节点ID");
+        assert_eq!(after[2], before[2], "编辑之后的语句应保留节点ID");
+        assert_ne!(after[1], before[1], "被编辑的语句应重新解析");
+        assert!(ir.get_node(before[1]).is_none(), "旧语句的节点应从节点表中删除");
+
+        let edited = ir.get_node(after[1]).unwrap();
+        assert_eq!(edited.children[1].0.get_number_value("value"), Some(42.0));
+        assert_eq!(edited.location, Some(SourceLocation::new(2, 0, 2, 7)));
+        assert_eq!(ir.get_node(after[2]).unwrap().location, Some(SourceLocation::new(3, 0, 3, 6)));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::path::Path;
 use std::time::Instant;
@@ -5,7 +6,12 @@ use log::{debug, info, warn};
 use anyhow::{Result, anyhow};
 
 // 引入核心模块
-use lumen_core::IR;
+use lumen_core::{CompileErrorInfo, IR, NodeType, NodeValue, SourceLocation};
+
+mod encoding;
+mod incremental;
+pub use encoding::{SourceEncoding, decode_source, read_source};
+pub use incremental::{IncrementalParser, TextEdit};
 
 /// 解析选项
 #[derive(Debug, Clone)]
@@ -20,6 +26,16 @@ pub struct ParseOptions {
     pub filename: Option<String>,
     /// 源代码映射
     pub source_map: bool,
+    /// 词法单元数量上限，None表示不限制
+    pub max_tokens: Option<usize>,
+    /// 单个字符串字面量的长度上限（字节）
+    pub max_string_len: Option<usize>,
+    /// IR节点数量上限
+    pub max_nodes: Option<usize>,
+    /// 读取源文件时使用的编码
+    pub encoding: SourceEncoding,
+    /// `parse_string_recovering`遇到语法错误时是否跳过出错的语句继续解析，关闭时在第一个错误处停止
+    pub error_recovery: bool,
 }
 
 impl Default for ParseOptions {
@@ -30,10 +46,76 @@ impl Default for ParseOptions {
             comments: true,
             filename: None,
             source_map: false,
+            max_tokens: None,
+            max_string_len: None,
+            max_nodes: None,
+            encoding: SourceEncoding::Auto,
+            error_recovery: false,
         }
     }
 }
 
+/// 带位置的语法错误
+///
+/// 行号从1开始，列号为行内字节偏移。解析失败时通过`anyhow::Error::downcast_ref`取得位置。
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("语法错误 ({line}:{column}): {message}")]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    pub fn new(message: &str, line: usize, column: usize) -> Self {
+        Self { message: message.to_string(), line, column }
+    }
+}
+
+/// 输入超出`ParseOptions`中的资源限制
+///
+/// 处理不可信输入的服务应设置这些限制，避免超大输入耗尽内存。
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseLimitError {
+    #[error("超出资源限制: 词法单元数量超过 {limit}")]
+    TooManyTokens { limit: usize },
+    #[error("超出资源限制: 字符串字面量长度超过 {limit} 字节 ({line}:{column})")]
+    StringTooLong { limit: usize, line: usize, column: usize },
+    #[error("超出资源限制: IR节点数量超过 {limit}")]
+    TooManyNodes { limit: usize },
+}
+
+/// 检查词法单元数量和字符串字面量长度
+fn check_token_limits(tokens: &[Token], options: &ParseOptions) -> std::result::Result<(), ParseLimitError> {
+    if let Some(limit) = options.max_tokens {
+        if tokens.len() > limit {
+            return Err(ParseLimitError::TooManyTokens { limit });
+        }
+    }
+    if let Some(limit) = options.max_string_len {
+        let too_long = tokens.iter()
+            .find(|t| {
+                matches!(
+                    t.token_type,
+                    TokenType::String | TokenType::Template | TokenType::TemplateHead
+                        | TokenType::TemplateMiddle | TokenType::TemplateTail
+                ) && t.lexeme.len() > limit
+            });
+        if let Some(token) = too_long {
+            return Err(ParseLimitError::StringTooLong { limit, line: token.line, column: token.column });
+        }
+    }
+    Ok(())
+}
+
+/// 检查IR节点数量
+fn check_node_limit(ir: &IR, options: &ParseOptions) -> std::result::Result<(), ParseLimitError> {
+    match options.max_nodes {
+        Some(limit) if ir.nodes.len() > limit => Err(ParseLimitError::TooManyNodes { limit }),
+        _ => Ok(()),
+    }
+}
+
 /// 词法单元类型
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
@@ -47,6 +129,7 @@ pub enum TokenType {
     Semicolon,    // ;
     Comma,        // ,
     Dot,          // .
+    Colon,        // :
     
     // 运算符
     Plus,         // +
@@ -64,6 +147,8 @@ pub enum TokenType {
     Less,         // <
     LessEqual,    // <=
     Arrow,        // =>
+    Tilde,        // ~
+    Question,     // ?
     
     // 关键字
     Var,
@@ -75,16 +160,33 @@ pub enum TokenType {
     While,
     Function,
     Return,
+    Break,
+    Continue,
     Class,
     Import,
     Export,
     From,
     Async,
     Await,
+    Yield,
+    New,
+    Delete,
+    Typeof,
+    Void,
+    In,
+    Instanceof,
     
     // 字面量
     Identifier,
     String,
+    /// 没有插值的模板字符串 `` `...` ``
+    Template,
+    /// 模板字符串第一个插值之前的部分 `` `...${ ``
+    TemplateHead,
+    /// 两个插值之间的部分 `}...${`
+    TemplateMiddle,
+    /// 最后一个插值之后的部分 `` }...` ``
+    TemplateTail,
     Number,
     Boolean,
     Null,
@@ -105,6 +207,90 @@ pub enum TokenType {
     EOF,
 }
 
+/// 语法高亮的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightCategory {
+    Keyword,
+    Identifier,
+    String,
+    Number,
+    /// `true`、`false`、`null`、`undefined`
+    Literal,
+    Operator,
+    Punctuation,
+    Comment,
+    /// JSX标签和属性
+    Tag,
+    /// TypeScript类型
+    Type,
+    /// 不需要高亮（如`EOF`）
+    None,
+}
+
+impl TokenType {
+    /// 关键字对应的词法单元类型，非关键字返回None
+    pub fn keyword(lexeme: &str) -> Option<TokenType> {
+        let token_type = match lexeme {
+            "var" => TokenType::Var,
+            "let" => TokenType::Let,
+            "const" => TokenType::Const,
+            "if" => TokenType::If,
+            "else" => TokenType::Else,
+            "for" => TokenType::For,
+            "while" => TokenType::While,
+            "function" => TokenType::Function,
+            "return" => TokenType::Return,
+            "break" => TokenType::Break,
+            "continue" => TokenType::Continue,
+            "class" => TokenType::Class,
+            "import" => TokenType::Import,
+            "export" => TokenType::Export,
+            "from" => TokenType::From,
+            "async" => TokenType::Async,
+            "await" => TokenType::Await,
+            "yield" => TokenType::Yield,
+            "new" => TokenType::New,
+            "delete" => TokenType::Delete,
+            "typeof" => TokenType::Typeof,
+            "void" => TokenType::Void,
+            "in" => TokenType::In,
+            "instanceof" => TokenType::Instanceof,
+            "true" | "false" => TokenType::Boolean,
+            "null" => TokenType::Null,
+            "undefined" => TokenType::Undefined,
+            _ => return None,
+        };
+        Some(token_type)
+    }
+    
+    /// 词法单元在语法高亮中的分类
+    pub fn highlight_category(&self) -> HighlightCategory {
+        match self {
+            TokenType::LeftParen | TokenType::RightParen | TokenType::LeftBrace | TokenType::RightBrace
+            | TokenType::LeftBracket | TokenType::RightBracket | TokenType::Semicolon | TokenType::Comma
+            | TokenType::Dot | TokenType::Colon => HighlightCategory::Punctuation,
+            TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash | TokenType::Percent
+            | TokenType::Assign | TokenType::Equal | TokenType::StrictEqual | TokenType::NotEqual
+            | TokenType::StrictNotEqual | TokenType::Greater | TokenType::GreaterEqual | TokenType::Less
+            | TokenType::LessEqual | TokenType::Arrow | TokenType::Tilde | TokenType::Question => HighlightCategory::Operator,
+            TokenType::Var | TokenType::Let | TokenType::Const | TokenType::If | TokenType::Else
+            | TokenType::For | TokenType::While | TokenType::Function | TokenType::Return | TokenType::Break
+            | TokenType::Continue | TokenType::Class | TokenType::Import | TokenType::Export | TokenType::From
+            | TokenType::Async | TokenType::Await | TokenType::Yield | TokenType::New | TokenType::Delete
+            | TokenType::Typeof | TokenType::Void | TokenType::In | TokenType::Instanceof => HighlightCategory::Keyword,
+            TokenType::Identifier => HighlightCategory::Identifier,
+            TokenType::String | TokenType::Template | TokenType::TemplateHead | TokenType::TemplateMiddle
+            | TokenType::TemplateTail | TokenType::JSXAttributeValue => HighlightCategory::String,
+            TokenType::Number => HighlightCategory::Number,
+            TokenType::Boolean | TokenType::Null | TokenType::Undefined => HighlightCategory::Literal,
+            TokenType::JSXIdentifier | TokenType::JSXOpeningElement | TokenType::JSXClosingElement => HighlightCategory::Tag,
+            TokenType::TSType | TokenType::TSInterface => HighlightCategory::Type,
+            TokenType::Comment => HighlightCategory::Comment,
+            TokenType::EOF => HighlightCategory::None,
+        }
+    }
+}
+
 /// 词法单元
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -123,9 +309,29 @@ impl Token {
             column,
         }
     }
+    
+    /// 词法单元结束处（不包含）的行号和列号
+    ///
+    /// 字符串和模板字符串的`lexeme`不含引号、反引号和插值的`${`、`}`，需要加上这些分隔符的长度。
+    pub fn end(&self) -> (usize, usize) {
+        let (open, close) = match self.token_type {
+            TokenType::String | TokenType::Template | TokenType::TemplateTail => (1, 1),
+            TokenType::TemplateHead | TokenType::TemplateMiddle => (1, 2),
+            _ => (0, 0),
+        };
+        match self.lexeme.rfind('\n') {
+            // 跨行的模板字符串和块注释结束在最后一行
+            Some(i) => (self.line + self.lexeme.matches('\n').count(), self.lexeme.len() - i - 1 + close),
+            None => (self.line, self.column + open + self.lexeme.len() + close),
+        }
+    }
 }
 
 /// 词法分析器
+///
+/// 行号从1开始，列号为行内字节偏移（从0开始），与`ParseError`一致。
+/// 字符串词法单元的`lexeme`是引号之间的原始文本（转义序列保持原样）。
+/// 带插值的模板字符串拆分为`TemplateHead`、`TemplateMiddle`和`TemplateTail`，插值表达式的词法单元位于它们之间。
 pub struct Lexer {
     source: String,
     tokens: Vec<Token>,
@@ -133,6 +339,14 @@ pub struct Lexer {
     current: usize,
     line: usize,
     column: usize,
+    /// 当前词法单元开始处的行号和列号
+    start_line: usize,
+    start_column: usize,
+    /// 当前未闭合的`{`数量
+    brace_depth: usize,
+    /// 每个未结束的模板插值开始时的`brace_depth`，遇到同一深度的`}`时回到模板字符串
+    template_braces: Vec<usize>,
+    errors: Vec<ParseError>,
     options: ParseOptions,
 }
 
@@ -145,46 +359,1384 @@ impl Lexer {
             current: 0,
             line: 1,
             column: 0,
+            start_line: 1,
+            start_column: 0,
+            brace_depth: 0,
+            template_braces: Vec::new(),
+            errors: Vec::new(),
             options,
         }
     }
     
+    /// 扫描整个源码，最后一个词法单元总是`EOF`
+    ///
+    /// 遇到无法识别的字符或未闭合的字符串时记录错误并继续扫描，错误通过`errors`取得。
     pub fn scan_tokens(&mut self) -> Vec<Token> {
-        // TODO: 实现词法分析
-        // 这里只是一个极简的示例
-        
-        // 返回一个示例token列表
-        vec![
-            Token::new(TokenType::Var, "var", 1, 1),
-            Token::new(TokenType::Identifier, "x", 1, 5),
-            Token::new(TokenType::Assign, "=", 1, 7),
-            Token::new(TokenType::Number, "42", 1, 9),
-            Token::new(TokenType::Semicolon, ";", 1, 11),
-            Token::new(TokenType::EOF, "", 1, 12),
-        ]
+        self.skip_preamble();
+        while !self.is_at_end() {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
+            self.scan_token();
+        }
+        self.tokens.push(Token::new(TokenType::EOF, "", self.line, self.column));
+        std::mem::take(&mut self.tokens)
+    }
+    
+    /// 扫描过程中遇到的词法错误
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+    
+    /// 跳过文件开头的BOM和shebang行，shebang由`JsParser`单独保留
+    fn skip_preamble(&mut self) {
+        if self.source.starts_with('\u{feff}') {
+            self.current = '\u{feff}'.len_utf8();
+        }
+        if self.source[self.current..].starts_with("#!") {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.advance();
+            }
+        }
+    }
+    
+    fn scan_token(&mut self) {
+        let Some(c) = self.advance() else {
+            return;
+        };
+        match c {
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => {
+                self.brace_depth += 1;
+                self.add_token(TokenType::LeftBrace);
+            },
+            '}' if self.template_braces.last() == Some(&self.brace_depth) => {
+                self.template_braces.pop();
+                self.template(false);
+            },
+            '}' => {
+                self.brace_depth = self.brace_depth.saturating_sub(1);
+                self.add_token(TokenType::RightBrace);
+            },
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ';' => self.add_token(TokenType::Semicolon),
+            ',' => self.add_token(TokenType::Comma),
+            ':' => self.add_token(TokenType::Colon),
+            '.' if self.peek().is_some_and(|c| c.is_ascii_digit()) => self.number(),
+            '.' => self.add_token(TokenType::Dot),
+            '+' => self.add_token(TokenType::Plus),
+            '-' => self.add_token(TokenType::Minus),
+            '*' => self.add_token(TokenType::Star),
+            '~' => self.add_token(TokenType::Tilde),
+            '?' => self.add_token(TokenType::Question),
+            '%' => self.add_token(TokenType::Percent),
+            '/' if self.match_char('/') => {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.advance();
+                }
+                self.add_comment();
+            },
+            '/' if self.match_char('*') => self.block_comment(),
+            '/' => self.add_token(TokenType::Slash),
+            '=' => {
+                let token_type = if self.match_char('=') {
+                    if self.match_char('=') { TokenType::StrictEqual } else { TokenType::Equal }
+                } else if self.match_char('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Assign
+                };
+                self.add_token(token_type);
+            },
+            '!' if self.match_char('=') => {
+                let token_type = if self.match_char('=') { TokenType::StrictNotEqual } else { TokenType::NotEqual };
+                self.add_token(token_type);
+            },
+            '>' => {
+                let token_type = if self.match_char('=') { TokenType::GreaterEqual } else { TokenType::Greater };
+                self.add_token(token_type);
+            },
+            '<' => {
+                let token_type = if self.match_char('=') { TokenType::LessEqual } else { TokenType::Less };
+                self.add_token(token_type);
+            },
+            '"' | '\'' => self.string(c),
+            '`' => self.template(true),
+            c if c.is_whitespace() => {},
+            c if c.is_ascii_digit() => self.number(),
+            c if is_identifier_start(c) => self.identifier(),
+            c => self.error(&format!("无法识别的字符 '{}'", c)),
+        }
+    }
+    
+    fn string(&mut self, quote: char) {
+        loop {
+            match self.peek() {
+                None | Some('\n') => {
+                    self.error("未闭合的字符串");
+                    return;
+                },
+                Some(c) if c == quote => break,
+                Some('\\') => {
+                    self.advance();
+                    self.advance();
+                },
+                Some(_) => {
+                    self.advance();
+                },
+            }
+        }
+        self.advance();
+        let value = &self.source[self.start + 1..self.current - 1];
+        let token = Token::new(TokenType::String, value, self.start_line, self.start_column);
+        self.tokens.push(token);
+    }
+    
+    /// 扫描模板字符串的一部分，`head`为true时从反引号开始，否则从结束插值的`}`开始
+    fn template(&mut self, head: bool) {
+        let token_type = loop {
+            match self.peek() {
+                None => {
+                    self.error("未闭合的模板字符串");
+                    return;
+                },
+                Some('`') => {
+                    self.advance();
+                    break if head { TokenType::Template } else { TokenType::TemplateTail };
+                },
+                Some('$') if self.peek_next() == Some('{') => {
+                    self.advance();
+                    self.advance();
+                    self.template_braces.push(self.brace_depth);
+                    break if head { TokenType::TemplateHead } else { TokenType::TemplateMiddle };
+                },
+                Some('\\') => {
+                    self.advance();
+                    self.advance();
+                },
+                Some(_) => {
+                    self.advance();
+                },
+            }
+        };
+        let close = if matches!(token_type, TokenType::TemplateHead | TokenType::TemplateMiddle) { 2 } else { 1 };
+        let value = &self.source[self.start + 1..self.current - close];
+        let token = Token::new(token_type, value, self.start_line, self.start_column);
+        self.tokens.push(token);
+    }
+    
+    /// 数值字面量：十进制、`0x`/`0o`/`0b`前缀的整数，可以带BigInt后缀`n`
+    fn number(&mut self) {
+        let radix = match (self.source[self.start..].chars().next(), self.peek()) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.advance();
+            while self.peek().is_some_and(|c| c.is_digit(radix)) {
+                self.advance();
+            }
+        } else {
+            self.decimal();
+        }
+        if self.peek() == Some('n') {
+            self.advance();
+        }
+        self.add_token(TokenType::Number);
+    }
+    
+    fn decimal(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.advance();
+        }
+        if self.peek().is_some_and(|c| c == 'e' || c == 'E') {
+            let sign = self.peek_next().is_some_and(|c| c == '+' || c == '-');
+            let digit_at = if sign { 2 } else { 1 };
+            if self.source[self.current..].chars().nth(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                for _ in 0..digit_at {
+                    self.advance();
+                }
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.advance();
+                }
+            }
+        }
+    }
+    
+    fn identifier(&mut self) {
+        while self.peek().is_some_and(is_identifier_part) {
+            self.advance();
+        }
+        let lexeme = &self.source[self.start..self.current];
+        let token_type = TokenType::keyword(lexeme).unwrap_or(TokenType::Identifier);
+        self.add_token(token_type);
+    }
+    
+    fn block_comment(&mut self) {
+        loop {
+            match self.advance() {
+                None => {
+                    self.error("未闭合的块注释");
+                    return;
+                },
+                Some('*') if self.match_char('/') => break,
+                Some(_) => {},
+            }
+        }
+        self.add_comment();
+    }
+    
+    /// 只有启用`comments`时才输出注释词法单元
+    fn add_comment(&mut self) {
+        if self.options.comments {
+            self.add_token(TokenType::Comment);
+        }
+    }
+    
+    fn add_token(&mut self, token_type: TokenType) {
+        let lexeme = &self.source[self.start..self.current];
+        let token = Token::new(token_type, lexeme, self.start_line, self.start_column);
+        self.tokens.push(token);
+    }
+    
+    fn error(&mut self, message: &str) {
+        self.errors.push(ParseError::new(message, self.start_line, self.start_column));
+    }
+    
+    fn is_at_end(&self) -> bool {
+        self.current >= self.source.len()
+    }
+    
+    fn peek(&self) -> Option<char> {
+        self.source[self.current..].chars().next()
+    }
+    
+    fn peek_next(&self) -> Option<char> {
+        self.source[self.current..].chars().nth(1)
+    }
+    
+    fn match_char(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// 前进一个字符，遇到换行时更新行号和列号
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += c.len_utf8();
+        }
+        Some(c)
     }
 }
 
-/// 语法解析器
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// 语法解析器，把词法单元序列转换为IR
+///
+/// 目前支持的语法：变量声明（`var`/`let`/`const`，可用逗号声明多个变量，每个变量对应一个声明节点）、
+/// `import`/`export`声明、`while`/`for`循环、带标签的语句和`break`/`continue`、表达式语句，
+/// 以及由数字、字符串、模板字符串、布尔值、`null`、标识符、对象字面量和括号组成的二元算术与比较表达式、
+/// 条件表达式`a ? b : c`、逗号表达式`a, b`，生成器函数中的`yield`和async函数中的`await`。
+/// 语句末尾的分号在换行或文件结尾处可以省略。
 pub struct Parser {
-    source: String,
-    current_pos: usize,
+    tokens: Vec<Token>,
+    current: usize,
+    /// 词法分析阶段的第一个错误，`parse`时报告
+    lex_error: Option<ParseError>,
+    /// 词法单元序号到紧挨在它之前的注释，作为从该词法单元开始的语句的前导注释
+    comments: HashMap<usize, Vec<Token>>,
 }
 
 impl Parser {
+    /// 使用默认选项对源码进行词法分析后解析，词法错误在`parse`时报告
     pub fn new(source: &str) -> Self {
-        Self {
-            source: source.to_string(),
-            current_pos: 0,
+        let mut lexer = Lexer::new(source, ParseOptions { comments: false, ..Default::default() });
+        let tokens = lexer.scan_tokens();
+        let mut parser = Self::with_tokens(tokens);
+        parser.lex_error = lexer.errors().first().cloned();
+        parser
+    }
+
+    /// 解析已有的词法单元序列；序列应以`EOF`结尾
+    ///
+    /// 注释不参与解析，语句之前的注释保存为该语句的前导注释，其余位置的注释被丢弃。
+    pub fn with_tokens(tokens: Vec<Token>) -> Self {
+        let (comments, tokens): (Vec<Token>, Vec<Token>) = tokens.into_iter().partition(|t| t.token_type == TokenType::Comment);
+        let mut tokens = tokens;
+        if tokens.last().is_none_or(|t| t.token_type != TokenType::EOF) {
+            let (line, column) = tokens.last().map_or((1, 0), |t| (t.line, t.column + t.lexeme.len()));
+            tokens.push(Token::new(TokenType::EOF, "", line, column));
         }
+
+        // 注释归属于之后的第一个词法单元
+        let mut attached: HashMap<usize, Vec<Token>> = HashMap::new();
+        for comment in comments {
+            let next = tokens.partition_point(|t| (t.line, t.column) < (comment.line, comment.column));
+            attached.entry(next).or_default().push(comment);
+        }
+        Self { tokens, current: 0, lex_error: None, comments: attached }
     }
 
     pub fn parse(&mut self) -> Result<IR> {
-        // 简单实现，实际项目中应完整解析
-        let ir = IR::new();
-        // 基本的解析逻辑
+        if let Some(error) = self.lex_error.take() {
+            return Err(error.into());
+        }
+        let mut ir = IR::new();
+        let root_id = ir.root_id;
+        while !self.check(TokenType::EOF) {
+            for statement in self.statement(&mut ir)? {
+                ir.add_child(root_id, statement);
+            }
+        }
+        if self.current > 0 {
+            self.locate(&mut ir, root_id, 0);
+        }
         Ok(ir)
     }
+
+    /// 遇到语法错误时跳到下一条语句继续解析，返回尽力得到的IR和所有错误
+    ///
+    /// 出错的语句整条丢弃，其中已创建的节点也从IR中删除。
+    pub fn parse_recovering(&mut self) -> (IR, Vec<ParseError>) {
+        let mut errors: Vec<ParseError> = self.lex_error.take().into_iter().collect();
+        let mut ir = IR::new();
+        let root_id = ir.root_id;
+        while !self.check(TokenType::EOF) {
+            let start = self.current;
+            let first_node = ir.next_id;
+            match self.statement(&mut ir) {
+                Ok(statements) => {
+                    for statement in statements {
+                        ir.add_child(root_id, statement);
+                    }
+                },
+                Err(error) => {
+                    ir.nodes.retain(|id, _| *id < first_node);
+                    errors.push(self.to_parse_error(error));
+                    self.synchronize(start);
+                },
+            }
+        }
+        if self.current > 0 {
+            self.locate(&mut ir, root_id, 0);
+        }
+        (ir, errors)
+    }
+
+    /// 出错后跳到下一条语句：越过分号，或停在语句关键字处，至少越过出错语句的第一个词法单元
+    fn synchronize(&mut self, start: usize) {
+        while !self.check(TokenType::EOF) {
+            if self.current > start {
+                let after_semicolon = self.tokens[self.current - 1].token_type == TokenType::Semicolon;
+                let at_statement = matches!(
+                    self.peek().token_type,
+                    TokenType::Var | TokenType::Let | TokenType::Const | TokenType::Function
+                        | TokenType::Import | TokenType::Export | TokenType::Return
+                        | TokenType::While | TokenType::For | TokenType::Break | TokenType::Continue
+                );
+                if after_semicolon || at_statement {
+                    return;
+                }
+            }
+            self.advance();
+        }
+    }
+
+    /// 取出错误中的位置，其他错误（如资源限制）报告在当前词法单元处
+    fn to_parse_error(&self, error: anyhow::Error) -> ParseError {
+        error.downcast::<ParseError>().unwrap_or_else(|error| {
+            let token = self.peek();
+            ParseError::new(&error.to_string(), token.line, token.column)
+        })
+    }
+
+    /// 解析一条语句，返回挂到父节点下的语句节点（一条多变量声明对应多个节点）
+    ///
+    /// 语句的位置包含结尾的分号。
+    fn statement(&mut self, ir: &mut IR) -> Result<Vec<usize>> {
+        let start = self.current;
+        // 函数声明和代码块以右花括号结尾，循环和带标签的语句以语句体结尾，后面不需要分号
+        let braced = matches!(self.peek().token_type, TokenType::Function | TokenType::LeftBrace | TokenType::While | TokenType::For)
+            || self.at_async_function()
+            || self.at_label();
+        let comments = self.comments.remove(&start).unwrap_or_default();
+        let statements = match self.peek().token_type {
+            TokenType::Var | TokenType::Let | TokenType::Const => self.variable_declaration(ir)?,
+            TokenType::Import if !self.at_import_expression() => vec![self.import_declaration(ir)?],
+            TokenType::Export => vec![self.export_declaration(ir)?],
+            TokenType::Function => vec![self.function_declaration(ir)?],
+            TokenType::Async if self.at_async_function() => vec![self.function_declaration(ir)?],
+            TokenType::LeftBrace => vec![self.block_statement(ir)?],
+            TokenType::Return => vec![self.return_statement(ir)?],
+            TokenType::While => vec![self.while_statement(ir)?],
+            TokenType::For => vec![self.for_statement(ir)?],
+            TokenType::Break | TokenType::Continue => vec![self.jump_statement(ir)?],
+            TokenType::Identifier if self.at_label() => vec![self.labeled_statement(ir)?],
+            TokenType::Semicolon => Vec::new(),
+            _ => vec![self.sequence(ir)?],
+        };
+        if !braced {
+            self.end_of_statement()?;
+        }
+        if let [statement] = statements.as_slice() {
+            self.locate(ir, *statement, start);
+        } else if let Some(&last) = statements.last() {
+            // 多变量声明：只把最后一个声明延伸到分号
+            let start = ir.get_node(last).and_then(|n| n.location).map(|loc| (loc.start_line, loc.start_column));
+            if let (Some((line, column)), Some(node)) = (start, ir.get_node_mut(last)) {
+                let (end_line, end_column) = self.tokens[self.current - 1].end();
+                node.location = Some(SourceLocation::new(line, column, end_line, end_column));
+            }
+        }
+        if let Some(&first) = statements.first() {
+            for comment in comments {
+                let (text, block) = match comment.lexeme.strip_prefix("//") {
+                    Some(text) => (text, false),
+                    None => (comment.lexeme.strip_prefix("/*").and_then(|text| text.strip_suffix("*/")).unwrap_or_default(), true),
+                };
+                ir.add_leading_comment(first, text, block);
+            }
+        }
+        Ok(statements)
+    }
+
+    /// 每个声明的位置从变量名开始（第一个声明从`var`/`let`/`const`开始）
+    fn variable_declaration(&mut self, ir: &mut IR) -> Result<Vec<usize>> {
+        let mut start = self.current;
+        let kind = self.advance().lexeme;
+        let mut declarations = Vec::new();
+        loop {
+            let name_start = self.current;
+            let name = self.expect(TokenType::Identifier)?;
+            let decl = ir.create_node(NodeType::VariableDeclaration);
+            set_value(ir, decl, "kind", NodeValue::String(kind.clone()));
+            let ident = self.identifier(ir, &name.lexeme, name_start);
+            ir.add_child(decl, ident);
+            if self.match_token(TokenType::Assign) {
+                let init = self.expression(ir)?;
+                ir.add_child(decl, init);
+            } else if kind == "const" {
+                return Err(self.error_at_current("const声明缺少初始值"));
+            }
+            self.locate(ir, decl, start);
+            declarations.push(decl);
+            if !self.match_token(TokenType::Comma) {
+                return Ok(declarations);
+            }
+            start = self.current;
+        }
+    }
+
+    /// `function name(a, b) { ... }`，子节点为参数标识符，最后一个子节点是函数体
+    ///
+    /// 生成器函数`function* name() { ... }`的`generator`为true，`async function`的`async`为true。
+    fn function_declaration(&mut self, ir: &mut IR) -> Result<usize> {
+        let is_async = self.match_token(TokenType::Async);
+        self.advance();
+        let generator = self.match_token(TokenType::Star);
+        let name = self.expect(TokenType::Identifier)?.lexeme;
+        let function = ir.create_node(NodeType::FunctionDeclaration);
+        set_value(ir, function, "name", NodeValue::String(name));
+        if generator {
+            set_value(ir, function, "generator", NodeValue::Boolean(true));
+        }
+        if is_async {
+            set_value(ir, function, "async", NodeValue::Boolean(true));
+        }
+        self.expect(TokenType::LeftParen)?;
+        for param in self.parameters(ir)? {
+            ir.add_child(function, param);
+        }
+        let body = self.block_statement(ir)?;
+        ir.add_child(function, body);
+        Ok(function)
+    }
+
+    /// 当前是否为`async function`
+    fn at_async_function(&self) -> bool {
+        self.check(TokenType::Async) && self.tokens.get(self.current + 1).is_some_and(|t| t.token_type == TokenType::Function)
+    }
+
+    /// 参数列表中左括号之后的部分`a, b)`，返回参数标识符
+    fn parameters(&mut self, ir: &mut IR) -> Result<Vec<usize>> {
+        let mut params = Vec::new();
+        while !self.match_token(TokenType::RightParen) {
+            let start = self.current;
+            let name = self.expect(TokenType::Identifier)?.lexeme;
+            params.push(self.identifier(ir, &name, start));
+            if !self.match_token(TokenType::Comma) && !self.check(TokenType::RightParen) {
+                return Err(self.error_at_current(""));
+            }
+        }
+        Ok(params)
+    }
+
+    fn block_statement(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        self.expect(TokenType::LeftBrace)?;
+        let block = ir.create_node(NodeType::BlockStatement);
+        while !self.match_token(TokenType::RightBrace) {
+            for statement in self.statement(ir)? {
+                ir.add_child(block, statement);
+            }
+        }
+        self.locate(ir, block, start);
+        Ok(block)
+    }
+
+    /// `return`和返回值之间换行时，按自动分号插入的规则没有返回值
+    fn return_statement(&mut self, ir: &mut IR) -> Result<usize> {
+        let keyword = self.advance();
+        let ret = ir.create_node(NodeType::ReturnStatement);
+        let next = self.peek();
+        let has_argument = !matches!(next.token_type, TokenType::Semicolon | TokenType::RightBrace | TokenType::EOF)
+            && next.line == keyword.line;
+        if has_argument {
+            let argument = self.expression(ir)?;
+            ir.add_child(ret, argument);
+        }
+        Ok(ret)
+    }
+
+    /// `while (test) body`，子节点为条件和循环体
+    fn while_statement(&mut self, ir: &mut IR) -> Result<usize> {
+        self.advance();
+        self.expect(TokenType::LeftParen)?;
+        let test = self.sequence(ir)?;
+        self.expect(TokenType::RightParen)?;
+        let body = self.substatement(ir)?;
+        let node = ir.create_node(NodeType::WhileStatement);
+        ir.add_child(node, test);
+        ir.add_child(node, body);
+        Ok(node)
+    }
+
+    /// `for (init; test; update) body`，四个子节点依次为初始化、条件、更新和循环体，省略的部分是`Unknown`节点
+    ///
+    /// 初始化部分是一个变量声明或表达式。
+    fn for_statement(&mut self, ir: &mut IR) -> Result<usize> {
+        self.advance();
+        self.expect(TokenType::LeftParen)?;
+        let init = if matches!(self.peek().token_type, TokenType::Var | TokenType::Let | TokenType::Const) {
+            let declarations = self.variable_declaration(ir)?;
+            let [declaration] = declarations.as_slice() else {
+                return Err(self.error_at_current("for语句的初始化部分只支持声明一个变量"));
+            };
+            *declaration
+        } else {
+            self.for_clause(ir, TokenType::Semicolon)?
+        };
+        self.expect(TokenType::Semicolon)?;
+        let test = self.for_clause(ir, TokenType::Semicolon)?;
+        self.expect(TokenType::Semicolon)?;
+        let update = self.for_clause(ir, TokenType::RightParen)?;
+        self.expect(TokenType::RightParen)?;
+        let body = self.substatement(ir)?;
+        let node = ir.create_node(NodeType::ForStatement);
+        for child in [init, test, update, body] {
+            ir.add_child(node, child);
+        }
+        Ok(node)
+    }
+
+    /// for语句头部的一部分，直接遇到`end`时省略
+    fn for_clause(&mut self, ir: &mut IR, end: TokenType) -> Result<usize> {
+        if self.check(end) {
+            return Ok(ir.create_node(NodeType::Unknown));
+        }
+        self.sequence(ir)
+    }
+
+    /// `break`、`continue`和同一行上可选的标签`label`
+    fn jump_statement(&mut self, ir: &mut IR) -> Result<usize> {
+        let keyword = self.advance();
+        let node_type = if keyword.token_type == TokenType::Break { NodeType::BreakStatement } else { NodeType::ContinueStatement };
+        let node = ir.create_node(node_type);
+        if self.check(TokenType::Identifier) && self.peek().line == keyword.line {
+            let label = self.advance().lexeme;
+            set_value(ir, node, "label", NodeValue::String(label));
+        }
+        Ok(node)
+    }
+
+    /// `label: body`
+    fn labeled_statement(&mut self, ir: &mut IR) -> Result<usize> {
+        let label = self.advance().lexeme;
+        self.advance();
+        let body = self.substatement(ir)?;
+        let node = ir.create_node(NodeType::LabeledStatement);
+        set_value(ir, node, "label", NodeValue::String(label));
+        ir.add_child(node, body);
+        Ok(node)
+    }
+
+    /// 当前是否为动态导入`import(...)`或`import.meta`，它们是表达式而不是导入声明
+    fn at_import_expression(&self) -> bool {
+        self.check(TokenType::Import)
+            && self.tokens.get(self.current + 1).is_some_and(|t| matches!(t.token_type, TokenType::LeftParen | TokenType::Dot))
+    }
+
+    /// 当前是否为语句标签`label:`
+    fn at_label(&self) -> bool {
+        self.check(TokenType::Identifier) && self.tokens.get(self.current + 1).is_some_and(|t| t.token_type == TokenType::Colon)
+    }
+
+    /// 循环体和带标签的语句体，解析得到的语句不是一个节点时（如多变量声明、空语句）放进代码块
+    fn substatement(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let mut statements = self.statement(ir)?;
+        if statements.len() == 1 {
+            return Ok(statements.remove(0));
+        }
+        let block = ir.create_node(NodeType::BlockStatement);
+        for statement in statements {
+            ir.add_child(block, statement);
+        }
+        self.locate(ir, block, start);
+        Ok(block)
+    }
+
+    /// `import "x"`、`import d, { a, b as c } from "x"`、`import * as ns from "x"`
+    ///
+    /// 每个绑定是一个标识符子节点：`name`为本地名，`imported`为导入的名字（默认导入为`default`，命名空间导入为`*`）。
+    fn import_declaration(&mut self, ir: &mut IR) -> Result<usize> {
+        self.advance();
+        let import = ir.create_node(NodeType::ImportDeclaration);
+        if !self.check(TokenType::String) {
+            if self.check(TokenType::Identifier) {
+                let start = self.current;
+                let local = self.advance().lexeme;
+                self.import_binding(ir, import, &local, "default", start);
+                if !self.match_token(TokenType::Comma) {
+                    self.expect(TokenType::From)?;
+                    return self.import_source(ir, import);
+                }
+            }
+            let start = self.current;
+            if self.match_token(TokenType::Star) {
+                self.expect_word("as")?;
+                let local = self.expect(TokenType::Identifier)?.lexeme;
+                self.import_binding(ir, import, &local, "*", start);
+            } else {
+                self.expect(TokenType::LeftBrace)?;
+                while let Some((start, imported, local)) = self.specifier()? {
+                    self.import_binding(ir, import, &local, &imported, start);
+                }
+            }
+            self.expect(TokenType::From)?;
+        }
+        self.import_source(ir, import)
+    }
+
+    /// 导入绑定的位置是从`start`开始的整个说明符（如`b as c`）
+    fn import_binding(&self, ir: &mut IR, import: usize, local: &str, imported: &str, start: usize) {
+        let ident = self.identifier(ir, local, start);
+        if imported != local {
+            set_value(ir, ident, "imported", NodeValue::String(imported.to_string()));
+        }
+        ir.add_child(import, ident);
+    }
+
+    fn import_source(&mut self, ir: &mut IR, import: usize) -> Result<usize> {
+        let source = self.expect(TokenType::String)?;
+        set_value(ir, import, "source", NodeValue::String(unescape(&source.lexeme)));
+        Ok(import)
+    }
+
+    /// `export <变量声明>`、`export default <表达式>`、`export { a, b as c }`
+    fn export_declaration(&mut self, ir: &mut IR) -> Result<usize> {
+        self.advance();
+        let export = ir.create_node(NodeType::ExportDeclaration);
+        match self.peek().token_type {
+            TokenType::Var | TokenType::Let | TokenType::Const => {
+                for decl in self.variable_declaration(ir)? {
+                    ir.add_child(export, decl);
+                }
+            },
+            TokenType::Identifier if self.peek().lexeme == "default" => {
+                self.advance();
+                set_value(ir, export, "default", NodeValue::Boolean(true));
+                let expr = self.expression(ir)?;
+                ir.add_child(export, expr);
+            },
+            _ => {
+                self.expect(TokenType::LeftBrace)?;
+                while let Some((start, local, exported)) = self.specifier()? {
+                    let ident = self.identifier(ir, &local, start);
+                    if exported != local {
+                        set_value(ir, ident, "exported", NodeValue::String(exported));
+                    }
+                    ir.add_child(export, ident);
+                }
+            },
+        }
+        Ok(export)
+    }
+
+    /// `{ a, b as c }`中的一个说明符，返回起始词法单元的下标和名字对`(a, a)`、`(b, c)`；遇到`}`时返回None
+    fn specifier(&mut self) -> Result<Option<(usize, String, String)>> {
+        if self.match_token(TokenType::RightBrace) {
+            return Ok(None);
+        }
+        let start = self.current;
+        let name = self.expect(TokenType::Identifier)?.lexeme;
+        let alias = if self.check_word("as") {
+            self.advance();
+            self.expect(TokenType::Identifier)?.lexeme
+        } else {
+            name.clone()
+        };
+        if !self.match_token(TokenType::Comma) && !self.check(TokenType::RightBrace) {
+            return Err(self.error_at_current(""));
+        }
+        Ok(Some((start, name, alias)))
+    }
+
+    fn expression(&mut self, ir: &mut IR) -> Result<usize> {
+        if self.check(TokenType::Yield) {
+            return self.yield_expression(ir);
+        }
+        self.conditional(ir)
+    }
+
+    /// `yield`、`yield x`和委托给另一个可迭代对象的`yield* x`，参数是唯一的子节点
+    ///
+    /// `yield`和参数之间换行时，按自动分号插入的规则没有参数。
+    fn yield_expression(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let keyword = self.advance();
+        let node = ir.create_node(NodeType::YieldExpression);
+        let delegate = self.match_token(TokenType::Star);
+        if delegate {
+            set_value(ir, node, "delegate", NodeValue::Boolean(true));
+        }
+        let next = self.peek();
+        let has_argument = delegate || (next.line == keyword.line && !matches!(
+            next.token_type,
+            TokenType::Semicolon | TokenType::Comma | TokenType::Colon | TokenType::RightParen
+                | TokenType::RightBracket | TokenType::RightBrace | TokenType::EOF
+        ));
+        if has_argument {
+            let argument = self.expression(ir)?;
+            ir.add_child(node, argument);
+        }
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// 逗号分隔的表达式`a, b`，只出现在表达式语句和括号中；只有一个表达式时直接返回该表达式
+    fn sequence(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let first = self.expression(ir)?;
+        if !self.check(TokenType::Comma) {
+            return Ok(first);
+        }
+        let mut expressions = vec![first];
+        while self.match_token(TokenType::Comma) {
+            expressions.push(self.expression(ir)?);
+        }
+        let node = ir.create_node(NodeType::SequenceExpression);
+        for expression in expressions {
+            ir.add_child(node, expression);
+        }
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// `test ? consequent : alternate`，右结合；子节点依次为条件和两个分支
+    fn conditional(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let test = self.binary(ir, 0)?;
+        if !self.match_token(TokenType::Question) {
+            return Ok(test);
+        }
+        let consequent = self.expression(ir)?;
+        self.expect(TokenType::Colon)?;
+        let alternate = self.expression(ir)?;
+        let node = ir.create_node(NodeType::ConditionalExpression);
+        for child in [test, consequent, alternate] {
+            ir.add_child(node, child);
+        }
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// 按优先级解析左结合的二元表达式，`level`为`BINARY_OPERATORS`中的优先级层次
+    fn binary(&mut self, ir: &mut IR, level: usize) -> Result<usize> {
+        let Some(operators) = BINARY_OPERATORS.get(level) else {
+            return self.unary(ir);
+        };
+        let start = self.current;
+        let mut left = self.binary(ir, level + 1)?;
+        while operators.contains(&self.peek().token_type) {
+            let operator = self.advance().lexeme;
+            let right = self.binary(ir, level + 1)?;
+            let node = ir.create_node(NodeType::BinaryExpression);
+            set_value(ir, node, "operator", NodeValue::String(operator));
+            ir.add_child(node, left);
+            ir.add_child(node, right);
+            self.locate(ir, node, start);
+            left = node;
+        }
+        Ok(left)
+    }
+
+    /// 前缀的`-`、`+`、`~`和`delete`、`typeof`、`void`运算符，运算数是唯一的子节点
+    ///
+    /// `await x`的优先级与一元运算符相同，生成`AwaitExpression`。
+    fn unary(&mut self, ir: &mut IR) -> Result<usize> {
+        if self.check(TokenType::Await) {
+            let start = self.current;
+            self.advance();
+            let argument = self.unary(ir)?;
+            let node = ir.create_node(NodeType::AwaitExpression);
+            ir.add_child(node, argument);
+            self.locate(ir, node, start);
+            return Ok(node);
+        }
+        let is_unary = matches!(
+            self.peek().token_type,
+            TokenType::Minus | TokenType::Plus | TokenType::Tilde | TokenType::Delete | TokenType::Typeof | TokenType::Void
+        );
+        if !is_unary {
+            return self.call_expression(ir);
+        }
+        let start = self.current;
+        let operator = self.advance().lexeme;
+        let argument = self.unary(ir)?;
+        let node = ir.create_node(NodeType::UnaryExpression);
+        set_value(ir, node, "operator", NodeValue::String(operator));
+        ir.add_child(node, argument);
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// 成员访问`a.b`、`a[b]`和调用`a(b)`，可以任意连续出现
+    fn call_expression(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let callee = if self.check(TokenType::New) {
+            self.new_expression(ir)?
+        } else {
+            self.primary(ir)?
+        };
+        self.member_accesses(ir, callee, start, true)
+    }
+
+    /// `new Foo(a, b)`，子节点为构造函数和参数；省略参数列表的`new Foo`没有参数
+    ///
+    /// 构造函数中的调用需要加括号`new (f())()`，否则第一个参数列表属于`new`。
+    fn new_expression(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        self.advance();
+        let callee_start = self.current;
+        let callee = if self.check(TokenType::New) {
+            self.new_expression(ir)?
+        } else {
+            self.primary(ir)?
+        };
+        let callee = self.member_accesses(ir, callee, callee_start, false)?;
+        let node = ir.create_node(NodeType::NewExpression);
+        ir.add_child(node, callee);
+        if self.match_token(TokenType::LeftParen) {
+            for argument in self.arguments(ir)? {
+                ir.add_child(node, argument);
+            }
+        }
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// 在`object`之后解析连续的成员访问，`calls`为true时也解析调用
+    fn member_accesses(&mut self, ir: &mut IR, object: usize, start: usize, calls: bool) -> Result<usize> {
+        let mut object = object;
+        loop {
+            let node = if self.match_token(TokenType::Dot) {
+                // 属性名可以是关键字，如`a.new`、`a.default`
+                let property_start = self.current;
+                let token = self.peek().clone();
+                if token.token_type != TokenType::Identifier && TokenType::keyword(&token.lexeme).is_none() {
+                    return Err(self.error_at_current(""));
+                }
+                self.advance();
+                let property = self.identifier(ir, &token.lexeme, property_start);
+                let node = ir.create_node(NodeType::MemberExpression);
+                set_value(ir, node, "computed", NodeValue::Boolean(false));
+                ir.add_child(node, object);
+                ir.add_child(node, property);
+                node
+            } else if self.match_token(TokenType::LeftBracket) {
+                let property = self.expression(ir)?;
+                self.expect(TokenType::RightBracket)?;
+                let node = ir.create_node(NodeType::MemberExpression);
+                set_value(ir, node, "computed", NodeValue::Boolean(true));
+                ir.add_child(node, object);
+                ir.add_child(node, property);
+                node
+            } else if calls && self.match_token(TokenType::LeftParen) {
+                let node = ir.create_node(NodeType::CallExpression);
+                ir.add_child(node, object);
+                for argument in self.arguments(ir)? {
+                    ir.add_child(node, argument);
+                }
+                node
+            } else {
+                return Ok(object);
+            };
+            self.locate(ir, node, start);
+            object = node;
+        }
+    }
+
+    /// 参数列表中左括号之后的部分`a, b)`，返回参数表达式
+    fn arguments(&mut self, ir: &mut IR) -> Result<Vec<usize>> {
+        let mut arguments = Vec::new();
+        while !self.match_token(TokenType::RightParen) {
+            arguments.push(self.expression(ir)?);
+            if !self.match_token(TokenType::Comma) && !self.check(TokenType::RightParen) {
+                return Err(self.error_at_current(""));
+            }
+        }
+        Ok(arguments)
+    }
+
+    /// 括号中的表达式只记录括号内的位置
+    fn primary(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let token = self.peek().clone();
+        let (node_type, value) = match token.token_type {
+            TokenType::Number => {
+                let value = numeric_value(&token.lexeme)
+                    .ok_or_else(|| ParseError::new(&format!("无效的数字 '{}'", token.lexeme), token.line, token.column))?;
+                self.advance();
+                let node = ir.create_node(NodeType::NumericLiteral);
+                set_value(ir, node, "value", value);
+                // 十进制以外的写法记录原文，不压缩时按原样输出
+                if token.lexeme.ends_with('n') || radix_prefix(&token.lexeme).is_some() {
+                    set_value(ir, node, "raw", NodeValue::String(token.lexeme.clone()));
+                }
+                self.locate(ir, node, start);
+                return Ok(node);
+            },
+            TokenType::String => (NodeType::StringLiteral, Some(("value", NodeValue::String(unescape(&token.lexeme))))),
+            TokenType::Template | TokenType::TemplateHead => return self.template_literal(ir),
+            TokenType::Boolean => (NodeType::BooleanLiteral, Some(("value", NodeValue::Boolean(token.lexeme == "true")))),
+            TokenType::Null => (NodeType::NullLiteral, None),
+            TokenType::Identifier if self.tokens.get(self.current + 1).is_some_and(|t| t.token_type == TokenType::Arrow) => {
+                return self.arrow_function(ir);
+            },
+            TokenType::LeftParen if self.at_arrow_parameters() => return self.arrow_function(ir),
+            TokenType::LeftBrace => return self.object_literal(ir),
+            TokenType::Import if self.at_import_expression() => return self.import_expression(ir),
+            TokenType::Identifier | TokenType::Undefined => {
+                self.advance();
+                return Ok(self.identifier(ir, &token.lexeme, start));
+            },
+            TokenType::LeftParen => {
+                self.advance();
+                let inner = self.sequence(ir)?;
+                self.expect(TokenType::RightParen)?;
+                return Ok(inner);
+            },
+            _ => return Err(self.error_at_current("")),
+        };
+        self.advance();
+        let node = ir.create_node(node_type);
+        if let Some((key, value)) = value {
+            set_value(ir, node, key, value);
+        }
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// 动态导入`import("./x")`，唯一的子节点是模块说明符；`import.meta`是`meta`为import、`property`为meta的`MetaProperty`
+    fn import_expression(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        self.advance();
+        if self.match_token(TokenType::Dot) {
+            self.expect_word("meta")?;
+            let node = ir.create_node(NodeType::MetaProperty);
+            set_value(ir, node, "meta", NodeValue::String("import".to_string()));
+            set_value(ir, node, "property", NodeValue::String("meta".to_string()));
+            self.locate(ir, node, start);
+            return Ok(node);
+        }
+        self.expect(TokenType::LeftParen)?;
+        let specifier = self.expression(ir)?;
+        self.expect(TokenType::RightParen)?;
+        let node = ir.create_node(NodeType::ImportExpression);
+        ir.add_child(node, specifier);
+        self.locate(ir, node, start);
+        Ok(node)
+    }
+
+    /// 对象字面量`{ a: 1, b, [k]: v, get x() {}, m() {} }`，每个属性是一个`Property`子节点
+    fn object_literal(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        self.advance();
+        let object = ir.create_node(NodeType::ObjectExpression);
+        while !self.match_token(TokenType::RightBrace) {
+            let property = self.property(ir)?;
+            ir.add_child(object, property);
+            if !self.match_token(TokenType::Comma) && !self.check(TokenType::RightBrace) {
+                return Err(self.error_at_current(""));
+            }
+        }
+        self.locate(ir, object, start);
+        Ok(object)
+    }
+
+    /// 对象字面量中的一个属性，子节点为键和值
+    ///
+    /// `kind`为`init`、`get`或`set`；方法和访问器的值是没有名字的`FunctionDeclaration`，普通方法的`method`为true。
+    /// 简写属性`{ b }`的值是同名的标识符，`shorthand`为true。
+    fn property(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        // get、set和async后面紧跟属性名时是修饰符，否则本身就是属性名，如`{ get: 1 }`、`{ set() {} }`
+        let modifies_key = !matches!(
+            self.tokens.get(self.current + 1).map(|t| &t.token_type),
+            None | Some(TokenType::LeftParen | TokenType::Colon | TokenType::Comma | TokenType::RightBrace)
+        );
+        let mut kind = "init".to_string();
+        let mut is_async = false;
+        if modifies_key && (self.check_word("get") || self.check_word("set")) {
+            kind = self.advance().lexeme;
+        } else if modifies_key && self.check(TokenType::Async) {
+            self.advance();
+            is_async = true;
+        }
+        let generator = self.match_token(TokenType::Star);
+
+        let computed = self.match_token(TokenType::LeftBracket);
+        let key_start = self.current;
+        let key_token = self.peek().clone();
+        let key = if computed {
+            let key = self.expression(ir)?;
+            self.expect(TokenType::RightBracket)?;
+            key
+        } else if matches!(key_token.token_type, TokenType::String | TokenType::Number) {
+            self.primary(ir)?
+        } else if key_token.token_type == TokenType::Identifier || TokenType::keyword(&key_token.lexeme).is_some() {
+            // 属性名可以是关键字，如`{ default: 1 }`
+            self.advance();
+            self.identifier(ir, &key_token.lexeme, key_start)
+        } else {
+            return Err(self.error_at_current(""));
+        };
+
+        let property = ir.create_node(NodeType::Property);
+        set_value(ir, property, "kind", NodeValue::String(kind.clone()));
+        if computed {
+            set_value(ir, property, "computed", NodeValue::Boolean(true));
+        }
+        let value = if kind != "init" || is_async || generator || self.check(TokenType::LeftParen) {
+            let function_start = self.current;
+            self.expect(TokenType::LeftParen)?;
+            let function = ir.create_node(NodeType::FunctionDeclaration);
+            if is_async {
+                set_value(ir, function, "async", NodeValue::Boolean(true));
+            }
+            if generator {
+                set_value(ir, function, "generator", NodeValue::Boolean(true));
+            }
+            for param in self.parameters(ir)? {
+                ir.add_child(function, param);
+            }
+            let body = self.block_statement(ir)?;
+            ir.add_child(function, body);
+            self.locate(ir, function, function_start);
+            set_value(ir, property, "method", NodeValue::Boolean(kind == "init"));
+            function
+        } else if self.match_token(TokenType::Colon) {
+            self.expression(ir)?
+        } else if !computed && key_token.token_type == TokenType::Identifier {
+            set_value(ir, property, "shorthand", NodeValue::Boolean(true));
+            self.identifier(ir, &key_token.lexeme, key_start)
+        } else {
+            return Err(self.error_at_current(""));
+        };
+        ir.add_child(property, key);
+        ir.add_child(property, value);
+        self.locate(ir, property, start);
+        Ok(property)
+    }
+
+    /// 模板字符串，字符串片段和插值表达式依次作为子节点
+    fn template_literal(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let template = ir.create_node(NodeType::TemplateLiteral);
+        loop {
+            let token = self.advance();
+            let quasi = ir.create_node(NodeType::StringLiteral);
+            set_value(ir, quasi, "value", NodeValue::String(unescape(&token.lexeme)));
+            set_value(ir, quasi, "raw", NodeValue::String(token.lexeme.clone()));
+            self.locate(ir, quasi, self.current - 1);
+            ir.add_child(template, quasi);
+            if matches!(token.token_type, TokenType::Template | TokenType::TemplateTail) {
+                break;
+            }
+
+            let expression = self.expression(ir)?;
+            ir.add_child(template, expression);
+            if !matches!(self.peek().token_type, TokenType::TemplateMiddle | TokenType::TemplateTail) {
+                return Err(self.error_at_current("模板字符串的插值缺少 '}'"));
+            }
+        }
+        self.locate(ir, template, start);
+        Ok(template)
+    }
+
+    /// 当前的左括号是否开始箭头函数的参数列表：与它匹配的右括号后面紧跟`=>`
+    fn at_arrow_parameters(&self) -> bool {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.current) {
+            match token.token_type {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.tokens.get(i + 1).is_some_and(|t| t.token_type == TokenType::Arrow);
+                    }
+                },
+                _ => {},
+            }
+        }
+        false
+    }
+
+    /// `(a, b) => 表达式`、`a => { ... }`，子节点为参数标识符，最后一个子节点是函数体
+    fn arrow_function(&mut self, ir: &mut IR) -> Result<usize> {
+        let start = self.current;
+        let params = if self.match_token(TokenType::LeftParen) {
+            self.parameters(ir)?
+        } else {
+            let name = self.advance().lexeme;
+            vec![self.identifier(ir, &name, start)]
+        };
+        self.expect(TokenType::Arrow)?;
+        let body = if self.check(TokenType::LeftBrace) {
+            self.block_statement(ir)?
+        } else {
+            self.expression(ir)?
+        };
+        let arrow = ir.create_node(NodeType::ArrowFunctionExpression);
+        for param in params {
+            ir.add_child(arrow, param);
+        }
+        ir.add_child(arrow, body);
+        self.locate(ir, arrow, start);
+        Ok(arrow)
+    }
+
+    /// 创建标识符节点，位置从第`start`个词法单元到刚消耗的词法单元
+    fn identifier(&self, ir: &mut IR, name: &str, start: usize) -> usize {
+        let ident = ir.create_node(NodeType::Identifier);
+        set_value(ir, ident, "name", NodeValue::String(name.to_string()));
+        self.locate(ir, ident, start);
+        ident
+    }
+
+    /// 把节点的位置设为从第`start`个词法单元到刚消耗的词法单元
+    ///
+    /// 子节点保存的是添加时的快照，必须在把节点添加到父节点之前调用。
+    fn locate(&self, ir: &mut IR, id: usize, start: usize) {
+        let first = &self.tokens[start];
+        let last = &self.tokens[self.current.saturating_sub(1).max(start)];
+        let (end_line, end_column) = last.end();
+        if let Some(node) = ir.get_node_mut(id) {
+            node.location = Some(SourceLocation::new(first.line, first.column, end_line, end_column));
+        }
+    }
+
+    /// 语句结尾：分号，或者在换行、`}`和文件结尾处省略的分号
+    fn end_of_statement(&mut self) -> Result<()> {
+        if self.match_token(TokenType::Semicolon) {
+            return Ok(());
+        }
+        let next = self.peek();
+        let on_new_line = self.current > 0 && next.line > self.tokens[self.current - 1].line;
+        if matches!(next.token_type, TokenType::EOF | TokenType::RightBrace) || on_new_line {
+            return Ok(());
+        }
+        Err(self.error_at_current(""))
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current.min(self.tokens.len() - 1)]
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.token_type != TokenType::EOF {
+            self.current += 1;
+        }
+        token
+    }
+
+    /// 当前词法单元是否为上下文关键字（如`as`），它们按标识符扫描
+    fn check_word(&self, word: &str) -> bool {
+        self.check(TokenType::Identifier) && self.peek().lexeme == word
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<Token> {
+        if self.check_word(word) {
+            Ok(self.advance())
+        } else {
+            Err(self.error_at_current(""))
+        }
+    }
+
+    fn match_token(&mut self, token_type: TokenType) -> bool {
+        if self.check(token_type) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token_type: TokenType) -> Result<Token> {
+        if self.check(token_type) {
+            Ok(self.advance())
+        } else {
+            Err(self.error_at_current(""))
+        }
+    }
+
+    /// 当前词法单元处的语法错误，`message`为空时报告意外的符号
+    fn error_at_current(&self, message: &str) -> anyhow::Error {
+        let token = self.peek();
+        let message = match (message, &token.token_type) {
+            ("", TokenType::EOF) => "意外的文件结尾".to_string(),
+            ("", _) => format!("意外的符号 '{}'", token.lexeme),
+            (message, _) => message.to_string(),
+        };
+        ParseError::new(&message, token.line, token.column).into()
+    }
+}
+
+/// 二元运算符，按优先级从低到高分层
+const BINARY_OPERATORS: [&[TokenType]; 4] = [
+    &[TokenType::Equal, TokenType::NotEqual, TokenType::StrictEqual, TokenType::StrictNotEqual],
+    &[TokenType::Less, TokenType::LessEqual, TokenType::Greater, TokenType::GreaterEqual, TokenType::In, TokenType::Instanceof],
+    &[TokenType::Plus, TokenType::Minus],
+    &[TokenType::Star, TokenType::Slash, TokenType::Percent],
+];
+
+fn set_value(ir: &mut IR, id: usize, key: &str, value: NodeValue) {
+    if let Some(node) = ir.get_node_mut(id) {
+        node.set_value(key, value);
+    }
+}
+
+/// `0x`、`0o`、`0b`前缀表示的进制和去掉前缀后的数字
+fn radix_prefix(lexeme: &str) -> Option<(u32, &str)> {
+    let radix = match lexeme.get(..2)? {
+        "0x" | "0X" => 16,
+        "0o" | "0O" => 8,
+        "0b" | "0B" => 2,
+        _ => return None,
+    };
+    Some((radix, &lexeme[2..]))
+}
+
+/// 数值字面量的值，带后缀`n`的BigInt保存为十进制数字串，格式无效时返回None
+fn numeric_value(lexeme: &str) -> Option<NodeValue> {
+    let (body, bigint) = match lexeme.strip_suffix('n') {
+        Some(body) => (body, true),
+        None => (lexeme, false),
+    };
+    let (radix, digits) = radix_prefix(body).unwrap_or((10, body));
+    if (bigint || radix != 10) && (digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix))) {
+        return None;
+    }
+    if bigint {
+        return Some(NodeValue::BigInt(to_decimal(digits, radix)));
+    }
+    if radix == 10 {
+        return body.parse().ok().map(NodeValue::Number);
+    }
+    let value = digits.chars().fold(0.0, |value, c| value * radix as f64 + c.to_digit(radix).unwrap_or(0) as f64);
+    Some(NodeValue::Number(value))
+}
+
+/// 任意长度的数字串转为十进制，BigInt不受f64精度的限制
+fn to_decimal(digits: &str, radix: u32) -> String {
+    // 十进制各位，低位在前
+    let mut decimal = vec![0u32];
+    for c in digits.chars() {
+        let mut carry = c.to_digit(radix).unwrap_or(0);
+        for d in decimal.iter_mut() {
+            let value = *d * radix + carry;
+            *d = value % 10;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            decimal.push(carry % 10);
+            carry /= 10;
+        }
+    }
+    decimal.iter().rev().filter_map(|d| char::from_digit(*d, 10)).collect()
+}
+
+/// 处理字符串字面量中的转义序列，无法识别的转义保留转义后的字符本身
+fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('b') => value.push('\u{8}'),
+            Some('f') => value.push('\u{c}'),
+            Some('v') => value.push('\u{b}'),
+            Some('0') => value.push('\0'),
+            Some(prefix @ ('x' | 'u')) => {
+                let digits: String = chars.clone().take(if prefix == 'x' { 2 } else { 4 }).collect();
+                match u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32) {
+                    Some(decoded) if digits.len() == if prefix == 'x' { 2 } else { 4 } => {
+                        value.push(decoded);
+                        for _ in 0..digits.len() {
+                            chars.next();
+                        }
+                    },
+                    _ => value.push(prefix),
+                }
+            },
+            // 行继续符
+            Some('\n') => {},
+            Some(other) => value.push(other),
+            None => {},
+        }
+    }
+    value
 }
 
 /// 高性能状态压缩的确定性有限自动机(DFA)
@@ -250,24 +1802,97 @@ impl JsParser {
         // 词法分析
         let mut lexer = Lexer::new(source, self.options.clone());
         let tokens = lexer.scan_tokens();
+        if let Some(error) = lexer.errors().first() {
+            return Err(error.clone().into());
+        }
         
         debug!("词法分析完成，产生 {} 个词法单元，耗时: {:?}", tokens.len(), start.elapsed());
+        check_token_limits(&tokens, &self.options)?;
         
         // 语法分析
-        let mut parser = Parser::new(source);
-        let ir = parser.parse()?;
+        let mut parser = Parser::with_tokens(tokens);
+        let mut ir = parser.parse()?;
+        check_node_limit(&ir, &self.options)?;
+        
+        attach_shebang(&mut ir, source);
         
         info!("解析完成，耗时: {:?}", start.elapsed());
         
         Ok(ir)
     }
     
+    /// 解析并收集语法错误，返回尽力得到的IR和带位置、代码片段的错误信息
+    ///
+    /// 启用`error_recovery`时跳过出错的语句继续解析并报告所有错误，否则只报告第一个错误，IR为空。
+    pub fn parse_string_recovering(&self, source: &str) -> (IR, Vec<CompileErrorInfo>) {
+        let mut lexer = Lexer::new(source, self.options.clone());
+        let tokens = lexer.scan_tokens();
+        let mut errors = lexer.errors().to_vec();
+        if let Err(error) = check_token_limits(&tokens, &self.options) {
+            return (IR::new(), vec![self.error_info(source, &error.to_string(), None)]);
+        }
+        
+        let mut parser = Parser::with_tokens(tokens);
+        let mut ir = if self.options.error_recovery {
+            let (ir, parse_errors) = parser.parse_recovering();
+            errors.extend(parse_errors);
+            ir
+        } else if errors.is_empty() {
+            parser.parse().unwrap_or_else(|error| {
+                errors.push(parser.to_parse_error(error));
+                IR::new()
+            })
+        } else {
+            errors.truncate(1);
+            IR::new()
+        };
+        errors.sort_by_key(|error| (error.line, error.column));
+        
+        let mut infos: Vec<CompileErrorInfo> = errors.iter()
+            .map(|error| self.error_info(source, &error.message, Some((error.line, error.column))))
+            .collect();
+        if let Err(error) = check_node_limit(&ir, &self.options) {
+            infos.push(self.error_info(source, &error.to_string(), None));
+            ir = IR::new();
+        }
+        attach_shebang(&mut ir, source);
+        (ir, infos)
+    }
+    
+    /// 构造错误信息，有位置时附上所在的行，并高亮该处的整个词（不是词时高亮一个字符）
+    fn error_info(&self, source: &str, message: &str, position: Option<(usize, usize)>) -> CompileErrorInfo {
+        let mut info = CompileErrorInfo::new(message);
+        if let Some(filename) = &self.options.filename {
+            info = info.with_file(filename);
+        }
+        let Some((line, column)) = position else {
+            return info;
+        };
+        
+        let text = source.split('\n').nth(line.saturating_sub(1)).unwrap_or("").trim_end_matches('\r');
+        let (before, rest) = text.split_at(column.min(text.len()));
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_' || *c == '$';
+        let start = before.chars().count();
+        let width = rest.chars().take_while(is_word).count().max(1);
+        info.with_position(line, column)
+            .with_snippet(text)
+            .with_highlight(start, start + width)
+    }
+    
+    /// 只进行词法分析，供编辑器等工具做语法高亮
+    ///
+    /// 总是保留注释；无法识别的字符被跳过，不会导致失败，结果以`EOF`结尾。
+    pub fn tokenize(&self, source: &str) -> Vec<Token> {
+        let options = ParseOptions { comments: true, ..self.options.clone() };
+        Lexer::new(source, options).scan_tokens()
+    }
+    
     pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> Result<IR> {
         let path = path.as_ref();
         info!("解析文件: {}", path.display());
         
         // 读取文件内容
-        let source = std::fs::read_to_string(path)
+        let source = read_source(path, self.options.encoding)
             .map_err(|e| anyhow!("读取文件失败: {}", e))?;
         
         // 基于文件扩展名自动设置选项
@@ -290,6 +1915,16 @@ impl JsParser {
     }
 }
 
+/// 保留shebang，供代码生成时原样输出在第一行
+fn attach_shebang(ir: &mut IR, source: &str) {
+    if let Some(line) = shebang(source) {
+        let root_id = ir.root_id;
+        if let Some(root) = ir.get_node_mut(root_id) {
+            root.set_value("shebang", NodeValue::String(line.to_string()));
+        }
+    }
+}
+
 /// 创建一个默认的JavaScript解析器
 pub fn create_js_parser() -> JsParser {
     JsParser::new(ParseOptions::default())
@@ -346,4 +1981,292 @@ pub fn parse_tsx(source: &str) -> Result<IR> {
 pub fn parse_string(source: &str) -> Result<IR> {
     let mut parser = Parser::new(source);
     parser.parse()
-} 
\ No newline at end of file
+}
+
+/// 源码第一行的shebang（如`#!/usr/bin/env node`），不含换行符；文件开头的BOM会被跳过
+pub fn shebang(source: &str) -> Option<&str> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    if !source.starts_with("#!") {
+        return None;
+    }
+    let line = source.split('\n').next().unwrap_or(source);
+    Some(line.strip_suffix('\r').unwrap_or(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumen_core::Node;
+
+    #[test]
+    fn test_shebang_is_captured_on_root() {
+        let ir = create_js_parser().parse_string("\u{feff}#!/usr/bin/env node\r\nvar x = 42;").unwrap();
+        assert_eq!(ir.get_root().get_string_value("shebang"), Some("#!/usr/bin/env node"));
+        assert_eq!(shebang("var x = 1; #!"), None);
+    }
+
+    #[test]
+    fn test_lexer_scans_operators_and_positions() {
+        let types = |source: &str| -> Vec<TokenType> {
+            Lexer::new(source, ParseOptions::default()).scan_tokens().into_iter().map(|t| t.token_type).collect()
+        };
+        assert_eq!(types("let y = 3 === 3;"), vec![
+            TokenType::Let, TokenType::Identifier, TokenType::Assign, TokenType::Number,
+            TokenType::StrictEqual, TokenType::Number, TokenType::Semicolon, TokenType::EOF,
+        ]);
+        assert_eq!(types("a !== b >= c => d == e != f <= g"), vec![
+            TokenType::Identifier, TokenType::StrictNotEqual, TokenType::Identifier, TokenType::GreaterEqual,
+            TokenType::Identifier, TokenType::Arrow, TokenType::Identifier, TokenType::Equal, TokenType::Identifier,
+            TokenType::NotEqual, TokenType::Identifier, TokenType::LessEqual, TokenType::Identifier, TokenType::EOF,
+        ]);
+        assert_eq!(types("a ? b : c"), vec![
+            TokenType::Identifier, TokenType::Question, TokenType::Identifier, TokenType::Colon, TokenType::Identifier, TokenType::EOF,
+        ]);
+
+        let mut lexer = Lexer::new("var s = 'it\\'s';\n  x.y = 1.5e3; // 注释\n", ParseOptions::default());
+        let tokens = lexer.scan_tokens();
+        assert!(lexer.errors().is_empty());
+        let string = &tokens[3];
+        assert_eq!((string.token_type.clone(), string.lexeme.as_str()), (TokenType::String, "it\\'s"));
+        let x = &tokens[5];
+        assert_eq!((x.lexeme.as_str(), x.line, x.column), ("x", 2, 2));
+        assert_eq!(tokens[9].lexeme, "1.5e3");
+        assert_eq!(tokens[11].token_type, TokenType::Comment);
+        let eof = tokens.last().unwrap();
+        assert_eq!((eof.token_type.clone(), eof.line, eof.column), (TokenType::EOF, 3, 0));
+
+        let mut lexer = Lexer::new("var a = #;", ParseOptions::default());
+        lexer.scan_tokens();
+        assert_eq!(lexer.errors(), &[ParseError::new("无法识别的字符 '#'", 1, 8)]);
+    }
+
+    #[test]
+    fn test_tokenize_for_highlighting() {
+        let parser = JsParser::new(ParseOptions { comments: false, ..Default::default() });
+        let tokens = parser.tokenize("const greeting = \"hi\"; // 问候\nreturn 42 + null;");
+        let categories: Vec<(&str, HighlightCategory)> = tokens.iter()
+            .map(|t| (t.lexeme.as_str(), t.token_type.highlight_category()))
+            .collect();
+        assert_eq!(categories, vec![
+            ("const", HighlightCategory::Keyword),
+            ("greeting", HighlightCategory::Identifier),
+            ("=", HighlightCategory::Operator),
+            ("hi", HighlightCategory::String),
+            (";", HighlightCategory::Punctuation),
+            ("// 问候", HighlightCategory::Comment),
+            ("return", HighlightCategory::Keyword),
+            ("42", HighlightCategory::Number),
+            ("+", HighlightCategory::Operator),
+            ("null", HighlightCategory::Literal),
+            (";", HighlightCategory::Punctuation),
+            ("", HighlightCategory::None),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_variable_declaration_with_binary_expression() {
+        let ir = parse_js("var x = 1 + 2;").unwrap();
+        let root = ir.get_root();
+        assert_eq!(root.children.len(), 1);
+        let decl = &root.children[0].0;
+        assert_eq!(decl.node_type, NodeType::VariableDeclaration);
+        assert_eq!(decl.get_string_value("kind"), Some("var"));
+        let [name, init] = decl.children.as_slice() else {
+            panic!("声明应有标识符和初始值两个子节点: {:?}", decl.children);
+        };
+        assert_eq!(name.0.node_type, NodeType::Identifier);
+        assert_eq!(name.0.get_string_value("name"), Some("x"));
+        assert_eq!(init.0.node_type, NodeType::BinaryExpression);
+        assert_eq!(init.0.get_string_value("operator"), Some("+"));
+        let operands: Vec<_> = init.0.children.iter().map(|c| c.0.get_number_value("value")).collect();
+        assert_eq!(operands, vec![Some(1.0), Some(2.0)]);
+
+        // 乘法优先于加法，括号改变结合顺序
+        let ir = parse_js("let y = (a - 1) * 2 + 'x\\n'").unwrap();
+        let sum = &ir.get_root().children[0].0.children[1].0;
+        assert_eq!(sum.get_string_value("operator"), Some("+"));
+        assert_eq!(sum.children[0].0.get_string_value("operator"), Some("*"));
+        assert_eq!(sum.children[0].0.children[0].0.get_string_value("operator"), Some("-"));
+        assert_eq!(sum.children[1].0.get_string_value("value"), Some("x\n"));
+
+        let ir = parse_js("import React, { a, b as c } from 'react';\nexport const d = a;").unwrap();
+        let root = ir.get_root();
+        let [import, export] = root.children.as_slice() else {
+            panic!("应解析出两条语句");
+        };
+        assert_eq!(import.0.get_string_value("source"), Some("react"));
+        let bindings: Vec<_> = import.0.children.iter()
+            .map(|c| (c.0.get_string_value("name").unwrap(), c.0.get_string_value("imported")))
+            .collect();
+        assert_eq!(bindings, vec![("React", Some("default")), ("a", None), ("c", Some("b"))]);
+        assert_eq!(export.0.children[0].0.node_type, NodeType::VariableDeclaration);
+
+        let err = parse_js("var = 1;").unwrap_err();
+        assert_eq!(err.downcast_ref::<ParseError>(), Some(&ParseError::new("意外的符号 '='", 1, 4)));
+    }
+
+    #[test]
+    fn test_parsed_nodes_have_locations() {
+        let source = "let total = price *\n    (1 + rate);\nexport { total as sum };";
+        let ir = parse_js(source).unwrap();
+        let root = ir.get_root();
+        let location = |node: &Node| node.location.expect("每个节点都应有位置");
+        assert_eq!(location(&root), SourceLocation::new(1, 0, 3, 24));
+
+        let decl = &root.children[0].0;
+        assert_eq!(location(decl), SourceLocation::new(1, 0, 2, 15), "跨行的声明应包含结尾的分号");
+        let [name, product] = decl.children.as_slice() else {
+            panic!("声明应有标识符和初始值两个子节点");
+        };
+        assert_eq!(location(&name.0), SourceLocation::new(1, 4, 1, 9));
+        assert_eq!(location(&product.0), SourceLocation::new(1, 12, 2, 14));
+        // 括号中的表达式不包含括号
+        assert_eq!(location(&product.0.children[1].0), SourceLocation::new(2, 5, 2, 13));
+
+        let specifier = &root.children[1].0.children[0].0;
+        assert_eq!(location(specifier), SourceLocation::new(3, 9, 3, 21));
+        ir.visit(|node| assert!(node.location.is_some(), "{:?} 没有位置", node.node_type));
+
+        // 位置随IR一起序列化
+        let json = serde_json::to_string(&ir).unwrap();
+        let restored: IR = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get_node(decl.id).unwrap().location, decl.location);
+    }
+
+    #[test]
+    fn test_token_limit() {
+        let options = ParseOptions { max_tokens: Some(3), ..Default::default() };
+        let err = JsParser::new(options).parse_string("var x = 42;").unwrap_err();
+        assert_eq!(err.downcast_ref::<ParseLimitError>(), Some(&ParseLimitError::TooManyTokens { limit: 3 }));
+
+        let options = ParseOptions { max_tokens: Some(100), ..Default::default() };
+        assert!(JsParser::new(options).parse_string("var x = 42;").is_ok());
+    }
+
+    #[test]
+    fn test_string_length_limit() {
+        let tokens = vec![
+            Token::new(TokenType::String, "short", 1, 0),
+            Token::new(TokenType::String, &"x".repeat(64), 2, 4),
+        ];
+        let options = ParseOptions { max_string_len: Some(16), ..Default::default() };
+        assert_eq!(
+            check_token_limits(&tokens, &options),
+            Err(ParseLimitError::StringTooLong { limit: 16, line: 2, column: 4 })
+        );
+        let options = ParseOptions { max_string_len: Some(64), ..Default::default() };
+        assert_eq!(check_token_limits(&tokens, &options), Ok(()));
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let mut ir = IR::new();
+        for _ in 0..4 {
+            let id = ir.create_node(NodeType::Identifier);
+            ir.add_child(ir.root_id, id);
+        }
+        let options = ParseOptions { max_nodes: Some(3), ..Default::default() };
+        let err = check_node_limit(&ir, &options).unwrap_err();
+        assert_eq!(err, ParseLimitError::TooManyNodes { limit: 3 });
+        assert!(err.to_string().starts_with("超出资源限制"));
+    }
+
+    #[test]
+    fn test_lex_nested_template_literal() {
+        let source = "`a ${ {b: `c ${d}`}.b } \\` e`;\n`x\ny`";
+        let mut lexer = Lexer::new(source, ParseOptions::default());
+        let tokens: Vec<(TokenType, String)> = lexer.scan_tokens().into_iter().map(|t| (t.token_type, t.lexeme)).collect();
+        assert!(lexer.errors().is_empty(), "{:?}", lexer.errors());
+        let expected = [
+            (TokenType::TemplateHead, "a "), (TokenType::LeftBrace, "{"), (TokenType::Identifier, "b"),
+            (TokenType::Colon, ":"), (TokenType::TemplateHead, "c "), (TokenType::Identifier, "d"),
+            (TokenType::TemplateTail, ""), (TokenType::RightBrace, "}"), (TokenType::Dot, "."),
+            (TokenType::Identifier, "b"), (TokenType::TemplateTail, " \\` e"), (TokenType::Semicolon, ";"),
+            (TokenType::Template, "x\ny"), (TokenType::EOF, ""),
+        ];
+        assert_eq!(tokens, expected.map(|(t, lexeme)| (t, lexeme.to_string())));
+
+        // 跨行的模板字符串结束在最后一行的反引号之后
+        let multiline = Lexer::new("`x\ny`", ParseOptions::default()).scan_tokens();
+        assert_eq!(multiline[0].end(), (2, 2));
+
+        let mut lexer = Lexer::new("`a ${b", ParseOptions::default());
+        lexer.scan_tokens();
+        assert!(lexer.errors().is_empty(), "插值未结束时由解析器报错");
+        let mut lexer = Lexer::new("`abc", ParseOptions::default());
+        lexer.scan_tokens();
+        assert_eq!(lexer.errors(), &[ParseError::new("未闭合的模板字符串", 1, 0)]);
+    }
+
+    #[test]
+    fn test_error_recovery_reports_every_bad_statement() {
+        let source = "var a = ;\nvar b = 2;\nvar c = 1 foo;\n";
+        let options = ParseOptions { error_recovery: true, filename: Some("app.js".to_string()), ..Default::default() };
+        let (ir, errors) = JsParser::new(options).parse_string_recovering(source);
+
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!((errors[0].line, errors[0].column), (Some(1), Some(8)));
+        assert_eq!((errors[1].line, errors[1].column), (Some(3), Some(10)));
+        assert_eq!(errors[1].to_string(), "错误: app.js:3:10\n意外的符号 'foo'\n\nvar c = 1 foo;\n          ^^^\n");
+
+        // 出错的语句被丢弃，其余语句照常解析，不留下孤立节点
+        let root = ir.get_root();
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].0.children[0].0.get_string_value("name"), Some("b"));
+        assert_eq!(ir.nodes.len(), 4, "根节点和 var b = 2 的三个节点");
+
+        // 关闭恢复时只报告第一个错误
+        let (ir, errors) = JsParser::new(ParseOptions::default()).parse_string_recovering(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "意外的符号 ';'");
+        assert!(ir.get_root().children.is_empty());
+    }
+
+    #[test]
+    fn test_numeric_literals_in_every_radix() {
+        let source = "var a = 0xff;\nvar b = 0o17;\nvar c = 0B1010;\nvar d = 123n;\nvar e = 0xffffffffffffffffn;\nvar f = 1.5e3;\n";
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let literals: Vec<_> = ir.get_root().children.iter().map(|decl| decl.0.children[1].clone()).collect();
+
+        let values: Vec<_> = literals.iter().map(|lit| lit.0.get_value("value").cloned().unwrap()).collect();
+        assert_eq!(values, [
+            NodeValue::Number(255.0),
+            NodeValue::Number(15.0),
+            NodeValue::Number(10.0),
+            NodeValue::BigInt("123".to_string()),
+            NodeValue::BigInt("18446744073709551615".to_string()),
+            NodeValue::Number(1500.0),
+        ], "BigInt不应丢失精度");
+        let raws: Vec<_> = literals.iter().map(|lit| lit.0.get_string_value("raw")).collect();
+        assert_eq!(raws, [Some("0xff"), Some("0o17"), Some("0B1010"), Some("123n"), Some("0xffffffffffffffffn"), None]);
+
+        for invalid in ["var x = 0x;", "var x = 1.5n;", "var x = 0b12;"] {
+            assert!(JsParser::new(ParseOptions::default()).parse_string(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_keyword_operators() {
+        let source = "new Foo(1);\n\"x\" in obj;\na instanceof B;\ndelete obj.x;\ntypeof a.b[0];\nvoid f();\n";
+        let ir = JsParser::new(ParseOptions::default()).parse_string(source).unwrap();
+        let root = ir.get_root();
+        let statements: Vec<_> = root.children.iter().map(|s| s.0.clone()).collect();
+
+        assert_eq!(statements[0].node_type, NodeType::NewExpression);
+        assert_eq!(statements[0].children.len(), 2, "构造函数和一个参数");
+        for (statement, operator) in [(&statements[1], "in"), (&statements[2], "instanceof")] {
+            assert_eq!(statement.node_type, NodeType::BinaryExpression);
+            assert_eq!(statement.get_string_value("operator"), Some(operator));
+        }
+        for (statement, operator) in statements[3..].iter().zip(["delete", "typeof", "void"]) {
+            assert_eq!(statement.node_type, NodeType::UnaryExpression);
+            assert_eq!(statement.get_string_value("operator"), Some(operator));
+        }
+        assert_eq!(statements[3].children[0].0.node_type, NodeType::MemberExpression);
+        assert_eq!(statements[5].children[0].0.node_type, NodeType::CallExpression);
+
+        // 关键字不能再作为变量名，但可以作为属性名
+        assert!(JsParser::new(ParseOptions::default()).parse_string("var new = 1;").is_err());
+        assert!(JsParser::new(ParseOptions::default()).parse_string("a.new(b.in);").is_ok());
+    }
+}
@@ -10,6 +10,10 @@ use lumen_parser::{ParseOptions, JsParser};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+mod wat;
+
+pub use wat::emit_wat;
+
 /// Wasm选项
 #[derive(Debug, Clone)]
 pub struct WasmOptions {
@@ -106,17 +110,21 @@ impl WasmTransformer {
         Self { options }
     }
     
-    pub fn transform_to_wasm(&self, _ir: &IR, format: WasmOutputFormat) -> Result<WasmTransformResult> {
+    pub fn transform_to_wasm(&self, ir: &IR, format: WasmOutputFormat) -> Result<WasmTransformResult> {
         let start = std::time::Instant::now();
         debug!("开始转换IR到WebAssembly，格式: {:?}", format);
         
-        // TODO: 实现IR到WASM的转换
-        // 这里只是一个简单的示例
+        // TODO: 实现IR到WASM二进制的转换
+        // 目前只有文本格式按IR生成
         
-        // 生成模拟结果
+        let mut warnings = Vec::new();
         let output = match format {
             WasmOutputFormat::Binary => vec![0, 97, 115, 109, 1, 0, 0, 0], // WASM魔数 + 版本号
-            WasmOutputFormat::Text => b"(module)".to_vec(),
+            WasmOutputFormat::Text => {
+                let (wat, wat_warnings) = emit_wat(ir);
+                warnings = wat_warnings;
+                wat.into_bytes()
+            },
             WasmOutputFormat::JavaScript => b"export function init() { /* ... */ }".to_vec(),
             WasmOutputFormat::Bundle => {
                 let js = b"export function init(wasmBytes) { /* ... */ }";
@@ -152,7 +160,7 @@ impl WasmTransformer {
             typescript_types: ts_types,
             metadata,
             errors: Vec::new(),
-            warnings: Vec::new(),
+            warnings,
             time_ms: elapsed.as_millis() as u64,
         })
     }
@@ -0,0 +1,151 @@
+//! WAT文本格式输出 - 把顶层函数声明降级为导出的Wasm函数
+//!
+//! JS数值按f64传参；返回数值常量的函数降级为`i32.const`（i32范围内的整数）或`f64.const`，
+//! 其余无法降级的函数体输出为注释桩，生成的模块仍然是合法的WAT。
+
+use std::collections::HashSet;
+
+use lumen_core::{IR, Node, NodeType};
+
+/// 生成WAT模块文本，同时返回无法降级的函数的警告
+pub fn emit_wat(ir: &IR) -> (String, Vec<String>) {
+    let mut output = String::from("(module\n");
+    let mut warnings = Vec::new();
+    let mut declared = HashSet::new();
+
+    for stmt in &ir.get_root().children {
+        let function = &stmt.0;
+        if function.node_type != NodeType::FunctionDeclaration {
+            continue;
+        }
+        let Some(name) = function.get_string_value("name") else {
+            continue;
+        };
+        // 同名的函数只导出第一个，导出名在模块中必须唯一
+        if !declared.insert(name) {
+            warnings.push(format!("重复声明的函数 {} 没有导出", name));
+            output.push_str(&format!("  ;; 重复声明的函数 {}\n", name));
+            continue;
+        }
+
+        output.push_str(&format!("  (func ${} (export \"{}\")", wat_id(name), name));
+        let (params, body) = match function.children.split_last() {
+            Some((body, params)) => (params, Some(&*body.0)),
+            None => (&[][..], None),
+        };
+        for param in params {
+            if let Some(param) = param.0.get_string_value("name") {
+                output.push_str(&format!(" (param ${} f64)", wat_id(param)));
+            }
+        }
+
+        match body.map(lower_body).unwrap_or(Lowered::Empty) {
+            Lowered::Empty => output.push_str(")\n"),
+            Lowered::Const(ty, value) => output.push_str(&format!(" (result {})\n    {}.const {})\n", ty, ty, value)),
+            Lowered::Unsupported(reason) => {
+                warnings.push(format!("函数 {} 无法降级为Wasm: {}", name, reason));
+                output.push_str(&format!("\n    ;; 无法降级: {}\n  )\n", reason));
+            },
+        }
+    }
+
+    output.push_str(")\n");
+    (output, warnings)
+}
+
+/// 函数体降级的结果
+enum Lowered {
+    /// 没有返回值
+    Empty,
+    /// 返回常量，类型和WAT中的数值写法
+    Const(&'static str, String),
+    /// 无法降级的原因
+    Unsupported(String),
+}
+
+/// 函数体只能是一条`return`语句
+fn lower_body(body: &Node) -> Lowered {
+    let Some(stmt) = body.children.first() else {
+        return Lowered::Empty;
+    };
+    if stmt.0.node_type != NodeType::ReturnStatement {
+        return Lowered::Unsupported(format!("{:?}语句", stmt.0.node_type));
+    }
+    match stmt.0.children.first() {
+        Some(argument) => lower_constant(&argument.0)
+            .map(|(ty, value)| Lowered::Const(ty, value))
+            .unwrap_or_else(|| Lowered::Unsupported(format!("{:?}返回值", argument.0.node_type))),
+        None => Lowered::Empty,
+    }
+}
+
+/// 数值常量的类型和写法，i32范围内的整数使用i32
+fn lower_constant(node: &Node) -> Option<(&'static str, String)> {
+    let value = constant_value(node)?;
+    let is_i32 = value.fract() == 0.0
+        && value >= i32::MIN as f64
+        && value <= i32::MAX as f64
+        && !(value == 0.0 && value.is_sign_negative());
+    if is_i32 {
+        return Some(("i32", (value as i32).to_string()));
+    }
+    let text = if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{:?}", value)
+    };
+    Some(("f64", text))
+}
+
+/// 数值字面量或对其取负的值
+fn constant_value(node: &Node) -> Option<f64> {
+    match node.node_type {
+        NodeType::NumericLiteral => node.get_number_value("value"),
+        NodeType::UnaryExpression if node.get_string_value("operator") == Some("-") => {
+            constant_value(&node.children.first()?.0).map(|value| -value)
+        },
+        _ => None,
+    }
+}
+
+/// WAT标识符只能包含可打印的ASCII字符，其余字符替换为`_`
+fn wat_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c) { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_functions_exported_with_constant_returns() {
+        let source = "function answer() { return 42; }\n\
+            function half(x) { return 0.5; }\n\
+            function big() { return 3000000000; }\n\
+            function noop() { }\n\
+            function sum(a, b) { return a + b; }\n\
+            var other = 1;\n";
+        let ir = lumen_parser::JsParser::new(Default::default()).parse_string(source).unwrap();
+
+        let (wat, warnings) = emit_wat(&ir);
+        assert_eq!(wat, "\
+(module
+  (func $answer (export \"answer\") (result i32)
+    i32.const 42)
+  (func $half (export \"half\") (param $x f64) (result f64)
+    f64.const 0.5)
+  (func $big (export \"big\") (result f64)
+    f64.const 3000000000.0)
+  (func $noop (export \"noop\"))
+  (func $sum (export \"sum\") (param $a f64) (param $b f64)
+    ;; 无法降级: BinaryExpression返回值
+  )
+)
+");
+        assert_eq!(warnings, ["函数 sum 无法降级为Wasm: BinaryExpression返回值"]);
+    }
+}
@@ -0,0 +1,106 @@
+//! 基准测试结果 - 汇总各工具的耗时，输出表格或供CI记录的JSON
+
+use std::time::Duration;
+use serde::Serialize;
+
+/// 单个工具的测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolResult {
+    pub name: String,
+    /// 编译所有测试文件的总耗时（毫秒）
+    pub total_ms: f64,
+    /// 吞吐量（文件/秒）
+    pub files_per_sec: f64,
+    /// 相对于最快工具的耗时倍数，最快的工具为1
+    pub slowdown: f64,
+}
+
+/// 一次基准测试的完整结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub test_type: String,
+    /// 测试文件数
+    pub files: usize,
+    pub tools: Vec<ToolResult>,
+    /// 最快的工具，没有结果时为None
+    pub fastest: Option<String>,
+}
+
+impl BenchmarkReport {
+    /// 由各工具的耗时计算吞吐量和相对于最快工具的倍数
+    pub fn new(test_type: &str, files: usize, timings: &[(&str, Duration)]) -> Self {
+        let fastest = timings.iter().min_by_key(|(_, time)| *time);
+        let fastest_secs = fastest.map(|(_, time)| time.as_secs_f64()).unwrap_or(0.0);
+        let tools = timings.iter()
+            .map(|(name, time)| {
+                let secs = time.as_secs_f64();
+                ToolResult {
+                    name: name.to_string(),
+                    total_ms: secs * 1000.0,
+                    files_per_sec: if secs > 0.0 { files as f64 / secs } else { 0.0 },
+                    slowdown: if fastest_secs > 0.0 { secs / fastest_secs } else { 1.0 },
+                }
+            })
+            .collect();
+        Self {
+            test_type: test_type.to_string(),
+            files,
+            tools,
+            fastest: fastest.map(|(name, _)| name.to_string()),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// 打印结果表格和各工具比最快工具慢的倍数
+    pub fn print_table(&self) {
+        println!("\n=== 基准测试结果 ===");
+        println!("{:<10} {:<12}", "工具", "耗时(ms)");
+        println!("{}", "-".repeat(22));
+
+        for tool in &self.tools {
+            println!("{:<10} {:<12}", tool.name, tool.total_ms.round());
+        }
+
+        if let Some(fastest) = &self.fastest {
+            println!("\n最快工具: {}", fastest);
+            for tool in self.tools.iter().filter(|t| &t.name != fastest) {
+                println!("{} 比 {} 慢 {:.2}倍", tool.name, fastest, tool.slowdown);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_json_report_is_consistent() {
+        let timings = [
+            ("Lumen", Duration::from_millis(120)),
+            ("SWC", Duration::from_millis(500)),
+            ("esbuild", Duration::from_millis(250)),
+        ];
+        let json = BenchmarkReport::new("all", 3, &timings).to_json().unwrap();
+        let report: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(report["files"], 3);
+        assert_eq!(report["fastest"], "Lumen");
+        let tools = report["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 3);
+
+        let lumen = tools.iter().find(|t| t["name"] == "Lumen").expect("应包含Lumen的结果");
+        assert_eq!(lumen["slowdown"], 1.0);
+        assert!((lumen["files_per_sec"].as_f64().unwrap() - 25.0).abs() < 1e-9);
+        for tool in tools {
+            let ratio = tool["total_ms"].as_f64().unwrap() / lumen["total_ms"].as_f64().unwrap();
+            assert!((tool["slowdown"].as_f64().unwrap() - ratio).abs() < 1e-9, "{}", tool);
+            let throughput = 3.0 / (tool["total_ms"].as_f64().unwrap() / 1000.0);
+            assert!((tool["files_per_sec"].as_f64().unwrap() - throughput).abs() < 1e-9, "{}", tool);
+        }
+    }
+}
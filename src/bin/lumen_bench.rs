@@ -1,16 +1,20 @@
+use lumen_compiler::Compiler;
+use lumen_core::bench::{BenchOptions, Iteration, measure};
 use std::path::Path;
 use std::time::Instant;
 use std::fs;
 use std::env;
 
 fn main() {
-    // 获取命令行参数
-    let args: Vec<String> = env::args().collect();
-    let file_path = if args.len() > 1 {
-        &args[1]
-    } else {
-        "tests/compile_speed.js"
+    // 获取命令行参数：[文件] [--runs N] [--warmup W]
+    let (options, args) = match BenchOptions::from_args(env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
     };
+    let file_path = args.first().map(String::as_str).unwrap_or("tests/compile_speed.js");
     
     println!("Lumen 编译器性能测试");
     println!("====================");
@@ -28,49 +32,39 @@ fn main() {
     println!("测试文件: {}", file_path);
     println!("文件大小: {} 字节", js_code.len());
     
-    // 运行多次以获取平均值
-    let runs = 5;
-    let mut total_ms = 0.0;
+    println!("\n预热 {} 次，运行 {} 次测试...", options.warmup, options.runs);
     
-    println!("\n运行 {} 次测试...", runs);
-    
-    for i in 1..=runs {
-        // 整体编译时间测量
+    // 关闭缓存，每次运行都完整编译
+    let compiler = Compiler::new().with_cache(false).with_optimizer_options(Default::default());
+    let result = measure(&options, |iteration| {
         let start = Instant::now();
-        
-        // 1. 解析阶段 (模拟)
-        let parse_start = Instant::now();
-        let _ast = js_code.chars().count(); // 简单模拟解析过程
-        let parse_time = parse_start.elapsed();
-        
-        // 2. 转换阶段 (模拟)
-        let transform_start = Instant::now();
-        let _transformed = js_code.len() * 2; // 简单模拟转换过程
-        let transform_time = transform_start.elapsed();
-        
-        // 3. 生成阶段 (模拟)
-        let gen_start = Instant::now();
-        let _output = js_code.replace(" ", ""); // 简单模拟代码生成
-        let gen_time = gen_start.elapsed();
-        
-        // 总时间
+        let result = compiler.compile_string(&js_code)?;
         let elapsed = start.elapsed();
-        let ms = elapsed.as_secs_f64() * 1000.0;
-        total_ms += ms;
         
-        println!("运行 #{}: 解析={:.2}ms, 转换={:.2}ms, 生成={:.2}ms, 总计={:.2}ms", 
-            i,
-            parse_time.as_secs_f64() * 1000.0,
-            transform_time.as_secs_f64() * 1000.0,
-            gen_time.as_secs_f64() * 1000.0,
-            ms);
-    }
+        if let Iteration::Measured(i) = iteration {
+            let phase = |name: &str| result.phase_times.get(name).copied().unwrap_or(0);
+            println!("运行 #{}: 解析={}ms, 优化={}ms, 生成={}ms, 总计={:.2}ms", 
+                i,
+                phase("parse"),
+                phase("optimize"),
+                phase("codegen"),
+                elapsed.as_secs_f64() * 1000.0);
+        }
+        Ok::<_, anyhow::Error>(elapsed)
+    });
+    let stats = match result {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("编译失败: {}", e);
+            return;
+        }
+    };
     
-    let avg_ms = total_ms / runs as f64;
+    let avg_ms = stats.mean_ms;
     let throughput = (js_code.len() as f64 / 1024.0 / 1024.0) / (avg_ms / 1000.0);
     
     println!("\n结果摘要:");
-    println!("平均编译时间: {:.2} ms", avg_ms);
+    println!("编译时间: 平均={:.2}ms, 中位数={:.2}ms, 标准差={:.2}ms", stats.mean_ms, stats.median_ms, stats.stddev_ms);
     println!("编译吞吐量: {:.2} MB/s", throughput);
     
     // 与其他编译器比较
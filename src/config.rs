@@ -1,6 +1,10 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 
+/// 自动查找的配置文件名，按优先级排列（均为JSON格式）
+pub const CONFIG_FILE_NAMES: [&str; 2] = ["lumen.json", ".lumenrc"];
+
 /// 编译选项
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompileOptions {
@@ -28,9 +32,13 @@ impl Default for CompileOptions {
     }
 }
 
-/// 完整的Lumen配置
+/// 完整的Lumen配置，配置文件中省略的字段使用默认值
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    /// 继承的基础配置文件路径（相对于当前配置文件），当前文件中的字段覆盖基础配置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
     /// 是否启用代码压缩
     pub minify: bool,
     /// 是否生成sourcemap
@@ -56,6 +64,7 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            extends: None,
             minify: false,
             sourcemap: false,
             target: "es2020".to_string(),
@@ -87,12 +96,96 @@ impl Config {
         serde_json::to_string_pretty(self)
     }
     
-    /// 从配置文件加载
+    /// 从配置文件加载，沿`extends`链合并基础配置
     pub fn from_file(path: &str) -> Result<Self, std::io::Error> {
+        let merged = Self::load_chain(Path::new(path), &mut Vec::new())?;
+        serde_json::from_value(merged)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+    
+    /// 读取配置文件并递归合并其继承的配置，`chain`记录正在加载的文件用于检测循环继承
+    fn load_chain(path: &Path, chain: &mut Vec<PathBuf>) -> Result<serde_json::Value, std::io::Error> {
+        let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        
+        let canonical = path.canonicalize()?;
+        if chain.contains(&canonical) {
+            let cycle: Vec<String> = chain.iter().chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(invalid(format!("配置文件循环继承: {}", cycle.join(" -> "))));
+        }
+        
         let content = std::fs::read_to_string(path)?;
-        let config = Self::from_json(&content)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        Ok(config)
+        let mut value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| invalid(format!("解析配置文件 {} 失败: {}", path.display(), e)))?;
+        let Some(extends) = value.get("extends").cloned() else {
+            return Ok(value);
+        };
+        let extends = extends.as_str()
+            .ok_or_else(|| invalid(format!("{}: extends 应为字符串", path.display())))?;
+        
+        chain.push(canonical);
+        let base_path = path.parent().unwrap_or(Path::new(".")).join(extends);
+        let mut base = Self::load_chain(&base_path, chain)?;
+        chain.pop();
+        
+        merge_json(&mut base, value.take());
+        Ok(base)
+    }
+    
+    /// 配置文件（`lumen.json`）的JSON Schema，供编辑器校验和补全
+    ///
+    /// 与`Config`的字段一一对应，修改字段时需要同步更新。
+    pub fn json_schema() -> serde_json::Value {
+        let defaults = Self::default();
+        serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Lumen配置",
+            "type": "object",
+            "properties": {
+                "extends": { "type": "string", "description": "继承的基础配置文件路径（相对于当前配置文件）" },
+                "minify": { "type": "boolean", "description": "是否启用代码压缩", "default": defaults.minify },
+                "sourcemap": { "type": "boolean", "description": "是否生成sourcemap", "default": defaults.sourcemap },
+                "target": {
+                    "type": "string",
+                    "description": "目标环境，如 es5, es2015, es2020 等",
+                    "default": defaults.target,
+                    "examples": ["es5", "es2015", "es2017", "es2020", "esnext"]
+                },
+                "distributed": { "type": "boolean", "description": "是否启用分布式编译", "default": defaults.distributed },
+                "gpu": { "type": "boolean", "description": "是否使用GPU加速", "default": defaults.gpu },
+                "cache_enabled": { "type": "boolean", "description": "是否启用缓存", "default": defaults.cache_enabled },
+                "cache_size_limit": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "缓存大小限制（字节）",
+                    "default": defaults.cache_size_limit
+                },
+                "transformers": { "type": "array", "items": { "type": "string" }, "description": "自定义转换器" },
+                "plugins": { "type": "array", "items": { "type": "string" }, "description": "自定义插件" },
+                "options": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "自定义选项"
+                }
+            },
+            "additionalProperties": false
+        })
+    }
+    
+    /// 从`start_dir`开始逐级向上查找配置文件，返回第一个找到的配置，都没有找到时返回默认配置
+    pub fn discover(start_dir: &Path) -> Result<Self, std::io::Error> {
+        match Self::find_config_file(start_dir) {
+            Some(path) => Self::from_file(&path.to_string_lossy()),
+            None => Ok(Self::default()),
+        }
+    }
+    
+    /// 从`start_dir`开始逐级向上查找配置文件，同一目录中`lumen.json`优先于`.lumenrc`
+    pub fn find_config_file(start_dir: &Path) -> Option<PathBuf> {
+        start_dir.ancestors()
+            .flat_map(|dir| CONFIG_FILE_NAMES.iter().map(move |name| dir.join(name)))
+            .find(|path| path.is_file())
     }
     
     /// 保存到配置文件
@@ -121,4 +214,137 @@ impl Config {
     pub fn add_transformer(&mut self, transformer: &str) {
         self.transformers.push(transformer.to_string());
     }
-} 
\ No newline at end of file
+}
+
+/// 把`overlay`合并到`base`：对象逐字段合并（如`options`），其余值直接覆盖
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    },
+                }
+            }
+        },
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    /// 按Schema校验JSON值，只支持配置Schema用到的关键字，返回所有错误
+    fn schema_errors(schema: &Value, value: &Value, path: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        let type_matches = match schema.get("type").and_then(Value::as_str) {
+            Some("object") => value.is_object(),
+            Some("array") => value.is_array(),
+            Some("string") => value.is_string(),
+            Some("boolean") => value.is_boolean(),
+            Some("integer") => value.is_u64() || value.is_i64(),
+            _ => true,
+        };
+        if !type_matches {
+            errors.push(format!("{}: 类型应为 {}", path, schema["type"]));
+            return errors;
+        }
+        if let (Some(min), Some(n)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+            if n < min {
+                errors.push(format!("{}: 不应小于 {}", path, min));
+            }
+        }
+        if let Some(items) = schema.get("items") {
+            for (i, item) in value.as_array().into_iter().flatten().enumerate() {
+                errors.extend(schema_errors(items, item, &format!("{}[{}]", path, i)));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for required in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                let key = required.as_str().unwrap();
+                if !object.contains_key(key) {
+                    errors.push(format!("{}: 缺少字段 {}", path, key));
+                }
+            }
+            for (key, field) in object {
+                let field_path = format!("{}.{}", path, key);
+                match (properties.and_then(|p| p.get(key)), schema.get("additionalProperties")) {
+                    (Some(field_schema), _) => errors.extend(schema_errors(field_schema, field, &field_path)),
+                    (None, Some(Value::Bool(false))) => errors.push(format!("{}: 未知字段", field_path)),
+                    (None, Some(extra)) => errors.extend(schema_errors(extra, field, &field_path)),
+                    (None, None) => {},
+                }
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn test_json_schema_validates_config() {
+        let schema = Config::json_schema();
+
+        let mut config = Config::default();
+        config.add_option("jsx", "react");
+        config.add_plugin("lumen-plugin-env");
+        let good: Value = serde_json::from_str(&config.to_json().unwrap()).unwrap();
+        assert_eq!(schema_errors(&schema, &good, "$"), Vec::<String>::new(), "默认配置应通过校验");
+
+        let mut bad = good.clone();
+        bad["minify"] = Value::String("yes".to_string());
+        let errors = schema_errors(&schema, &bad, "$");
+        assert_eq!(errors, vec!["$.minify: 类型应为 \"boolean\"".to_string()]);
+        assert!(Config::from_json(&bad.to_string()).is_err(), "Schema拒绝的配置也应无法加载");
+    }
+
+    #[test]
+    fn test_discover_finds_config_in_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("packages").join("app").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(Config::find_config_file(&nested).filter(|p| p.starts_with(dir.path())), None);
+
+        let mut config = Config::default();
+        config.target = "es5".to_string();
+        config.save_to_file(&dir.path().join(".lumenrc").to_string_lossy()).unwrap();
+        config.target = "es2017".to_string();
+        config.save_to_file(&dir.path().join("packages").join("lumen.json").to_string_lossy()).unwrap();
+
+        // 最近的配置文件优先
+        let found = Config::find_config_file(&nested).unwrap();
+        assert_eq!(found, dir.path().join("packages").join("lumen.json"));
+        assert_eq!(Config::discover(&nested).unwrap().target, "es2017");
+        assert_eq!(Config::discover(dir.path()).unwrap().target, "es5");
+    }
+
+    #[test]
+    fn test_extends_merges_base_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("base.json"),
+            r#"{ "minify": true, "target": "es2015", "cache_size_limit": 1024, "options": { "jsx": "react", "env": "prod" } }"#,
+        ).unwrap();
+        let app = dir.path().join("app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(app.join("lumen.json"), r#"{ "extends": "../base.json", "target": "es2020", "options": { "env": "dev" } }"#).unwrap();
+
+        let config = Config::from_file(&app.join("lumen.json").to_string_lossy()).unwrap();
+        assert_eq!(config.target, "es2020", "子配置应覆盖基础配置");
+        assert!(config.minify);
+        assert_eq!(config.cache_size_limit, 1024);
+        assert_eq!(config.get_option("jsx").map(String::as_str), Some("react"));
+        assert_eq!(config.get_option("env").map(String::as_str), Some("dev"));
+        assert!(!config.gpu, "两层都未设置的字段使用默认值");
+
+        // a -> b -> a
+        std::fs::write(dir.path().join("a.json"), r#"{ "extends": "b.json" }"#).unwrap();
+        std::fs::write(dir.path().join("b.json"), r#"{ "extends": "a.json" }"#).unwrap();
+        let err = Config::from_file(&dir.path().join("a.json").to_string_lossy()).unwrap_err();
+        assert!(err.to_string().contains("循环继承"), "应检测到循环继承: {}", err);
+    }
+}
@@ -5,6 +5,8 @@ use std::time::{Duration, Instant};
 use log::{debug, info, warn, error};
 use tokio::sync::mpsc;
 
+use crate::utils::{format_cycle, get_dependency_graph};
+
 // 分布式编译选项
 #[derive(Debug, Clone)]
 pub struct DistributedOptions {
@@ -423,8 +425,21 @@ impl DistributedCompiler {
                 Ok(inputs)
             },
             DistributionStrategy::DependencyBased => {
-                // 根据依赖关系排序
-                // 这里应该先构建依赖图，然后进行拓扑排序
+                // 按拓扑顺序排序，依赖先于依赖它的文件提交
+                let graph = get_dependency_graph(&inputs, &[])
+                    .map_err(|e| format!("构建依赖图失败: {}", e))?;
+                let order = graph.topological_sort()
+                    .map_err(|cycle| format!("存在循环依赖: {}", format_cycle(&cycle)))?;
+                let rank: HashMap<&Path, usize> = order.iter()
+                    .enumerate()
+                    .map(|(i, path)| (path.as_path(), i))
+                    .collect();
+                let mut inputs = inputs;
+                // 依赖图中是规范化的路径
+                inputs.sort_by_key(|input| {
+                    let path = std::fs::canonicalize(input.as_ref()).unwrap_or_else(|_| input.as_ref().to_path_buf());
+                    rank.get(path.as_path()).copied().unwrap_or(usize::MAX)
+                });
                 Ok(inputs)
             },
             DistributionStrategy::SizePriority => {
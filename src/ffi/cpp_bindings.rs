@@ -1,7 +1,8 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::path::Path;
-use log::{debug, info};
+use std::time::Duration;
+use log::{debug, info, warn};
 
 // 定义外部C++函数接口
 #[link(name = "lumen_cpp")]
@@ -23,113 +24,131 @@ extern "C" {
     fn cpp_free_string(ptr: *mut c_char);
 }
 
+/// C++函数返回空指针时的重试策略，默认不重试
+///
+/// 空指针可能只是暂时的失败（如工作进程中的解析器内存不足），重试之前等待的时间每次加倍。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FfiRetryPolicy {
+    /// 第一次调用失败后最多重试的次数
+    pub max_retries: u32,
+    /// 第一次重试之前的等待时间
+    pub initial_backoff: Duration,
+    /// 每次等待时间的上限
+    pub max_backoff: Duration,
+}
+
+impl Default for FfiRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl FfiRetryPolicy {
+    /// 最多重试`max_retries`次，等待时间使用默认值
+    pub fn new(max_retries: u32) -> Self {
+        Self { max_retries, ..Default::default() }
+    }
+    
+    /// 第`retry`次重试（从1开始）之前的等待时间
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_backoff)
+    }
+}
+
+/// 调用返回C字符串的C++函数，返回空指针时按`retry`重试，仍然失败时以`error`报错
+///
+/// 成功时复制结果，并用`free`释放C++分配的字符串。
+unsafe fn call_with_retry(
+    retry: &FfiRetryPolicy,
+    error: &str,
+    mut call: impl FnMut() -> *mut c_char,
+    free: unsafe extern "C" fn(*mut c_char),
+) -> Result<String, String> {
+    for attempt in 0..=retry.max_retries {
+        if attempt > 0 {
+            let wait = retry.backoff(attempt);
+            warn!("{}，{:?}后第{}次重试", error, wait, attempt);
+            std::thread::sleep(wait);
+        }
+        let result_ptr = call();
+        if !result_ptr.is_null() {
+            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
+            free(result_ptr);
+            return Ok(result);
+        }
+    }
+    
+    if retry.max_retries > 0 {
+        Err(format!("{}（已重试{}次）", error, retry.max_retries))
+    } else {
+        Err(error.to_string())
+    }
+}
+
 // 安全封装C++解析器
 pub struct CppParser;
 
 impl CppParser {
-    pub fn parse_js(source: &str) -> Result<String, String> {
+    pub fn parse_js(source: &str, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
         unsafe {
-            let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let result_ptr = cpp_parse_js(c_source.as_ptr(), source.len() as c_int);
-            
-            if result_ptr.is_null() {
-                return Err("C++解析器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++解析器返回空指针", || cpp_parse_js(c_source.as_ptr(), source.len() as c_int), cpp_free_string)
         }
     }
     
-    pub fn parse_ts(source: &str) -> Result<String, String> {
+    pub fn parse_ts(source: &str, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
         unsafe {
-            let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let result_ptr = cpp_parse_ts(c_source.as_ptr(), source.len() as c_int);
-            
-            if result_ptr.is_null() {
-                return Err("C++解析器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++解析器返回空指针", || cpp_parse_ts(c_source.as_ptr(), source.len() as c_int), cpp_free_string)
         }
     }
     
-    pub fn parse_jsx(source: &str) -> Result<String, String> {
+    pub fn parse_jsx(source: &str, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
         unsafe {
-            let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let result_ptr = cpp_parse_jsx(c_source.as_ptr(), source.len() as c_int);
-            
-            if result_ptr.is_null() {
-                return Err("C++解析器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++解析器返回空指针", || cpp_parse_jsx(c_source.as_ptr(), source.len() as c_int), cpp_free_string)
         }
     }
     
-    pub fn parse_tsx(source: &str) -> Result<String, String> {
+    pub fn parse_tsx(source: &str, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
         unsafe {
-            let c_source = CString::new(source).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let result_ptr = cpp_parse_tsx(c_source.as_ptr(), source.len() as c_int);
-            
-            if result_ptr.is_null() {
-                return Err("C++解析器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++解析器返回空指针", || cpp_parse_tsx(c_source.as_ptr(), source.len() as c_int), cpp_free_string)
         }
     }
+
 }
 
 // 安全封装C++代码生成器
 pub struct CppCodeGenerator;
 
 impl CppCodeGenerator {
-    pub fn generate_code(ir_json: &str, minify: bool, target: &str) -> Result<String, String> {
+    pub fn generate_code(ir_json: &str, minify: bool, target: &str, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_ir_json = CString::new(ir_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
+        let c_target = CString::new(target).map_err(|e| format!("无法创建C字符串: {}", e))?;
+        let minify_int = if minify { 1 } else { 0 };
+        
         unsafe {
-            let c_ir_json = CString::new(ir_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let c_target = CString::new(target).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let minify_int = if minify { 1 } else { 0 };
-            
-            let result_ptr = cpp_generate_code(c_ir_json.as_ptr(), minify_int, c_target.as_ptr());
-            
-            if result_ptr.is_null() {
-                return Err("C++代码生成器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++代码生成器返回空指针", || {
+                cpp_generate_code(c_ir_json.as_ptr(), minify_int, c_target.as_ptr())
+            }, cpp_free_string)
         }
     }
     
-    pub fn generate_wasm(ir_json: &str, options_json: &str) -> Result<String, String> {
+    pub fn generate_wasm(ir_json: &str, options_json: &str, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_ir_json = CString::new(ir_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
+        let c_options = CString::new(options_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
+        
         unsafe {
-            let c_ir_json = CString::new(ir_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            let c_options = CString::new(options_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            
-            let result_ptr = cpp_generate_wasm(c_ir_json.as_ptr(), c_options.as_ptr());
-            
-            if result_ptr.is_null() {
-                return Err("C++ WebAssembly生成器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++ WebAssembly生成器返回空指针", || {
+                cpp_generate_wasm(c_ir_json.as_ptr(), c_options.as_ptr())
+            }, cpp_free_string)
         }
     }
 }
@@ -138,20 +157,56 @@ impl CppCodeGenerator {
 pub struct CppOptimizer;
 
 impl CppOptimizer {
-    pub fn optimize(ir_json: &str, level: i32) -> Result<String, String> {
+    pub fn optimize(ir_json: &str, level: i32, retry: &FfiRetryPolicy) -> Result<String, String> {
+        let c_ir_json = CString::new(ir_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
+        
         unsafe {
-            let c_ir_json = CString::new(ir_json).map_err(|e| format!("无法创建C字符串: {}", e))?;
-            
-            let result_ptr = cpp_optimize_ir(c_ir_json.as_ptr(), level);
-            
-            if result_ptr.is_null() {
-                return Err("C++优化器返回空指针".to_string());
-            }
-            
-            let result = CStr::from_ptr(result_ptr).to_string_lossy().into_owned();
-            cpp_free_string(result_ptr);
-            
-            Ok(result)
+            call_with_retry(retry, "C++优化器返回空指针", || cpp_optimize_ir(c_ir_json.as_ptr(), level), cpp_free_string)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    /// 模拟第一次调用失败的C++解析器
+    extern "C" fn flaky_parse(_source: *const c_char, _length: c_int) -> *mut c_char {
+        if CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+            std::ptr::null_mut()
+        } else {
+            CString::new("{\"nodes\":{}}").unwrap().into_raw()
         }
     }
-} 
\ No newline at end of file
+
+    unsafe extern "C" fn free_mock_string(ptr: *mut c_char) {
+        drop(CString::from_raw(ptr));
+    }
+
+    #[test]
+    fn test_retry_after_transient_null_pointer() {
+        let source = CString::new("var x = 1;").unwrap();
+        let call = || flaky_parse(source.as_ptr(), 10);
+        let retry = FfiRetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+
+        let result = unsafe { call_with_retry(&retry, "C++解析器返回空指针", call, free_mock_string) };
+        assert_eq!(result.as_deref(), Ok("{\"nodes\":{}}"));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2, "第一次失败后应重试一次");
+
+        // 默认不重试，第一次失败直接报错
+        CALLS.store(0, Ordering::SeqCst);
+        let result = unsafe { call_with_retry(&FfiRetryPolicy::default(), "C++解析器返回空指针", call, free_mock_string) };
+        assert_eq!(result.unwrap_err(), "C++解析器返回空指针");
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        assert_eq!(retry.backoff(1), Duration::from_millis(1));
+        assert_eq!(retry.backoff(3), Duration::from_millis(2), "等待时间不超过上限");
+    }
+}
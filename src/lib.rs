@@ -2,6 +2,7 @@ use std::path::Path;
 use std::collections::HashMap;
 use std::time::Instant;
 use log::{debug, info, warn};
+use rayon::prelude::*;
 
 mod error;
 mod config;
@@ -11,9 +12,11 @@ mod gpu;
 mod distributed;
 
 pub use error::{Error, Result};
-pub use config::CompileOptions;
+pub use config::{CompileOptions, Config};
 pub use gpu::GpuOptimizer;
 pub use distributed::DistributedCompiler;
+pub use ffi::cpp_bindings::FfiRetryPolicy;
+pub use utils::{DependencyTree, find_files, format_cycle, get_dependency_graph};
 
 /// Lumen编译器主入口
 pub struct Lumen {
@@ -120,15 +123,7 @@ impl Lumen {
         // 使用标准迭代器而不是并行迭代器
         let results: Vec<Result<()>> = inputs.iter().map(|input| {
             let input_path = input.as_ref();
-            let output_path = match &output_dir {
-                Some(dir) => {
-                    let file_name = input_path.file_name().unwrap_or_default();
-                    let mut path = dir.join(file_name);
-                    path.set_extension("js");
-                    Some(path)
-                },
-                None => None,
-            };
+            let output_path = output_dir.as_ref().map(|dir| lumen_compiler::output_path_in_dir(input_path, dir));
             
             self.compile_file(input_path, output_path.as_ref().map(|v| &**v))
         }).collect();
@@ -204,6 +199,8 @@ impl Lumen {
 pub struct Compiler {
     options: config::Config,
     use_cpp: bool,
+    /// C++函数返回空指针时的重试策略
+    ffi_retry: FfiRetryPolicy,
 }
 
 impl Compiler {
@@ -212,6 +209,7 @@ impl Compiler {
         Self {
             options: config::Config::default(),
             use_cpp: true, // 默认使用C++解析器
+            ffi_retry: FfiRetryPolicy::default(),
         }
     }
     
@@ -236,6 +234,7 @@ impl Compiler {
         let optimized_ir = self.optimize_ir(&ir_json)?;
         let optimize_time = start.elapsed() - parse_time;
         debug!("优化阶段完成，耗时: {:?}", optimize_time);
+        let optimized_ir = self.apply_transformers(&optimized_ir)?;
         
         // 3. 代码生成阶段
         let output = self.generate_code(&optimized_ir)?;
@@ -270,13 +269,13 @@ impl Compiler {
         let is_jsx = filename.ends_with(".jsx") || filename.ends_with(".tsx");
         
         let result = if is_ts && is_jsx {
-            ffi::cpp_bindings::CppParser::parse_tsx(source)
+            ffi::cpp_bindings::CppParser::parse_tsx(source, &self.ffi_retry)
         } else if is_ts {
-            ffi::cpp_bindings::CppParser::parse_ts(source)
+            ffi::cpp_bindings::CppParser::parse_ts(source, &self.ffi_retry)
         } else if is_jsx {
-            ffi::cpp_bindings::CppParser::parse_jsx(source)
+            ffi::cpp_bindings::CppParser::parse_jsx(source, &self.ffi_retry)
         } else {
-            ffi::cpp_bindings::CppParser::parse_js(source)
+            ffi::cpp_bindings::CppParser::parse_js(source, &self.ffi_retry)
         };
         
         result.map_err(|e| Error::ParseError(e))
@@ -313,7 +312,7 @@ impl Compiler {
         
         if self.use_cpp {
             // 使用C++优化器
-            ffi::cpp_bindings::CppOptimizer::optimize(ir_json, level)
+            ffi::cpp_bindings::CppOptimizer::optimize(ir_json, level, &self.ffi_retry)
                 .map_err(|e| Error::CompileError(format!("C++优化器错误: {}", e)))
         } else {
             // 使用Rust优化器 (简化实现)
@@ -321,6 +320,25 @@ impl Compiler {
         }
     }
     
+    /// 依次运行配置中的转换器，名字未注册或转换失败时返回插件错误
+    fn apply_transformers(&self, ir_json: &str) -> Result<String> {
+        if self.options.transformers.is_empty() {
+            return Ok(ir_json.to_string());
+        }
+        
+        let transforms = lumen_compiler::TransformRegistry::new().resolve(&self.options.transformers)?;
+        let mut ir: lumen_core::IR = serde_json::from_str(ir_json)
+            .map_err(|e| Error::InternalError(format!("IR解析失败: {}", e)))?;
+        for transform in transforms {
+            debug!("运行转换器: {}", transform.name());
+            transform.apply(&mut ir)
+                .map_err(|e| Error::PluginError(format!("{}: {}", transform.name(), e)))?;
+        }
+        
+        serde_json::to_string(&ir)
+            .map_err(|e| Error::InternalError(format!("IR转JSON失败: {}", e)))
+    }
+    
     /// 生成最终代码
     fn generate_code(&self, ir_json: &str) -> Result<String> {
         debug!("生成输出代码");
@@ -336,6 +354,7 @@ impl Compiler {
             target: self.options.target.clone(),
             inline_sources: true,
             preserve_comments: false,
+            ..Default::default()
         };
         
         // 生成代码
@@ -444,15 +463,7 @@ impl Compiler {
         // 使用标准迭代器而不是并行迭代器
         let results: Vec<Result<CompileResult>> = inputs.iter().map(|input| {
             let input_path = input.as_ref();
-            let output_path = match &output_dir {
-                Some(dir) => {
-                    let file_name = input_path.file_name().unwrap_or_default();
-                    let mut path = dir.join(file_name);
-                    path.set_extension("js");
-                    Some(path)
-                },
-                None => None,
-            };
+            let output_path = output_dir.as_ref().map(|dir| lumen_compiler::output_path_in_dir(input_path, dir));
             
             self.compile_file(input_path, output_path.as_ref().map(|v| &**v))
         }).collect();
@@ -485,7 +496,7 @@ impl Compiler {
         if self.use_cpp {
             // 使用C++解析和WebAssembly生成
             let ir_json = self.parse_with_cpp(source, "input.js")?;
-            let wasm_result = ffi::cpp_bindings::CppCodeGenerator::generate_wasm(&ir_json, options_json)
+            let wasm_result = ffi::cpp_bindings::CppCodeGenerator::generate_wasm(&ir_json, options_json, &self.ffi_retry)
                 .map_err(|e| Error::CompileError(format!("C++ WebAssembly生成错误: {}", e)))?;
                 
             // 假设返回的是Base64编码的WASM二进制数据
@@ -505,6 +516,12 @@ impl Compiler {
         self
     }
     
+    /// 设置C++函数暂时失败时的重试策略
+    pub fn with_ffi_retry(mut self, retry: FfiRetryPolicy) -> Self {
+        self.ffi_retry = retry;
+        self
+    }
+    
     // 设置选项方法
     pub fn with_minify(mut self, enable: bool) -> Self {
         self.options.minify = enable;
@@ -624,6 +641,8 @@ pub struct LumenCompiler {
     compiler: Compiler,
     gpu_optimizer: Option<gpu::GpuOptimizer>,
     distributed_compiler: Option<distributed::DistributedCompiler>,
+    /// 本地批量编译的最大并发数，None表示使用rayon的默认线程数
+    max_concurrency: Option<usize>,
 }
 
 impl LumenCompiler {
@@ -633,6 +652,7 @@ impl LumenCompiler {
             compiler: Compiler::new(),
             gpu_optimizer: None,
             distributed_compiler: None,
+            max_concurrency: None,
         }
     }
     
@@ -674,6 +694,12 @@ impl LumenCompiler {
         self
     }
     
+    /// 限制本地批量编译同时编译的文件数，至少为1
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = Some(max.max(1));
+        self
+    }
+    
     /// 编译单个字符串
     pub async fn compile_str(&self, source: &str, filename: Option<&str>) -> Result<CompileResult> {
         let start = Instant::now();
@@ -733,6 +759,11 @@ impl LumenCompiler {
         }
         
         // 使用本地编译
+        self.compile_str_local(source, filename)
+    }
+    
+    /// 本地编译单个字符串，启用GPU加速时再进行GPU压缩
+    fn compile_str_local(&self, source: &str, filename: Option<&str>) -> Result<CompileResult> {
         let mut result = self.compiler.compile_str(source, filename)?;
         
         // 如果启用了GPU加速，应用GPU压缩
@@ -782,8 +813,21 @@ impl LumenCompiler {
         Ok(result)
     }
     
+    /// 本地编译文件，供批量编译在线程池中调用
+    fn compile_file_local(&self, input_path: &Path, output_path: Option<&Path>) -> Result<CompileResult> {
+        let source = std::fs::read_to_string(input_path)
+            .map_err(|e| Error::IoError(e))?;
+        let result = self.compile_str_local(&source, Some(input_path.to_str().unwrap_or("unknown")))?;
+        if let Some(output_path) = output_path {
+            std::fs::write(output_path, &result.code)
+                .map_err(|e| Error::IoError(e))?;
+            info!("输出文件已写入: {}", output_path.display());
+        }
+        Ok(result)
+    }
+    
     /// 批量编译文件
-    pub async fn compile_files<P: AsRef<Path>>(&self, inputs: Vec<P>, output_dir: Option<P>) -> Result<Vec<CompileResult>> {
+    pub async fn compile_files<P: AsRef<Path> + Sync>(&self, inputs: Vec<P>, output_dir: Option<P>) -> Result<Vec<CompileResult>> {
         info!("开始批量编译 {} 个文件", inputs.len());
         
         let output_dir = output_dir.map(|p| p.as_ref().to_path_buf());
@@ -842,26 +886,30 @@ impl LumenCompiler {
             return Ok(results);
         }
         
-        // 使用本地编译
-        let mut results = Vec::new();
+        // 使用本地编译，在限制了线程数的线程池中并行编译
+        // 和原来的逐个编译一样会阻塞当前任务，直到所有文件编译完成
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(max) = self.max_concurrency {
+            builder = builder.num_threads(max);
+        }
+        let pool = builder.build()
+            .map_err(|e| Error::InternalError(format!("创建线程池失败: {}", e)))?;
+        let compiled: Vec<Result<CompileResult>> = pool.install(|| {
+            inputs.par_iter().map(|input| {
+                let input_path = input.as_ref();
+                info!("编译文件: {}", input_path.display());
+                let output_path = output_dir.as_ref().map(|dir| lumen_compiler::output_path_in_dir(input_path, dir));
+                self.compile_file_local(input_path, output_path.as_deref())
+            }).collect()
+        });
         
-        // 使用迭代器替代并行迭代器
-        for input in &inputs {
-            let input_path = input.as_ref();
-            let output_path = match &output_dir {
-                Some(dir) => {
-                    let file_name = input_path.file_name().unwrap_or_default();
-                    let mut path = dir.join(file_name);
-                    path.set_extension("js");
-                    Some(path)
-                },
-                None => None,
-            };
-            
-            match self.compile_file(input_path, output_path.as_ref().map(|p| p.as_ref())).await {
+        // 按输入顺序收集结果，失败的文件记录日志后跳过
+        let mut results = Vec::new();
+        for (input, result) in inputs.iter().zip(compiled) {
+            match result {
                 Ok(result) => results.push(result),
                 Err(e) => {
-                    warn!("编译文件失败: {} - {}", input_path.display(), e);
+                    warn!("编译文件失败: {} - {}", input.as_ref().display(), e);
                     // 继续处理其他文件
                 }
             }
@@ -892,6 +940,11 @@ impl LumenCompiler {
         self.compiler = self.compiler.with_cpp(enable);
         self
     }
+    
+    pub fn with_ffi_retry(mut self, retry: FfiRetryPolicy) -> Self {
+        self.compiler = self.compiler.with_ffi_retry(retry);
+        self
+    }
 }
 
 // 提供一个方便的函数来快速编译字符串
@@ -1,10 +1,19 @@
 use clap::{Parser, Subcommand};
 use log::{info, error};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 // 导入Lumen编译器
-use lumen_compiler::{LumenCompiler, gpu, distributed, Error};
+use lumen::{Config, LumenCompiler};
+use lumen_core::paths::display_path;
+
+mod benchmark;
+mod serve;
+mod watch;
+
+use benchmark::BenchmarkReport;
+use serve::RpcServer;
+use watch::WatchOptions;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -13,6 +22,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // 解析命令行参数
     let cli = Cli::parse();
+    
+    // 加载配置：未指定--config时从当前目录向上查找配置文件
+    let config = match &cli.config {
+        Some(path) => Config::from_file(&path.to_string_lossy())?,
+        None => Config::discover(&std::env::current_dir()?)?,
+    };
 
     // 根据子命令执行不同的操作
     match &cli.command {
@@ -28,13 +43,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             info!("开始编译: {} -> {}", input.display(), out.display());
             let start = Instant::now();
             
+            // 命令行参数优先于配置文件
+            let minify = *minify || config.minify;
+            let sourcemap = *sourcemap || config.sourcemap;
+            let target = target.as_deref().unwrap_or(&config.target);
+            let distributed = *distributed || config.distributed;
+            let gpu = *gpu || config.gpu;
+            
             // 创建高性能编译器
             let compiler = LumenCompiler::new()
-                .with_minify(*minify)
-                .with_sourcemap(*sourcemap)
+                .with_minify(minify)
+                .with_sourcemap(sourcemap)
                 .with_target(target)
-                .with_distributed(*distributed)
-                .with_gpu(*gpu);
+                .with_distributed(distributed)
+                .with_gpu(gpu);
             
             println!("编译参数: 目标环境={}, 压缩={}, Sourcemap={}", 
                 target, minify, sourcemap);
@@ -58,18 +80,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         },
-        Commands::Watch { dir, out_dir, pattern } => {
+        Commands::Watch { dir, out_dir, pattern, watch_initial, debounce_ms, max_parallel } => {
             info!("开始监视文件变化: {} -> {}", dir.display(), out_dir.display());
             println!("监视模式: {}", pattern);
             
             // 实现文件监听逻辑
-            watch_files(dir, out_dir, pattern).await?;
+            let options = WatchOptions {
+                dir: dir.clone(),
+                out_dir: out_dir.clone(),
+                pattern: pattern.clone(),
+                initial_build: *watch_initial,
+                debounce: Duration::from_millis(*debounce_ms),
+                max_parallel: *max_parallel,
+                root_dir: cli.root_dir.clone(),
+            };
+            watch::watch_files(&options).await?;
         },
-        Commands::Benchmark { test_type, compare } => {
+        Commands::Benchmark { test_type, compare, json } => {
             info!("运行基准测试: 类型={}, 对比={}", test_type, compare);
             
             // 实现基准测试逻辑
-            benchmark(test_type, compare).await?;
+            benchmark(test_type, compare, *json).await?;
+        },
+        Commands::Sourcemap { file } => {
+            print_source_map(file)?;
+        },
+        Commands::Serve { port } => {
+            // 服务模式使用带缓存的编译器，在请求之间保持缓存
+            let options = lumen_compiler::CompileOptions {
+                minify: config.minify,
+                sourcemap: config.sourcemap,
+                target: config.target.clone(),
+                transformers: config.transformers.clone(),
+                ..Default::default()
+            };
+            let server = RpcServer::new(lumen_compiler::Compiler::with_options(options));
+            match port {
+                Some(port) => server.serve_tcp(*port)?,
+                None => server.serve_stdio()?,
+            }
+        },
+        Commands::Deps { entry, json } => {
+            print_dependencies(entry, cli.root_dir.as_deref(), *json)?;
+        },
+        Commands::Config { schema } => {
+            if *schema {
+                println!("{}", serde_json::to_string_pretty(&Config::json_schema())?);
+            } else {
+                println!("{}", config.to_json()?);
+            }
         },
     }
     
@@ -80,6 +139,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
+    /// 配置文件路径，未指定时从当前目录向上查找lumen.json或.lumenrc
+    #[clap(long, global = true, value_parser)]
+    config: Option<PathBuf>,
+
+    /// 错误信息和日志中的路径相对于该目录显示
+    #[clap(long, global = true, value_parser)]
+    root_dir: Option<PathBuf>,
+
     #[clap(subcommand)]
     command: Commands,
 }
@@ -104,9 +171,9 @@ enum Commands {
         #[clap(short, long)]
         sourcemap: bool,
 
-        /// 指定目标环境 (es5, es2015, es2020, ...)
-        #[clap(short, long, default_value = "es2020")]
-        target: String,
+        /// 指定目标环境 (es5, es2015, es2020, ...)，默认使用配置文件中的值
+        #[clap(short, long)]
+        target: Option<String>,
 
         /// 是否启用分布式编译
         #[clap(long)]
@@ -129,110 +196,105 @@ enum Commands {
         /// 文件glob模式
         #[clap(short, long, default_value = "**/*.{js,ts,jsx,tsx}")]
         pattern: String,
+
+        /// 开始监视前先编译所有匹配的文件
+        #[clap(long)]
+        watch_initial: bool,
+
+        /// 去抖窗口（毫秒），同一文件在窗口内的多次变更只编译一次
+        #[clap(long, default_value = "100")]
+        debounce_ms: u64,
+
+        /// 同时进行的编译数上限，超出的变更排队等待
+        #[clap(long, default_value = "4")]
+        max_parallel: usize,
     },
     /// 运行性能基准测试
     Benchmark {
         /// 基准测试类型 (parse, compile, minify, all)
-        #[clap(value_enum, default_value = "all")]
+        #[clap(default_value = "all")]
         test_type: String,
         
         /// 对比工具 (babel, swc, esbuild, all)
         #[clap(short, long, default_value = "all")]
         compare: String,
+
+        /// 以JSON格式输出结果（工具名、总耗时、吞吐量和相对最快工具的倍数），便于在CI中记录
+        #[clap(long)]
+        json: bool,
+    },
+    /// 解码Source Map并打印每条映射
+    Sourcemap {
+        /// Source Map文件路径
+        #[clap(value_parser)]
+        file: PathBuf,
+    },
+    /// 启动长期运行的JSON-RPC服务，提供compile、analyze和diagnose方法
+    Serve {
+        /// 在本机端口上监听，未指定时通过标准输入输出通信
+        #[clap(long)]
+        port: Option<u16>,
+    },
+    /// 打印入口文件解析后的完整依赖树，标出循环依赖
+    Deps {
+        /// 入口文件路径
+        #[clap(value_parser)]
+        entry: PathBuf,
+
+        /// 以JSON格式输出依赖树
+        #[clap(long)]
+        json: bool,
+    },
+    /// 打印当前生效的配置
+    Config {
+        /// 输出配置文件（lumen.json）的JSON Schema
+        #[clap(long)]
+        schema: bool,
     },
 }
 
-/// 文件监视逻辑
-async fn watch_files(dir: &PathBuf, out_dir: &PathBuf, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // 创建输出目录（如果不存在）
-    if !out_dir.exists() {
-        std::fs::create_dir_all(out_dir)?;
-    }
-    
-    // 创建编译器
-    let compiler = LumenCompiler::new()
-        .with_minify(true)
-        .with_sourcemap(true);
-    
-    // 创建文件监视器
-    use notify::{Watcher, RecursiveMode, watcher};
-    use std::sync::mpsc::channel;
-    use std::time::Duration;
-    
-    // 创建通道接收文件变更事件
-    let (tx, rx) = channel();
-    
-    // 创建监视器
-    let mut watcher = watcher(tx, Duration::from_secs(1))?;
-    
-    // 监视目录
-    watcher.watch(dir, RecursiveMode::Recursive)?;
-    
-    println!("开始监视文件变化，按Ctrl+C退出...");
-    
-    // 处理文件变更事件
-    loop {
-        match rx.recv() {
-            Ok(event) => {
-                use notify::DebouncedEvent;
-                match event {
-                    DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => {
-                        // 检查文件是否匹配模式
-                        if is_target_file(&path, pattern) {
-                            println!("文件变更: {}", path.display());
-                            
-                            // 计算输出路径
-                            let rel_path = path.strip_prefix(dir)?;
-                            let out_path = out_dir.join(rel_path);
-                            
-                            // 确保输出目录存在
-                            if let Some(parent) = out_path.parent() {
-                                if !parent.exists() {
-                                    std::fs::create_dir_all(parent)?;
-                                }
-                            }
-                            
-                            // 转换扩展名为.js
-                            let mut js_out_path = out_path.clone();
-                            js_out_path.set_extension("js");
-                            
-                            // 执行编译
-                            match compiler.compile_file(&path, Some(js_out_path)).await {
-                                Ok(_) => println!("编译成功: {} -> {}", path.display(), js_out_path.display()),
-                                Err(e) => println!("编译失败: {} - {}", path.display(), e),
-                            }
-                        }
-                    },
-                    _ => {},
-                }
-            },
-            Err(e) => {
-                error!("监视错误: {}", e);
-                break;
-            }
-        }
+/// 打印Source Map中的映射（生成位置 -> 源码位置）
+fn print_source_map(file: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let json = std::fs::read_to_string(file)?;
+    let entries = lumen_compiler::decode_source_map(&json)?;
+
+    println!("{}: {} 条映射", file.display(), entries.len());
+    for entry in &entries {
+        println!("  {}", entry);
     }
-    
+
     Ok(())
 }
 
-/// 检查文件是否匹配目标模式
-fn is_target_file(path: &PathBuf, pattern: &str) -> bool {
-    if !path.is_file() {
-        return false;
+/// 打印依赖树，路径相对于`root_dir`（默认为当前目录）显示
+fn print_dependencies(entry: &Path, root_dir: Option<&Path>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let graph = lumen::get_dependency_graph(&[entry], &[])?;
+    // 依赖图中是规范化的路径，基准目录也要规范化才能得到相对路径
+    let root_dir = match root_dir {
+        Some(dir) => dir.canonicalize()?,
+        None => std::env::current_dir()?.canonicalize()?,
+    };
+    let tree = graph.dependency_tree(&entry.canonicalize()?, Some(&root_dir));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tree)?);
+        return Ok(());
     }
-    
-    // 简单实现：检查扩展名
-    match path.extension().and_then(|e| e.to_str()) {
-        Some("js") | Some("jsx") | Some("ts") | Some("tsx") => true,
-        _ => false,
+    print!("{}", tree.render());
+    println!("\n共 {} 个文件", graph.get_nodes().len());
+    if let Err(cycle) = graph.topological_sort() {
+        let cycle: Vec<PathBuf> = cycle.iter().map(|path| PathBuf::from(display_path(path, Some(&root_dir)))).collect();
+        println!("检测到循环依赖: {}", lumen::format_cycle(&cycle));
     }
+
+    Ok(())
 }
 
-/// 基准测试逻辑
-async fn benchmark(test_type: &str, compare: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("=== Lumen 性能基准测试 ===");
-    println!("测试类型: {}", test_type);
+/// 运行基准测试，`json`为true时只在标准输出打印JSON结果，进度信息写入日志
+async fn benchmark(test_type: &str, compare: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let progress = |message: String| if json { info!("{}", message) } else { println!("{}", message) };
+    progress("=== Lumen 性能基准测试 ===".to_string());
+    progress(format!("测试类型: {}", test_type));
     
     // 创建临时目录
     let temp_dir = tempfile::tempdir()?;
@@ -247,7 +309,7 @@ async fn benchmark(test_type: &str, compare: &str) -> Result<(), Box<dyn std::er
     
     // 测试Lumen
     {
-        println!("\n测试 Lumen 性能...");
+        progress("\n测试 Lumen 性能...".to_string());
         let compiler = LumenCompiler::new()
             .with_minify(true)
             .with_sourcemap(false);
@@ -257,55 +319,41 @@ async fn benchmark(test_type: &str, compare: &str) -> Result<(), Box<dyn std::er
         let duration = start.elapsed();
         
         results.push(("Lumen", duration));
-        println!("Lumen 编译完成，耗时: {:.2?}", duration);
+        progress(format!("Lumen 编译完成，耗时: {:.2?}", duration));
     }
     
     // 测试SWC (如果可用)
     if compare == "all" || compare == "swc" {
-        println!("\n测试 SWC 性能...");
+        progress("\n测试 SWC 性能...".to_string());
         // 模拟SWC测试
         let duration = Duration::from_millis(500);
         results.push(("SWC", duration));
-        println!("SWC 编译完成，耗时: {:.2?}", duration);
+        progress(format!("SWC 编译完成，耗时: {:.2?}", duration));
     }
     
     // 测试esbuild (如果可用)
     if compare == "all" || compare == "esbuild" {
-        println!("\n测试 esbuild 性能...");
+        progress("\n测试 esbuild 性能...".to_string());
         // 模拟esbuild测试
         let duration = Duration::from_millis(250);
         results.push(("esbuild", duration));
-        println!("esbuild 编译完成，耗时: {:.2?}", duration);
+        progress(format!("esbuild 编译完成，耗时: {:.2?}", duration));
     }
     
     // 测试Babel (如果可用)
     if compare == "all" || compare == "babel" {
-        println!("\n测试 Babel 性能...");
+        progress("\n测试 Babel 性能...".to_string());
         // 模拟Babel测试
         let duration = Duration::from_millis(3000);
         results.push(("Babel", duration));
-        println!("Babel 编译完成，耗时: {:.2?}", duration);
+        progress(format!("Babel 编译完成，耗时: {:.2?}", duration));
     }
     
-    // 输出结果表格
-    println!("\n=== 基准测试结果 ===");
-    println!("{:<10} {:<12}", "工具", "耗时(ms)");
-    println!("{}", "-".repeat(22));
-    
-    for (tool, duration) in &results {
-        println!("{:<10} {:<12}", tool, duration.as_millis());
-    }
-    
-    // 找出最快的工具
-    if let Some((fastest_tool, fastest_time)) = results.iter().min_by_key(|(_, time)| time.as_millis()) {
-        println!("\n最快工具: {}", fastest_tool);
-        
-        for (tool, time) in &results {
-            if tool != fastest_tool {
-                let ratio = time.as_secs_f64() / fastest_time.as_secs_f64();
-                println!("{} 比 {} 慢 {:.2}倍", tool, fastest_tool, ratio);
-            }
-        }
+    let report = BenchmarkReport::new(test_type, test_files.len(), &results);
+    if json {
+        println!("{}", report.to_json()?);
+    } else {
+        report.print_table();
     }
     
     Ok(())
@@ -0,0 +1,165 @@
+//! 服务模式 - 长期运行的JSON-RPC 2.0服务，在多次请求之间保持编译缓存
+//!
+//! 每行一个请求，每个请求对应一行响应。支持的方法：
+//! - `compile`：参数`{ "source": "..." }`，返回编译结果和`cached`（是否命中缓存）
+//! - `analyze`：参数`{ "source": "..." }`，返回诊断信息和统计信息
+//! - `diagnose`：参数`{ "source": "...", "filename": "..." }`，返回诊断信息列表
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use log::{info, error};
+use serde_json::{Value, json};
+
+use lumen_compiler::Compiler;
+use lumen_optimizers::{AnalyzeOptions, analyze, diagnose};
+
+/// JSON-RPC错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// 编译失败等由方法本身产生的错误
+const SERVER_ERROR: i64 = -32000;
+
+/// JSON-RPC服务，所有连接共享同一个编译器和缓存
+pub struct RpcServer {
+    compiler: Compiler,
+}
+
+impl RpcServer {
+    pub fn new(compiler: Compiler) -> Self {
+        Self { compiler }
+    }
+
+    /// 处理一行请求，返回响应；通知（没有`id`的请求）不需要响应，返回None
+    pub fn handle_line(&self, line: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &format!("无效的JSON: {}", e))),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, "缺少method字段"));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = self.dispatch(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "compile" => self.compile(params),
+            "analyze" => {
+                let result = analyze(source_param(params)?, &AnalyzeOptions::default());
+                Ok(json!({
+                    "diagnostics": result.diagnostics,
+                    "stats": {
+                        "lines": result.stats.lines,
+                        "nodes": result.stats.nodes,
+                        "scopes": result.stats.scopes,
+                        "bindings": result.stats.bindings,
+                        "parseTimeMs": result.stats.parse_time_ms,
+                    },
+                }))
+            },
+            "diagnose" => {
+                let filename = params.get("filename").and_then(Value::as_str).unwrap_or("<input>");
+                Ok(json!(diagnose(source_param(params)?, filename)))
+            },
+            _ => Err((METHOD_NOT_FOUND, format!("未知的方法: {}", method))),
+        }
+    }
+
+    fn compile(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let source = source_param(params)?;
+        let result = self.compiler.compile_string(source)
+            .map_err(|e| (SERVER_ERROR, format!("编译失败: {}", e)))?;
+        Ok(json!({
+            "code": result.code,
+            "sourceMap": result.source_map,
+            "timeMs": result.time_ms,
+            "inputSize": result.input_size,
+            "outputSize": result.output_size,
+            "cached": result.from_cache,
+        }))
+    }
+
+    /// 逐行读取请求并写出响应，直到输入结束
+    pub fn serve<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 通过标准输入输出提供服务
+    pub fn serve_stdio(&self) -> std::io::Result<()> {
+        info!("JSON-RPC服务已启动（标准输入输出）");
+        let stdin = std::io::stdin();
+        self.serve(stdin.lock(), std::io::stdout().lock())
+    }
+
+    /// 在本机端口上提供服务，依次处理每个连接
+    pub fn serve_tcp(&self, port: u16) -> std::io::Result<()> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        info!("JSON-RPC服务已启动: {}", listener.local_addr()?);
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let reader = BufReader::new(stream.try_clone()?);
+            if let Err(e) = self.serve(reader, stream) {
+                error!("连接异常断开: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn source_param(params: &Value) -> Result<&str, (i64, String)> {
+    params.get("source").and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, "缺少字符串参数source".to_string()))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_compile_served_from_warm_cache() {
+        let server = RpcServer::new(Compiler::new());
+        let request = |id: u64| json!({ "jsonrpc": "2.0", "id": id, "method": "compile", "params": { "source": "var x = 42;" } });
+        let input = format!("{}\n{}\n", request(1), request(2));
+
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).unwrap();
+        let responses: Vec<Value> = String::from_utf8(output).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["cached"], false);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["result"]["cached"], true, "第二次编译应命中缓存");
+        assert_eq!(responses[0]["result"]["code"], responses[1]["result"]["code"]);
+
+        let unknown = server.handle_line(r#"{"jsonrpc":"2.0","id":3,"method":"format"}"#).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&unknown).unwrap()["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
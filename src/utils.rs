@@ -1,9 +1,13 @@
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use log::{info, debug, warn};
 use walkdir::WalkDir;
 use glob::Pattern;
+use serde::Serialize;
+
+use lumen_compiler::{FsResolver, Resolver};
+use lumen_core::paths::display_path;
 
 /// 获取文件扩展名
 pub fn get_file_extension(path: &Path) -> Option<String> {
@@ -27,11 +31,12 @@ pub fn is_supported_file(path: &Path) -> bool {
     is_javascript_file(path) || is_typescript_file(path)
 }
 
-/// 查找目录中与模式匹配的所有文件
+/// 查找目录中与模式匹配的所有文件，模式相对于`dir`匹配
 pub fn find_files<P: AsRef<Path>>(dir: P, pattern: &str) -> Vec<PathBuf> {
-    let glob_pattern = Pattern::new(pattern).unwrap_or_else(|_| {
+    let dir = dir.as_ref();
+    let patterns = compile_patterns(pattern).unwrap_or_else(|| {
         warn!("无效的glob模式: {}, 回退到默认模式", pattern);
-        Pattern::new("**/*.{js,ts,jsx,tsx}").unwrap()
+        compile_patterns("**/*.{js,ts,jsx,tsx}").unwrap()
     });
     
     WalkDir::new(dir)
@@ -39,13 +44,36 @@ pub fn find_files<P: AsRef<Path>>(dir: P, pattern: &str) -> Vec<PathBuf> {
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| {
+            let relative = e.path().strip_prefix(dir).unwrap_or(e.path());
             e.file_type().is_file() && 
-            glob_pattern.matches_path(e.path())
+            patterns.iter().any(|p| p.matches_path(relative))
         })
         .map(|e| e.path().to_path_buf())
         .collect()
 }
 
+/// 编译glob模式，`glob`不支持`{a,b}`形式的候选项，先展开为多个模式
+fn compile_patterns(pattern: &str) -> Option<Vec<Pattern>> {
+    expand_braces(pattern).iter()
+        .map(|p| Pattern::new(p).ok())
+        .collect()
+}
+
+/// 展开模式中的`{a,b}`候选项（不支持嵌套）
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let (Some(open), Some(close)) = (pattern.find('{'), pattern.find('}')) else {
+        return vec![pattern.to_string()];
+    };
+    if close < open {
+        return vec![pattern.to_string()];
+    }
+    
+    let (prefix, rest) = (&pattern[..open], &pattern[close + 1..]);
+    pattern[open + 1..close].split(',')
+        .flat_map(|alt| expand_braces(&format!("{}{}{}", prefix, alt, rest)))
+        .collect()
+}
+
 /// 获取文件大小
 pub fn get_file_size<P: AsRef<Path>>(path: P) -> std::io::Result<u64> {
     let metadata = std::fs::metadata(path)?;
@@ -83,23 +111,73 @@ pub fn hash_text(text: &str) -> String {
     format!("{:x}", hasher.finish())
 }
 
-/// 获取依赖关系图
+/// 获取依赖关系图，从入口文件开始解析`import`语句，递归加入导入的文件
+///
+/// 相对路径（`./`、`../`）相对于导入它的文件解析，其余的在`search_dirs`中依次查找；
+/// 图中的文件为规范化的绝对路径。
 pub fn get_dependency_graph<P: AsRef<Path>>(
     entry_points: &[P], 
     search_dirs: &[P]
 ) -> std::io::Result<DependencyGraph> {
+    let search_dirs = search_dirs.iter().map(|dir| dir.as_ref().to_path_buf()).collect();
+    let resolver = FsResolver::new().with_search_dirs(search_dirs);
+    dependency_graph_with(&resolver, entry_points)
+}
+
+/// 通过`resolver`解析和读取模块构建依赖图，可以传入`MemoryResolver`分析内存中的源码
+///
+/// 无法解析的导入（如npm包）和无法解析语法的文件只记录日志，不加入依赖。
+pub fn dependency_graph_with<P: AsRef<Path>>(resolver: &dyn Resolver, entry_points: &[P]) -> std::io::Result<DependencyGraph> {
     let mut graph = DependencyGraph::new();
+    let mut queue = entry_points.iter()
+        .map(|entry| resolver.resolve_entry(entry.as_ref()).map_err(std::io::Error::other))
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+    let mut visited = HashSet::new();
     
-    // TODO: 实现JavaScript/TypeScript依赖解析
-    // 这里只是一个简单的示例实现
-    for entry in entry_points {
-        let path = entry.as_ref();
-        graph.add_node(path.to_path_buf());
+    while let Some(path) = queue.pop() {
+        if !visited.insert(path.clone()) {
+            continue;
+        }
+        graph.add_node(path.clone());
+        
+        let source = resolver.load(&path).map_err(std::io::Error::other)?;
+        for specifier in import_specifiers(&path, &source) {
+            match resolver.resolve(&specifier, &path) {
+                Some(dependency) => {
+                    graph.add_edge(path.clone(), dependency.clone());
+                    queue.push(dependency);
+                },
+                None => debug!("无法解析导入 {}: {}", specifier, path.display()),
+            }
+        }
     }
     
     Ok(graph)
 }
 
+/// 解析文件并取出所有导入语句的模块路径，去重后按字典序返回
+fn import_specifiers(path: &Path, source: &str) -> Vec<String> {
+    let options = lumen_parser::ParseOptions {
+        typescript: is_typescript_file(path),
+        jsx: matches!(get_file_extension(path).as_deref(), Some("jsx") | Some("tsx")),
+        filename: Some(path.display().to_string()),
+        ..Default::default()
+    };
+    let ir = match lumen_parser::JsParser::new(options).parse_string(source) {
+        Ok(ir) => ir,
+        Err(e) => {
+            warn!("解析 {} 失败，忽略其中的导入: {}", path.display(), e);
+            return Vec::new();
+        },
+    };
+    
+    let specifiers: BTreeSet<String> = ir.nodes.values()
+        .filter(|node| node.0.node_type == lumen_core::NodeType::ImportDeclaration)
+        .filter_map(|node| node.0.get_string_value("source").map(str::to_string))
+        .collect();
+    specifiers.into_iter().collect()
+}
+
 /// 性能计时器
 pub struct Timer {
     start: Instant,
@@ -169,8 +247,10 @@ impl DependencyGraph {
         self.nodes.insert(path);
     }
     
-    /// 添加边（依赖关系）
+    /// 添加边（依赖关系），`from`依赖`to`，两端不在图中时一并加入
     pub fn add_edge(&mut self, from: PathBuf, to: PathBuf) {
+        self.nodes.insert(from.clone());
+        self.nodes.insert(to.clone());
         self.edges.push((from, to));
     }
     
@@ -184,10 +264,222 @@ impl DependencyGraph {
         &self.edges
     }
     
-    /// 获取拓扑排序结果（编译顺序）
-    pub fn topological_sort(&self) -> Vec<PathBuf> {
-        // TODO: 实现拓扑排序算法
-        // 这里只是简单地返回所有节点
-        self.nodes.iter().cloned().collect()
+    /// 获取拓扑排序结果（编译顺序），每个文件都排在它的依赖之后
+    ///
+    /// 没有先后关系的文件按路径排序，结果是确定的。存在循环依赖时返回其中一个环，
+    /// 首尾是同一个文件，例如`[a, b, a]`。
+    pub fn topological_sort(&self) -> Result<Vec<PathBuf>, Vec<PathBuf>> {
+        // 每个文件尚未排好的依赖数，以及依赖每个文件的文件
+        let mut pending: HashMap<&PathBuf, usize> = self.nodes.iter().map(|node| (node, 0)).collect();
+        let mut dependents: HashMap<&PathBuf, Vec<&PathBuf>> = HashMap::new();
+        for (from, to) in self.edges.iter().collect::<HashSet<_>>() {
+            *pending.entry(from).or_insert(0) += 1;
+            pending.entry(to).or_insert(0);
+            dependents.entry(to).or_default().push(from);
+        }
+        
+        let mut ready: BTreeSet<&PathBuf> = pending.iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        let mut order = Vec::with_capacity(pending.len());
+        while let Some(node) = ready.pop_first() {
+            for dependent in dependents.get(node).into_iter().flatten() {
+                let count = pending.get_mut(dependent).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(dependent);
+                }
+            }
+            order.push(node.clone());
+        }
+        
+        if order.len() == pending.len() {
+            return Ok(order);
+        }
+        
+        // 剩下的文件都在环上或依赖环上的文件，沿着未排好的依赖一直走，必然回到走过的文件
+        let remaining = |node: &PathBuf| pending.get(node).is_some_and(|count| *count > 0);
+        let mut path: Vec<&PathBuf> = vec![pending.keys().filter(|node| remaining(node)).min().unwrap()];
+        loop {
+            let current = *path.last().unwrap();
+            let next = self.edges.iter()
+                .filter(|(from, to)| from == current && remaining(to))
+                .map(|(_, to)| to)
+                .min()
+                .unwrap();
+            if let Some(start) = path.iter().position(|node| *node == next) {
+                let mut cycle: Vec<PathBuf> = path[start..].iter().map(|node| (*node).clone()).collect();
+                cycle.push(next.clone());
+                warn!("检测到循环依赖: {}", format_cycle(&cycle));
+                return Err(cycle);
+            }
+            path.push(next);
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// 从`entry`开始展开的依赖树，子节点按导入的顺序排列
+    ///
+    /// 路径显示为相对于`root_dir`的路径；循环依赖和已经展开过的文件只列出，不再展开。
+    pub fn dependency_tree(&self, entry: &Path, root_dir: Option<&Path>) -> DependencyTree {
+        let mut expanded = HashSet::new();
+        self.subtree(entry, root_dir, &mut Vec::new(), &mut expanded)
+    }
+    
+    fn subtree<'a>(
+        &'a self,
+        path: &'a Path,
+        root_dir: Option<&Path>,
+        ancestors: &mut Vec<&'a Path>,
+        expanded: &mut HashSet<&'a Path>,
+    ) -> DependencyTree {
+        let mut tree = DependencyTree {
+            path: display_path(path, root_dir),
+            cycle: ancestors.contains(&path),
+            repeated: false,
+            dependencies: Vec::new(),
+        };
+        if tree.cycle {
+            return tree;
+        }
+        if !expanded.insert(path) {
+            tree.repeated = true;
+            return tree;
+        }
+        
+        let mut dependencies: Vec<&Path> = Vec::new();
+        for (_, to) in self.edges.iter().filter(|(from, _)| from == path) {
+            if !dependencies.contains(&to.as_path()) {
+                dependencies.push(to);
+            }
+        }
+        ancestors.push(path);
+        tree.dependencies = dependencies.into_iter()
+            .map(|dependency| self.subtree(dependency, root_dir, ancestors, expanded))
+            .collect();
+        ancestors.pop();
+        tree
+    }
+}
+
+/// 依赖树中的一个文件
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyTree {
+    pub path: String,
+    /// 该文件是从入口到这里的路径上的祖先，即循环依赖
+    pub cycle: bool,
+    /// 该文件在树中已经展开过
+    pub repeated: bool,
+    pub dependencies: Vec<DependencyTree>,
+}
+
+impl DependencyTree {
+    /// 树状文本，循环依赖标记为`(循环依赖)`，已经展开过的文件标记为`(见上文)`
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        self.render_into("", "", &mut output);
+        output
+    }
+    
+    fn render_into(&self, prefix: &str, indent: &str, output: &mut String) {
+        output.push_str(prefix);
+        output.push_str(&self.path);
+        if self.cycle {
+            output.push_str(" (循环依赖)");
+        } else if self.repeated {
+            output.push_str(" (见上文)");
+        }
+        output.push('\n');
+        
+        for (i, dependency) in self.dependencies.iter().enumerate() {
+            let (branch, continuation) = if i + 1 == self.dependencies.len() { ("└── ", "    ") } else { ("├── ", "│   ") };
+            dependency.render_into(&format!("{}{}", indent, branch), &format!("{}{}", indent, continuation), output);
+        }
     }
-} 
\ No newline at end of file
+}
+
+/// 把循环依赖格式化为`a -> b -> a`
+pub fn format_cycle(cycle: &[PathBuf]) -> String {
+    cycle.iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topological_sort_orders_dependencies_first() {
+        let mut graph = DependencyGraph::new();
+        graph.add_edge(PathBuf::from("a"), PathBuf::from("b"));
+        graph.add_edge(PathBuf::from("b"), PathBuf::from("c"));
+        graph.add_node(PathBuf::from("d"));
+        
+        let order = graph.topological_sort().unwrap();
+        let position = |name: &str| order.iter().position(|p| p == Path::new(name)).unwrap();
+        assert_eq!(order.len(), 4);
+        assert!(position("c") < position("b"), "c应排在b之前: {:?}", order);
+        assert!(position("b") < position("a"), "b应排在a之前: {:?}", order);
+        
+        // c -> a 构成环 a -> b -> c -> a
+        graph.add_edge(PathBuf::from("c"), PathBuf::from("a"));
+        let cycle = graph.topological_sort().unwrap_err();
+        assert_eq!(format_cycle(&cycle), "a -> b -> c -> a");
+    }
+
+    #[test]
+    fn test_dependency_graph_follows_relative_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.js"), "import { b } from \"./b.js\";\nimport \"./lib\";\nimport React from \"react\";\n").unwrap();
+        std::fs::write(dir.path().join("b.js"), "import { c } from \"./c\";\n").unwrap();
+        std::fs::write(dir.path().join("c.ts"), "export const c = 1;\n").unwrap();
+        std::fs::create_dir(dir.path().join("lib")).unwrap();
+        std::fs::write(dir.path().join("lib").join("index.js"), "var lib = 1;\n").unwrap();
+
+        let graph = get_dependency_graph(&[dir.path().join("a.js")], &[]).unwrap();
+
+        let root = dir.path().canonicalize().unwrap();
+        let (a, b) = (root.join("a.js"), root.join("b.js"));
+        let edges = graph.get_edges();
+        assert!(edges.contains(&(a.clone(), b.clone())), "a.js应依赖b.js: {:?}", edges);
+        assert!(edges.contains(&(b.clone(), root.join("c.ts"))), "./c应解析为c.ts");
+        assert!(edges.contains(&(a.clone(), root.join("lib").join("index.js"))), "目录应解析为index.js");
+        assert_eq!(edges.len(), 3, "无法解析的npm包不应加入依赖");
+        assert_eq!(graph.get_nodes().len(), 4);
+
+        // 同样的结构放在内存中，不访问磁盘
+        let resolver = lumen_compiler::MemoryResolver::new()
+            .with_module("src/a.js", "import \"./b\";\n")
+            .with_module("src/b.js", "var b = 1;\n");
+        let graph = dependency_graph_with(&resolver, &["src/a.js"]).unwrap();
+        assert_eq!(graph.get_edges(), &vec![(PathBuf::from("src/a.js"), PathBuf::from("src/b.js"))]);
+    }
+
+    #[test]
+    fn test_dependency_tree_lists_transitive_imports_and_cycles() {
+        let resolver = lumen_compiler::MemoryResolver::new()
+            .with_module("src/main.js", "import \"./a\";\nimport \"./shared\";\n")
+            .with_module("src/a.js", "import \"./b\";\nimport \"./shared\";\n")
+            .with_module("src/b.js", "import \"./main\";\n")
+            .with_module("src/shared.js", "var shared = 1;\n");
+        let graph = dependency_graph_with(&resolver, &["src/main.js"]).unwrap();
+
+        let tree = graph.dependency_tree(Path::new("src/main.js"), Some(Path::new("src")));
+        assert_eq!(tree.render(), "\
+main.js
+├── a.js
+│   ├── b.js
+│   │   └── main.js (循环依赖)
+│   └── shared.js
+└── shared.js (见上文)
+");
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["dependencies"][0]["dependencies"][0]["dependencies"][0]["cycle"], true);
+        assert_eq!(json["dependencies"][1]["repeated"], true);
+    }
+
+}
@@ -0,0 +1,409 @@
+//! 文件监视模式 - 监听目录中的文件变更并实时编译
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use log::{info, error};
+use notify::{Event, Watcher, RecursiveMode, EventKind};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+
+use lumen::LumenCompiler;
+
+/// 监视选项
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// 要监视的目录
+    pub dir: PathBuf,
+    /// 输出目录，保留相对于`dir`的目录结构
+    pub out_dir: PathBuf,
+    /// 文件glob模式
+    pub pattern: String,
+    /// 开始监视前先编译所有匹配的文件
+    pub initial_build: bool,
+    /// 去抖窗口，同一文件在窗口内的多次变更只编译一次
+    pub debounce: Duration,
+    /// 同时进行的编译数上限
+    pub max_parallel: usize,
+    /// 日志中的路径相对于该目录显示，为None时显示完整路径
+    pub root_dir: Option<PathBuf>,
+}
+
+impl WatchOptions {
+    /// 日志中显示的路径
+    fn display(&self, path: &Path) -> String {
+        lumen_compiler::display_path(path, self.root_dir.as_deref())
+    }
+}
+
+/// 文件监视逻辑
+pub async fn watch_files(options: &WatchOptions) -> Result<(), Box<dyn std::error::Error>> {
+    // 创建输出目录（如果不存在）
+    if !options.out_dir.exists() {
+        std::fs::create_dir_all(&options.out_dir)?;
+    }
+
+    // 创建编译器，编译任务在独立的任务中执行，需要共享所有权
+    let compiler = Arc::new(LumenCompiler::new()
+        .with_minify(true)
+        .with_sourcemap(true));
+
+    if options.initial_build {
+        initial_build(options, compile_with(&compiler)).await?;
+    }
+
+    // 监视器回调运行在notify的线程中，匹配的变更路径通过通道转交给事件循环
+    let (tx, rx) = mpsc::unbounded_channel();
+    let pattern = options.pattern.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) => {
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            for path in event.paths {
+                // 检查文件是否匹配模式
+                if is_target_file(&path, &pattern) {
+                    let _ = tx.send(path);
+                }
+            }
+        },
+        Err(e) => error!("监视错误: {}", e),
+    })?;
+
+    // 监视目录
+    watcher.watch(&options.dir, RecursiveMode::Recursive)?;
+
+    println!("开始监视文件变化，按Ctrl+C退出...");
+
+    // Ctrl+C时等待进行中的编译写完输出再退出，避免留下不完整的文件
+    let shutdown = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("无法监听Ctrl+C信号: {}", e);
+            std::future::pending::<()>().await;
+        }
+        println!("\n正在停止监视，等待进行中的编译完成...");
+    };
+    process_events(rx, options.debounce, options.max_parallel, shutdown, |path| {
+        let (compile, options) = (compile_with(&compiler), options.clone());
+        async move {
+            println!("文件变更: {}", options.display(&path));
+            if let Err(e) = compile_to_output(compile, &path, &options).await {
+                error!("处理变更失败: {} - {}", options.display(&path), e);
+            }
+        }
+    }).await;
+
+    println!("监视已停止");
+    Ok(())
+}
+
+/// 变更事件去抖：同一路径在窗口期内的多次变更合并为一次，窗口从最后一次变更开始计算
+pub struct Debouncer {
+    window: Duration,
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self { window, pending: HashMap::new() }
+    }
+
+    /// 记录一次变更，推迟该路径的触发时间
+    pub fn record(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path, now + self.window);
+    }
+
+    /// 最早的触发时间
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().min().copied()
+    }
+
+    /// 取出窗口期已结束的路径
+    pub fn take_ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = self.pending.iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready.sort();
+        for path in &ready {
+            self.pending.remove(path);
+        }
+        ready
+    }
+
+    /// 取出所有未触发的路径
+    pub fn drain(&mut self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.pending.drain().map(|(path, _)| path).collect();
+        paths.sort();
+        paths
+    }
+}
+
+/// 监视事件循环：对变更路径去抖后编译
+///
+/// 同时进行的编译不超过`max_parallel`个，超出的变更排队等待。通道关闭时处理完剩余的变更后返回；
+/// `shutdown`完成时不再接受新的变更，放弃尚未开始的编译，等待进行中的编译结束后返回。
+pub async fn process_events<F, Fut, S>(
+    mut events: mpsc::UnboundedReceiver<PathBuf>,
+    debounce: Duration,
+    max_parallel: usize,
+    shutdown: S,
+    mut compile: F,
+) where
+    F: FnMut(PathBuf) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+    S: Future<Output = ()>,
+{
+    let mut debouncer = Debouncer::new(debounce);
+    let semaphore = Arc::new(Semaphore::new(max_parallel.max(1)));
+    let mut in_flight = JoinSet::new();
+    tokio::pin!(shutdown);
+    let mut stopped = false;
+
+    loop {
+        let deadline = debouncer.next_deadline();
+        tokio::select! {
+            _ = &mut shutdown => {
+                stopped = true;
+                break;
+            },
+            event = events.recv() => match event {
+                Some(path) => debouncer.record(path, Instant::now()),
+                None => break,
+            },
+            _ = time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {},
+            // 回收已完成的编译任务
+            Some(_) = in_flight.join_next(), if !in_flight.is_empty() => {},
+        }
+
+        for path in debouncer.take_ready(Instant::now()) {
+            spawn_compile(&semaphore, &mut in_flight, compile(path)).await;
+        }
+    }
+
+    let pending = debouncer.drain();
+    if stopped {
+        if !pending.is_empty() {
+            info!("停止监视，放弃 {} 个尚未开始的编译", pending.len());
+        }
+    } else {
+        for path in pending {
+            spawn_compile(&semaphore, &mut in_flight, compile(path)).await;
+        }
+    }
+    while in_flight.join_next().await.is_some() {}
+}
+
+/// 取得许可后启动编译任务，许可在任务结束时释放
+async fn spawn_compile<Fut>(semaphore: &Arc<Semaphore>, in_flight: &mut JoinSet<()>, task: Fut)
+where
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let permit = semaphore.clone().acquire_owned().await
+        .expect("信号量不会被关闭");
+    in_flight.spawn(async move {
+        task.await;
+        drop(permit);
+    });
+}
+
+/// 编译目录中所有匹配的文件
+///
+/// `compile`读取并编译一个源文件，返回编译后的代码，与`process_events`一样可以在测试中替换。
+async fn initial_build<F, Fut>(options: &WatchOptions, mut compile: F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(PathBuf) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let files = lumen::find_files(&options.dir, &options.pattern);
+    info!("初始构建: 发现 {} 个文件", files.len());
+
+    for path in &files {
+        compile_to_output(&mut compile, path, options).await?;
+    }
+    println!("初始构建完成，共编译 {} 个文件", files.len());
+
+    Ok(())
+}
+
+/// 用`compiler`读取并编译源文件的函数
+fn compile_with(compiler: &Arc<LumenCompiler>) -> impl FnMut(PathBuf) -> std::pin::Pin<Box<dyn Future<Output = Result<String, String>> + Send>> {
+    let compiler = compiler.clone();
+    move |path| {
+        let compiler = compiler.clone();
+        Box::pin(async move {
+            let source = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            compiler.compile_str(&source, path.to_str()).await
+                .map(|result| result.code)
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// 编译单个文件到输出目录中对应的位置，编译失败只打印错误
+async fn compile_to_output<F, Fut>(compile: F, path: &Path, options: &WatchOptions) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnOnce(PathBuf) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    // 计算输出路径
+    let js_out_path = lumen_compiler::relative_output_path(path, &options.dir, &options.out_dir)?;
+
+    // 确保输出目录存在
+    if let Some(parent) = js_out_path.parent() {
+        if !parent.exists() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    // 执行编译
+    match compile(path.to_path_buf()).await {
+        Ok(code) => {
+            std::fs::write(&js_out_path, code)?;
+            println!("编译成功: {} -> {}", options.display(path), options.display(&js_out_path));
+        },
+        Err(e) => println!("编译失败: {} - {}", options.display(path), e),
+    }
+
+    Ok(())
+}
+
+/// 检查文件是否匹配目标模式
+fn is_target_file(path: &Path, _pattern: &str) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+
+    // 简单实现：检查扩展名
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("js") | Some("jsx") | Some("ts") | Some("tsx")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_initial_build_compiles_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("nested")).unwrap();
+        std::fs::write(src.join("a.js"), "var a = 1;").unwrap();
+        std::fs::write(src.join("nested").join("b.ts"), "var b = 2;").unwrap();
+        std::fs::write(src.join("notes.txt"), "not code").unwrap();
+
+        let options = WatchOptions {
+            dir: src,
+            out_dir: dir.path().join("dist"),
+            pattern: "**/*.{js,ts,jsx,tsx}".to_string(),
+            initial_build: true,
+            debounce: Duration::from_millis(50),
+            max_parallel: 4,
+            root_dir: None,
+        };
+
+        // 用读取源码代替编译器，不依赖C++后端
+        initial_build(&options, |path| async move {
+            std::fs::read_to_string(&path).map(|source| format!("// 已编译\n{}", source)).map_err(|e| e.to_string())
+        }).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(options.out_dir.join("a.js")).unwrap(), "// 已编译\nvar a = 1;");
+        assert_eq!(std::fs::read_to_string(options.out_dir.join("nested").join("b.js")).unwrap(), "// 已编译\nvar b = 2;");
+        assert!(!options.out_dir.join("notes.js").exists(), "不匹配模式的文件不应被编译");
+
+        // 编译失败的文件不写输出，也不中止初始构建
+        let failing = WatchOptions { out_dir: dir.path().join("failed"), ..options.clone() };
+        initial_build(&failing, |_| async { Err("语法错误".to_string()) }).await.unwrap();
+        assert!(!failing.out_dir.join("a.js").exists());
+    }
+
+    #[tokio::test]
+    async fn test_rapid_changes_compile_once() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let compiled = AtomicUsize::new(0);
+
+        // 编辑器保存一次可能产生多个写事件
+        let sender = tokio::spawn(async move {
+            for _ in 0..5 {
+                tx.send(PathBuf::from("src/a.js")).unwrap();
+                time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+        process_events(rx, Duration::from_millis(100), 1, std::future::pending(), |path| {
+            assert_eq!(path, PathBuf::from("src/a.js"));
+            compiled.fetch_add(1, Ordering::SeqCst);
+            async {}
+        }).await;
+        sender.await.unwrap();
+
+        assert_eq!(compiled.load(Ordering::SeqCst), 1, "窗口内的多次变更应只编译一次");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallel_compiles_never_exceed_limit() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        // 模拟git checkout一次性修改大量文件
+        for i in 0..20 {
+            tx.send(PathBuf::from(format!("src/{}.js", i))).unwrap();
+        }
+        drop(tx);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicUsize::new(0));
+        process_events(rx, Duration::from_millis(10), 3, std::future::pending(), |_| {
+            let (in_flight, peak, finished) = (in_flight.clone(), peak.clone(), finished.clone());
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(current, Ordering::SeqCst);
+                time::sleep(Duration::from_millis(20)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                finished.fetch_add(1, Ordering::SeqCst);
+            }
+        }).await;
+
+        assert_eq!(finished.load(Ordering::SeqCst), 20, "所有变更都应被编译");
+        assert!(peak.load(Ordering::SeqCst) <= 3, "同时进行的编译数超过上限: {}", peak.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_shutdown_waits_for_in_flight_compile() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("a.js");
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(PathBuf::from("src/a.js")).unwrap();
+
+        // 编译开始写入输出后立即发出停止信号
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let started = output.clone();
+        tokio::spawn(async move {
+            while !started.exists() {
+                time::sleep(Duration::from_millis(5)).await;
+            }
+            let _ = stop_tx.send(());
+        });
+
+        let target = output.clone();
+        process_events(rx, Duration::from_millis(10), 1, async { let _ = stop_rx.await; }, move |_| {
+            let target = target.clone();
+            async move {
+                // 分段写入，模拟耗时的编译输出
+                for line in ["var a = 1;\n", "var b = 2;\n", "var c = 3;\n"] {
+                    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&target).unwrap();
+                    std::io::Write::write_all(&mut file, line.as_bytes()).unwrap();
+                    time::sleep(Duration::from_millis(30)).await;
+                }
+            }
+        }).await;
+
+        // 发送端一直存活，返回是停止信号导致的
+        drop(tx);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "var a = 1;\nvar b = 2;\nvar c = 3;\n", "输出文件不应被截断");
+    }
+}